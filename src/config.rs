@@ -0,0 +1,510 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::document::{ContentNormalization, ExtensionGroup, DEFAULT_GENERIC_FILENAMES, DEFAULT_SUMMARY_LENGTH};
+use crate::github::{DEFAULT_CACHE_CAPACITY, DEFAULT_MAX_CONCURRENT_REQUESTS};
+use crate::server::NotReadyPolicy;
+use crate::storage::{RecencyBoost, ScoringWeights, TokenizerKind};
+
+/// Command line arguments
+///
+/// Every option can also be set in the config file; flags given on the command
+/// line take precedence over it.
+#[derive(Debug, Default, Parser)]
+pub struct Cli {
+    /// A JSON config file to read the options from (optional)
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+    /// A github repository to scan for documentation [default: rust-sdk]
+    #[clap(long)]
+    pub github_repo: Option<String>,
+    /// A local directory to index documentation from, instead of or in addition to a github repository (optional)
+    #[clap(long)]
+    pub local_path: Option<PathBuf>,
+    /// A web page to index, by HTTP(S) URL (optional, repeatable)
+    #[clap(long = "url")]
+    pub urls: Vec<String>,
+    /// A file listing web pages to index, one URL per line (optional)
+    #[clap(long)]
+    pub urls_file: Option<PathBuf>,
+    /// Follow symbolic links when scanning the local directory
+    #[clap(long)]
+    pub follow_symlinks: bool,
+    /// Also index files linked from outside of the local directory, with --follow-symlinks
+    #[clap(long)]
+    pub external_symlinks: bool,
+    /// A github repository subfolder to scan for documentation (optional, repeatable or comma separated)
+    #[clap(long, value_delimiter = ',')]
+    pub github_subfolder: Vec<String>,
+    /// A github repository owner [default: modelcontextprotocol]
+    #[clap(long)]
+    pub github_owner: Option<String>,
+    /// A github personal access token to use for authentication (optional)
+    #[clap(long)]
+    pub github_pat: Option<String>,
+    /// The maximum duration of a single GitHub request, in seconds [default: 30]
+    #[clap(long)]
+    pub request_timeout: Option<u64>,
+    /// The maximum number of GitHub requests in flight at once [default: 5]
+    #[clap(long)]
+    pub max_concurrent_requests: Option<usize>,
+    /// The maximum number of subdirectory levels to scan below the subfolder (optional, 0 scans the subfolder only)
+    #[clap(long)]
+    pub max_depth: Option<usize>,
+    /// Only scan the files of a group of extensions: markdown, text, docs or notebooks (optional, repeatable or comma separated)
+    #[clap(long = "extensions-group", value_delimiter = ',')]
+    pub extension_groups: Vec<ExtensionGroup>,
+    /// A directory to persist the document storage in (optional, documents are kept in memory by default)
+    #[clap(long)]
+    pub storage_path: Option<PathBuf>,
+    /// Save the document storage gzip compressed
+    #[clap(long)]
+    pub compress_storage: bool,
+    /// Reduce keywords to their stem, so inflected forms of a word match each other
+    #[clap(long)]
+    pub stemming: bool,
+    /// Remove diacritics from keywords, so accented and unaccented forms of a word match each other
+    #[clap(long)]
+    pub fold_diacritics: bool,
+    /// Render the HTML of documents when they're stored, instead of on every request for it
+    #[clap(long)]
+    pub prerender_html: bool,
+    /// Expand query keywords with their synonyms, such as auth for authentication
+    #[clap(long)]
+    pub expand_queries: bool,
+    /// Keep code identifiers such as std::vec, --flag or file.rs whole in keywords
+    #[clap(long)]
+    pub code_tokenizer: bool,
+    /// Approximate maximum length of document summaries, in bytes (default: 200)
+    #[clap(long)]
+    pub max_summary_length: Option<usize>,
+    /// Maximum number of keywords kept per document, the most frequent ones (default: unlimited)
+    #[clap(long)]
+    pub max_keywords_per_document: Option<usize>,
+    /// Maximum number of GitHub files kept in memory, 0 to disable the cache (default: 1000)
+    #[clap(long)]
+    pub cache_capacity: Option<usize>,
+    /// Poll the repository for new commits every this many seconds, re-indexing the changed files (optional)
+    #[clap(long)]
+    pub watch_interval: Option<u64>,
+    /// Rescan the repository in the background when queried this many seconds after the last scan (optional)
+    #[clap(long)]
+    pub stale_ttl: Option<u64>,
+    /// Maximum size of the content returned by a tool call, in bytes, longer content being truncated (optional)
+    #[clap(long)]
+    pub max_response_bytes: Option<usize>,
+    /// Prefix the titles shared by several documents with their directory in tool responses
+    #[clap(long)]
+    pub qualify_titles: bool,
+    /// Hide internal documents from every tool, serving public documents only
+    #[clap(long)]
+    pub public_only: bool,
+    /// A token clients present to be served internal documents despite --public-only (optional)
+    #[clap(long)]
+    pub internal_token: Option<String>,
+    /// What search tools do before the initial scan completes: reject, wait or partial [default: wait]
+    #[clap(long)]
+    pub not_ready_policy: Option<NotReadyPolicy>,
+    /// Don't scan the README at the repository root when it's outside of the subfolders
+    #[clap(long)]
+    pub no_root_readme: bool,
+    /// Fetch when each file was last changed in the repository, with one more request per file
+    #[clap(long)]
+    pub track_last_modified: bool,
+    /// Also index the pages of the repository wiki, under the wiki/ directory
+    #[clap(long)]
+    pub include_wiki: bool,
+    /// Also index the notes of the GitHub releases of the repository, under the releases/ directory
+    #[clap(long)]
+    pub include_releases: bool,
+    /// Store content with its original line endings instead of converting them to \n
+    #[clap(long)]
+    pub preserve_line_endings: bool,
+    /// Expand tabs in content to spaces, up to tab stops every this many columns (optional)
+    #[clap(long)]
+    pub tab_width: Option<usize>,
+    /// Leading directories to remove from the paths documents are stored under (optional)
+    #[clap(long)]
+    pub strip_prefix: Option<String>,
+    /// A directory to prepend to the paths documents are stored under (optional)
+    #[clap(long)]
+    pub path_prefix: Option<String>,
+    /// Only update the documents changed since this commit, branch or tag (requires a persistent storage path)
+    #[clap(long)]
+    pub since_ref: Option<String>,
+    /// List the files that would be indexed, with their sizes, then exit without fetching or storing them
+    #[clap(long)]
+    pub dry_run: bool,
+}
+
+/// Server configuration, read from a JSON config file and the command line
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// The GitHub repository to scan for documentation
+    pub github_repo: String,
+    /// A local directory to index documentation from
+    ///
+    /// The default repository isn't scanned when a local directory is given, so
+    /// it can be used offline; a repository set explicitly is scanned as well.
+    pub local_path: Option<PathBuf>,
+    /// URLs of web pages to index, stored under their URL
+    ///
+    /// Like a local directory, they keep the default repository from being scanned.
+    pub urls: Vec<String>,
+    /// A file listing the URLs of more web pages to index, one per line
+    pub urls_file: Option<PathBuf>,
+    /// Whether symbolic links are followed when scanning the local directory
+    pub follow_symlinks: bool,
+    /// Whether followed links may lead outside of the local directory
+    pub external_symlinks: bool,
+    /// The subfolders of the repository to scan, the whole repository if empty
+    #[serde(deserialize_with = "string_or_list")]
+    pub github_subfolder: Vec<String>,
+    /// The owner of the GitHub repository
+    pub github_owner: String,
+    /// A personal access token, required for private repositories
+    pub github_pat: Option<String>,
+    /// The maximum duration of a single GitHub request, in seconds
+    pub request_timeout: u64,
+    /// The maximum number of GitHub requests in flight at once
+    pub max_concurrent_requests: usize,
+    /// The maximum number of subdirectory levels to scan below the subfolder
+    pub max_depth: Option<usize>,
+    /// Glob patterns of the paths to index, relative to the repository root (all paths if empty)
+    pub include: Vec<String>,
+    /// Glob patterns of the paths to skip, relative to the repository root
+    pub exclude: Vec<String>,
+    /// Glob patterns of the paths of internal documents, as stored, unless their front matter says otherwise
+    pub internal_paths: Vec<String>,
+    /// Groups of the extensions of the files to index, the default extensions if empty
+    pub extension_groups: Vec<ExtensionGroup>,
+    /// Glob patterns of the paths indexed before the others, relative to the repository root
+    pub priority: Vec<String>,
+    /// File names, without extension, too generic to title documents without a
+    /// title, which are titled after their directory instead
+    pub generic_filenames: Vec<String>,
+    /// Whether the README at the repository root is scanned whatever the subfolders
+    pub include_root_readme: bool,
+    /// Whether the time of the last commit of each file is fetched, costing one request per file
+    pub track_last_modified: bool,
+    /// Whether the pages of the repository wiki are indexed as well
+    pub include_wiki: bool,
+    /// Whether the notes of the repository releases are indexed as well
+    pub include_releases: bool,
+    /// Whether content keeps its original line endings instead of being converted to `\n`
+    pub preserve_line_endings: bool,
+    /// Number of columns tabs are expanded to, tabs being kept if not set
+    pub tab_width: Option<usize>,
+    /// How often the repository is polled for new commits, in seconds, not at all if not set
+    pub watch_interval: Option<u64>,
+    /// Age of the index, in seconds, after which a query triggers a rescan, never if not set
+    pub stale_ttl: Option<u64>,
+    /// Leading directories removed from the paths documents are stored under
+    pub strip_prefix: Option<String>,
+    /// Directory prepended to the paths documents are stored under, after stripping
+    pub path_prefix: Option<String>,
+    /// The directory to persist the document storage in, documents are kept in memory if not set
+    pub storage_path: Option<PathBuf>,
+    /// Whether the document storage is saved gzip compressed
+    pub compress_storage: bool,
+    /// Multipliers applied to title, heading and body keyword matches
+    pub scoring: ScoringWeights,
+    /// Boost of recently modified documents in search results, none by default
+    pub recency_boost: Option<RecencyBoost>,
+    /// Whether keywords are reduced to their stem
+    pub stemming: bool,
+    /// Whether diacritics are removed from keywords
+    pub fold_diacritics: bool,
+    /// Whether the HTML of documents is rendered when they're stored
+    pub prerender_html: bool,
+    /// Custom stopwords by language code, replacing the built-in list of that language
+    pub stopwords: HashMap<String, Vec<String>>,
+    /// Whether query keywords are expanded with their synonyms
+    pub expand_queries: bool,
+    /// How text is split into the words keywords are extracted from
+    pub tokenizer: TokenizerKind,
+    /// Approximate maximum length of document summaries, in bytes
+    pub max_summary_length: usize,
+    /// Maximum number of content keywords kept per document, all of them if not set
+    pub max_keywords_per_document: Option<usize>,
+    /// Maximum number of GitHub file contents cached in memory, 0 to disable the cache
+    pub cache_capacity: usize,
+    /// Custom synonyms by word, in addition to the built-in list
+    pub synonyms: HashMap<String, Vec<String>>,
+    /// Maximum size of the content returned by a tool call, in bytes, unlimited if not set
+    pub max_response_bytes: Option<usize>,
+    /// Whether titles shared by several documents are prefixed with their directory in tool responses
+    pub qualify_titles: bool,
+    /// Whether internal documents are hidden from every tool
+    pub public_only: bool,
+    /// The token clients present to be served internal documents despite `public_only`
+    pub internal_token: Option<String>,
+    /// Whether search tools wait for the initial scan or fail until it completes
+    pub not_ready_policy: NotReadyPolicy,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            github_repo: "rust-sdk".to_string(),
+            local_path: None,
+            urls: Vec::new(),
+            urls_file: None,
+            follow_symlinks: false,
+            external_symlinks: false,
+            github_subfolder: Vec::new(),
+            github_owner: "modelcontextprotocol".to_string(),
+            github_pat: None,
+            request_timeout: 30,
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            max_depth: None,
+            extension_groups: Vec::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            internal_paths: Vec::new(),
+            priority: Vec::new(),
+            generic_filenames: DEFAULT_GENERIC_FILENAMES.iter().map(|name| name.to_string()).collect(),
+            include_root_readme: true,
+            track_last_modified: false,
+            include_wiki: false,
+            include_releases: false,
+            preserve_line_endings: false,
+            tab_width: None,
+            watch_interval: None,
+            stale_ttl: None,
+            strip_prefix: None,
+            path_prefix: None,
+            storage_path: None,
+            compress_storage: false,
+            scoring: ScoringWeights::default(),
+            recency_boost: None,
+            stemming: false,
+            fold_diacritics: false,
+            prerender_html: false,
+            stopwords: HashMap::new(),
+            expand_queries: false,
+            tokenizer: TokenizerKind::default(),
+            max_summary_length: DEFAULT_SUMMARY_LENGTH,
+            max_keywords_per_document: None,
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            synonyms: HashMap::new(),
+            max_response_bytes: None,
+            qualify_titles: false,
+            public_only: false,
+            internal_token: None,
+            not_ready_policy: NotReadyPolicy::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Build the configuration from the config file given on the command line, if
+    /// any, overridden by the other command line flags
+    pub fn load(cli: &Cli) -> Result<Self> {
+        let (config, file_sets_repo) = match &cli.config {
+            Some(path) => {
+                let content = Self::read_file(path)?;
+                let file_sets_repo = serde_json::from_str::<serde_json::Value>(&content)
+                    .is_ok_and(|value| value.get("github_repo").is_some());
+                (Self::parse(path, &content)?, file_sets_repo)
+            }
+            None => (Self::default(), false),
+        };
+
+        let mut config = config.merge(cli);
+
+        // Index only the local directory and web pages unless a repository was asked for
+        let has_other_sources = config.local_path.is_some() || !config.urls.is_empty() || config.urls_file.is_some();
+        if has_other_sources && cli.github_repo.is_none() && !file_sets_repo {
+            config.github_repo = String::new();
+        }
+
+        Ok(config)
+    }
+
+    /// Read the configuration from a JSON file, using defaults for missing options
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        Self::parse(path, &Self::read_file(path)?)
+    }
+
+    fn read_file(path: &Path) -> Result<String> {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))
+    }
+
+    fn parse(path: &Path, content: &str) -> Result<Self> {
+        serde_json::from_str(content)
+            .with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    /// The URLs of the web pages to index: those given directly, then those of
+    /// the URLs file, without duplicates
+    ///
+    /// Blank lines and lines starting with `#` of the URLs file are skipped.
+    pub fn page_urls(&self) -> Result<Vec<String>> {
+        let mut urls = self.urls.clone();
+        if let Some(path) = &self.urls_file {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read URLs file {}", path.display()))?;
+            urls.extend(
+                content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        urls.retain(|url| seen.insert(url.clone()));
+        Ok(urls)
+    }
+
+    /// How the content of scanned files is normalized before it's indexed
+    pub fn content_normalization(&self) -> ContentNormalization {
+        ContentNormalization {
+            line_endings: !self.preserve_line_endings,
+            tab_width: self.tab_width,
+        }
+    }
+
+    /// Override the configuration with the flags given on the command line
+    pub fn merge(mut self, cli: &Cli) -> Self {
+        if let Some(github_repo) = &cli.github_repo {
+            self.github_repo = github_repo.clone();
+        }
+        if !cli.urls.is_empty() {
+            self.urls = cli.urls.clone();
+        }
+        if cli.urls_file.is_some() {
+            self.urls_file = cli.urls_file.clone();
+        }
+        if cli.local_path.is_some() {
+            self.local_path = cli.local_path.clone();
+        }
+        if !cli.github_subfolder.is_empty() {
+            self.github_subfolder = cli.github_subfolder.clone();
+        }
+        if let Some(github_owner) = &cli.github_owner {
+            self.github_owner = github_owner.clone();
+        }
+        if cli.github_pat.is_some() {
+            self.github_pat = cli.github_pat.clone();
+        }
+        if let Some(request_timeout) = cli.request_timeout {
+            self.request_timeout = request_timeout;
+        }
+        if let Some(max_concurrent_requests) = cli.max_concurrent_requests {
+            self.max_concurrent_requests = max_concurrent_requests;
+        }
+        if cli.watch_interval.is_some() {
+            self.watch_interval = cli.watch_interval;
+        }
+        if cli.stale_ttl.is_some() {
+            self.stale_ttl = cli.stale_ttl;
+        }
+        if cli.follow_symlinks {
+            self.follow_symlinks = true;
+        }
+        if cli.external_symlinks {
+            self.external_symlinks = true;
+        }
+        if cli.max_depth.is_some() {
+            self.max_depth = cli.max_depth;
+        }
+        if !cli.extension_groups.is_empty() {
+            self.extension_groups = cli.extension_groups.clone();
+        }
+        if cli.no_root_readme {
+            self.include_root_readme = false;
+        }
+        if cli.track_last_modified {
+            self.track_last_modified = true;
+        }
+        if cli.include_wiki {
+            self.include_wiki = true;
+        }
+        if cli.include_releases {
+            self.include_releases = true;
+        }
+        if cli.preserve_line_endings {
+            self.preserve_line_endings = true;
+        }
+        if cli.tab_width.is_some() {
+            self.tab_width = cli.tab_width;
+        }
+        if cli.strip_prefix.is_some() {
+            self.strip_prefix = cli.strip_prefix.clone();
+        }
+        if cli.path_prefix.is_some() {
+            self.path_prefix = cli.path_prefix.clone();
+        }
+        if cli.storage_path.is_some() {
+            self.storage_path = cli.storage_path.clone();
+        }
+        if cli.compress_storage {
+            self.compress_storage = true;
+        }
+        if cli.qualify_titles {
+            self.qualify_titles = true;
+        }
+        if cli.public_only {
+            self.public_only = true;
+        }
+        if cli.internal_token.is_some() {
+            self.internal_token = cli.internal_token.clone();
+        }
+        if let Some(not_ready_policy) = cli.not_ready_policy {
+            self.not_ready_policy = not_ready_policy;
+        }
+        if cli.stemming {
+            self.stemming = true;
+        }
+        if cli.fold_diacritics {
+            self.fold_diacritics = true;
+        }
+        if cli.prerender_html {
+            self.prerender_html = true;
+        }
+        if cli.expand_queries {
+            self.expand_queries = true;
+        }
+        if cli.code_tokenizer {
+            self.tokenizer = TokenizerKind::Code;
+        }
+        if let Some(max_summary_length) = cli.max_summary_length {
+            self.max_summary_length = max_summary_length;
+        }
+        if cli.max_keywords_per_document.is_some() {
+            self.max_keywords_per_document = cli.max_keywords_per_document;
+        }
+        if cli.max_response_bytes.is_some() {
+            self.max_response_bytes = cli.max_response_bytes;
+        }
+        if let Some(cache_capacity) = cli.cache_capacity {
+            self.cache_capacity = cache_capacity;
+        }
+
+        self
+    }
+}
+
+/// Deserialize a list of strings, also accepting a single string
+fn string_or_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    Ok(match StringOrList::deserialize(deserializer)? {
+        StringOrList::String(value) => vec![value],
+        StringOrList::List(values) => values,
+    })
+}
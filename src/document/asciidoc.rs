@@ -0,0 +1,96 @@
+use super::Heading;
+
+/// Delimiters of AsciiDoc blocks whose content is verbatim rather than prose:
+/// listing/source, literal, passthrough, and comment blocks
+const VERBATIM_DELIMITERS: [char; 4] = ['-', '.', '+', '/'];
+
+/// Keep only the lines of an AsciiDoc document that are outside verbatim blocks
+/// such as `[source]` listings, so code is never mistaken for headings or prose
+fn prose_lines(content: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    let mut open_delimiter: Option<&str> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim_end();
+
+        if let Some(delimiter) = open_delimiter {
+            if trimmed == delimiter {
+                open_delimiter = None;
+            }
+            continue;
+        }
+
+        if is_verbatim_delimiter(trimmed) {
+            open_delimiter = Some(trimmed);
+            continue;
+        }
+
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Check whether a line opens or closes a verbatim block (e.g. `----`)
+fn is_verbatim_delimiter(line: &str) -> bool {
+    line.len() >= 4
+        && VERBATIM_DELIMITERS
+            .iter()
+            .any(|delimiter| line.chars().all(|c| c == *delimiter))
+}
+
+/// Parse a section header line (`= Title`, `== Section`, ...) into its level and text
+fn parse_heading(line: &str) -> Option<Heading> {
+    let level = line.chars().take_while(|c| *c == '=').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+
+    let rest = &line[level..];
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let text = rest.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    Some(Heading {
+        level,
+        text: text.to_string(),
+    })
+}
+
+/// Extract the document title (the level 0 `= Title` header)
+pub fn extract_title(content: &str) -> Option<String> {
+    prose_lines(content)
+        .into_iter()
+        .filter_map(parse_heading)
+        .find(|heading| heading.level == 1)
+        .map(|heading| heading.text)
+}
+
+/// Extract the document title and section headers, in document order
+pub fn extract_headings(content: &str) -> Vec<Heading> {
+    prose_lines(content)
+        .into_iter()
+        .filter_map(parse_heading)
+        .collect()
+}
+
+/// Extract the prose of the document, without headers, attributes, comments or code
+pub fn prose(content: &str) -> String {
+    prose_lines(content)
+        .into_iter()
+        .filter(|line| {
+            let trimmed = line.trim();
+            // Attribute entries (`:toc:`), block attributes (`[source,rust]`) and line comments
+            let is_markup = (trimmed.starts_with(':') && trimmed[1..].contains(':'))
+                || (trimmed.starts_with('[') && trimmed.ends_with(']'))
+                || trimmed.starts_with("//");
+            parse_heading(trimmed).is_none() && !is_markup
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+/// A term defined by a document, such as a glossary entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Definition {
+    pub term: String,
+    pub definition: String,
+}
+
+/// Maximum number of words of a term, so sentences aren't taken for terms
+const MAX_TERM_WORDS: usize = 6;
+
+/// Separators between a term and its definition in a bullet
+const BULLET_SEPARATORS: [&str; 3] = [" — ", " – ", " -- "];
+
+/// Extract the terms defined by a document, in document order
+///
+/// Two syntaxes are recognized, outside of fenced code blocks:
+/// - definition lists, a term on its own line followed by lines starting with
+///   `: ` holding its definitions, each one being recorded
+/// - bullets of the form `- Term — definition`, with an em dash, an en dash or
+///   `--` as separator, the term being possibly bold or code
+///
+/// Indented lines following a definition continue it.
+pub fn extract_definitions(content: &str) -> Vec<Definition> {
+    let mut definitions: Vec<Definition> = Vec::new();
+    let mut in_code_block = false;
+    // The term of the definition list being read, if any
+    let mut term: Option<String> = None;
+    // Whether the last definition may be continued by indented lines
+    let mut continued = false;
+    let mut previous = "";
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            term = None;
+            continued = false;
+            previous = "";
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+
+        if let Some(definition) = trimmed.strip_prefix(": ").map(str::trim) {
+            // A definition line defines the term on the line before, or the same term as the definition before
+            if term.is_none() && !previous.is_empty() {
+                term = clean_term(previous);
+            }
+            if let (Some(term), false) = (&term, definition.is_empty()) {
+                definitions.push(Definition { term: term.clone(), definition: definition.to_string() });
+                continued = true;
+            }
+        } else if continued && !trimmed.is_empty() && line.starts_with(char::is_whitespace) && !is_list_item(trimmed) {
+            if let Some(last) = definitions.last_mut() {
+                last.definition.push(' ');
+                last.definition.push_str(trimmed);
+            }
+        } else if let Some(definition) = bullet_definition(trimmed) {
+            definitions.push(definition);
+            term = None;
+            continued = true;
+        } else {
+            // A blank line between a term and its definitions is allowed, but ends a definition
+            if !trimmed.is_empty() {
+                term = None;
+                previous = trimmed;
+            }
+            continued = false;
+        }
+    }
+
+    definitions
+}
+
+/// Check whether a line is a bullet, such as a nested list below a definition
+fn is_list_item(line: &str) -> bool {
+    ["- ", "* ", "+ "].iter().any(|marker| line.starts_with(marker))
+}
+
+/// Parse a `- Term — definition` bullet
+fn bullet_definition(line: &str) -> Option<Definition> {
+    let item = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")).or_else(|| line.strip_prefix("+ "))?;
+    let (term, definition) = BULLET_SEPARATORS.iter().find_map(|separator| item.split_once(separator))?;
+    let definition = definition.trim();
+    if definition.is_empty() {
+        return None;
+    }
+    Some(Definition { term: clean_term(term)?, definition: definition.to_string() })
+}
+
+/// Strip the emphasis and code markers around a term, none if it's too long to be one
+fn clean_term(term: &str) -> Option<String> {
+    let term = term.trim().trim_end_matches(':').trim_matches(['*', '_', '`']).trim();
+    let words = term.split_whitespace().count();
+    let heading = term.starts_with('#') || term.starts_with('|') || term.starts_with('>');
+    (words > 0 && words <= MAX_TERM_WORDS && !heading).then(|| term.to_string())
+}
@@ -0,0 +1,51 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// A named category of file extensions, to enable a family of formats at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtensionGroup {
+    /// Markdown and MDX: `.md`, `.mdx` and `.markdown`
+    Markdown,
+    /// Plain text: `.txt`
+    Text,
+    /// Other documentation markup: `.rst` and `.adoc`
+    Docs,
+    /// Jupyter notebooks: `.ipynb`
+    Notebooks,
+}
+
+impl ExtensionGroup {
+    /// Every group, which together make the default supported extensions
+    pub const ALL: [ExtensionGroup; 4] = [
+        ExtensionGroup::Markdown,
+        ExtensionGroup::Text,
+        ExtensionGroup::Docs,
+        ExtensionGroup::Notebooks,
+    ];
+
+    /// The lowercase extensions of the group, without the leading dot
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            ExtensionGroup::Markdown => &["md", "mdx", "markdown"],
+            ExtensionGroup::Text => &["txt"],
+            ExtensionGroup::Docs => &["rst", "adoc"],
+            ExtensionGroup::Notebooks => &["ipynb"],
+        }
+    }
+}
+
+impl FromStr for ExtensionGroup {
+    type Err = anyhow::Error;
+
+    fn from_str(group: &str) -> Result<Self> {
+        match group.trim().to_lowercase().as_str() {
+            "markdown" => Ok(ExtensionGroup::Markdown),
+            "text" => Ok(ExtensionGroup::Text),
+            "docs" => Ok(ExtensionGroup::Docs),
+            "notebooks" => Ok(ExtensionGroup::Notebooks),
+            _ => anyhow::bail!("Unknown extension group: {} (expected markdown, text, docs or notebooks)", group),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use scraper::{ElementRef, Html, Node, Selector};
+
+/// Elements whose content isn't part of the text of a page
+const SKIPPED_ELEMENTS: &[&str] = &["head", "script", "style", "noscript", "template", "svg", "iframe", "nav"];
+
+/// Elements starting a new block of text
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "dd", "details", "div", "dl", "dt", "figcaption",
+    "figure", "footer", "form", "header", "hr", "main", "ol", "p", "section", "summary", "table", "tr", "ul",
+];
+
+/// A web page converted to markdown, to be indexed like other documents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtmlPage {
+    /// Text of the `<title>` element, if any
+    pub title: Option<String>,
+    /// The text of the body, with headings, lists and code blocks in markdown
+    pub markdown: String,
+}
+
+/// Convert an HTML page to markdown, keeping its text, headings, list items
+/// and code blocks, so it's indexed and rendered like a markdown document
+///
+/// Scripts, styles and navigation are left out, and whitespace is collapsed
+/// outside of `<pre>` blocks.
+pub fn html_to_markdown(html: &str) -> HtmlPage {
+    let document = Html::parse_document(html);
+    let title_selector = Selector::parse("title").unwrap();
+    let title = document
+        .select(&title_selector)
+        .next()
+        .map(|title| collapse_whitespace(&title.text().collect::<String>()))
+        .filter(|title| !title.is_empty());
+
+    let mut writer = MarkdownWriter::default();
+    writer.element(document.root_element());
+    writer.end_block();
+
+    HtmlPage {
+        title,
+        markdown: writer.blocks.join("\n\n"),
+    }
+}
+
+/// Writes the markdown of an element tree, block by block
+#[derive(Default)]
+struct MarkdownWriter {
+    blocks: Vec<String>,
+    // Text of the block being written
+    line: String,
+}
+
+impl MarkdownWriter {
+    fn element(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        if SKIPPED_ELEMENTS.contains(&name) {
+            return;
+        }
+
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.end_block();
+                self.children(element);
+                let text = std::mem::take(&mut self.line);
+                let level = usize::from(name.as_bytes()[1] - b'0');
+                if !text.trim().is_empty() {
+                    self.blocks.push(format!("{} {}", "#".repeat(level), text.trim()));
+                }
+            }
+            "pre" => {
+                self.end_block();
+                let code: String = element.text().collect();
+                let code = code.trim_matches('\n');
+                if !code.trim().is_empty() {
+                    self.blocks.push(format!("```\n{}\n```", code));
+                }
+            }
+            "li" => {
+                self.end_block();
+                self.line.push_str("- ");
+                self.children(element);
+                self.end_block();
+            }
+            "code" => {
+                self.line.push('`');
+                self.children(element);
+                self.line.push('`');
+            }
+            "br" => self.end_block(),
+            "td" | "th" => {
+                if !self.line.trim().is_empty() {
+                    self.line.push_str(" | ");
+                }
+                self.children(element);
+            }
+            "img" => {
+                // Alt text describes diagrams and screenshots
+                if let Some(alt) = element.value().attr("alt").filter(|alt| !alt.trim().is_empty()) {
+                    self.text(alt);
+                }
+            }
+            name if BLOCK_ELEMENTS.contains(&name) => {
+                self.end_block();
+                self.children(element);
+                self.end_block();
+            }
+            _ => self.children(element),
+        }
+    }
+
+    fn children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Add inline text, collapsing runs of whitespace to a single space
+    fn text(&mut self, text: &str) {
+        let starts_with_space = text.starts_with(char::is_whitespace);
+        let ends_with_space = text.ends_with(char::is_whitespace);
+        let words = collapse_whitespace(text);
+        if (starts_with_space || words.is_empty()) && !self.line.is_empty() && !self.line.ends_with(' ') {
+            self.line.push(' ');
+        }
+        self.line.push_str(&words);
+        if ends_with_space && !words.is_empty() {
+            self.line.push(' ');
+        }
+    }
+
+    /// Finish the block being written, if it has any text
+    fn end_block(&mut self) {
+        let line = std::mem::take(&mut self.line);
+        let line = line.trim();
+        if !line.is_empty() && line != "-" {
+            self.blocks.push(line.to_string());
+        }
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
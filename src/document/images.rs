@@ -0,0 +1,107 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use super::{resolve_link, DocumentFormat};
+
+/// An image referenced by a document, such as a diagram or a screenshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageRef {
+    /// Source of the image, as written in the document
+    pub src: String,
+    /// Alternative text describing the image, empty if there is none
+    pub alt: String,
+    /// Whether the image is hosted outside of the repository
+    pub remote: bool,
+    /// Repository-relative path of a local image
+    pub path: Option<String>,
+}
+
+impl ImageRef {
+    fn new(document_path: &str, src: &str, alt: &str) -> Self {
+        let src = src.trim();
+        let remote = src.starts_with("//") || has_scheme(src);
+        Self {
+            src: src.to_string(),
+            alt: alt.trim().to_string(),
+            remote,
+            path: if remote { None } else { resolve_link(document_path, src) },
+        }
+    }
+}
+
+/// Extract the images of a markdown document, from `![alt](src)` and HTML `<img>` tags, in document order
+///
+/// Images referenced several times are listed once. Other formats have no images extracted.
+pub fn extract_images(document_path: &str, content: &str) -> Vec<ImageRef> {
+    if !matches!(DocumentFormat::from_path(document_path), DocumentFormat::Markdown | DocumentFormat::Notebook) {
+        return Vec::new();
+    }
+
+    let mut images: Vec<ImageRef> = Vec::new();
+    // Source of the markdown image being read, and its alt text so far
+    let mut current: Option<(String, String)> = None;
+    for event in Parser::new(content) {
+        match event {
+            Event::Start(Tag::Image { dest_url, .. }) => current = Some((dest_url.to_string(), String::new())),
+            Event::End(TagEnd::Image) => {
+                if let Some((src, alt)) = current.take() {
+                    push_unique(&mut images, ImageRef::new(document_path, &src, &alt));
+                }
+            }
+            Event::Text(text) | Event::Code(text) if current.is_some() => {
+                if let Some((_, alt)) = current.as_mut() {
+                    alt.push_str(&text);
+                }
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                for (src, alt) in html_images(&html) {
+                    push_unique(&mut images, ImageRef::new(document_path, &src, &alt));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    images
+}
+
+/// Add an image to a list unless it's already listed
+fn push_unique(images: &mut Vec<ImageRef>, image: ImageRef) {
+    if !images.contains(&image) {
+        images.push(image);
+    }
+}
+
+/// Extract the source and alt text of the `<img>` tags of an HTML fragment
+fn html_images(html: &str) -> Vec<(String, String)> {
+    static IMG_TAG: OnceLock<Regex> = OnceLock::new();
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    let img_tag = IMG_TAG.get_or_init(|| Regex::new(r"(?is)<img\b[^>]*>").unwrap());
+    let attribute = ATTRIBUTE
+        .get_or_init(|| Regex::new(r#"(?is)(?:^|\s)(src|alt)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap());
+
+    img_tag
+        .find_iter(html)
+        .filter_map(|tag| {
+            let mut src = None;
+            let mut alt = String::new();
+            for capture in attribute.captures_iter(tag.as_str()) {
+                let value = capture.get(2).or(capture.get(3)).or(capture.get(4)).map_or("", |m| m.as_str());
+                match capture[1].to_lowercase().as_str() {
+                    "src" => src = Some(value.to_string()),
+                    _ => alt = value.to_string(),
+                }
+            }
+            Some((src?, alt))
+        })
+        .collect()
+}
+
+/// Check whether an image source has a URL scheme, such as `https:` or `data:`
+fn has_scheme(src: &str) -> bool {
+    src.split_once(':').is_some_and(|(scheme, _)| {
+        !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
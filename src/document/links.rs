@@ -0,0 +1,97 @@
+use pulldown_cmark::{Event, Parser, Tag};
+
+use super::DocumentFormat;
+
+/// Resolve a link found in a document to the repository-relative path it points to
+///
+/// Relative targets are resolved against the directory of the document, and
+/// targets starting with `/` against the repository root. Anchors and query
+/// strings are dropped. Returns `None` for external links, anchors within the
+/// document, and targets outside the repository.
+pub fn resolve_link(document_path: &str, target: &str) -> Option<String> {
+    let target = target.trim();
+
+    // External links (`https://`, `mailto:`, ...) have a scheme
+    if let Some((scheme, _)) = target.split_once(':')
+        && !scheme.is_empty()
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    {
+        return None;
+    }
+
+    let target = target.split(['#', '?']).next().unwrap_or_default();
+    if target.is_empty() {
+        return None;
+    }
+    let target = percent_decode(target);
+
+    // Start from the directory of the document, or the root for absolute targets
+    let mut segments: Vec<&str> = if target.starts_with('/') {
+        Vec::new()
+    } else {
+        let mut directory: Vec<&str> = document_path.split('/').filter(|s| !s.is_empty() && *s != ".").collect();
+        directory.pop();
+        directory
+    };
+
+    for segment in target.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                // Links can't point above the repository root
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    if segments.is_empty() {
+        return None;
+    }
+    Some(segments.join("/"))
+}
+
+/// Extract the repository-relative paths of the documents linked from a markdown document
+///
+/// Links are deduplicated, in document order. Other formats have no links extracted.
+pub fn extract_links(document_path: &str, content: &str) -> Vec<String> {
+    if !matches!(DocumentFormat::from_path(document_path), DocumentFormat::Markdown | DocumentFormat::Notebook) {
+        return Vec::new();
+    }
+
+    let mut links = Vec::new();
+    for event in Parser::new(content) {
+        if let Event::Start(Tag::Link { dest_url, .. }) = event
+            && let Some(link) = resolve_link(document_path, &dest_url)
+            && !links.contains(&link)
+        {
+            links.push(link);
+        }
+    }
+
+    links
+}
+
+/// Decode `%XX` escapes (e.g. `%20` for spaces), leaving invalid escapes as is
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes[i] {
+            b'%' => text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| text.to_string())
+}
@@ -1,8 +1,48 @@
 use anyhow::Result;
-use pulldown_cmark::{html, Parser};
-use regex::Regex;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::sync::Arc;
+use walkdir::{DirEntry, WalkDir};
+
+mod asciidoc;
+mod definitions;
+mod extensions;
+mod html;
+mod images;
+mod links;
+mod notebook;
+mod openapi;
+mod plaintext;
+mod processor;
+mod render;
+mod restructuredtext;
+mod sniff;
+mod summary;
+mod visibility;
+mod whitespace;
+
+pub use definitions::{extract_definitions, Definition};
+pub use extensions::ExtensionGroup;
+pub use html::{html_to_markdown, HtmlPage};
+pub use images::{extract_images, ImageRef};
+pub use links::{extract_links, resolve_link};
+pub use notebook::{Notebook, NotebookCell};
+pub use openapi::{OpenApiSpec, Operation, Schema};
+pub use processor::{DefaultProcessor, DocumentProcessor};
+pub use render::{render, RenderFormat};
+pub use sniff::is_binary;
+pub use summary::split_sentences;
+pub use visibility::{front_matter_visibility, Visibility};
+pub use whitespace::ContentNormalization;
+
+/// Default approximate maximum length of generated summaries, in bytes
+pub const DEFAULT_SUMMARY_LENGTH: usize = 200;
+
+/// Default file names, without extension, too generic to tell documents apart
+/// when used as a fallback title
+pub const DEFAULT_GENERIC_FILENAMES: &[&str] = &["index", "readme"];
 
 /// Represents a document with its content and metadata
 #[derive(Debug, Clone)]
@@ -13,9 +53,132 @@ pub struct Document {
     pub summary: Option<String>,
 }
 
+/// A file a scan would index, with its size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedFile {
+    /// Path relative to the scanned directory or repository
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// A heading of a document outline
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    /// Heading level, 1 being the document title
+    pub level: usize,
+    pub text: String,
+}
+
+/// Markup format of a documentation file, derived from its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Markdown,
+    AsciiDoc,
+    ReStructuredText,
+    Notebook,
+    PlainText,
+    /// An OpenAPI or Swagger specification, in JSON or YAML
+    OpenApi,
+}
+
+impl DocumentFormat {
+    /// Detect the format of a file from its path
+    ///
+    /// Unknown extensions are treated as markdown, which is the most common
+    /// format for documentation.
+    pub fn from_path(path: &str) -> Self {
+        Self::detect(path).unwrap_or(DocumentFormat::Markdown)
+    }
+
+    /// Detect the format of a file from its path, none if its extension is unknown
+    pub fn detect(path: &str) -> Option<Self> {
+        if openapi::is_spec_path(path) {
+            return Some(DocumentFormat::OpenApi);
+        }
+
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+
+        match extension.as_deref()? {
+            "md" | "mdx" | "markdown" => Some(DocumentFormat::Markdown),
+            "adoc" | "asciidoc" => Some(DocumentFormat::AsciiDoc),
+            "rst" => Some(DocumentFormat::ReStructuredText),
+            "ipynb" => Some(DocumentFormat::Notebook),
+            "txt" => Some(DocumentFormat::PlainText),
+            _ => None,
+        }
+    }
+
+    /// Name of the format, as reported to clients
+    pub fn name(&self) -> &'static str {
+        match self {
+            DocumentFormat::Markdown => "markdown",
+            DocumentFormat::AsciiDoc => "asciidoc",
+            DocumentFormat::ReStructuredText => "restructuredtext",
+            DocumentFormat::Notebook => "notebook",
+            DocumentFormat::PlainText => "plaintext",
+            DocumentFormat::OpenApi => "openapi",
+        }
+    }
+
+    /// MIME type of the content stored for a document of this format
+    ///
+    /// Notebooks and specifications are stored converted to markdown.
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            DocumentFormat::Markdown | DocumentFormat::Notebook | DocumentFormat::OpenApi => "text/markdown",
+            DocumentFormat::AsciiDoc => "text/asciidoc",
+            DocumentFormat::ReStructuredText => "text/x-rst",
+            DocumentFormat::PlainText => "text/plain",
+        }
+    }
+}
+
+/// Extract the prose of a document, without markup or code, based on its format
+///
+/// Used where only natural language matters, such as language detection.
+pub fn prose(path: &str, content: &str) -> String {
+    match DocumentFormat::from_path(path) {
+        DocumentFormat::AsciiDoc => asciidoc::prose(content),
+        DocumentFormat::ReStructuredText => restructuredtext::prose(content),
+        _ => plaintext::markdown_to_prose(content),
+    }
+}
+
+/// Get the text of a document to extract keywords from, based on its format
+///
+/// reStructuredText loses its directive and role markup, so markers such as
+/// `note` or `ref` don't become keywords. Other formats are indexed as they are.
+pub fn keyword_text<'a>(path: &str, content: &'a str) -> Cow<'a, str> {
+    match DocumentFormat::from_path(path) {
+        DocumentFormat::ReStructuredText => Cow::Owned(restructuredtext::to_text(content)),
+        _ => Cow::Borrowed(content),
+    }
+}
+
 /// Document scanner that finds and processes documentation files
 pub struct DocumentScanner {
     supported_extensions: Vec<String>,
+    // Paths to scan, all of them if not set
+    include: Option<GlobSet>,
+    // Paths to skip, even if included
+    exclude: Option<GlobSet>,
+    // Approximate maximum length of generated summaries, in bytes
+    summary_length: usize,
+    // Whether symbolic links are followed when scanning directories
+    follow_symlinks: bool,
+    // Whether followed links may lead outside of the scanned directory
+    external_symlinks: bool,
+    // How raw content is normalized before it's processed
+    normalization: ContentNormalization,
+    // Lowercase file stems replaced by their directory name in fallback titles
+    generic_filenames: Vec<String>,
+    // Processors building documents from raw content, in the order they run,
+    // the default processor with the settings of the scanner if empty
+    processors: Vec<Arc<dyn DocumentProcessor>>,
 }
 
 impl DocumentScanner {
@@ -29,7 +192,131 @@ impl DocumentScanner {
                 "txt".to_string(),
                 "rst".to_string(),
                 "adoc".to_string(),
+                "ipynb".to_string(),
             ],
+            include: None,
+            exclude: None,
+            summary_length: DEFAULT_SUMMARY_LENGTH,
+            follow_symlinks: false,
+            external_symlinks: false,
+            normalization: ContentNormalization::default(),
+            generic_filenames: DEFAULT_GENERIC_FILENAMES.iter().map(|name| name.to_string()).collect(),
+            processors: Vec::new(),
+        }
+    }
+
+    /// Add a processor to rework the documents built by the current ones,
+    /// starting with the default processor
+    pub fn add_processor(&mut self, processor: Arc<dyn DocumentProcessor>) {
+        if self.processors.is_empty() {
+            self.processors.push(Arc::new(self.default_processor()));
+        }
+        self.processors.push(processor);
+    }
+
+    /// Replace the processors documents are built with, run in order, or go
+    /// back to the default processor if empty
+    ///
+    /// The first processor builds documents from the raw content of files, and
+    /// each following one reworks the documents built by the previous ones.
+    /// Include `default_processor` to keep extracting titles and summaries based
+    /// on the format of each file, after processors rewriting the raw content.
+    pub fn set_processors(&mut self, processors: Vec<Arc<dyn DocumentProcessor>>) {
+        self.processors = processors;
+    }
+
+    /// Get the processors documents are built with, empty for the default processor
+    pub fn processors(&self) -> &[Arc<dyn DocumentProcessor>] {
+        &self.processors
+    }
+
+    /// Get the processor building documents based on their format, with the
+    /// summary length and generic file names of the scanner
+    pub fn default_processor(&self) -> DefaultProcessor {
+        DefaultProcessor::new(self.summary_length, &self.generic_filenames)
+    }
+
+    /// Set the approximate maximum length of generated summaries, in bytes
+    pub fn set_summary_length(&mut self, summary_length: usize) {
+        self.summary_length = summary_length;
+    }
+
+    /// Get the approximate maximum length of generated summaries, in bytes
+    pub fn summary_length(&self) -> usize {
+        self.summary_length
+    }
+
+    /// Set how raw content is normalized before it's processed
+    ///
+    /// Line endings are converted to `\n` by default, so titles, summaries and
+    /// snippets don't depend on the platform files were written on.
+    pub fn set_normalization(&mut self, normalization: ContentNormalization) {
+        self.normalization = normalization;
+    }
+
+    /// Get how raw content is normalized before it's processed
+    pub fn normalization(&self) -> ContentNormalization {
+        self.normalization
+    }
+
+    /// Set the file names, without extension, too generic to be the title of a
+    /// document without one, such as `index` or `README`
+    ///
+    /// Documents with such a name are titled after their directory instead,
+    /// e.g. `Guides` for `guides/index.md`. Names are compared ignoring case.
+    pub fn set_generic_filenames(&mut self, generic_filenames: &[String]) {
+        self.generic_filenames = generic_filenames.iter().map(|name| name.to_lowercase()).collect();
+    }
+
+    /// Get the file names too generic to be the title of a document
+    pub fn generic_filenames(&self) -> &[String] {
+        &self.generic_filenames
+    }
+
+    /// Set whether symbolic links are followed when scanning directories, and
+    /// whether they may lead to files outside of the scanned directory
+    ///
+    /// Links to directories aren't followed by default, links to files are
+    /// scanned wherever they lead.
+    pub fn set_follow_symlinks(&mut self, follow_symlinks: bool, external_symlinks: bool) {
+        self.follow_symlinks = follow_symlinks;
+        self.external_symlinks = external_symlinks;
+    }
+
+    /// Restrict scanning to paths matching the include glob patterns (if any)
+    /// and not matching the exclude glob patterns
+    pub fn set_patterns(&mut self, include: &[String], exclude: &[String]) -> Result<()> {
+        self.include = Self::build_glob_set(include)?;
+        self.exclude = Self::build_glob_set(exclude)?;
+        Ok(())
+    }
+
+    fn build_glob_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern)?);
+        }
+        Ok(Some(builder.build()?))
+    }
+
+    /// Only support the extensions of the given groups, e.g. `.md`, `.mdx` and
+    /// `.markdown` for `markdown`, instead of the default ones
+    ///
+    /// Extensions can still be added one by one with `add_extension`. No groups
+    /// keep the current extensions.
+    pub fn set_extension_groups(&mut self, groups: &[ExtensionGroup]) {
+        if groups.is_empty() {
+            return;
+        }
+        self.supported_extensions.clear();
+        for extension in groups.iter().flat_map(|group| group.extensions()) {
+            if !self.supported_extensions.iter().any(|supported| supported == extension) {
+                self.supported_extensions.push(extension.to_string());
+            }
         }
     }
 
@@ -39,130 +326,229 @@ impl DocumentScanner {
     }
 
     /// Check if a file is a supported documentation file
+    ///
+    /// JSON and YAML files are only supported when named like API specifications.
     pub fn is_supported_file(&self, path: &Path) -> bool {
-        if let Some(extension) = path.extension() {
-            if let Some(ext_str) = extension.to_str() {
-                return self.supported_extensions.contains(&ext_str.to_lowercase());
-            }
+        if path.to_str().is_some_and(openapi::is_spec_path) {
+            return true;
         }
-        false
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| self.supported_extensions.contains(&extension.to_lowercase()))
+    }
+
+    /// Check if a path, relative to the scanned root, matches the include and exclude patterns
+    pub fn is_included(&self, relative_path: &Path) -> bool {
+        let included = self.include.as_ref().is_none_or(|include| include.is_match(relative_path));
+        let excluded = self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(relative_path));
+        included && !excluded
     }
 
     /// Scan a directory for documentation files
-    pub fn scan_directory(&self, dir_path: &Path) -> Result<Vec<Document>> {
+    ///
+    /// Documents are stored under their path relative to the directory, with `/`
+    /// separators like repository paths. `max_depth` limits how many levels of
+    /// subdirectories are scanned, 0 being the given directory only. Directories
+    /// reached again through symbolic links are scanned once, so link cycles
+    /// don't loop.
+    pub fn scan_directory(&self, dir_path: &Path, max_depth: Option<usize>) -> Result<Vec<Document>> {
         let mut documents = Vec::new();
-
-        for entry in WalkDir::new(dir_path).into_iter().filter_map(|e| e.ok()) {
-            let path = entry.path();
-            if path.is_file() && self.is_supported_file(path) {
-                if let Ok(doc) = self.process_file(path) {
-                    documents.push(doc);
+        for (path, relative_path) in self.walk_files(dir_path, max_depth)? {
+            let content = match std::fs::read(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    tracing::warn!("Failed to read file {}: {}", path.display(), e);
+                    continue;
                 }
+            };
+            // Files with a supported extension may still hold binary data
+            if is_binary(&content) {
+                tracing::warn!("Skipping binary file {}", path.display());
+                continue;
+            }
+            match String::from_utf8(content) {
+                Ok(content) => match self.process_content(&relative_path, content) {
+                    Ok(doc) => documents.push(doc),
+                    Err(e) => tracing::warn!("Failed to process file {}: {}", path.display(), e),
+                },
+                Err(e) => tracing::warn!("Failed to read file {}: {}", path.display(), e),
             }
         }
 
         Ok(documents)
     }
 
+    /// List the files a scan of a directory would index, with their sizes, without reading them
+    pub fn preview_directory(&self, dir_path: &Path, max_depth: Option<usize>) -> Result<Vec<ListedFile>> {
+        let mut files = Vec::new();
+        for (path, relative_path) in self.walk_files(dir_path, max_depth)? {
+            match std::fs::metadata(&path) {
+                Ok(metadata) => files.push(ListedFile {
+                    path: relative_path,
+                    size: metadata.len(),
+                }),
+                Err(e) => tracing::warn!("Failed to read metadata of {}: {}", path.display(), e),
+            }
+        }
+        Ok(files)
+    }
+
+    /// Walk a directory for the supported and included files a scan reads, with
+    /// their paths relative to the directory
+    fn walk_files(&self, dir_path: &Path, max_depth: Option<usize>) -> Result<Vec<(PathBuf, String)>> {
+        let mut files = Vec::new();
+        let root = dir_path.canonicalize()?;
+        let mut visited = HashSet::new();
+
+        // Files directly in the directory are one level below it for walkdir
+        let mut walker = WalkDir::new(dir_path).follow_links(self.follow_symlinks);
+        if let Some(max_depth) = max_depth {
+            walker = walker.max_depth(max_depth + 1);
+        }
+
+        let entries = walker.into_iter().filter_entry(|entry| self.should_walk(entry, &root, &mut visited));
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let relative_path = path.strip_prefix(dir_path).unwrap_or(path);
+            if path.is_file() && self.is_supported_file(path) && self.is_included(relative_path) {
+                let relative_path = relative_path
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((path.to_path_buf(), relative_path));
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Check whether a directory entry is scanned, skipping directories already
+    /// visited through another path when links are followed
+    ///
+    /// Without following links, linked files are still scanned, and linked
+    /// directories are never entered, so no cycle can arise.
+    fn should_walk(&self, entry: &DirEntry, root: &Path, visited: &mut HashSet<PathBuf>) -> bool {
+        if !self.follow_symlinks || (!entry.path_is_symlink() && !entry.file_type().is_dir()) {
+            return true;
+        }
+
+        // Links are followed, so compare where entries actually are
+        let Ok(canonical) = entry.path().canonicalize() else {
+            return false;
+        };
+        if !self.external_symlinks && !canonical.starts_with(root) {
+            tracing::debug!("Skipping {}, linked outside of the scanned directory", entry.path().display());
+            return false;
+        }
+        !entry.file_type().is_dir() || visited.insert(canonical)
+    }
+
     /// Process a single documentation file
     pub fn process_file(&self, file_path: &Path) -> Result<Document> {
         let content = std::fs::read_to_string(file_path)?;
         let relative_path = self.get_relative_path(file_path)?;
 
-        // Extract title from the content (first heading or filename)
-        let title = self.extract_title(&content)
-            .unwrap_or_else(|| {
-                file_path
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("Untitled")
-                    .to_string()
-            });
+        self.process_content(&relative_path, content)
+    }
+
+    /// Build a document from the raw content of a file with the processors of
+    /// the scanner, by default based on its extension
+    ///
+    /// Fails if the content looks binary, so it doesn't add noise to the keywords.
+    pub fn process_content(&self, path: &str, raw: String) -> Result<Document> {
+        if is_binary(raw.as_bytes()) {
+            anyhow::bail!("{} looks like a binary file", path);
+        }
+        let raw = self.normalization.apply(raw);
 
-        // Generate a summary if possible
-        let summary = self.generate_summary(&content);
+        let Some((first, rest)) = self.processors.split_first() else {
+            return processor::build_document(raw, path, self.summary_length, &self.generic_filenames);
+        };
+        let mut document = first.process(&raw, path)?;
+        for processor in rest {
+            document = processor.postprocess(document)?;
+        }
+        Ok(document)
+    }
 
-        Ok(Document {
-            path: relative_path,
-            content,
-            title,
-            summary,
-        })
+    /// Title a document without one after its filename, or after its directory
+    /// when the filename is generic, e.g. `Getting Started` for `getting-started/index.md`
+    pub fn fallback_title(&self, path: &str) -> String {
+        fallback_title(path, &self.generic_filenames)
     }
 
-    /// Extract the title from a markdown document (first heading)
+    /// Extract the title from a markdown document: the text of its first level 1 heading
+    ///
+    /// Closed headings (`# Title #`) lose their closing `#` run, headings inside
+    /// fenced code blocks are ignored, and a leading byte order mark is skipped.
     pub fn extract_title(&self, content: &str) -> Option<String> {
-        let heading_regex = Regex::new(r"(?m)^#\s+(.+)$").ok()?;
-        heading_regex.captures(content).map(|cap| cap[1].to_string())
+        extract_title(content)
     }
 
-    /// Generate a summary from the document content
-    pub fn generate_summary(&self, content: &str) -> Option<String> {
-        // Take the first paragraph that's not a heading
-        let paragraph_regex = Regex::new(r"(?m)^(?!#)(.+)$").ok()?;
-        let mut summary = String::new();
-
-        for cap in paragraph_regex.captures_iter(content) {
-            let line = cap[1].trim();
-            if !line.is_empty() {
-                summary.push_str(line);
-                summary.push(' ');
-
-                // Limit summary length
-                if summary.len() > 200 {
-                    summary.truncate(197);
-                    summary.push_str("...");
-                    break;
-                }
-            }
+    /// Extract the outline (title and section headings) of a document, based on its format
+    pub fn extract_outline(&self, path: &str, content: &str) -> Vec<Heading> {
+        match DocumentFormat::from_path(path) {
+            DocumentFormat::AsciiDoc => asciidoc::extract_headings(content),
+            DocumentFormat::ReStructuredText => restructuredtext::extract_headings(content),
+            _ => Self::markdown_headings(content),
         }
+    }
+
+    /// Extract the ATX headings of a markdown document, ignoring fenced code blocks
+    fn markdown_headings(content: &str) -> Vec<Heading> {
+        let mut headings = Vec::new();
+        let mut in_code_block = false;
+        let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+        for line in content.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+                continue;
+            }
+            if in_code_block {
+                continue;
+            }
+
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            if level == 0 || level > 6 || !trimmed[level..].starts_with(char::is_whitespace) {
+                continue;
+            }
 
-        if summary.is_empty() {
-            None
-        } else {
-            Some(summary.trim().to_string())
+            // Closed ATX headings end with a run of `#` after a space, unlike `# C#`
+            let text = trimmed[level..].trim();
+            let unclosed = text.trim_end_matches('#');
+            let text = if unclosed.is_empty() || unclosed.ends_with(char::is_whitespace) {
+                unclosed.trim_end()
+            } else {
+                text
+            };
+            if !text.is_empty() {
+                headings.push(Heading {
+                    level,
+                    text: text.to_string(),
+                });
+            }
         }
+
+        headings
+    }
+
+    /// Generate a summary from the document content
+    ///
+    /// The summary is made of the first complete sentences of the text outside
+    /// of headings and code blocks, up to about the configured summary length.
+    pub fn generate_summary(&self, content: &str) -> Option<String> {
+        generate_summary(content, self.summary_length)
     }
 
     /// Convert markdown to plain text
+    ///
+    /// Tables are rendered as aligned rows, links as `text (url)`, and lists keep
+    /// their numbering and nesting.
     pub fn markdown_to_text(&self, markdown: &str) -> String {
-        // Parse the markdown
-        let parser = Parser::new(markdown);
-
-        // Convert to HTML first
-        let mut html_output = String::new();
-        html::push_html(&mut html_output, parser);
-
-        // Simple HTML to text conversion (very basic)
-        let text = html_output
-            .replace("<p>", "")
-            .replace("</p>", "\n\n")
-            .replace("<h1>", "")
-            .replace("</h1>", "\n\n")
-            .replace("<h2>", "")
-            .replace("</h2>", "\n\n")
-            .replace("<h3>", "")
-            .replace("</h3>", "\n\n")
-            .replace("<h4>", "")
-            .replace("</h4>", "\n\n")
-            .replace("<h5>", "")
-            .replace("</h5>", "\n\n")
-            .replace("<h6>", "")
-            .replace("</h6>", "\n\n")
-            .replace("<ul>", "")
-            .replace("</ul>", "\n")
-            .replace("<li>", "- ")
-            .replace("</li>", "\n")
-            .replace("<code>", "`")
-            .replace("</code>", "`")
-            .replace("<pre>", "```\n")
-            .replace("</pre>", "\n```\n")
-            .replace("<em>", "*")
-            .replace("</em>", "*")
-            .replace("<strong>", "**")
-            .replace("</strong>", "**");
-
-        text
+        plaintext::markdown_to_text(markdown)
     }
 
     /// Get the relative path of a file from the current directory
@@ -178,6 +564,73 @@ impl DocumentScanner {
     }
 }
 
+/// Extract the text of the first level 1 heading of a markdown document
+fn extract_title(content: &str) -> Option<String> {
+    DocumentScanner::markdown_headings(content)
+        .into_iter()
+        .find(|heading| heading.level == 1)
+        .map(|heading| heading.text)
+}
+
+/// Title a document after its filename, or after its directory when the
+/// filename is one of the given generic names, in lowercase
+fn fallback_title(path: &str, generic_filenames: &[String]) -> String {
+    let path = Path::new(path);
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return "Untitled".to_string();
+    };
+    if !generic_filenames.contains(&stem.to_lowercase()) {
+        return stem.to_string();
+    }
+
+    // Documents at the root keep their filename, there's no directory to tell them apart
+    match path.parent().and_then(|parent| parent.file_name()).and_then(|name| name.to_str()) {
+        Some(directory) => directory
+            .split(['-', '_', ' '])
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let mut chars = word.chars();
+                chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => stem.to_string(),
+    }
+}
+
+/// Summarize content with its first complete sentences outside of headings
+/// and code blocks, up to about `summary_length` bytes
+fn generate_summary(content: &str, summary_length: usize) -> Option<String> {
+    let mut text = String::new();
+    let mut in_code_block = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with("```") || line.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        text.push_str(line);
+        text.push(' ');
+
+        // Enough text to fill the summary with whole sentences
+        if text.len() > summary_length * 2 {
+            break;
+        }
+    }
+
+    let text = text.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(summary::truncate_at_sentence(text, summary_length))
+    }
+}
+
 impl Default for DocumentScanner {
     fn default() -> Self {
         Self::new()
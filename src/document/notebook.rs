@@ -0,0 +1,126 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+/// A Jupyter notebook cell relevant for indexing
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotebookCell {
+    /// Prose written in markdown
+    Markdown(String),
+    /// Source code
+    Code(String),
+}
+
+/// The indexable parts of a Jupyter notebook
+#[derive(Debug, Clone)]
+pub struct Notebook {
+    pub cells: Vec<NotebookCell>,
+    /// The kernel language, used to tag code blocks
+    pub language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawNotebook {
+    cells: Vec<RawCell>,
+    #[serde(default)]
+    metadata: RawMetadata,
+}
+
+#[derive(Deserialize)]
+struct RawCell {
+    cell_type: String,
+    #[serde(default)]
+    source: RawSource,
+}
+
+/// Cell sources are stored either as a single string or as a list of lines
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSource {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+impl Default for RawSource {
+    fn default() -> Self {
+        RawSource::Text(String::new())
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct RawMetadata {
+    kernelspec: Option<RawKernelSpec>,
+    language_info: Option<RawLanguageInfo>,
+}
+
+#[derive(Deserialize)]
+struct RawKernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawLanguageInfo {
+    name: Option<String>,
+}
+
+impl Notebook {
+    /// Parse a notebook from its JSON representation, skipping outputs and execution counts
+    pub fn parse(raw: &str) -> Result<Self> {
+        let notebook: RawNotebook = serde_json::from_str(raw)?;
+
+        let language = notebook
+            .metadata
+            .language_info
+            .and_then(|info| info.name)
+            .or_else(|| notebook.metadata.kernelspec.and_then(|spec| spec.language));
+
+        let cells = notebook
+            .cells
+            .into_iter()
+            .filter_map(|cell| {
+                let source = match cell.source {
+                    RawSource::Text(text) => text,
+                    RawSource::Lines(lines) => lines.concat(),
+                };
+
+                if source.trim().is_empty() {
+                    return None;
+                }
+
+                match cell.cell_type.as_str() {
+                    "markdown" => Some(NotebookCell::Markdown(source)),
+                    "code" => Some(NotebookCell::Code(source)),
+                    // Raw cells are not rendered, so they aren't documentation
+                    _ => None,
+                }
+            })
+            .collect();
+
+        Ok(Self { cells, language })
+    }
+
+    /// Concatenate the markdown cells only
+    pub fn markdown(&self) -> String {
+        self.cells
+            .iter()
+            .filter_map(|cell| match cell {
+                NotebookCell::Markdown(text) => Some(text.trim_end()),
+                NotebookCell::Code(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Render the notebook as markdown, with code cells as fenced code blocks
+    pub fn to_markdown(&self) -> String {
+        let language = self.language.as_deref().unwrap_or("");
+
+        self.cells
+            .iter()
+            .map(|cell| match cell {
+                NotebookCell::Markdown(text) => text.trim_end().to_string(),
+                NotebookCell::Code(code) => format!("```{}\n{}\n```", language, code.trim_end()),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
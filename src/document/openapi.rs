@@ -0,0 +1,238 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::path::Path;
+use yaml_rust2::{Yaml, YamlLoader};
+
+/// HTTP methods of the operations of a path item, in the order they're listed
+const METHODS: &[&str] = &["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+/// An operation of an API, on one path with one method
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operation {
+    /// Uppercase HTTP method
+    pub method: String,
+    pub path: String,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub operation_id: Option<String>,
+    /// Names of the parameters, with their description if any
+    pub parameters: Vec<(String, Option<String>)>,
+}
+
+/// A named schema of the request and response bodies of an API
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    pub name: String,
+    pub description: Option<String>,
+    /// Names of the properties, with their description if any
+    pub properties: Vec<(String, Option<String>)>,
+}
+
+/// The documentation of an OpenAPI 3 or Swagger 2 specification
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenApiSpec {
+    /// The `info.title` of the API
+    pub title: Option<String>,
+    /// The `info.description` of the API
+    pub description: Option<String>,
+    pub operations: Vec<Operation>,
+    pub schemas: Vec<Schema>,
+}
+
+/// Check whether a path names an OpenAPI or Swagger specification: a JSON or
+/// YAML file with `openapi` or `swagger` in its name, such as `openapi.yaml`
+/// or `petstore.swagger.json`
+///
+/// Other JSON and YAML files, such as CI configurations, aren't documentation.
+pub fn is_spec_path(path: &str) -> bool {
+    let path = Path::new(path);
+    let extension = path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase());
+    if !matches!(extension.as_deref(), Some("json") | Some("yaml") | Some("yml")) {
+        return false;
+    }
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_lowercase();
+    stem.contains("openapi") || stem.contains("swagger")
+}
+
+impl OpenApiSpec {
+    /// Parse a specification from its JSON or YAML text, based on the extension of its path
+    ///
+    /// Fails if the document has no `openapi` or `swagger` version field, so
+    /// arbitrary files named like specifications aren't indexed.
+    pub fn parse(path: &str, raw: &str) -> Result<Self> {
+        let is_json = path.to_lowercase().ends_with(".json");
+        let spec: Value = if is_json {
+            serde_json::from_str(raw)?
+        } else {
+            let documents = YamlLoader::load_from_str(raw)?;
+            documents.first().map(yaml_to_json).unwrap_or_default()
+        };
+
+        if spec.get("openapi").is_none() && spec.get("swagger").is_none() {
+            anyhow::bail!("{} is not an OpenAPI or Swagger specification", path);
+        }
+
+        let info = spec.get("info");
+        let operations = spec
+            .get("paths")
+            .and_then(Value::as_object)
+            .map(|paths| paths.iter().flat_map(|(path, item)| parse_operations(path, item)).collect())
+            .unwrap_or_default();
+
+        // OpenAPI 3 keeps schemas under components, Swagger 2 under definitions
+        let schemas = spec
+            .pointer("/components/schemas")
+            .or_else(|| spec.get("definitions"))
+            .and_then(Value::as_object)
+            .map(|schemas| schemas.iter().map(|(name, schema)| parse_schema(name, schema)).collect())
+            .unwrap_or_default();
+
+        Ok(Self {
+            title: text(info, "title"),
+            description: text(info, "description"),
+            operations,
+            schemas,
+        })
+    }
+
+    /// Render the specification as markdown, with a section per operation and schema
+    pub fn to_markdown(&self) -> String {
+        let mut sections = Vec::new();
+        if let Some(title) = &self.title {
+            sections.push(format!("# {}", title));
+        }
+        if let Some(description) = &self.description {
+            sections.push(description.trim().to_string());
+        }
+
+        for operation in &self.operations {
+            let mut section = format!("## {} {}", operation.method, operation.path);
+            for text in [&operation.summary, &operation.description].into_iter().flatten() {
+                section.push_str(&format!("\n\n{}", text.trim()));
+            }
+            if let Some(operation_id) = &operation.operation_id {
+                section.push_str(&format!("\n\nOperation ID: `{}`", operation_id));
+            }
+            if !operation.parameters.is_empty() {
+                section.push_str(&format!("\n\nParameters:\n\n{}", list(&operation.parameters)));
+            }
+            sections.push(section);
+        }
+
+        if !self.schemas.is_empty() {
+            sections.push("## Schemas".to_string());
+        }
+        for schema in &self.schemas {
+            let mut section = format!("### {}", schema.name);
+            if let Some(description) = &schema.description {
+                section.push_str(&format!("\n\n{}", description.trim()));
+            }
+            if !schema.properties.is_empty() {
+                section.push_str(&format!("\n\n{}", list(&schema.properties)));
+            }
+            sections.push(section);
+        }
+
+        sections.join("\n\n")
+    }
+}
+
+/// Parse the operations of a path item, one per HTTP method
+fn parse_operations(path: &str, item: &Value) -> Vec<Operation> {
+    METHODS
+        .iter()
+        .filter_map(|method| {
+            let operation = item.get(*method)?;
+            Some(Operation {
+                method: method.to_uppercase(),
+                path: path.to_string(),
+                summary: text(Some(operation), "summary"),
+                description: text(Some(operation), "description"),
+                operation_id: text(Some(operation), "operationId"),
+                parameters: operation
+                    .get("parameters")
+                    .and_then(Value::as_array)
+                    .map(|parameters| {
+                        parameters
+                            .iter()
+                            .filter_map(|parameter| Some((text(Some(parameter), "name")?, text(Some(parameter), "description"))))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Parse a named schema with the descriptions of its properties
+fn parse_schema(name: &str, schema: &Value) -> Schema {
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| (name.clone(), text(Some(property), "description")))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Schema {
+        name: name.to_string(),
+        description: text(Some(schema), "description"),
+        properties,
+    }
+}
+
+/// Get a non-empty string field of an object
+fn text(object: Option<&Value>, field: &str) -> Option<String> {
+    object?
+        .get(field)?
+        .as_str()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// Render named items as a markdown list, with their description if any
+fn list(items: &[(String, Option<String>)]) -> String {
+    items
+        .iter()
+        .map(|(name, description)| match description {
+            Some(description) => format!("- `{}`: {}", name, description),
+            None => format!("- `{}`", name),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Convert a YAML document to JSON, so both spec formats are read the same way
+///
+/// Only text is indexed, so real numbers keep their written form as strings.
+fn yaml_to_json(yaml: &Yaml) -> Value {
+    match yaml {
+        Yaml::String(text) | Yaml::Real(text) => Value::String(text.clone()),
+        Yaml::Integer(number) => Value::from(*number),
+        Yaml::Boolean(value) => Value::Bool(*value),
+        Yaml::Array(items) => Value::Array(items.iter().map(yaml_to_json).collect()),
+        Yaml::Hash(hash) => {
+            let object: Map<String, Value> = hash
+                .iter()
+                .filter_map(|(key, value)| Some((yaml_key(key)?, yaml_to_json(value))))
+                .collect();
+            Value::Object(object)
+        }
+        _ => Value::Null,
+    }
+}
+
+/// Convert a YAML mapping key to a JSON object key, such as `200` for a response code
+fn yaml_key(key: &Yaml) -> Option<String> {
+    match key {
+        Yaml::String(text) | Yaml::Real(text) => Some(text.clone()),
+        Yaml::Integer(number) => Some(number.to_string()),
+        Yaml::Boolean(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
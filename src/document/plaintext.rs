@@ -0,0 +1,223 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// A table being collected, rendered once all its rows are known
+#[derive(Default)]
+struct Table {
+    rows: Vec<Vec<String>>,
+    row: Vec<String>,
+    cell: String,
+}
+
+impl Table {
+    /// Render the rows with columns padded to the same width
+    fn render(&self) -> String {
+        let columns = self.rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        let mut lines = Vec::with_capacity(self.rows.len() + 1);
+        for (index, row) in self.rows.iter().enumerate() {
+            let cells: Vec<String> = widths
+                .iter()
+                .enumerate()
+                .map(|(column, width)| {
+                    let cell = row.get(column).map(String::as_str).unwrap_or("");
+                    format!("{:width$}", cell, width = width)
+                })
+                .collect();
+            lines.push(cells.join(" | ").trim_end().to_string());
+
+            // Separate the header from the body
+            if index == 0 {
+                let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+                lines.push(rule.join("-|-"));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Converts the markdown event stream to plain text
+#[derive(Default)]
+struct TextWriter {
+    output: String,
+    // Next item number of each open list, `None` for bullet lists
+    lists: Vec<Option<u64>>,
+    // Destination of each open link, with the position where its text starts
+    links: Vec<(String, usize)>,
+    table: Option<Table>,
+    // Drop code blocks and inline code, keeping only the prose
+    prose_only: bool,
+    in_code_block: bool,
+}
+
+impl TextWriter {
+    /// The buffer text currently goes to
+    fn buffer(&mut self) -> &mut String {
+        match &mut self.table {
+            Some(table) => &mut table.cell,
+            None => &mut self.output,
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        if self.in_code_block {
+            return;
+        }
+        self.buffer().push_str(text);
+    }
+
+    /// Make sure the output ends with a line break
+    fn end_line(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+    }
+
+    /// Make sure the output ends with a blank line, unless inside a list
+    fn end_block(&mut self) {
+        self.end_line();
+        if self.lists.is_empty() && !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::List(start) => {
+                self.end_line();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.end_line();
+                let depth = self.lists.len().saturating_sub(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.output.push_str(&"  ".repeat(depth));
+                self.output.push_str(&marker);
+            }
+            Tag::CodeBlock(_) => {
+                self.end_line();
+                if self.prose_only {
+                    self.in_code_block = true;
+                } else {
+                    self.output.push_str("```\n");
+                }
+            }
+            Tag::Table(_) => {
+                self.end_block();
+                self.table = Some(Table::default());
+            }
+            Tag::Link { dest_url, .. } => {
+                let start = self.buffer().len();
+                self.links.push((dest_url.to_string(), start));
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::BlockQuote(_) | TagEnd::HtmlBlock => {
+                self.end_block();
+            }
+            TagEnd::List(_) => {
+                self.lists.pop();
+                self.end_block();
+            }
+            TagEnd::Item => self.end_line(),
+            TagEnd::CodeBlock => {
+                if self.prose_only {
+                    self.in_code_block = false;
+                } else {
+                    self.end_line();
+                    self.output.push_str("```");
+                }
+                self.end_block();
+            }
+            TagEnd::TableCell => {
+                if let Some(table) = &mut self.table {
+                    let cell = std::mem::take(&mut table.cell);
+                    table.row.push(cell.trim().to_string());
+                }
+            }
+            // Header cells aren't wrapped in a row
+            TagEnd::TableHead | TagEnd::TableRow => {
+                if let Some(table) = &mut self.table {
+                    let row = std::mem::take(&mut table.row);
+                    table.rows.push(row);
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.output.push_str(&table.render());
+                    self.end_block();
+                }
+            }
+            TagEnd::Link => {
+                if let Some((dest_url, start)) = self.links.pop() {
+                    // Autolinks already show their destination as text
+                    let text = self.buffer()[start..].to_string();
+                    if !dest_url.is_empty() && text != dest_url {
+                        self.push(&format!(" ({})", dest_url));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Convert markdown to plain text by walking the parsed event stream
+pub fn markdown_to_text(markdown: &str) -> String {
+    render(markdown, TextWriter::default())
+}
+
+/// Convert markdown to plain text without code blocks or inline code
+pub fn markdown_to_prose(markdown: &str) -> String {
+    render(markdown, TextWriter {
+        prose_only: true,
+        ..TextWriter::default()
+    })
+}
+
+fn render(markdown: &str, mut writer: TextWriter) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(tag) => writer.start(tag),
+            Event::End(tag) => writer.end(tag),
+            Event::Text(text) | Event::InlineMath(text) | Event::DisplayMath(text) => writer.push(&text),
+            Event::Code(_) if writer.prose_only => {}
+            Event::Code(code) => writer.push(&format!("`{}`", code)),
+            Event::SoftBreak => writer.push(" "),
+            Event::HardBreak => writer.push("\n"),
+            Event::Rule => {
+                writer.end_line();
+                writer.output.push_str("---");
+                writer.end_block();
+            }
+            Event::TaskListMarker(checked) => writer.push(if checked { "[x] " } else { "[ ] " }),
+            Event::FootnoteReference(label) => writer.push(&format!("[{}]", label)),
+            // Raw HTML has no plain text representation
+            Event::Html(_) | Event::InlineHtml(_) => {}
+        }
+    }
+
+    writer.output.trim_end().to_string()
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use std::fmt::Debug;
+
+use super::{asciidoc, restructuredtext, Document, DocumentFormat, Notebook, OpenApiSpec, DEFAULT_GENERIC_FILENAMES, DEFAULT_SUMMARY_LENGTH};
+
+/// Builds a document from the raw content of a file
+///
+/// Scanners run their processors in order on each file: the first one builds
+/// the document from the raw content of the file, normalized, and each
+/// following one reworks the document built by the previous ones. The
+/// document built by the last one is kept. Processors placed before a
+/// `DefaultProcessor` can rewrite the raw content, e.g. to expand
+/// `{% include %}` directives, and processors placed after it can adjust the
+/// title or summary it extracted by overriding `postprocess`.
+pub trait DocumentProcessor: Debug + Send + Sync {
+    /// Build a document from the raw content of the file at `path`
+    fn process(&self, raw: &str, path: &str) -> Result<Document>;
+
+    /// Rework the document built by the previous processors of a scanner
+    ///
+    /// By default, the document is built again from its content, as a
+    /// processor running on the content rewritten by the previous ones.
+    fn postprocess(&self, document: Document) -> Result<Document> {
+        self.process(&document.content, &document.path)
+    }
+}
+
+/// Builds documents based on their format: notebooks and API specifications
+/// are converted to markdown, and the title and summary are extracted from
+/// the markup of each format
+///
+/// Documents without a title are titled after their path.
+#[derive(Debug, Clone)]
+pub struct DefaultProcessor {
+    // Approximate maximum length of generated summaries, in bytes
+    summary_length: usize,
+    // Lowercase file stems replaced by their directory name in fallback titles
+    generic_filenames: Vec<String>,
+}
+
+impl DefaultProcessor {
+    /// Create a processor generating summaries of about `summary_length`
+    /// bytes, and titling documents named after one of the generic file names
+    /// after their directory
+    pub fn new(summary_length: usize, generic_filenames: &[String]) -> Self {
+        Self {
+            summary_length,
+            generic_filenames: generic_filenames.iter().map(|name| name.to_lowercase()).collect(),
+        }
+    }
+}
+
+impl Default for DefaultProcessor {
+    fn default() -> Self {
+        Self {
+            summary_length: DEFAULT_SUMMARY_LENGTH,
+            generic_filenames: DEFAULT_GENERIC_FILENAMES.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+impl DocumentProcessor for DefaultProcessor {
+    fn process(&self, raw: &str, path: &str) -> Result<Document> {
+        build_document(raw.to_string(), path, self.summary_length, &self.generic_filenames)
+    }
+}
+
+/// Build a document based on its format, as the default processor does
+pub(super) fn build_document(raw: String, path: &str, summary_length: usize, generic_filenames: &[String]) -> Result<Document> {
+    let summarize = |content: &str| super::generate_summary(content, summary_length);
+
+    let (content, title, summary) = match DocumentFormat::from_path(path) {
+        // Notebooks are indexed as markdown, with title and summary from the prose only
+        DocumentFormat::Notebook => {
+            let notebook = Notebook::parse(&raw)?;
+            let prose = notebook.markdown();
+            (notebook.to_markdown(), super::extract_title(&prose), summarize(&prose))
+        }
+        // Specifications are indexed as markdown, with a section per operation and schema
+        DocumentFormat::OpenApi => {
+            let spec = OpenApiSpec::parse(path, &raw)?;
+            let markdown = spec.to_markdown();
+            let summary = summarize(spec.description.as_deref().unwrap_or(&markdown));
+            (markdown, spec.title, summary)
+        }
+        DocumentFormat::AsciiDoc => {
+            let title = asciidoc::extract_title(&raw);
+            let summary = summarize(&asciidoc::prose(&raw));
+            (raw, title, summary)
+        }
+        DocumentFormat::ReStructuredText => {
+            let title = restructuredtext::extract_title(&raw);
+            let summary = summarize(&restructuredtext::prose(&raw));
+            (raw, title, summary)
+        }
+        _ => {
+            let title = super::extract_title(&raw);
+            let summary = summarize(&raw);
+            (raw, title, summary)
+        }
+    };
+
+    let title = title.unwrap_or_else(|| super::fallback_title(path, generic_filenames));
+
+    Ok(Document {
+        path: path.to_string(),
+        content,
+        title,
+        summary,
+    })
+}
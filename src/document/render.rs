@@ -0,0 +1,88 @@
+use anyhow::Result;
+use pulldown_cmark::{html, Options, Parser};
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use super::{plaintext, restructuredtext, DocumentFormat};
+
+/// Representation of the content of a document served to clients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderFormat {
+    /// The content as stored, markdown for most documents
+    #[default]
+    Raw,
+    /// Plain text, without markup
+    Text,
+    /// An HTML fragment, for clients rendering HTML but not markdown
+    Html,
+}
+
+impl RenderFormat {
+    /// Name of the format, as given by clients
+    pub fn name(&self) -> &'static str {
+        match self {
+            RenderFormat::Raw => "raw",
+            RenderFormat::Text => "text",
+            RenderFormat::Html => "html",
+        }
+    }
+}
+
+impl FromStr for RenderFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format.trim().to_lowercase().as_str() {
+            "raw" => Ok(RenderFormat::Raw),
+            "text" => Ok(RenderFormat::Text),
+            "html" => Ok(RenderFormat::Html),
+            _ => anyhow::bail!("Unsupported content format: {}", format),
+        }
+    }
+}
+
+/// Render the content of a document in the given format
+///
+/// Markdown, notebooks and specifications, which are stored as markdown, are
+/// converted, and reStructuredText loses its markup as text. Other formats have
+/// no markup to convert, so their text is kept as is, in a `<pre>` block for HTML.
+pub fn render<'a>(path: &str, content: &'a str, format: RenderFormat) -> Cow<'a, str> {
+    let document_format = DocumentFormat::from_path(path);
+    let markdown = matches!(
+        document_format,
+        DocumentFormat::Markdown | DocumentFormat::Notebook | DocumentFormat::OpenApi
+    );
+    match format {
+        RenderFormat::Raw => Cow::Borrowed(content),
+        RenderFormat::Text if markdown => Cow::Owned(plaintext::markdown_to_text(content)),
+        RenderFormat::Text if document_format == DocumentFormat::ReStructuredText => {
+            Cow::Owned(restructuredtext::to_text(content))
+        }
+        RenderFormat::Text => Cow::Borrowed(content),
+        RenderFormat::Html if markdown => Cow::Owned(markdown_to_html(content)),
+        RenderFormat::Html => Cow::Owned(format!("<pre>{}</pre>\n", escape_html(content))),
+    }
+}
+
+/// Convert markdown to an HTML fragment, with the extensions the scanner parses
+fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut output = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut output, Parser::new_ext(markdown, options));
+    output
+}
+
+/// Escape the characters of text with a meaning in HTML
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
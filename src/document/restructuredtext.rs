@@ -0,0 +1,233 @@
+use regex::{Captures, Regex};
+use std::sync::OnceLock;
+
+use super::Heading;
+
+/// Characters section titles can be underlined (and overlined) with
+const ADORNMENT_CHARS: &str = "=-`:'\"~^_*+#<>.!$%&(),/;?@[\\]{|}";
+
+/// Directives whose argument is a path or a URL rather than text
+const URI_DIRECTIVES: [&str; 5] = ["image", "figure", "include", "literalinclude", "raw"];
+
+/// Directives whose content is code rather than prose
+const CODE_DIRECTIVES: [&str; 5] = ["code", "code-block", "sourcecode", "literalinclude", "highlight"];
+
+/// Check whether a line is made of a single repeated adornment character, such
+/// as the underline of a section title or a transition
+fn is_adornment(line: &str) -> bool {
+    let line = line.trim_end();
+    let mut chars = line.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    line.len() >= 2 && ADORNMENT_CHARS.contains(first) && chars.all(|c| c == first)
+}
+
+/// Check whether a line is a border of a grid or simple table, e.g. `+----+----+` or `====  ====`
+fn is_table_border(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 2 && line.contains(['-', '=']) && line.chars().all(|c| matches!(c, '+' | '-' | '=' | ' '))
+}
+
+/// Find the section titles of a document, with the line they're on and the
+/// lines of their adornment, in document order
+///
+/// Levels follow the order adornment styles first appear in, the first style
+/// being the document title, as in reStructuredText itself.
+fn sections(lines: &[&str]) -> Vec<(usize, Heading, Vec<usize>)> {
+    let mut styles: Vec<(char, bool)> = Vec::new();
+    let mut sections = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let blank_before = index == 0 || lines[index - 1].trim().is_empty();
+        let candidate = if !blank_before {
+            None
+        } else if is_adornment(lines[index])
+            && lines.get(index + 2).is_some_and(|under| under.trim_end() == lines[index].trim_end())
+            && lines.get(index + 1).is_some_and(|text| !text.trim().is_empty() && !is_adornment(text))
+        {
+            // Overlined title, whose text may be inset
+            Some((index + 1, lines[index + 1].trim(), vec![index, index + 2], true))
+        } else if lines.get(index + 1).is_some_and(|under| is_underline(lines[index], under)) {
+            Some((index, lines[index].trim(), vec![index + 1], false))
+        } else {
+            None
+        };
+
+        match candidate {
+            Some((line, text, adornment, overlined)) => {
+                let character = lines[adornment[0]].chars().next().unwrap_or('=');
+                let style = (character, overlined);
+                let level = match styles.iter().position(|known| *known == style) {
+                    Some(position) => position + 1,
+                    None => {
+                        styles.push(style);
+                        styles.len()
+                    }
+                };
+                index = adornment[adornment.len() - 1] + 1;
+                sections.push((line, Heading { level: level.min(6), text: text.to_string() }, adornment));
+            }
+            None => index += 1,
+        }
+    }
+
+    sections
+}
+
+/// Check whether a line of text is underlined by the next one, making it a section title
+fn is_underline(text: &str, underline: &str) -> bool {
+    let title_length = text.trim().chars().count();
+    !text.starts_with(char::is_whitespace)
+        && title_length > 0
+        && !is_adornment(text)
+        && is_adornment(underline)
+        && (underline.trim_end().len() >= title_length || underline.trim_end().len() >= 3)
+}
+
+/// Extract the document title: the first section title
+pub fn extract_title(content: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    sections(&lines).into_iter().next().map(|(_, heading, _)| heading.text)
+}
+
+/// Extract the document title and section titles, in document order
+pub fn extract_headings(content: &str) -> Vec<Heading> {
+    let lines: Vec<&str> = content.lines().collect();
+    sections(&lines).into_iter().map(|(_, heading, _)| heading).collect()
+}
+
+/// Convert a document to plain text, keeping section titles and the content of
+/// directives but none of their markup
+///
+/// Directive markers (`.. note::`), options (`:maxdepth: 2`), comments, targets
+/// and substitution definitions are dropped, and roles and references keep only
+/// their text, so `` :ref:`the guide <guide>` `` becomes `the guide`.
+pub fn to_text(content: &str) -> String {
+    convert(content, true)
+}
+
+/// Extract the prose of the document, without section titles, markup or code
+pub fn prose(content: &str) -> String {
+    convert(content, false)
+}
+
+fn convert(content: &str, keep_titles: bool) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let sections = sections(&lines);
+    let mut output = Vec::with_capacity(lines.len());
+    // Whether the indented lines being read are the content of a code block
+    let mut in_code = false;
+    // Whether the indented field lists being read are the options of a directive
+    let mut in_options = false;
+
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if in_options {
+            if line.starts_with(char::is_whitespace) && field_marker(trimmed).is_some() {
+                continue;
+            }
+            in_options = false;
+        }
+        if in_code {
+            if trimmed.is_empty() || line.starts_with(char::is_whitespace) {
+                continue;
+            }
+            in_code = false;
+        }
+
+        if let Some((_, heading, _)) = sections.iter().find(|(title_line, _, _)| *title_line == index) {
+            if keep_titles {
+                output.push(inline_text(&heading.text));
+            }
+            continue;
+        }
+        if sections.iter().any(|(_, _, adornment)| adornment.contains(&index)) || is_adornment(trimmed) || is_table_border(trimmed) {
+            continue;
+        }
+
+        // Explicit markup: directives, comments, targets, footnotes and substitutions
+        if let Some(markup) = trimmed.strip_prefix("..").filter(|rest| rest.is_empty() || rest.starts_with(' ')) {
+            let markup = markup.trim();
+            if let Some((name, argument)) = directive(markup) {
+                // The argument of a code directive is its language
+                let code = CODE_DIRECTIVES.contains(&name.as_str());
+                in_code = !keep_titles && code;
+                in_options = true;
+                if !code && !URI_DIRECTIVES.contains(&name.as_str()) && !argument.is_empty() {
+                    output.push(inline_text(argument));
+                }
+            } else if let Some(footnote) = markup.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+                output.push(inline_text(footnote.1.trim()));
+            }
+            continue;
+        }
+
+        // Other field lists, such as bibliographic fields, keep their value
+        let text = match field_marker(trimmed) {
+            Some(value) => value,
+            None => trimmed,
+        };
+
+        // A paragraph ending with `::` introduces a literal block
+        if text.ends_with("::") {
+            in_code = !keep_titles;
+            let text = text.trim_end_matches(':').trim_end();
+            if !text.is_empty() {
+                output.push(format!("{}:", inline_text(text)));
+            }
+            continue;
+        }
+
+        output.push(if text.is_empty() { String::new() } else { inline_text(text) });
+    }
+
+    output.join("\n")
+}
+
+/// Parse a directive (`name:: argument`, after the leading `..`) into its lowercase name and argument
+fn directive(markup: &str) -> Option<(String, &str)> {
+    let (name, argument) = markup.split_once("::")?;
+    // Substitution definitions (`|name| image:: logo.png`) hold a directive too
+    let name = match name.strip_prefix('|') {
+        Some(substitution) => substitution.split_once('|')?.1.trim(),
+        None => name,
+    };
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.' | '+'));
+    valid.then(|| (name.to_lowercase(), argument.trim()))
+}
+
+/// Get the value of a field list item (`:name: value`), if the line is one
+fn field_marker(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix(':')?;
+    let (name, value) = rest.split_once(':')?;
+    let valid = !name.is_empty() && !name.contains('`') && !name.starts_with(' ');
+    (valid && (value.is_empty() || value.starts_with(' '))).then(|| value.trim())
+}
+
+/// Replace the inline markup of a line by its text: literals, roles and references
+fn inline_text(line: &str) -> String {
+    static LITERAL: OnceLock<Regex> = OnceLock::new();
+    static ROLE: OnceLock<Regex> = OnceLock::new();
+    static REFERENCE: OnceLock<Regex> = OnceLock::new();
+    let literal = LITERAL.get_or_init(|| Regex::new(r"``([^`]+)``").unwrap());
+    let role = ROLE.get_or_init(|| Regex::new(r"(?::[\w.+-]+)+:`([^`]+)`").unwrap());
+    let reference = REFERENCE.get_or_init(|| Regex::new(r"`([^`]+)`(?:__?)?").unwrap());
+
+    let line = literal.replace_all(line, "$1");
+    let line = role.replace_all(&line, |captures: &Captures| reference_text(&captures[1]));
+    let line = reference.replace_all(&line, |captures: &Captures| reference_text(&captures[1]));
+    line.into_owned()
+}
+
+/// The text of a role or reference, without its explicit target (`text <target>`)
+/// or the `~` and `!` prefixes of cross-references
+fn reference_text(content: &str) -> String {
+    let content = content.trim_start_matches(['~', '!']);
+    match content.strip_suffix('>').and_then(|rest| rest.rsplit_once('<')) {
+        Some((text, _)) if !text.trim().is_empty() => text.trim().to_string(),
+        Some((_, target)) => target.trim().to_string(),
+        None => content.to_string(),
+    }
+}
@@ -0,0 +1,27 @@
+/// Number of leading bytes inspected to tell binary from text content
+const SNIFF_LENGTH: usize = 8192;
+
+/// Share of control bytes above which content is considered binary
+const MAX_CONTROL_RATIO: f64 = 0.1;
+
+/// Check whether content looks like binary data rather than text, whatever
+/// the extension of its file
+///
+/// Content with a NUL byte, or with too many control characters other than
+/// whitespace and escapes, in its first 8 KB is binary. Bytes of multibyte
+/// UTF-8 characters count as text.
+pub fn is_binary(content: &[u8]) -> bool {
+    let head = &content[..content.len().min(SNIFF_LENGTH)];
+    if head.is_empty() {
+        return false;
+    }
+    if head.contains(&0) {
+        return true;
+    }
+
+    let control = head
+        .iter()
+        .filter(|&&byte| (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b)) || byte == 0x7f)
+        .count();
+    control as f64 / head.len() as f64 > MAX_CONTROL_RATIO
+}
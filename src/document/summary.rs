@@ -0,0 +1,95 @@
+/// Words ending with a period that don't end a sentence, lowercased
+const ABBREVIATIONS: &[&str] = &[
+    "e.g.", "i.e.", "etc.", "vs.", "cf.", "approx.", "incl.", "mr.", "mrs.", "ms.", "dr.",
+    "prof.", "st.", "no.", "fig.", "eq.", "vol.", "ch.", "sec.", "inc.", "ltd.", "co.",
+];
+
+/// Split text into sentences ending with `.`, `!` or `?`
+///
+/// A period ending a common abbreviation or a single letter initial doesn't end
+/// a sentence, and neither does punctuation not followed by whitespace, as in
+/// `1.5` or `example.com`. Trailing text without final punctuation is the last
+/// sentence.
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+
+        // Closing quotes and brackets belong to the sentence they end
+        let mut end = index + c.len_utf8();
+        while let Some(&(next_index, next)) = chars.peek() {
+            if matches!(next, '"' | '\'' | ')' | ']' | '”' | '’') {
+                end = next_index + next.len_utf8();
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        let at_boundary = chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        if !at_boundary || (c == '.' && is_abbreviation(&text[start..index + 1])) {
+            continue;
+        }
+
+        let sentence = text[start..end].trim();
+        if !sentence.is_empty() {
+            sentences.push(sentence);
+        }
+        start = end;
+    }
+
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+
+    sentences
+}
+
+/// Check whether text ends with an abbreviation or an initial rather than a full sentence
+fn is_abbreviation(text: &str) -> bool {
+    let last_word = text.split_whitespace().last().unwrap_or_default();
+    let last_word = last_word.trim_start_matches(['(', '"', '\'']).to_lowercase();
+
+    let is_initial = last_word.chars().count() == 2 && last_word.starts_with(char::is_alphabetic);
+    is_initial || ABBREVIATIONS.contains(&last_word.as_str())
+}
+
+/// Shorten text to at most about `max_length` bytes, keeping whole sentences
+///
+/// Sentences are kept as long as they fit. If even the first one doesn't, it is
+/// cut at the last word that fits and ends with `...`.
+pub fn truncate_at_sentence(text: &str, max_length: usize) -> String {
+    let mut summary = String::new();
+    for sentence in split_sentences(text) {
+        let length = if summary.is_empty() { sentence.len() } else { summary.len() + 1 + sentence.len() };
+        if length > max_length {
+            break;
+        }
+        if !summary.is_empty() {
+            summary.push(' ');
+        }
+        summary.push_str(sentence);
+    }
+
+    if !summary.is_empty() {
+        return summary;
+    }
+
+    // The first sentence alone is too long, cut it between words
+    let mut end = max_length.saturating_sub(3).min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = &text[..end];
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(space) if space > 0 => &cut[..space],
+        _ => cut,
+    };
+    format!("{}...", cut.trim_end_matches([',', ';', ':', ' ']))
+}
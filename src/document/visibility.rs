@@ -0,0 +1,54 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Who a document may be shown to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// Any client
+    #[default]
+    Public,
+    /// Only clients of servers not restricted to public documents
+    Internal,
+}
+
+impl Visibility {
+    /// Name of the visibility, as reported to clients
+    pub fn name(&self) -> &'static str {
+        match self {
+            Visibility::Public => "public",
+            Visibility::Internal => "internal",
+        }
+    }
+}
+
+impl FromStr for Visibility {
+    type Err = anyhow::Error;
+
+    fn from_str(visibility: &str) -> Result<Self> {
+        match visibility.trim().to_lowercase().as_str() {
+            "public" => Ok(Visibility::Public),
+            "internal" | "private" => Ok(Visibility::Internal),
+            _ => anyhow::bail!("Unknown visibility: {} (expected public or internal)", visibility),
+        }
+    }
+}
+
+/// Read the visibility set in the front matter of a document, e.g. `visibility: internal`
+///
+/// The front matter is a block of `key: value` lines between `---` lines at
+/// the very start of the document. Unknown values are ignored.
+pub fn front_matter_visibility(content: &str) -> Option<Visibility> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut lines = content.lines();
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+
+    lines
+        .take_while(|line| !matches!(line.trim_end(), "---" | "..."))
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("visibility"))
+        .and_then(|(_, value)| value.trim().trim_matches(['"', '\'']).parse().ok())
+}
@@ -0,0 +1,64 @@
+/// How the raw content of files is normalized before it's indexed and stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentNormalization {
+    /// Whether `\r\n` and lone `\r` line endings are converted to `\n`
+    pub line_endings: bool,
+    /// Number of columns tabs are expanded to, tabs being kept if not set
+    pub tab_width: Option<usize>,
+}
+
+impl Default for ContentNormalization {
+    fn default() -> Self {
+        Self {
+            line_endings: true,
+            tab_width: None,
+        }
+    }
+}
+
+impl ContentNormalization {
+    /// Keep content as it is in its file
+    pub fn preserve() -> Self {
+        Self {
+            line_endings: false,
+            tab_width: None,
+        }
+    }
+
+    /// Normalize content, only copying it if anything changes
+    pub fn apply(&self, content: String) -> String {
+        let content = if self.line_endings && content.contains('\r') {
+            content.replace("\r\n", "\n").replace('\r', "\n")
+        } else {
+            content
+        };
+        match self.tab_width {
+            Some(tab_width) if content.contains('\t') => expand_tabs(&content, tab_width),
+            _ => content,
+        }
+    }
+}
+
+/// Replace tabs with spaces up to the next tab stop, every `tab_width` columns
+fn expand_tabs(content: &str, tab_width: usize) -> String {
+    let mut expanded = String::with_capacity(content.len());
+    let mut column = 0;
+    for c in content.chars() {
+        match c {
+            '\t' => {
+                let spaces = if tab_width == 0 { 0 } else { tab_width - column % tab_width };
+                expanded.extend(std::iter::repeat_n(' ', spaces));
+                column += spaces;
+            }
+            '\n' | '\r' => {
+                expanded.push(c);
+                column = 0;
+            }
+            _ => {
+                expanded.push(c);
+                column += 1;
+            }
+        }
+    }
+    expanded
+}
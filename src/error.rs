@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+/// Errors of the GitHub connector and the document storage
+///
+/// Callers embedding the crate can match on the failure mode, such as telling
+/// a missing token from an exhausted rate limit.
+#[derive(Debug, thiserror::Error)]
+pub enum KodeError {
+    /// GitHub rejected the credentials, or they don't grant access to the repository
+    #[error("GitHub authentication failed: {0}")]
+    AuthenticationFailed(String),
+    /// The GitHub API rate limit is exhausted
+    #[error("GitHub API rate limit exceeded")]
+    RateLimited {
+        /// Delay requested by the server through the `Retry-After` header, or
+        /// until the quota resets
+        retry_after: Option<Duration>,
+    },
+    /// A repository, file or ref doesn't exist
+    #[error("Not found: {0}")]
+    NotFound(String),
+    /// GitHub answered with another unsuccessful status
+    #[error("GitHub API returned HTTP {status}")]
+    Http {
+        status: u16,
+        /// Delay requested by the server through the `Retry-After` header
+        retry_after: Option<Duration>,
+    },
+    /// A request didn't complete in time
+    #[error("Request timed out after {0:?}")]
+    Timeout(Duration),
+    /// An option or argument is invalid
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    /// Reading or writing a file failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A response or a stored file couldn't be decoded
+    #[error("Failed to parse: {0}")]
+    Parse(String),
+    /// A file meant to be indexed has binary content, such as an image
+    #[error("{0} looks like a binary file")]
+    BinaryContent(String),
+    /// Any other failure
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Result of the GitHub connector and the document storage
+pub type Result<T> = std::result::Result<T, KodeError>;
+
+impl KodeError {
+    /// Build the error of an unsuccessful HTTP response
+    pub fn from_status(status: u16, retry_after: Option<Duration>, uri: &str) -> Self {
+        match status {
+            401 => KodeError::AuthenticationFailed(format!("unauthorized to access {}", uri)),
+            // GitHub answers 403 both for exhausted quotas, which come with a delay or
+            // the time the quota resets, and missing permissions
+            403 if retry_after.is_some() => KodeError::RateLimited { retry_after },
+            403 => KodeError::AuthenticationFailed(format!("forbidden to access {}", uri)),
+            429 => KodeError::RateLimited { retry_after },
+            404 => KodeError::NotFound(uri.to_string()),
+            status => KodeError::Http { status, retry_after },
+        }
+    }
+
+    /// Delay the server asked to wait before retrying, if any
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            KodeError::RateLimited { retry_after } | KodeError::Http { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Whether retrying the same request may succeed
+    ///
+    /// Fetching the same content again doesn't make it parse or turn it into
+    /// text, so parse errors and binary content aren't retried.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            KodeError::AuthenticationFailed(_)
+                | KodeError::NotFound(_)
+                | KodeError::InvalidArgument(_)
+                | KodeError::Parse(_)
+                | KodeError::BinaryContent(_)
+        )
+    }
+}
+
+impl From<serde_json::Error> for KodeError {
+    fn from(error: serde_json::Error) -> Self {
+        KodeError::Parse(error.to_string())
+    }
+}
+
+impl From<octocrab::Error> for KodeError {
+    fn from(error: octocrab::Error) -> Self {
+        match &error {
+            octocrab::Error::GitHub { source, .. } => match source.status_code.as_u16() {
+                // The message tells which resource or rate limit the error is about
+                403 if source.message.to_lowercase().contains("rate limit") => {
+                    KodeError::RateLimited { retry_after: None }
+                }
+                401 | 403 => KodeError::AuthenticationFailed(source.message.clone()),
+                404 => KodeError::NotFound(source.message.clone()),
+                status => KodeError::Http { status, retry_after: None },
+            },
+            _ => KodeError::Other(error.into()),
+        }
+    }
+}
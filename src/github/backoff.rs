@@ -0,0 +1,46 @@
+use tokio::time::Duration;
+
+/// Delay before the first retry, before jitter
+const BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// Exponential backoff with full jitter
+///
+/// Each delay is drawn uniformly between zero and a bound that doubles with
+/// every retry, so concurrent requests failing on the same rate limit spread
+/// their retries out instead of hitting the API again all at once.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    rng: fastrand::Rng,
+    // Upper bound of the next delay
+    bound: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Backoff {
+    /// Create a backoff drawing its delays from a randomly seeded generator
+    pub fn new() -> Self {
+        Self::with_rng(fastrand::Rng::new())
+    }
+
+    /// Create a backoff drawing its delays from a generator with a fixed seed,
+    /// to get the same delays every time
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rng(fastrand::Rng::with_seed(seed))
+    }
+
+    fn with_rng(rng: fastrand::Rng) -> Self {
+        Self { rng, bound: BASE_DELAY }
+    }
+
+    /// Get the delay to wait before the next retry, and double the bound of the following one
+    pub fn next_delay(&mut self) -> Duration {
+        let millis = self.rng.u64(0..=self.bound.as_millis() as u64);
+        self.bound *= 2;
+        Duration::from_millis(millis)
+    }
+}
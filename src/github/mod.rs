@@ -1,11 +1,313 @@
-use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use lru::LruCache;
 use octocrab::Octocrab;
+use octocrab::Page;
+use octocrab::service::middleware::retry::RetryConfig;
+use http_body_util::BodyExt;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
-use crate::document::Document;
-use std::collections::HashMap;
+use crate::document::{is_binary, ContentNormalization, Document, DocumentProcessor, DocumentScanner, ExtensionGroup, ListedFile, DEFAULT_GENERIC_FILENAMES, DEFAULT_SUMMARY_LENGTH};
+use crate::error::{KodeError, Result};
+use crate::storage::DocumentStorage;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::future::Future;
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::{RwLock, Semaphore};
-use tokio::time::{sleep, Duration};
+use tokio::sync::{Mutex, OnceCell, RwLock, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{sleep, timeout, Duration};
+
+mod backoff;
+mod report;
+mod watch;
+mod wiki;
+
+pub use backoff::Backoff;
+pub use report::{ScanReport, SCAN_REPORT_FILE};
+pub use watch::Watcher;
+
+/// Directory wiki pages are stored under, to tell them apart from repository files
+pub const WIKI_PREFIX: &str = "wiki";
+
+/// Directory release notes are stored under, to tell them apart from repository files
+pub const RELEASES_PREFIX: &str = "releases";
+
+/// Number of releases listed per page of the releases API, the most it allows
+const RELEASES_PER_PAGE: usize = 100;
+
+/// Default maximum number of file contents kept in memory
+pub const DEFAULT_CACHE_CAPACITY: usize = 1000;
+
+/// Default maximum number of GitHub requests in flight at once, low enough to avoid rate limiting
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 5;
+
+/// Upper bound on how long a `Retry-After` header can make us wait
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Reason binary files with a documentation extension are skipped for
+const BINARY_CONTENT: &str = "binary content";
+
+/// Options for connecting to GitHub
+#[derive(Debug, Clone)]
+pub struct ConnectorOptions {
+    /// Maximum duration of a single request before it is aborted and retried
+    pub request_timeout: Duration,
+    /// Maximum number of requests in flight at once, at least 1
+    pub max_concurrent_requests: usize,
+    /// Base URI of the GitHub API, for GitHub Enterprise instances or testing
+    pub base_uri: Option<String>,
+    /// Glob patterns of the paths to scan, relative to the repository root (all paths if empty)
+    pub include: Vec<String>,
+    /// Glob patterns of the paths to skip, relative to the repository root
+    pub exclude: Vec<String>,
+    /// Leading directories removed from the paths documents are stored under
+    pub strip_prefix: Option<String>,
+    /// Directory prepended to the paths documents are stored under, after stripping
+    pub path_prefix: Option<String>,
+    /// Whether the `README.md` or `README.rst` at the repository root is scanned
+    /// whatever the scanned folders
+    pub include_root_readme: bool,
+    /// Whether the time of the last commit of each file is fetched, which takes
+    /// one more request per file
+    pub track_last_modified: bool,
+    /// Base URI wiki git repositories are cloned from, GitHub if not set
+    pub wiki_base_uri: Option<String>,
+    /// Approximate maximum length of document summaries, in bytes
+    pub summary_length: usize,
+    /// Maximum number of file contents kept in memory, the least recently used
+    /// being evicted first, or 0 not to cache them
+    pub cache_capacity: usize,
+    /// Glob patterns of the paths fetched and stored first by `scan_into`,
+    /// relative to the repository root
+    pub priority: Vec<String>,
+    /// How the content of fetched files is normalized before it's indexed
+    pub normalization: ContentNormalization,
+    /// File names, without extension, replaced by their directory name in the
+    /// titles of documents without one
+    pub generic_filenames: Vec<String>,
+    /// Groups of the extensions of the files to scan, the default extensions if empty
+    pub extension_groups: Vec<ExtensionGroup>,
+    /// Processors building documents from the content of fetched files, run in
+    /// order, the default processor with these options if empty
+    pub processors: Vec<Arc<dyn DocumentProcessor>>,
+}
+
+impl Default for ConnectorOptions {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(30),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            base_uri: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            strip_prefix: None,
+            path_prefix: None,
+            include_root_readme: true,
+            track_last_modified: false,
+            wiki_base_uri: None,
+            summary_length: DEFAULT_SUMMARY_LENGTH,
+            cache_capacity: DEFAULT_CACHE_CAPACITY,
+            priority: Vec::new(),
+            normalization: ContentNormalization::default(),
+            generic_filenames: DEFAULT_GENERIC_FILENAMES.iter().map(|name| name.to_string()).collect(),
+            extension_groups: Vec::new(),
+            processors: Vec::new(),
+        }
+    }
+}
+
+/// The part of a contents API response needed to read a file
+#[derive(Deserialize)]
+struct FileContent {
+    // Base64 encoded file content, empty for files over 1 MB
+    content: Option<String>,
+    // `none` when the file is too large for its content to be included
+    encoding: Option<String>,
+    // URL of the raw file content
+    download_url: Option<String>,
+}
+
+/// A recursive Git tree listing
+#[derive(Deserialize)]
+struct GitTree {
+    tree: Vec<GitTreeEntry>,
+    // Set when the tree exceeds the maximum size GitHub returns
+    #[serde(default)]
+    truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct GitTreeEntry {
+    path: String,
+    // `blob` for files, `tree` for directories
+    r#type: String,
+    // Size of files in bytes, missing for directories
+    #[serde(default)]
+    size: u64,
+}
+
+/// An entry of a contents API directory listing
+#[derive(Deserialize)]
+struct DirectoryEntry {
+    path: String,
+    // `file`, `dir`, `symlink` or `submodule`
+    r#type: String,
+    #[serde(default)]
+    size: u64,
+}
+
+/// A path that could not be scanned, and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FetchFailure {
+    pub path: String,
+    pub reason: String,
+}
+
+/// The outcome of a repository scan
+///
+/// A scan keeps going when individual directories or files fail, so the
+/// documents may be incomplete; `failures` lists everything that was skipped.
+#[derive(Debug, Clone, Default)]
+pub struct ScanResult {
+    pub documents: Vec<Document>,
+    pub failures: Vec<FetchFailure>,
+    /// Time of the last commit of each document, by stored path, as a Unix timestamp
+    pub last_modified: HashMap<String, u64>,
+}
+
+/// The files a scan would fetch, listed without fetching their content
+#[derive(Debug, Clone, Default)]
+pub struct ScanPreview {
+    /// Files matching the scan settings, with their repository path
+    pub files: Vec<ListedFile>,
+    pub failures: Vec<FetchFailure>,
+}
+
+/// Progress of the file fetching phase of a scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanProgress {
+    pub fetched: usize,
+    pub total: usize,
+}
+
+/// Number of progress reports over a whole scan, so large scans aren't reported after every file,
+/// and scans of fewer files aren't reported at all
+const PROGRESS_STEPS: usize = 10;
+
+/// Files changed between two commits
+#[derive(Debug, Clone, Default)]
+pub struct ChangeSet {
+    /// Added and modified documents
+    pub documents: Vec<Document>,
+    /// Paths of removed files, including the previous paths of renamed files
+    pub removed: Vec<String>,
+    pub failures: Vec<FetchFailure>,
+    /// Time of the last commit of each changed document, by stored path, as a Unix timestamp
+    pub last_modified: HashMap<String, u64>,
+}
+
+impl ChangeSet {
+    /// Store the changed documents and remove the removed ones in a single
+    /// batch, returning the files that couldn't be fetched
+    pub fn apply(self, store: &mut DocumentStorage) -> Result<Vec<FetchFailure>> {
+        store.begin_batch();
+        store.store_documents(self.documents)?;
+        store.remove_documents(&self.removed)?;
+        store.set_last_modified(&self.last_modified)?;
+        store.commit()?;
+        Ok(self.failures)
+    }
+}
+
+/// The part of a compare API response needed to list changed files
+#[derive(Deserialize)]
+struct Comparison {
+    #[serde(default)]
+    files: Vec<ComparedFile>,
+}
+
+#[derive(Deserialize)]
+struct ComparedFile {
+    filename: String,
+    // `added`, `removed`, `modified`, `renamed`, ...
+    status: String,
+    // Set for renamed files
+    previous_filename: Option<String>,
+}
+
+/// A commit of a commit list response, with only what's needed to identify it and date a file
+#[derive(Deserialize)]
+struct CommitEntry {
+    sha: String,
+    commit: CommitDetails,
+}
+
+#[derive(Deserialize)]
+struct CommitDetails {
+    committer: Option<CommitSignature>,
+}
+
+#[derive(Deserialize)]
+struct CommitSignature {
+    // RFC 3339 date
+    date: String,
+}
+
+/// The part of a repository API response with its default branch
+#[derive(Deserialize)]
+struct RepositoryResponse {
+    default_branch: String,
+}
+
+/// The part of a releases API entry needed to index its notes
+#[derive(Deserialize)]
+struct ReleaseEntry {
+    tag_name: String,
+    // Title of the release, which may be empty or missing
+    name: Option<String>,
+    // Release notes, in markdown
+    body: Option<String>,
+    #[serde(default)]
+    draft: bool,
+}
+
+/// Maximum number of files listed by the compare API
+const MAX_COMPARE_FILES: usize = 300;
+
+/// Current GitHub API quota of the connector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct RateLimitStatus {
+    /// Maximum number of requests per hour
+    pub limit: u64,
+    /// Number of requests left until the quota resets
+    pub remaining: u64,
+    /// Time the quota resets at, as a Unix timestamp
+    pub reset: u64,
+}
+
+impl RateLimitStatus {
+    /// Seconds left until the quota resets
+    pub fn seconds_until_reset(&self) -> u64 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        self.reset.saturating_sub(now)
+    }
+}
+
+/// The part of a rate limit API response with the quota of the core API
+#[derive(Deserialize)]
+struct RateLimitResponse {
+    resources: RateLimitResources,
+}
+
+#[derive(Deserialize)]
+struct RateLimitResources {
+    core: RateLimitStatus,
+}
 
 /// GitHub repository connector that handles authentication and repository operations
 #[derive(Clone)]
@@ -14,29 +316,69 @@ pub struct GitHubConnector {
     owner: String,
     pub repo: String,
     // Cache for file contents to avoid redundant API calls
-    file_cache: Arc<RwLock<HashMap<String, String>>>,
+    file_cache: Option<Arc<Mutex<LruCache<String, String>>>>,
     // Semaphore to limit concurrent requests to GitHub API
     request_semaphore: Arc<Semaphore>,
     // Maximum number of concurrent requests
     max_concurrent_requests: usize,
+    // Maximum duration of a single request
+    request_timeout: Duration,
+    // Scanner deciding which files to index and how to process them
+    scanner: Arc<DocumentScanner>,
+    // Leading directories removed from stored document paths, empty if none
+    strip_prefix: String,
+    // Directory prepended to stored document paths, empty if none
+    path_prefix: String,
+    // Whether the root README is scanned whatever the scanned folders
+    include_root_readme: bool,
+    // Whether the time of the last commit of each file is fetched
+    track_last_modified: bool,
+    // Personal access token, also used to clone the wiki
+    token: Option<String>,
+    // Base URI wiki git repositories are cloned from
+    wiki_base_uri: String,
+    // Paths fetched and stored before the others, none if not set
+    priority: Option<Arc<GlobSet>>,
+    // Default branch of the repository, once detected
+    default_branch: Arc<OnceCell<String>>,
+    // Summary of the last full scan, none before the first one completes
+    scan_report: Arc<Mutex<Option<ScanReport>>>,
+    // Quota reported by the last rate limit status request, none before the first one
+    rate_limit: Arc<Mutex<Option<RateLimitStatus>>>,
 }
 
 impl GitHubConnector {
     pub async fn new(owner: &str, repo: &str, token: Option<&str>) -> Result<Self> {
+        Self::with_options(owner, repo, token, ConnectorOptions::default()).await
+    }
+
+    /// Create a connector with custom connection options
+    pub async fn with_options(owner: &str, repo: &str, token: Option<&str>, options: ConnectorOptions) -> Result<Self> {
         // Validate parameters
         if owner.trim().is_empty() {
-            anyhow::bail!("Owner cannot be empty");
+            return Err(KodeError::InvalidArgument("owner cannot be empty".to_string()));
         }
         if repo.trim().is_empty() {
-            anyhow::bail!("Repository name cannot be empty");
+            return Err(KodeError::InvalidArgument("repository name cannot be empty".to_string()));
         }
 
-        let mut builder = Octocrab::builder();
+        let mut builder = Octocrab::builder()
+            .set_connect_timeout(Some(options.request_timeout))
+            .set_read_timeout(Some(options.request_timeout));
+
+        // Retries are handled by the connector, which honors `Retry-After`
+        builder = builder.add_retry_config(RetryConfig::None);
+
+        if let Some(base_uri) = &options.base_uri {
+            builder = builder
+                .base_uri(base_uri.as_str())
+                .map_err(|e| KodeError::InvalidArgument(format!("invalid base URI {}: {}", base_uri, e)))?;
+        }
 
         // Set personal access token if provided
         if let Some(token) = token {
             if token.trim().is_empty() {
-                anyhow::bail!("Personal access token cannot be empty");
+                return Err(KodeError::InvalidArgument("personal access token cannot be empty".to_string()));
             }
             tracing::info!("Using personal access token for GitHub API");
             builder = builder.personal_token(token);
@@ -44,189 +386,1079 @@ impl GitHubConnector {
 
         let client = builder.build()?;
 
-        // Default to 5 concurrent requests to avoid rate limiting
-        let max_concurrent_requests = 5;
+        let mut scanner = DocumentScanner::new();
+        scanner
+            .set_patterns(&options.include, &options.exclude)
+            .map_err(|e| KodeError::InvalidArgument(e.to_string()))?;
+        scanner.set_summary_length(options.summary_length);
+        scanner.set_normalization(options.normalization);
+        scanner.set_generic_filenames(&options.generic_filenames);
+        scanner.set_extension_groups(&options.extension_groups);
+        scanner.set_processors(options.processors.clone());
+        let priority = build_priority(&options.priority)?;
+
+        let max_concurrent_requests = options.max_concurrent_requests.max(1);
 
         Ok(Self {
             client: Arc::new(client),
             owner: owner.to_string(),
             repo: repo.to_string(),
-            file_cache: Arc::new(RwLock::new(HashMap::new())),
+            file_cache: NonZeroUsize::new(options.cache_capacity)
+                .map(|capacity| Arc::new(Mutex::new(LruCache::new(capacity)))),
             request_semaphore: Arc::new(Semaphore::new(max_concurrent_requests)),
             max_concurrent_requests,
+            request_timeout: options.request_timeout,
+            scanner: Arc::new(scanner),
+            strip_prefix: options.strip_prefix.as_deref().map(normalize_path).unwrap_or_default(),
+            path_prefix: options.path_prefix.as_deref().map(normalize_path).unwrap_or_default(),
+            include_root_readme: options.include_root_readme,
+            track_last_modified: options.track_last_modified,
+            token: token.map(str::to_string),
+            wiki_base_uri: options.wiki_base_uri.unwrap_or_else(|| "https://github.com".to_string()),
+            priority,
+            default_branch: Arc::new(OnceCell::new()),
+            scan_report: Arc::new(Mutex::new(None)),
+            rate_limit: Arc::new(Mutex::new(None)),
         })
     }
 
+    /// The owner of the repository
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// Fetch the pages of the repository wiki, stored under the `wiki/` directory
+    ///
+    /// Wikis live in a separate git repository, which is cloned to a temporary
+    /// directory. Returns no pages if the repository has no wiki.
+    pub async fn list_wiki_pages(&self) -> Result<Vec<Document>> {
+        let url = format!("{}/{}/{}.wiki.git", self.wiki_base_uri.trim_end_matches('/'), self.owner, self.repo);
+        let directory = tempfile::tempdir()?;
+        if !wiki::clone(&url, self.token.as_deref(), directory.path()).await? {
+            tracing::info!("Repository {}/{} has no wiki, skipping", self.owner, self.repo);
+            return Ok(Vec::new());
+        }
+
+        // Wiki pages are all at the root, the repository patterns don't apply to them
+        let mut scanner = DocumentScanner::new();
+        scanner.set_summary_length(self.scanner.summary_length());
+        scanner.set_normalization(self.scanner.normalization());
+        scanner.set_processors(self.scanner.processors().to_vec());
+        let mut pages = scanner.scan_directory(directory.path(), None)?;
+        for page in &mut pages {
+            page.path = format!("{}/{}", WIKI_PREFIX, page.path);
+        }
+        Ok(pages)
+    }
+
+    /// Fetch the notes of the published releases of the repository, stored
+    /// under the `releases/` directory, e.g. `releases/v1.2.0.md`
+    ///
+    /// Each release is titled by its name, or its tag if it has none. Drafts
+    /// and releases without notes are skipped. Returns no documents if the
+    /// repository has no releases.
+    pub async fn list_releases(&self) -> Result<Vec<Document>> {
+        let mut releases = Vec::new();
+        for page in 1.. {
+            let route = format!("/repos/{}/{}/releases?per_page={}&page={}", self.owner, self.repo, RELEASES_PER_PAGE, page);
+            let _permit = self.request_semaphore.clone().acquire_owned().await.map_err(anyhow::Error::from)?;
+            let body = match self.with_retries(&route, || self.get_text(&route)).await {
+                Ok(body) => body,
+                Err(KodeError::NotFound(_)) => {
+                    tracing::info!("Repository {}/{} has no releases, skipping", self.owner, self.repo);
+                    return Ok(Vec::new());
+                }
+                Err(e) => return Err(e),
+            };
+            let mut entries: Vec<ReleaseEntry> = serde_json::from_str(&body)?;
+            let last_page = entries.len() < RELEASES_PER_PAGE;
+            releases.append(&mut entries);
+            if last_page {
+                break;
+            }
+        }
+
+        let mut documents = Vec::new();
+        for release in releases {
+            let notes = release.body.unwrap_or_default();
+            if release.draft || notes.trim().is_empty() {
+                continue;
+            }
+            let path = format!("{}/{}.md", RELEASES_PREFIX, release.tag_name.replace('/', "-"));
+            let mut document = match self.scanner.process_content(&path, notes) {
+                Ok(document) => document,
+                Err(e) => {
+                    tracing::warn!("Failed to process the notes of release {}: {}", release.tag_name, e);
+                    continue;
+                }
+            };
+            document.title = release
+                .name
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or(release.tag_name);
+            documents.push(document);
+        }
+        Ok(documents)
+    }
+
+    /// Rewrite a repository path to the path its document is stored under,
+    /// according to the `strip_prefix` and `path_prefix` options
+    ///
+    /// Paths outside of the stripped prefix are only prefixed.
+    pub fn stored_path(&self, path: &str) -> String {
+        let path = if self.strip_prefix.is_empty() {
+            path
+        } else {
+            path.strip_prefix(&self.strip_prefix)
+                .and_then(|relative| relative.strip_prefix('/'))
+                .unwrap_or(path)
+        };
+
+        if self.path_prefix.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}/{}", self.path_prefix, path)
+        }
+    }
+
     /// Get the contents of a file from the repository with retry logic
     pub async fn get_file_contents(&self, path: &str) -> Result<String> {
-        // Check if the file is in the cache
+        self.get_file_contents_at(path, None).await
+    }
+
+    /// Get the contents of a file at a given ref (branch, tag or commit), or
+    /// on the default branch if no ref is given
+    pub async fn get_file_contents_at(&self, path: &str, git_ref: Option<&str>) -> Result<String> {
+        let cache_key = match git_ref {
+            Some(git_ref) => format!("{}@{}", path, git_ref),
+            None => path.to_string(),
+        };
+
+        // Check if the file is in the cache, marking it as recently used
+        if let Some(cache) = &self.file_cache
+            && let Some(content) = cache.lock().await.get(&cache_key)
         {
-            let cache = self.file_cache.read().await;
-            if let Some(content) = cache.get(path) {
-                return Ok(content.clone());
-            }
+            return Ok(content.clone());
         }
 
         // Acquire a permit from the semaphore to limit concurrent requests
-        let _permit = self.request_semaphore.clone().acquire_owned().await?;
+        let _permit = self.request_semaphore.clone().acquire_owned().await.map_err(anyhow::Error::from)?;
+
+        let file_content = self
+            .with_retries(path, || self.fetch_file_content(path, git_ref))
+            .await?;
+
+        // Fetching content that isn't text again wouldn't change it, so it's checked once retries are over
+        if is_binary(&file_content) {
+            return Err(KodeError::BinaryContent(path.to_string()));
+        }
+        let file_content = String::from_utf8(file_content)
+            .map_err(|e| KodeError::Parse(format!("invalid UTF-8 content of {}: {}", path, e)))?;
+
+        // Store in cache, evicting the least recently used file if it's full
+        if let Some(cache) = &self.file_cache {
+            cache.lock().await.put(cache_key, file_content.clone());
+        }
+
+        Ok(file_content)
+    }
+
+    /// Get the time of the last commit changing a file, at the given ref or the
+    /// default branch, as a Unix timestamp
+    ///
+    /// Returns `None` if no commit changed the file.
+    pub async fn last_modified(&self, path: &str, git_ref: Option<&str>) -> Result<Option<u64>> {
+        let mut route = format!(
+            "/repos/{}/{}/commits?per_page=1&path={}",
+            self.owner,
+            self.repo,
+            encode_path(path).replace('/', "%2F")
+        );
+        if let Some(git_ref) = git_ref {
+            route.push_str(&format!("&sha={}", encode_path(git_ref).replace('/', "%2F")));
+        }
+
+        let _permit = self.request_semaphore.clone().acquire_owned().await.map_err(anyhow::Error::from)?;
+        let body = self.with_retries(path, || self.get_text(&route)).await?;
+        let commits: Vec<CommitEntry> = serde_json::from_str(&body)?;
+
+        let Some(date) = commits.into_iter().next().and_then(|entry| entry.commit.committer).map(|committer| committer.date) else {
+            return Ok(None);
+        };
+        let date = chrono::DateTime::parse_from_rfc3339(&date).map_err(|e| KodeError::Parse(e.to_string()))?;
+        Ok(Some(date.timestamp().max(0) as u64))
+    }
+
+    /// Get the name of the default branch of the repository, such as `main`
+    ///
+    /// It's fetched from the repository API once, then reused.
+    pub async fn default_branch(&self) -> Result<String> {
+        let branch = self
+            .default_branch
+            .get_or_try_init(|| async {
+                let route = format!("/repos/{}/{}", self.owner, self.repo);
+                let body = self.with_retries(&route, || self.get_text(&route)).await?;
+                let repository: RepositoryResponse = serde_json::from_str(&body)?;
+                tracing::info!("Default branch of {}/{} is {}", self.owner, self.repo, repository.default_branch);
+                Ok::<_, KodeError>(repository.default_branch)
+            })
+            .await?;
+        Ok(branch.clone())
+    }
+
+    /// Check that the personal access token can read the repository, with a
+    /// single request of its metadata
+    ///
+    /// A token lacking read access to the repository fails with a clear
+    /// `AuthenticationFailed` error, rather than with an error per file once the
+    /// scan has started. Timeouts, server errors and rate limits are retried,
+    /// and fail with their own error if they persist. Without a token, nothing
+    /// is requested, but the low anonymous rate limit is warned about.
+    pub async fn validate_access(&self) -> Result<()> {
+        if self.token.is_none() {
+            tracing::warn!(
+                "No personal access token, the GitHub API allows 60 anonymous requests per hour, \
+                 which scanning large repositories exceeds"
+            );
+            return Ok(());
+        }
+
+        let route = format!("/repos/{}/{}", self.owner, self.repo);
+        self.with_retries(&route, || self.check_access(&route)).await
+    }
+
+    /// Request the repository metadata once, telling the token's lack of access
+    /// from failures retrying may fix
+    async fn check_access(&self, route: &str) -> Result<()> {
+        let request = async {
+            let response = self.client._get(route).await?;
+            Ok::<_, KodeError>((response.status().as_u16(), retry_after(response.headers())))
+        };
+        let (status, retry_after) = match timeout(self.request_timeout, request).await {
+            Ok(result) => result?,
+            Err(_) => return Err(KodeError::Timeout(self.request_timeout)),
+        };
+
+        match status {
+            200..=299 => Ok(()),
+            // GitHub hides private repositories from tokens that can't read them behind a 404
+            401 | 404 | 403 if retry_after.is_none() => Err(KodeError::AuthenticationFailed(format!(
+                "token cannot access {}/{} ({}), check that it exists and that the token is granted read access to its contents",
+                self.owner, self.repo, status
+            ))),
+            status => Err(KodeError::from_status(status, retry_after, route)),
+        }
+    }
 
-        // Implement retry logic with exponential backoff
+    /// Get the ref scans and updates are based on: the default branch, or
+    /// `HEAD` if it can't be detected
+    pub async fn baseline_ref(&self) -> String {
+        match self.default_branch().await {
+            Ok(branch) => branch,
+            Err(e) => {
+                tracing::warn!("Failed to detect the default branch, using HEAD: {}", e);
+                "HEAD".to_string()
+            }
+        }
+    }
+
+    /// Get the SHA of the latest commit of the default branch
+    ///
+    /// This takes a single small request, so it is cheap enough to poll for changes.
+    pub async fn head_sha(&self) -> Result<String> {
+        let route = format!("/repos/{}/{}/commits?per_page=1", self.owner, self.repo);
+        let _permit = self.request_semaphore.clone().acquire_owned().await.map_err(anyhow::Error::from)?;
+        let body = self.with_retries(&route, || self.get_text(&route)).await?;
+        let commits: Vec<CommitEntry> = serde_json::from_str(&body)?;
+
+        commits
+            .into_iter()
+            .next()
+            .map(|entry| entry.sha)
+            .ok_or_else(|| KodeError::NotFound(format!("commits of {}/{}", self.owner, self.repo)))
+    }
+
+    /// Run a request, retrying failures with exponential backoff and jitter
+    ///
+    /// When the server answers with a `Retry-After` header, that delay is used
+    /// instead of the backoff delay. Failures retrying can't fix, such as
+    /// missing files or rejected credentials, are returned right away.
+    async fn with_retries<T, F, Fut>(&self, path: &str, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        // Implement retry logic with exponential backoff, jittered so concurrent
+        // requests hitting the same rate limit don't retry in lockstep
         let max_retries = 3;
         let mut retry_count = 0;
-        let mut delay = Duration::from_millis(100);
+        let mut backoff = Backoff::new();
+        let mut retry_after: Option<Duration> = None;
 
         loop {
-            // If we've exceeded the maximum number of retries, bail out
-            if retry_count >= max_retries {
-                anyhow::bail!("Failed to fetch file after {} retries", max_retries);
-            }
-
             // If we're not on the first attempt, wait before retrying
             if retry_count > 0 {
-                sleep(delay).await;
-                // Exponential backoff: double the delay for each retry
-                delay *= 2;
-            }
-
-            // Attempt to fetch the file
-            match self.fetch_file_content(path).await {
-                Ok(file_content) => {
-                    // Store in cache
-                    {
-                        let mut cache = self.file_cache.write().await;
-                        cache.insert(path.to_string(), file_content.clone());
-                    }
-
-                    return Ok(file_content);
+                match retry_after.take() {
+                    // Wait as long as the server asked us to
+                    Some(wait) => sleep(wait.min(MAX_RETRY_AFTER)).await,
+                    None => sleep(backoff.next_delay()).await,
                 }
+            }
+
+            // Attempt the request
+            match request().await {
+                Ok(result) => return Ok(result),
                 Err(e) => {
-                    // If we get a rate limit error or a network error, retry
-                    if retry_count < max_retries {
-                        retry_count += 1;
-                        tracing::warn!("Retry {}/{} for file {}: {}", retry_count, max_retries, path, e);
-                    } else {
+                    if !e.is_retryable() {
                         return Err(e);
                     }
+                    retry_after = e.retry_after();
+
+                    // If we get a rate limit error, a network error or a timeout, retry
+                    retry_count += 1;
+                    if retry_count >= max_retries {
+                        tracing::warn!("Failed to fetch {} after {} retries: {}", path, max_retries, e);
+                        return Err(e);
+                    }
+                    tracing::warn!("Retry {}/{} for {}: {}", retry_count, max_retries, path, e);
                 }
             }
         }
     }
 
-    /// Helper method to fetch file content from GitHub
-    async fn fetch_file_content(&self, path: &str) -> Result<String> {
-        let content = self
-            .client
-            .repos(&self.owner, &self.repo)
-            .get_content()
-            .path(path)
-            .send()
-            .await?;
+    /// Helper method to fetch the raw bytes of a file from GitHub
+    async fn fetch_file_content(&self, path: &str, git_ref: Option<&str>) -> Result<Vec<u8>> {
+        let mut route = format!("/repos/{}/{}/contents/{}", self.owner, self.repo, encode_path(path));
+        if let Some(git_ref) = git_ref {
+            route.push_str(&format!("?ref={}", encode_path(git_ref).replace('/', "%2F")));
+        }
+
+        let body = self.get_text(&route).await?;
+        let file: FileContent = serde_json::from_str(&body)?;
 
-        if let Some(file) = content.items.first() {
-            if let Some(content) = &file.content {
-                let decoded = STANDARD.decode(content.replace('\n', ""))?;
-                let file_content = String::from_utf8(decoded)?;
-                return Ok(file_content);
+        // Files over 1 MB come without content, which has to be downloaded raw instead
+        let too_large = file.encoding.as_deref() == Some("none")
+            || file.content.as_deref().is_some_and(|content| content.trim().is_empty());
+        if too_large && let Some(download_url) = &file.download_url {
+            tracing::debug!("File {} is too large for the contents API, downloading it raw", path);
+            return self.get_bytes(download_url).await;
+        }
+
+        if let Some(content) = &file.content {
+            return STANDARD
+                .decode(content.replace('\n', ""))
+                .map_err(|e| KodeError::Parse(format!("invalid base64 content of {}: {}", path, e)));
+        }
+
+        Err(KodeError::NotFound(format!("content of {}", path)))
+    }
+
+    /// Get the body of a GitHub API route or URL as text, failing on unsuccessful responses
+    async fn get_text(&self, uri: &str) -> Result<String> {
+        let body = self.get_bytes(uri).await?;
+        String::from_utf8(body).map_err(|e| KodeError::Parse(format!("invalid UTF-8 response from {}: {}", uri, e)))
+    }
+
+    /// Get the raw body of a GitHub API route or URL, failing on unsuccessful responses
+    ///
+    /// The body isn't decoded, so binary files can be told apart from text.
+    async fn get_bytes(&self, uri: &str) -> Result<Vec<u8>> {
+        let request = async {
+            let response = self.client._get(uri).await?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let retry_after = retry_after(response.headers());
+                return Err(KodeError::from_status(status.as_u16(), retry_after, uri));
             }
+
+            Ok::<_, KodeError>(response.into_body().collect().await?.to_bytes().to_vec())
+        };
+
+        // Abort stalled requests so they can be retried
+        match timeout(self.request_timeout, request).await {
+            Ok(body) => body,
+            Err(_) => Err(KodeError::Timeout(self.request_timeout)),
+        }
+    }
+
+    /// List the entries of a single directory, following the pages of large directories
+    async fn list_directory(&self, path: &str, git_ref: Option<&str>) -> Result<Vec<DirectoryEntry>> {
+        let mut route = format!("/repos/{}/{}/contents/{}", self.owner, self.repo, encode_path(path));
+        if let Some(git_ref) = git_ref {
+            route.push_str(&format!("?ref={}", encode_path(git_ref).replace('/', "%2F")));
+        }
+        let mut page: Page<DirectoryEntry> = self.with_timeout(self.client.get(route, None::<&()>)).await?;
+
+        let mut entries = page.take_items();
+        while let Some(mut next_page) = self.with_timeout(self.client.get_page(&page.next)).await? {
+            entries.append(&mut next_page.take_items());
+            page = next_page;
         }
+        Ok(entries)
+    }
+
+    /// Abort a stalled octocrab request, so it can be retried
+    async fn with_timeout<T>(&self, request: impl Future<Output = octocrab::Result<T>>) -> Result<T> {
+        match timeout(self.request_timeout, request).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(KodeError::Timeout(self.request_timeout)),
+        }
+    }
+
+    /// Check whether a file should be indexed, based on its extension and the scan patterns
+    fn is_wanted(&self, path: &str) -> bool {
+        let path = Path::new(path);
+        self.scanner.is_supported_file(path) && self.scanner.is_included(path)
+    }
+
+    /// Check whether a repository path is under one of the scanned roots, at most
+    /// `max_depth` directories below it, or is the root README
+    fn is_in_scope(&self, path: &str, roots: &[String], max_depth: Option<usize>) -> bool {
+        let in_root = match roots.is_empty() {
+            true => is_within_depth(path, "", max_depth),
+            false => roots.iter().any(|root| is_within_depth(path, &normalize_path(root), max_depth)),
+        };
+        in_root || (self.include_root_readme && is_root_readme(path))
+    }
 
-        anyhow::bail!("File not found or empty")
+    /// Get the remaining GitHub API quota, which doesn't count against the quota itself
+    ///
+    /// The status is kept for scans to warn when they need more requests than
+    /// are left, without requesting it again.
+    pub async fn rate_limit_status(&self) -> Result<RateLimitStatus> {
+        let request = self.client.get::<RateLimitResponse, _, _>("/rate_limit", None::<&()>);
+        let status = match timeout(self.request_timeout, request).await {
+            Ok(response) => response?.resources.core,
+            Err(_) => return Err(KodeError::Timeout(self.request_timeout)),
+        };
+        *self.rate_limit.lock().await = Some(status);
+        Ok(status)
     }
 
-    /// List all files in a directory recursively with parallel processing
-    pub async fn list_files(&self, path: &str) -> Result<Vec<Document>> {
-        // Use an iterative approach with a queue to avoid deep recursion
-        let mut directories_to_process: Vec<String> = vec![path.to_string()];
-        let scanner = crate::document::DocumentScanner::new();
+    /// List the supported files under the given roots with a single recursive Git tree request
+    ///
+    /// Returns `None` if the tree can't be fetched or was truncated by GitHub,
+    /// in which case the directories need to be walked one by one.
+    async fn list_tree(&self, roots: &[String], max_depth: Option<usize>, git_ref: Option<&str>) -> Option<Vec<ListedFile>> {
+        let git_ref = match git_ref {
+            Some(git_ref) => git_ref.to_string(),
+            None => self.baseline_ref().await,
+        };
+        let route = format!(
+            "/repos/{}/{}/git/trees/{}?recursive=1",
+            self.owner,
+            self.repo,
+            encode_path(&git_ref).replace('/', "%2F")
+        );
+        tracing::debug!("Listing the repository tree at {}", git_ref);
+        let request = self.client.get::<GitTree, _, _>(route, None::<&()>);
+
+        let tree = match timeout(self.request_timeout, request).await {
+            Ok(Ok(tree)) => tree,
+            Ok(Err(e)) => {
+                tracing::warn!("Failed to fetch repository tree, walking directories instead: {}", e);
+                return None;
+            }
+            Err(_) => {
+                tracing::warn!("Fetching repository tree timed out, walking directories instead");
+                return None;
+            }
+        };
+
+        if tree.truncated {
+            tracing::warn!("Repository tree is truncated, walking directories instead");
+            return None;
+        }
+
+        let files = tree
+            .tree
+            .into_iter()
+            .filter(|entry| entry.r#type == "blob")
+            .filter(|entry| {
+                roots.iter().any(|root| is_within_depth(&entry.path, root, max_depth))
+                    || (self.include_root_readme && is_root_readme(&entry.path))
+            })
+            .filter(|entry| self.is_wanted(&entry.path))
+            .map(|entry| ListedFile {
+                path: entry.path,
+                size: entry.size,
+            })
+            .collect();
+
+        Some(files)
+    }
 
-        // First, collect all file paths to process
-        let mut file_items = Vec::new();
+    /// List the supported files under a path by walking its directories one by one,
+    /// at the given ref or the default branch
+    async fn walk_directories(
+        &self,
+        path: &str,
+        max_depth: Option<usize>,
+        git_ref: Option<&str>,
+        failures: &mut Vec<FetchFailure>,
+    ) -> Vec<ListedFile> {
+        // Use an iterative approach with a queue to avoid deep recursion,
+        // tracking the depth of each directory relative to the starting path
+        let mut directories_to_process: Vec<(String, usize)> = vec![(path.to_string(), 0)];
+        let mut files = Vec::new();
 
         // Collect all files from all directories
-        while let Some(current_path) = directories_to_process.pop() {
+        while let Some((current_path, depth)) = directories_to_process.pop() {
             let content = match self
-                .client
-                .repos(&self.owner, &self.repo)
-                .get_content()
-                .path(&current_path)
-                .send()
+                .with_retries(&current_path, || self.list_directory(&current_path, git_ref))
                 .await {
                     Ok(content) => content,
                     Err(e) => {
                         tracing::error!("Failed to list directory {}: {}", current_path, e);
+                        failures.push(FetchFailure {
+                            path: current_path,
+                            reason: format!("failed to list directory: {}", e),
+                        });
                         continue;
                     }
                 };
 
-            for item in content.items {
+            for item in content {
                 if item.r#type == "file" {
-                    file_items.push((item.path, item.name));
-                } else if item.r#type == "dir" {
+                    if self.is_wanted(&item.path) {
+                        files.push(ListedFile {
+                            path: item.path,
+                            size: item.size,
+                        });
+                    }
+                } else if item.r#type == "dir" && max_depth.is_none_or(|max_depth| depth < max_depth) {
                     // Add directory to the queue for processing
-                    directories_to_process.push(item.path);
+                    directories_to_process.push((item.path, depth + 1));
+                }
+            }
+        }
+
+        files
+    }
+
+    /// List all files under the given roots recursively with parallel processing
+    ///
+    /// An empty list of roots scans the whole repository. Files found under
+    /// several overlapping roots are only listed once. `max_depth` limits how
+    /// many levels of subdirectories are scanned below each root, 0 being the
+    /// root directory only.
+    pub async fn list_files(&self, roots: &[String], max_depth: Option<usize>) -> Result<ScanResult> {
+        self.list_files_with_progress(roots, max_depth, |_| {}).await
+    }
+
+    /// List all files under the given roots recursively, reporting how many files were fetched so far
+    ///
+    /// Progress is reported every tenth of the files, whatever the number of
+    /// requests in flight, and not at all for scans of fewer than ten files.
+    pub async fn list_files_with_progress(
+        &self,
+        roots: &[String],
+        max_depth: Option<usize>,
+        on_progress: impl Fn(ScanProgress),
+    ) -> Result<ScanResult> {
+        let mut failures = Vec::new();
+        let file_items = self.list_paths(roots, max_depth, None, &mut failures).await;
+        let mut skipped = Vec::new();
+        let (documents, last_modified) = self.fetch_documents(file_items, None, on_progress, &mut failures, &mut skipped).await;
+        add_unprocessed(&mut failures, skipped);
+
+        Ok(ScanResult {
+            documents,
+            failures,
+            last_modified,
+        })
+    }
+
+    /// Scan all files under the given roots into the storage, returning the files
+    /// that failed or couldn't be processed, binary files left out
+    ///
+    /// Files matching the priority patterns are fetched and stored first, so
+    /// they can be searched while the rest of the repository is being scanned.
+    /// Progress is only reported for the other files. Once the scan completes,
+    /// its summary is available from `scan_report`.
+    pub async fn scan_into(
+        &self,
+        roots: &[String],
+        max_depth: Option<usize>,
+        store: &RwLock<DocumentStorage>,
+        on_progress: impl Fn(ScanProgress),
+    ) -> Result<Vec<FetchFailure>> {
+        let started = std::time::Instant::now();
+        let mut failures = Vec::new();
+        let mut skipped = Vec::new();
+        let file_items = self.list_paths(roots, max_depth, None, &mut failures).await;
+        let files_discovered = file_items.len();
+        let (priority_items, file_items): (Vec<String>, Vec<String>) = file_items
+            .into_iter()
+            .partition(|path| self.priority.as_ref().is_some_and(|priority| priority.is_match(path)));
+
+        let mut files_indexed = 0;
+        let mut total_bytes = 0;
+        if !priority_items.is_empty() {
+            tracing::info!("Fetching {} priority files first", priority_items.len());
+            let (documents, last_modified) = self.fetch_documents(priority_items, None, |_| {}, &mut failures, &mut skipped).await;
+            files_indexed += documents.len();
+            total_bytes += documents.iter().map(|document| document.content.len() as u64).sum::<u64>();
+            store_scanned(store, documents, &last_modified).await?;
+        }
+
+        let (documents, last_modified) = self.fetch_documents(file_items, None, on_progress, &mut failures, &mut skipped).await;
+        files_indexed += documents.len();
+        total_bytes += documents.iter().map(|document| document.content.len() as u64).sum::<u64>();
+        store_scanned(store, documents, &last_modified).await?;
+
+        let report = ScanReport {
+            owner: self.owner.clone(),
+            repo: self.repo.clone(),
+            git_ref: self.baseline_ref().await,
+            files_discovered,
+            files_indexed,
+            skipped: skipped.clone(),
+            failures: failures.clone(),
+            total_bytes,
+            duration_ms: started.elapsed().as_millis() as u64,
+            completed_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default(),
+        };
+        *self.scan_report.lock().await = Some(report);
+
+        add_unprocessed(&mut failures, skipped);
+        Ok(failures)
+    }
+
+    /// Get the summary of the last full scan, none if no scan completed yet
+    pub async fn scan_report(&self) -> Option<ScanReport> {
+        self.scan_report.lock().await.clone()
+    }
+
+    /// List the files a scan of the given roots would fetch, with their size,
+    /// without fetching their content
+    ///
+    /// Only directory listings are requested, so this is a cheap way to check
+    /// the scan settings, such as include and exclude patterns, before a full scan.
+    pub async fn preview_scan(&self, roots: &[String], max_depth: Option<usize>) -> ScanPreview {
+        let mut failures = Vec::new();
+        let files = self.list_entries(roots, max_depth, None, &mut failures).await;
+        ScanPreview { files, failures }
+    }
+
+    /// List the paths of the files to fetch under the given roots, each once, at
+    /// the given ref or the baseline ref, recording the directories that failed
+    async fn list_paths(&self, roots: &[String], max_depth: Option<usize>, git_ref: Option<&str>, failures: &mut Vec<FetchFailure>) -> Vec<String> {
+        let file_items: Vec<String> = self
+            .list_entries(roots, max_depth, git_ref, failures)
+            .await
+            .into_iter()
+            .map(|file| file.path)
+            .collect();
+
+        // Each file takes a request, so warn early when the scan is going to hit the
+        // rate limit, based on the quota checked before the scan if any
+        if let Some(status) = *self.rate_limit.lock().await
+            && status.remaining < file_items.len() as u64
+        {
+            tracing::warn!(
+                "Fetching {} files needs more than the {} GitHub API requests left, the quota resets in {} seconds",
+                file_items.len(),
+                status.remaining,
+                status.seconds_until_reset()
+            );
+        }
+
+        file_items
+    }
+
+    /// List the files under the given roots with their size, each once, at the
+    /// given ref or the baseline ref, recording the directories that failed
+    async fn list_entries(&self, roots: &[String], max_depth: Option<usize>, git_ref: Option<&str>, failures: &mut Vec<FetchFailure>) -> Vec<ListedFile> {
+        // Scan the repository root when no subfolder is given, and each subfolder once
+        let mut roots: Vec<String> = roots.iter().map(|root| normalize_path(root)).collect();
+        if roots.is_empty() {
+            roots.push(String::new());
+        }
+        let mut seen_roots = HashSet::new();
+        roots.retain(|root| seen_roots.insert(root.clone()));
+
+        // First, collect all file paths to process, from the whole tree at once if possible
+        let file_items = match self.list_tree(&roots, max_depth, git_ref).await {
+            Some(files) => files,
+            None => {
+                let mut files = Vec::new();
+                for root in &roots {
+                    files.extend(self.walk_directories(root, max_depth, git_ref, failures).await);
+                }
+                if self.include_root_readme && !roots.iter().any(String::is_empty) {
+                    let root_files = self.walk_directories("", Some(0), git_ref, failures).await;
+                    files.extend(root_files.into_iter().filter(|file| is_root_readme(&file.path)));
+                }
+                files
+            }
+        };
+
+        // Overlapping roots list the files they share several times
+        let mut seen_files = HashSet::new();
+        file_items
+            .into_iter()
+            .filter(|file| seen_files.insert(file.path.clone()))
+            .collect()
+    }
+
+    /// List the supported files changed between two commits, using the compare API
+    ///
+    /// Only added and modified files under the given roots (the whole repository
+    /// if empty), at most `max_depth` directories below them, are fetched, at `head_ref`. Removed files, and the previous
+    /// paths of renamed files, are listed in `removed` so they can be deleted
+    /// from storage. Both use the paths documents are stored under.
+    pub async fn list_changed_files(
+        &self,
+        base_ref: &str,
+        head_ref: &str,
+        roots: &[String],
+        max_depth: Option<usize>,
+    ) -> Result<ChangeSet> {
+        let route = format!(
+            "/repos/{}/{}/compare/{}...{}",
+            self.owner,
+            self.repo,
+            encode_path(base_ref),
+            encode_path(head_ref)
+        );
+        let comparison: Comparison = self
+            .with_retries(&route, || async {
+                match timeout(self.request_timeout, self.client.get(route.as_str(), None::<&()>)).await {
+                    Ok(comparison) => Ok(comparison?),
+                    Err(_) => Err(KodeError::Timeout(self.request_timeout)),
                 }
+            })
+            .await?;
+
+        // The compare API lists at most 300 files, so a longer diff can't be trusted to be complete
+        if comparison.files.len() >= MAX_COMPARE_FILES {
+            return Err(KodeError::Other(anyhow::anyhow!(
+                "Too many files changed between {} and {} to update incrementally",
+                base_ref,
+                head_ref
+            )));
+        }
+
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+        for file in comparison.files {
+            if let Some(previous_filename) = file.previous_filename
+                && self.is_in_scope(&previous_filename, roots, max_depth)
+            {
+                removed.push(self.stored_path(&previous_filename));
+            }
+            if !self.is_in_scope(&file.filename, roots, max_depth) {
+                continue;
+            }
+            match file.status.as_str() {
+                "removed" => removed.push(self.stored_path(&file.filename)),
+                _ if self.is_wanted(&file.filename) => changed.push(file.filename),
+                _ => {}
             }
         }
 
-        // Fetch file contents in parallel with controlled concurrency
-        let mut file_contents = Vec::with_capacity(file_items.len());
+        let mut failures = Vec::new();
+        let mut skipped = Vec::new();
+        let (documents, last_modified) = self.fetch_documents(changed, Some(head_ref), |_| {}, &mut failures, &mut skipped).await;
+        add_unprocessed(&mut failures, skipped);
+
+        Ok(ChangeSet {
+            documents,
+            removed,
+            failures,
+            last_modified,
+        })
+    }
+
+    /// Fetch every file under the given roots at `head_ref`, listing the files
+    /// there at `base_ref` but no longer at `head_ref` as removed
+    ///
+    /// This updates the storage from `base_ref` to `head_ref` when the compare
+    /// API can't list the changes between them. Files under directories that
+    /// failed to be listed at `head_ref` aren't removed, they may still be there.
+    pub async fn rescan_changes(&self, base_ref: &str, head_ref: &str, roots: &[String], max_depth: Option<usize>) -> Result<ChangeSet> {
+        let mut failures = Vec::new();
+        let file_items = self.list_paths(roots, max_depth, Some(head_ref), &mut failures).await;
+
+        // Files listed at the base ref only are gone, unless they couldn't be listed
+        let listed: HashSet<&str> = file_items.iter().map(String::as_str).collect();
+        let removed: Vec<String> = self
+            .list_paths(roots, max_depth, Some(base_ref), &mut Vec::new())
+            .await
+            .into_iter()
+            .filter(|path| !listed.contains(path.as_str()))
+            .filter(|path| !failures.iter().any(|failure| is_within_depth(path, &failure.path, None)))
+            .map(|path| self.stored_path(&path))
+            .collect();
 
-        // Process files in chunks to control memory usage
-        let chunk_size = self.max_concurrent_requests;
-        for chunk in file_items.chunks(chunk_size) {
-            let mut tasks = Vec::with_capacity(chunk.len());
+        let mut skipped = Vec::new();
+        let (documents, last_modified) = self.fetch_documents(file_items, Some(head_ref), |_| {}, &mut failures, &mut skipped).await;
+        add_unprocessed(&mut failures, skipped);
+
+        Ok(ChangeSet {
+            documents,
+            removed,
+            failures,
+            last_modified,
+        })
+    }
 
-            // Fetch each file's content in parallel
-            for (item_path, _) in chunk {
-                let item_path = item_path.clone();
+    /// Fetch and process files in parallel with controlled concurrency, at the
+    /// given ref or the default branch, recording the files that failed to be
+    /// fetched and those skipped because their content is binary or couldn't be processed
+    async fn fetch_documents(
+        &self,
+        file_items: Vec<String>,
+        git_ref: Option<&str>,
+        on_progress: impl Fn(ScanProgress),
+        failures: &mut Vec<FetchFailure>,
+        skipped: &mut Vec<FetchFailure>,
+    ) -> (Vec<Document>, HashMap<String, u64>) {
+        // Keep a fetch task per request in flight, starting the next file as soon as one
+        // completes, so a slow file doesn't hold back the files after it
+        let concurrency = self.max_concurrent_requests;
+        let total = file_items.len();
+        let report_progress = total >= PROGRESS_STEPS;
+        let mut outcomes = Vec::with_capacity(total);
+        let mut pending = file_items.into_iter().enumerate();
+        let mut tasks = JoinSet::new();
+        // Path of each running task, to report the file of a task that panicked
+        let mut task_paths = HashMap::new();
+        let mut fetched = 0;
+        let mut last_step = 0;
+        loop {
+            while tasks.len() < concurrency {
+                let Some((index, item_path)) = pending.next() else {
+                    break;
+                };
+                let git_ref = git_ref.map(str::to_string);
                 let self_clone = self.clone();
 
-                // Spawn a task for each file to fetch its content
-                let task = tokio::spawn(async move {
-                    match self_clone.get_file_contents(&item_path).await {
-                        Ok(content) => Some((item_path, content)),
-                        Err(e) => {
-                            tracing::error!("Failed to fetch file {}: {}", item_path, e);
-                            None
+                // Each request waits for a permit of the connector's semaphore
+                let task_path = item_path.clone();
+                let task = tasks.spawn(async move {
+                    let result = async {
+                        let content = self_clone.get_file_contents_at(&item_path, git_ref.as_deref()).await?;
+
+                        // A missing date doesn't make the document unusable
+                        let mut last_modified = None;
+                        if self_clone.track_last_modified {
+                            match self_clone.last_modified(&item_path, git_ref.as_deref()).await {
+                                Ok(modified) => last_modified = modified,
+                                Err(e) => tracing::warn!("Failed to get the last commit of {}: {}", item_path, e),
+                            }
                         }
+
+                        Ok::<_, KodeError>((content, last_modified))
                     }
+                    .await;
+                    (index, item_path, result)
                 });
+                task_paths.insert(task.id(), (index, task_path));
+            }
 
-                tasks.push(task);
+            let Some(joined) = tasks.join_next_with_id().await else {
+                break;
+            };
+            match joined {
+                Ok((id, outcome)) => {
+                    task_paths.remove(&id);
+                    outcomes.push(Ok(outcome));
+                }
+                Err(e) => {
+                    if let Some((index, path)) = task_paths.remove(&e.id()) {
+                        outcomes.push(Err((index, path, e)));
+                    }
+                }
+            }
+
+            // Report progress when the scan crosses the next step, failed files included
+            fetched += 1;
+            let step = fetched * PROGRESS_STEPS / total;
+            if report_progress && step > last_step {
+                last_step = step;
+                on_progress(ScanProgress { fetched, total });
             }
+        }
 
-            // Wait for all tasks in this chunk to complete
-            for task in tasks {
-                if let Ok(Some((path, content))) = task.await {
-                    file_contents.push((path, content));
+        // Files complete in any order, they are processed in the order they were listed
+        outcomes.sort_unstable_by_key(|outcome| match outcome {
+            Ok((index, _, _)) | Err((index, _, _)) => *index,
+        });
+        let mut file_contents = Vec::with_capacity(outcomes.len());
+        for outcome in outcomes {
+            match outcome {
+                Ok((_, path, Ok((content, last_modified)))) => file_contents.push((path, content, last_modified)),
+                // Binary files with a documentation extension aren't documents, rather than failed ones
+                Ok((_, path, Err(KodeError::BinaryContent(_)))) => {
+                    tracing::info!("Skipping binary file {}", path);
+                    skipped.push(FetchFailure {
+                        path,
+                        reason: BINARY_CONTENT.to_string(),
+                    });
+                }
+                Ok((_, path, Err(e))) => {
+                    tracing::error!("Failed to fetch file {}: {}", path, e);
+                    failures.push(FetchFailure {
+                        path,
+                        reason: format!("failed to fetch file: {}", e),
+                    });
+                }
+                Err((_, path, e)) => {
+                    tracing::error!("Fetch task for file {} failed: {}", path, e);
+                    failures.push(FetchFailure {
+                        path,
+                        reason: format!("fetch task failed: {}", e),
+                    });
                 }
             }
         }
 
-        // Now process the file contents sequentially with a single scanner instance
+        // Now process the file contents sequentially with the connector's scanner
         let mut documents = Vec::with_capacity(file_contents.len());
+        let mut modified_times = HashMap::new();
+
+        for (path, content, last_modified) in file_contents {
+            // Documents are stored under their rewritten path, failures keep the repository path
+            match self.scanner.process_content(&self.stored_path(&path), content) {
+                Ok(document) => {
+                    if let Some(last_modified) = last_modified {
+                        modified_times.insert(document.path.clone(), last_modified);
+                    }
+                    documents.push(document);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to process file {}: {}", path, e);
+                    skipped.push(FetchFailure {
+                        reason: format!("failed to process file: {}", e),
+                        path,
+                    });
+                }
+            }
+        }
 
-        for (path, content) in file_contents {
-            // Extract filename from path for title fallback
-            let filename = path.split('/').last().unwrap_or("Untitled").to_string();
+        (documents, modified_times)
+    }
+}
 
-            // Extract title from content or use filename
-            let title = scanner.extract_title(&content)
-                .unwrap_or_else(|| filename);
+/// Add the skipped files to the failures, but for binary files, which aren't
+/// documents rather than documents missing from the index
+fn add_unprocessed(failures: &mut Vec<FetchFailure>, skipped: Vec<FetchFailure>) {
+    failures.extend(skipped.into_iter().filter(|file| file.reason != BINARY_CONTENT));
+}
 
-            // Generate a proper summary
-            let summary = scanner.generate_summary(&content);
+/// Store scanned documents and the time of their last commit in a single batch
+async fn store_scanned(
+    store: &RwLock<DocumentStorage>,
+    documents: Vec<Document>,
+    last_modified: &HashMap<String, u64>,
+) -> Result<()> {
+    let mut store = store.write().await;
+    store.begin_batch();
+    store.store_documents(documents)?;
+    store.set_last_modified(last_modified)?;
+    store.commit()
+}
 
-            let document = Document {
-                path,
-                content,
-                title,
-                summary,
-            };
+/// Compile the priority glob patterns, none if there are no patterns
+fn build_priority(patterns: &[String]) -> Result<Option<Arc<GlobSet>>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
 
-            documents.push(document);
-        }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| KodeError::InvalidArgument(format!("invalid priority pattern {}: {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    let priority = builder.build().map_err(|e| KodeError::InvalidArgument(e.to_string()))?;
+    Ok(Some(Arc::new(priority)))
+}
 
-        Ok(documents)
+/// Delay to wait before retrying an unsuccessful response: the `Retry-After`
+/// delay, or the time left until the quota resets once it's exhausted
+///
+/// GitHub answers requests over the primary rate limit with a 403 and no
+/// `Retry-After`, only telling when the quota resets.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+    if let Some(seconds) = header("retry-after") {
+        return Some(Duration::from_secs(seconds));
     }
+    if header("x-ratelimit-remaining") != Some(0) {
+        return None;
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let reset = header("x-ratelimit-reset").unwrap_or_default();
+    Some(Duration::from_secs(reset.saturating_sub(now)))
+}
+
+/// Percent-encode a repository path for use in an API route, keeping `/` separators
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.trim_start_matches('/').bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Normalize a repository path to the form used in API responses, without
+/// leading `./` or surrounding slashes
+fn normalize_path(path: &str) -> String {
+    path.trim()
+        .trim_start_matches("./")
+        .trim_matches('/')
+        .to_string()
+}
+
+/// Check whether a repository path is inside a folder, the empty folder being the repository root
+pub fn is_in_folder(path: &str, folder: &str) -> bool {
+    let folder = normalize_path(folder);
+    folder.is_empty() || path.starts_with(&format!("{}/", folder))
+}
+
+/// Check whether a repository path is inside any of the folders, an empty list
+/// meaning the whole repository
+pub fn is_in_any_folder(path: &str, folders: &[String]) -> bool {
+    folders.is_empty() || folders.iter().any(|folder| is_in_folder(path, folder))
+}
+
+/// Check whether a repository path is the README at the repository root
+fn is_root_readme(path: &str) -> bool {
+    let path = path.to_lowercase();
+    path == "readme.md" || path == "readme.rst"
+}
+
+/// Check whether a repository path is inside a folder, at most `max_depth`
+/// directories below it
+fn is_within_depth(path: &str, folder: &str, max_depth: Option<usize>) -> bool {
+    let relative = if folder.is_empty() {
+        Some(path)
+    } else {
+        path.strip_prefix(folder).and_then(|relative| relative.strip_prefix('/'))
+    };
+    // The depth of a file is the number of directories between it and the folder
+    relative.is_some_and(|relative| {
+        max_depth.is_none_or(|max_depth| relative.matches('/').count() <= max_depth)
+    })
 }
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::FetchFailure;
+use crate::error::Result;
+
+/// Name of the scan report file, written to the storage directory
+pub const SCAN_REPORT_FILE: &str = "scan_report.json";
+
+/// Machine-readable summary of a full repository scan, for CI pipelines to check
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub owner: String,
+    pub repo: String,
+    /// The ref the repository was scanned at
+    pub git_ref: String,
+    /// Files matching the scan settings
+    pub files_discovered: usize,
+    /// Files stored as documents
+    pub files_indexed: usize,
+    /// Files fetched but not indexed, such as notebooks that can't be parsed, and why
+    pub skipped: Vec<FetchFailure>,
+    /// Directories and files that couldn't be fetched, and why
+    pub failures: Vec<FetchFailure>,
+    /// Total size of the indexed content, in bytes
+    pub total_bytes: u64,
+    pub duration_ms: u64,
+    /// Unix timestamp of the end of the scan
+    pub completed_at: u64,
+}
+
+impl ScanReport {
+    /// Write the report to `scan_report.json` in a directory, replacing any previous report
+    pub fn save(&self, directory: impl AsRef<Path>) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(directory.as_ref().join(SCAN_REPORT_FILE), json)?;
+        Ok(())
+    }
+
+    /// Read the report saved in a directory
+    pub fn load(directory: impl AsRef<Path>) -> Result<Self> {
+        let json = std::fs::read_to_string(directory.as_ref().join(SCAN_REPORT_FILE))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
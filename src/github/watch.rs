@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration};
+use tokio_util::sync::CancellationToken;
+
+use super::GitHubConnector;
+use crate::error::Result;
+use crate::storage::DocumentStorage;
+
+/// Keeps the storage current with the repository, re-indexing the files changed
+/// whenever the head of the default branch moves
+pub struct Watcher {
+    connector: GitHubConnector,
+    store: Arc<RwLock<DocumentStorage>>,
+    // Subfolders of the repository to watch, the whole repository if empty
+    roots: Vec<String>,
+    // Maximum number of subdirectory levels rescanned below the subfolders
+    max_depth: Option<usize>,
+    // Head commit the storage is up to date with, none until the first poll
+    head: Option<String>,
+}
+
+impl Watcher {
+    /// Watch the given subfolders of the connector's repository, the storage
+    /// being up to date with `head` if given
+    pub fn new(
+        connector: GitHubConnector,
+        store: Arc<RwLock<DocumentStorage>>,
+        roots: &[String],
+        max_depth: Option<usize>,
+        head: Option<String>,
+    ) -> Self {
+        Self {
+            connector,
+            store,
+            roots: roots.to_vec(),
+            max_depth,
+            head,
+        }
+    }
+
+    /// The head commit the storage is up to date with
+    pub fn head(&self) -> Option<&str> {
+        self.head.as_deref()
+    }
+
+    /// Check the head of the repository once, updating the storage if it moved
+    ///
+    /// The first poll without a known head only records it. Changes are fetched
+    /// before taking the storage lock, so searches aren't blocked meanwhile.
+    /// Returns whether the storage was updated.
+    pub async fn poll(&mut self) -> Result<bool> {
+        let head = self.connector.head_sha().await?;
+        let Some(previous) = self.head.as_deref().filter(|previous| *previous != head) else {
+            self.head = Some(head);
+            return Ok(false);
+        };
+
+        tracing::info!("Repository head moved from {} to {}, updating documents", previous, head);
+        let failures = match self.connector.list_changed_files(previous, &head, &self.roots, self.max_depth).await {
+            Ok(changes) => changes.apply(&mut *self.store.write().await)?,
+            Err(e) => {
+                // Too many changes to compare, rescan everything at the new head instead
+                tracing::warn!("Failed to list changed files, rescanning the repository: {}", e);
+                let changes = self.connector.rescan_changes(previous, &head, &self.roots, self.max_depth).await?;
+                changes.apply(&mut *self.store.write().await)?
+            }
+        };
+        if !failures.is_empty() {
+            tracing::warn!("{} paths could not be updated, the index is incomplete", failures.len());
+        }
+
+        self.head = Some(head);
+        Ok(true)
+    }
+
+    /// Poll the repository at the given interval until cancelled
+    ///
+    /// Failed polls are logged and retried at the next interval.
+    pub async fn run(mut self, interval: Duration, cancellation: CancellationToken) {
+        loop {
+            tokio::select! {
+                _ = cancellation.cancelled() => break,
+                _ = sleep(interval) => {}
+            }
+
+            if let Err(e) = self.poll().await {
+                tracing::warn!("Failed to check the repository for changes: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::path::Path;
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+use crate::error::{KodeError, Result};
+
+/// Upper bound on how long cloning a wiki can take
+const CLONE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Messages git fails with when a repository doesn't exist
+const MISSING_REPOSITORY_ERRORS: &[&str] = &["not found", "does not exist"];
+
+/// Messages git fails with when the token is missing, invalid or doesn't grant
+/// access to the repository, git asking for credentials it can't prompt for
+const AUTHENTICATION_ERRORS: &[&str] = &["could not read username", "authentication failed"];
+
+/// Shallow clone the git repository of a wiki into an empty directory
+///
+/// The token, if any, is passed to git through the environment rather than the
+/// command line, so it doesn't show up in the process list. Returns `false`
+/// if the wiki doesn't exist, and fails with `KodeError::AuthenticationFailed`
+/// if the credentials don't grant access to it.
+pub async fn clone(url: &str, token: Option<&str>, directory: &Path) -> Result<bool> {
+    let mut command = Command::new("git");
+    command
+        .args(["clone", "--depth", "1", "--quiet", url])
+        .arg(directory)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    if let Some(token) = token {
+        let credentials = STANDARD.encode(format!("x-access-token:{}", token));
+        command
+            .env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+            .env("GIT_CONFIG_VALUE_0", format!("Authorization: Basic {}", credentials));
+    }
+
+    let output = match timeout(CLONE_TIMEOUT, command.output()).await {
+        Ok(output) => output?,
+        Err(_) => return Err(anyhow::anyhow!("Cloning {} timed out after {:?}", url, CLONE_TIMEOUT).into()),
+    };
+    if output.status.success() {
+        return Ok(true);
+    }
+
+    let error = String::from_utf8_lossy(&output.stderr).to_lowercase();
+    if MISSING_REPOSITORY_ERRORS.iter().any(|message| error.contains(message)) {
+        return Ok(false);
+    }
+    if AUTHENTICATION_ERRORS.iter().any(|message| error.contains(message)) {
+        return Err(KodeError::AuthenticationFailed(format!("unauthorized to clone {}", url)));
+    }
+    Err(anyhow::anyhow!("Failed to clone {}: {}", url, error.trim()).into())
+}
@@ -15,6 +15,9 @@
 //! - `storage`: Document storage and retrieval
 //! - `document`: Document processing and parsing
 //! - `github`: GitHub API integration for fetching documents
+//! - `web`: Fetching standalone web pages as documents
+//! - `config`: Server configuration from a config file and the command line
+//! - `error`: Typed errors of the GitHub connector and the document storage
 
 /// Server implementation and MCP tools
 pub mod server;
@@ -24,3 +27,9 @@ pub mod storage;
 pub mod document;
 /// GitHub API integration
 pub mod github;
+/// Web pages fetched by URL
+pub mod web;
+/// Server configuration
+pub mod config;
+/// Typed errors
+pub mod error;
@@ -1,38 +1,30 @@
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use anyhow::Result;
 use rmcp::{ServiceExt, transport::stdio};
 use tokio::sync::RwLock;
-use kode_ai_rs::server::Documents;
-use kode_ai_rs::storage::DocumentStorage;
+use tokio_util::sync::CancellationToken;
+use kode_ai_rs::server::{Documents, RepositoryInfo, ServerStatus};
+use kode_ai_rs::storage::{DocumentStorage, Stopwords, Synonyms};
 use clap::Parser;
-use kode_ai_rs::github::GitHubConnector;
+use kode_ai_rs::config::{Cli, ServerConfig};
+use kode_ai_rs::document::{Document, DocumentScanner};
+use kode_ai_rs::error::KodeError;
+use kode_ai_rs::github::{ConnectorOptions, FetchFailure, GitHubConnector, Watcher, RELEASES_PREFIX, SCAN_REPORT_FILE, WIKI_PREFIX};
+use kode_ai_rs::web::{FetchResult, WebFetcher, WebOptions};
 
 #[cfg(feature = "trace")]
 use tracing_subscriber::{EnvFilter};
 
-#[derive(Parser)]
-struct Cli {
-    /// A github repository to scan for documentation
-    #[clap(long, default_value = "rust-sdk")]
-    github_repo: String,
-    /// A github repository subfolder to scan for documentation (optional)
-    #[clap(long, default_value = "")]
-    github_subfolder: String,
-    /// A github repository owner (optional)
-    #[clap(long, default_value = "modelcontextprotocol")]
-    github_owner: String,
-    /// A github personal access token to use for authentication (optional)
-    #[clap(long)]
-    github_pat: Option<String>,
-}
-
 /// You can inspect the server using the Model Context Protocol Inspector.
 /// npx @modelcontextprotocol/inspector cargo run -p kode-ai-rs
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Get command line arguments
-    let args = Cli::parse();
+    // Get the configuration from the command line arguments and the config file if any
+    let cli = Cli::parse();
+    let config = ServerConfig::load(&cli)?;
 
     // Initialize the tracing subscriber with file and stdout logging
     #[cfg(feature = "trace")]
@@ -42,44 +34,440 @@ async fn main() -> Result<()> {
         .with_ansi(false)
         .init();
 
+    if cli.dry_run {
+        return dry_run(&config).await;
+    }
+
     tracing::info!("Starting MCP server");
 
-    // Document storage initialization in temporary directory
-    let temp_dir = tempfile::tempdir()?;
-    tracing::info!("Using temporary directory for document storage: {:?}", temp_dir.path());
-    let mut store = DocumentStorage::new(temp_dir.path())?;
-    tracing::info!("Document storage initialized at: {:?}", temp_dir.path());
+    // Document storage initialization in the configured directory, or in memory only
+    let mut store = match &config.storage_path {
+        Some(storage_path) => {
+            tracing::info!("Document storage initialized at: {:?}", storage_path);
+            DocumentStorage::new(storage_path)?
+        }
+        None => {
+            tracing::info!("No storage path specified, keeping documents in memory");
+            DocumentStorage::new_in_memory()
+        }
+    };
+    store.set_scoring_weights(config.scoring);
+    store.set_recency_boost(config.recency_boost);
+    store.set_compression(config.compress_storage);
+    store.set_stemming(config.stemming);
+    store.set_diacritic_folding(config.fold_diacritics);
+    store.set_html_prerendering(config.prerender_html);
+    let mut stopwords = Stopwords::default();
+    for (language, words) in &config.stopwords {
+        stopwords.set(language, words);
+    }
+    store.set_stopwords(stopwords);
+    store.set_max_keywords(config.max_keywords_per_document);
+    store.set_tokenizer(config.tokenizer.tokenizer());
+    store.set_internal_paths(&config.internal_paths)?;
+    if config.expand_queries {
+        let mut synonyms = Synonyms::default();
+        for (word, words) in &config.synonyms {
+            synonyms.add(word, words);
+        }
+        store.set_synonyms(Some(synonyms));
+    }
+
+    // Index the local directory, if any, before serving
+    if let Some(local_path) = &config.local_path {
+        let documents = scan_local_directory(local_path, &config)?;
+        tracing::info!("Found {} documents in local directory {:?}", documents.len(), local_path);
+        store.store_documents(documents)?;
+    }
+
+    // Index the web pages, if any, before serving
+    let urls = config.page_urls()?;
+    if !urls.is_empty() {
+        let result = fetch_pages(&urls, &config).await?;
+        tracing::info!("Fetched {} of {} web pages", result.documents.len(), urls.len());
+        if !result.failures.is_empty() {
+            tracing::warn!("{} web pages could not be fetched", result.failures.len());
+        }
+        store.store_documents(result.documents)?;
+    }
 
     // Setup Github connector
-    let github_connector = if !args.github_repo.is_empty() {
-        Some(GitHubConnector::new(
-            &args.github_owner,
-            &args.github_repo,
-            args.github_pat.as_deref(),
-        ).await?)
+    let github_connector = if !config.github_repo.is_empty() {
+        Some(connect(&config).await?)
     } else {
         tracing::info!("No github repository specified, skipping");
         None
     };
 
-    if let Some(connector) = &github_connector {
-        tracing::info!("Scanning GitHub repository {} in subfolder: {}", connector.repo, args.github_subfolder);
-        match connector.list_files(&args.github_subfolder).await {
-            Ok(documents) => {
-                tracing::info!("Found {} documents in the repository", documents.len());
-                store.store_documents(documents)?;
+    let store = Arc::new(RwLock::new(store));
+    let saved_store = store.clone();
+    let cancellation = CancellationToken::new();
+
+    // Serve right away, search tools following the not ready policy until the initial scan completes
+    let max_response_bytes = config.max_response_bytes;
+    let qualify_titles = config.qualify_titles;
+    let public_only = config.public_only;
+    let internal_token = config.internal_token.clone();
+    let scanner = content_scanner(&config);
+    let documents = match github_connector {
+        Some(connector) => {
+            let mut repository = RepositoryInfo::new(connector.owner(), &connector.repo, &config.github_subfolder);
+            repository.git_ref = connector.baseline_ref().await;
+            let mut documents = Documents::initializing(store.clone(), config.not_ready_policy).with_repository(repository);
+
+            // Without polling, a stale index is rescanned on the next query by a watcher
+            // set up once the initial scan is done
+            let stale_watcher: Arc<tokio::sync::Mutex<Option<Watcher>>> = Arc::default();
+            if let (Some(ttl), None) = (config.stale_ttl, config.watch_interval) {
+                let stale_watcher = stale_watcher.clone();
+                documents = documents.with_stale_ttl(Duration::from_secs(ttl), move || {
+                    let stale_watcher = stale_watcher.clone();
+                    async move {
+                        if let Some(watcher) = stale_watcher.lock().await.as_mut()
+                            && let Err(e) = watcher.poll().await
+                        {
+                            tracing::error!("Failed to rescan the stale index: {}", e);
+                        }
+                    }
+                });
+            }
+
+            let scanned_documents = documents.clone();
+            let watch_cancellation = cancellation.clone();
+            tokio::spawn(async move {
+                // Take the head before scanning, so changes pushed during the scan are picked up by the watcher
+                let head = match (config.watch_interval, config.stale_ttl) {
+                    (None, None) => None,
+                    _ => connector.head_sha().await.ok(),
+                };
+                match scan(&connector, &config, cli.since_ref.as_deref(), &store).await {
+                    Ok(failures) => {
+                        if !failures.is_empty() {
+                            tracing::warn!("{} paths could not be scanned, the index is incomplete", failures.len());
+                        }
+                        scanned_documents.set_scan_failures(failures).await;
+                        scanned_documents.set_status(ServerStatus::Ready);
+
+                        if let Some(interval) = config.watch_interval {
+                            tracing::info!("Watching the repository for changes every {} seconds", interval);
+                            let watcher = Watcher::new(connector, store, &config.github_subfolder, config.max_depth, head);
+                            watcher.run(Duration::from_secs(interval), watch_cancellation).await;
+                        } else if config.stale_ttl.is_some() {
+                            let watcher = Watcher::new(connector, store, &config.github_subfolder, config.max_depth, head);
+                            *stale_watcher.lock().await = Some(watcher);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to scan GitHub repository: {}", e);
+                        scanned_documents.set_status(ServerStatus::Error(e.to_string()));
+                    }
+                }
+            });
+            documents
+        }
+        None => Documents::new(store),
+    };
+    let documents = documents.with_max_response_bytes(max_response_bytes).with_qualified_titles(qualify_titles)
+        .with_public_only(public_only).with_internal_token(internal_token).with_scanner(scanner);
+
+    // Stop serving, and watching the repository, on SIGINT or SIGTERM
+    let service = documents
+        .serve_with_ct(stdio(), cancellation.clone()).await.inspect_err(|e| {
+            tracing::error!("serving error: {:?}", e);
+        })?;
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        tracing::info!("Shutdown requested, stopping the server");
+        cancellation.cancel();
+    });
+
+    let reason = service.waiting().await?;
+    tracing::info!("Server stopped: {:?}", reason);
+
+    // Documents are saved while holding the write lock, so taking it waits for any
+    // save in progress, and keeps new ones from starting before the process exits
+    let _store = saved_store.write().await;
+    tracing::info!("Document storage flushed, exiting");
+    Ok(())
+}
+
+/// Wait until the process is asked to stop, with Ctrl-C or SIGTERM
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
             }
             Err(e) => {
-                tracing::error!("Failed to scan GitHub repository: {}", e);
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
             }
         }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
     }
+}
 
-    let service = Documents::new(Arc::new(RwLock::new(store)))
-        .serve(stdio()).await.inspect_err(|e| {
-            tracing::error!("serving error: {:?}", e);
-        })?;
+/// Create the connector to the configured GitHub repository
+async fn connect(config: &ServerConfig) -> Result<GitHubConnector> {
+    let options = ConnectorOptions {
+        request_timeout: Duration::from_secs(config.request_timeout),
+        max_concurrent_requests: config.max_concurrent_requests,
+        include: config.include.clone(),
+        exclude: config.exclude.clone(),
+        strip_prefix: config.strip_prefix.clone(),
+        path_prefix: config.path_prefix.clone(),
+        include_root_readme: config.include_root_readme,
+        track_last_modified: config.track_last_modified,
+        summary_length: config.max_summary_length,
+        cache_capacity: config.cache_capacity,
+        priority: config.priority.clone(),
+        normalization: config.content_normalization(),
+        generic_filenames: config.generic_filenames.clone(),
+        extension_groups: config.extension_groups.clone(),
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options(
+        &config.github_owner,
+        &config.github_repo,
+        config.github_pat.as_deref(),
+        options,
+    ).await?;
+
+    // Fail early on a token that can't read the repository, rather than on every file of the scan,
+    // but don't give up on a persisted index because GitHub is unavailable for now
+    match connector.validate_access().await {
+        Ok(()) => {}
+        Err(e @ KodeError::AuthenticationFailed(_)) => return Err(e.into()),
+        Err(e) => tracing::warn!("Failed to check the access to the repository, scanning it anyway: {}", e),
+    }
+    Ok(connector)
+}
+
+/// Print the files the configured local directory and repository scans would
+/// index, with their sizes, without fetching or storing them
+async fn dry_run(config: &ServerConfig) -> Result<()> {
+    let mut files = Vec::new();
+    if let Some(local_path) = &config.local_path {
+        files.extend(local_scanner(local_path, config)?.preview_directory(local_path, config.max_depth)?);
+    }
+
+    let mut failures = Vec::new();
+    if !config.github_repo.is_empty() {
+        let connector = connect(config).await?;
+        let preview = connector.preview_scan(&config.github_subfolder, config.max_depth).await;
+        files.extend(preview.files);
+        failures = preview.failures;
+    }
 
-    service.waiting().await?;
+    for file in &files {
+        println!("{:>10}  {}", file.size, file.path);
+    }
+    let total: u64 = files.iter().map(|file| file.size).sum();
+    println!("{} files, {} bytes", files.len(), total);
+    // Failures go to stderr, so they don't mix with the listing when it's piped
+    for failure in &failures {
+        eprintln!("Failed to list {}: {}", failure.path, failure.reason);
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Scan a local directory for documents, with the configured patterns and depth
+fn scan_local_directory(local_path: &Path, config: &ServerConfig) -> Result<Vec<Document>> {
+    local_scanner(local_path, config)?.scan_directory(local_path, config.max_depth)
+}
+
+/// Fetch web pages, with the configured timeout and content processing
+async fn fetch_pages(urls: &[String], config: &ServerConfig) -> Result<FetchResult> {
+    let scanner = content_scanner(config);
+    let options = WebOptions {
+        request_timeout: Duration::from_secs(config.request_timeout),
+        ..WebOptions::default()
+    };
+    Ok(WebFetcher::new(options, scanner)?.fetch_pages(urls).await)
+}
+
+/// Create a scanner building documents from content, with the configured
+/// summary length, normalization and fallback titles
+fn content_scanner(config: &ServerConfig) -> DocumentScanner {
+    let mut scanner = DocumentScanner::new();
+    scanner.set_summary_length(config.max_summary_length);
+    scanner.set_normalization(config.content_normalization());
+    scanner.set_generic_filenames(&config.generic_filenames);
+    scanner
+}
+
+/// Create a scanner of a local directory, with the configured patterns
+fn local_scanner(local_path: &Path, config: &ServerConfig) -> Result<DocumentScanner> {
+    if !local_path.is_dir() {
+        anyhow::bail!("Local path {:?} is not a directory", local_path);
+    }
+
+    let mut scanner = DocumentScanner::new();
+    scanner.set_patterns(&config.include, &config.exclude)?;
+    scanner.set_summary_length(config.max_summary_length);
+    scanner.set_follow_symlinks(config.follow_symlinks, config.external_symlinks);
+    scanner.set_extension_groups(&config.extension_groups);
+    scanner.set_normalization(config.content_normalization());
+    scanner.set_generic_filenames(&config.generic_filenames);
+    Ok(scanner)
+}
+
+/// Fill the storage from the repository, updating only the documents changed
+/// since `since_ref` when possible
+async fn scan(
+    connector: &GitHubConnector,
+    config: &ServerConfig,
+    since_ref: Option<&str>,
+    store: &RwLock<DocumentStorage>,
+) -> Result<Vec<FetchFailure>> {
+    // Incremental updates only make sense on top of a previously filled persistent storage
+    let since_ref = match (since_ref, &config.storage_path) {
+        (Some(_), None) => {
+            tracing::warn!("--since-ref requires a persistent --storage-path, scanning the whole repository");
+            None
+        }
+        (Some(_), Some(_)) if store.read().await.get_all_documents().is_empty() => {
+            tracing::info!("Document storage is empty, scanning the whole repository");
+            None
+        }
+        (since_ref, _) => since_ref,
+    };
+
+    let failures = match since_ref {
+        Some(since_ref) => match update_changed_files(connector, config, since_ref, store).await {
+            Ok(failures) => failures,
+            Err(e) => {
+                tracing::warn!("Failed to update changed files, scanning the whole repository: {}", e);
+                scan_repository(connector, config, store).await?
+            }
+        },
+        None => scan_repository(connector, config, store).await?,
+    };
+
+    // The wiki is a separate repository, so it isn't versioned with the scanned refs
+    if config.include_wiki
+        && let Err(e) = scan_wiki(connector, store).await
+    {
+        tracing::warn!("Failed to scan the repository wiki: {}", e);
+    }
+    // Releases aren't versioned either, and are always fetched again
+    if config.include_releases
+        && let Err(e) = scan_releases(connector, store).await
+    {
+        tracing::warn!("Failed to scan the repository releases: {}", e);
+    }
+
+    Ok(failures)
+}
+
+/// Replace the wiki pages in the storage with the current pages of the repository wiki
+async fn scan_wiki(connector: &GitHubConnector, store: &RwLock<DocumentStorage>) -> Result<()> {
+    let pages = connector.list_wiki_pages().await?;
+    tracing::info!("Found {} wiki pages", pages.len());
+    Ok(store.write().await.replace_source(WIKI_PREFIX, pages)?)
+}
+
+/// Replace the release notes in the storage with the current releases of the repository
+async fn scan_releases(connector: &GitHubConnector, store: &RwLock<DocumentStorage>) -> Result<()> {
+    let releases = connector.list_releases().await?;
+    tracing::info!("Found {} releases with notes", releases.len());
+    Ok(store.write().await.replace_source(RELEASES_PREFIX, releases)?)
+}
+
+/// Scan the whole configured subfolders of the repository into the storage
+async fn scan_repository(
+    connector: &GitHubConnector,
+    config: &ServerConfig,
+    store: &RwLock<DocumentStorage>,
+) -> Result<Vec<FetchFailure>> {
+    check_rate_limit(connector).await?;
+
+    tracing::info!("Scanning GitHub repository {} in subfolders: {:?}", connector.repo, config.github_subfolder);
+    let failures = connector
+        .scan_into(&config.github_subfolder, config.max_depth, store, |progress| {
+            tracing::info!(
+                fetched = progress.fetched,
+                total = progress.total,
+                "Fetched {} of {} files",
+                progress.fetched,
+                progress.total
+            );
+        })
+        .await?;
+
+    // The store also holds local, wiki and previously persisted documents, so count the scanned ones only
+    let report = connector.scan_report().await;
+    if let Some(report) = &report {
+        tracing::info!("Found {} documents in the repository", report.files_indexed);
+    }
+
+    // Leave a summary of the scan next to the stored documents, for CI pipelines to check
+    if let (Some(report), Some(storage_path)) = (report, &config.storage_path) {
+        match report.save(storage_path) {
+            Ok(()) => tracing::info!("Scan report written to {:?}", storage_path.join(SCAN_REPORT_FILE)),
+            Err(e) => tracing::warn!("Failed to write the scan report: {}", e),
+        }
+    }
+    Ok(failures)
+}
+
+/// Log the remaining GitHub API quota, failing if it's already exhausted
+async fn check_rate_limit(connector: &GitHubConnector) -> Result<()> {
+    let status = match connector.rate_limit_status().await {
+        Ok(status) => status,
+        Err(e) => {
+            tracing::warn!("Failed to get the GitHub API rate limit status: {}", e);
+            return Ok(());
+        }
+    };
+
+    tracing::info!(
+        "GitHub API quota: {} of {} requests left, resetting in {} seconds",
+        status.remaining,
+        status.limit,
+        status.seconds_until_reset()
+    );
+    if status.remaining == 0 {
+        anyhow::bail!(
+            "GitHub API rate limit exceeded, retry in {} seconds or use a personal access token for a higher limit",
+            status.seconds_until_reset()
+        );
+    }
+
+    Ok(())
+}
+
+/// Update the storage with the documents of the configured subfolders changed since a ref
+async fn update_changed_files(
+    connector: &GitHubConnector,
+    config: &ServerConfig,
+    since_ref: &str,
+    store: &RwLock<DocumentStorage>,
+) -> Result<Vec<FetchFailure>> {
+    check_rate_limit(connector).await?;
+
+    tracing::info!("Updating documents of GitHub repository {} changed since {}", connector.repo, since_ref);
+    let head_ref = connector.baseline_ref().await;
+    let changes = connector.list_changed_files(since_ref, &head_ref, &config.github_subfolder, config.max_depth).await?;
+
+    tracing::info!(
+        "Found {} changed and {} removed documents",
+        changes.documents.len(),
+        changes.removed.len()
+    );
+    Ok(changes.apply(&mut *store.write().await)?)
+}
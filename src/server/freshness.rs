@@ -0,0 +1,57 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type Rescan = dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync;
+
+/// Keeps the index fresh lazily: the first query after the index got older
+/// than a time to live schedules a rescan in the background, and is answered
+/// from the current documents meanwhile
+pub struct Freshness {
+    ttl: Duration,
+    rescan: Box<Rescan>,
+    // When the index was last scanned
+    scanned_at: Mutex<Instant>,
+    // Whether a rescan is running, so concurrent queries schedule a single one
+    rescanning: AtomicBool,
+}
+
+impl Freshness {
+    /// Rescan with the given function once the index is older than `ttl`
+    pub fn new<F, Fut>(ttl: Duration, rescan: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            ttl,
+            rescan: Box::new(move || Box::pin(rescan())),
+            scanned_at: Mutex::new(Instant::now()),
+            rescanning: AtomicBool::new(false),
+        }
+    }
+
+    /// Record that the index was just scanned
+    pub fn mark_scanned(&self) {
+        *self.scanned_at.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Instant::now();
+    }
+
+    /// Schedule a rescan in the background if the index is older than the time
+    /// to live and no rescan is running, returning whether one was scheduled
+    pub fn rescan_if_stale(self: &Arc<Self>) -> bool {
+        let age = self.scanned_at.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).elapsed();
+        if age < self.ttl || self.rescanning.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+
+        let freshness = self.clone();
+        tokio::spawn(async move {
+            (freshness.rescan)().await;
+            freshness.mark_scanned();
+            freshness.rescanning.store(false, Ordering::Release);
+        });
+        true
+    }
+}
@@ -0,0 +1,45 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Calls and latency of one tool
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ToolStats {
+    pub calls: u64,
+    /// Calls that failed, or returned an error result
+    pub errors: u64,
+    pub total_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Per-tool call counts and latencies, aggregated since the server started
+///
+/// Calls are only recorded with the `metrics` feature.
+#[derive(Debug, Default)]
+pub struct ToolMetrics {
+    tools: Mutex<HashMap<String, ToolStats>>,
+}
+
+impl ToolMetrics {
+    /// Record a call of a tool, and how long it took
+    pub fn record(&self, tool: &str, elapsed: Duration, failed: bool) {
+        let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+        let mut tools = self.tools.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let stats = tools.entry(tool.to_string()).or_default();
+        stats.calls += 1;
+        if failed {
+            stats.errors += 1;
+        }
+        stats.total_ms += elapsed_ms;
+        stats.mean_ms = stats.total_ms / stats.calls as f64;
+        stats.max_ms = stats.max_ms.max(elapsed_ms);
+    }
+
+    /// Get the stats of every tool called so far, by tool name
+    pub fn snapshot(&self) -> BTreeMap<String, ToolStats> {
+        let tools = self.tools.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        tools.iter().map(|(tool, stats)| (tool.clone(), stats.clone())).collect()
+    }
+}
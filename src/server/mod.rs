@@ -1,3 +1,13 @@
+mod freshness;
+mod metrics;
+mod paths;
+mod readiness;
+mod repository;
 mod tools;
+mod truncate;
 
-pub use tools::Documents;
\ No newline at end of file
+pub use metrics::{ToolMetrics, ToolStats};
+pub use readiness::{NotReadyPolicy, Readiness, ServerStatus};
+pub use repository::RepositoryInfo;
+pub use truncate::TRUNCATION_MARKER;
+pub use tools::{EXPORTS_DIRECTORY, AddDocumentRequest, AutocompleteQueryRequest, DiffDocumentsRequest, Documents, ExplainRelevanceRequest, ExportIndexRequest, FindRelevantDocsRequest, GetAllDocsRequest, GetDocumentImagesRequest, GetDocumentRequest, GetDocumentsRequest, GetStaleDocumentsRequest, GetTableOfContentsRequest, ListTopicsRequest, LookupTermRequest, SearchInDocumentRequest, SummarizeDocumentRequest, UnlockInternalDocumentsRequest};
\ No newline at end of file
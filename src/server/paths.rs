@@ -0,0 +1,41 @@
+/// Why a document path given by a client was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidPath {
+    NullByte,
+    Absolute,
+    ParentComponent,
+}
+
+impl InvalidPath {
+    /// Reason reported to clients
+    pub fn reason(&self) -> &'static str {
+        match self {
+            InvalidPath::NullByte => "the path contains a null byte",
+            InvalidPath::Absolute => "the path must be relative to the repository root",
+            InvalidPath::ParentComponent => "the path must not contain .. components",
+        }
+    }
+}
+
+/// Check that a document path given by a client stays within the repository
+///
+/// Stored documents are only looked up by path for now, but rejecting paths
+/// that could escape a directory keeps serving files safe as well. Paths with
+/// a null byte, absolute paths, including Windows drive and UNC paths, and
+/// paths with a `..` component are rejected, whichever separator they use.
+pub fn validate_path(path: &str) -> Result<(), InvalidPath> {
+    if path.contains('\0') {
+        return Err(InvalidPath::NullByte);
+    }
+
+    let bytes = path.as_bytes();
+    let drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if path.starts_with(['/', '\\']) || drive {
+        return Err(InvalidPath::Absolute);
+    }
+
+    if path.split(['/', '\\']).any(|component| component == "..") {
+        return Err(InvalidPath::ParentComponent);
+    }
+    Ok(())
+}
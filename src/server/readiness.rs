@@ -0,0 +1,92 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+
+/// Status of the document index
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "status", content = "error")]
+pub enum ServerStatus {
+    /// The initial scan is still running
+    Initializing,
+    /// The index is built
+    Ready,
+    /// The initial scan failed, so the index may be empty
+    Error(String),
+}
+
+/// What search tools do when called before the initial scan completes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotReadyPolicy {
+    /// Fail with a `index_not_ready` error
+    Reject,
+    /// Wait until the scan completes, as if the server started once the index was built
+    #[default]
+    Wait,
+    /// Search the documents stored so far, such as the priority paths
+    Partial,
+}
+
+impl FromStr for NotReadyPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(policy: &str) -> Result<Self> {
+        match policy.trim().to_lowercase().as_str() {
+            "reject" => Ok(NotReadyPolicy::Reject),
+            "wait" => Ok(NotReadyPolicy::Wait),
+            "partial" => Ok(NotReadyPolicy::Partial),
+            _ => anyhow::bail!("Unknown not ready policy: {} (expected reject, wait or partial)", policy),
+        }
+    }
+}
+
+/// Readiness state of the server, shared between the scan and the tools
+#[derive(Debug, Clone, PartialEq)]
+pub struct Readiness {
+    pub status: ServerStatus,
+    /// Unix timestamp (seconds) of the last successful scan
+    pub last_scan_at: Option<u64>,
+}
+
+/// Tracks readiness changes so tools can wait for the initial scan
+pub struct ReadinessState {
+    sender: watch::Sender<Readiness>,
+}
+
+impl ReadinessState {
+    pub fn new(status: ServerStatus) -> Self {
+        let (sender, _) = watch::channel(Readiness {
+            status,
+            last_scan_at: None,
+        });
+        Self { sender }
+    }
+
+    pub fn get(&self) -> Readiness {
+        self.sender.borrow().clone()
+    }
+
+    pub fn set_status(&self, status: ServerStatus) {
+        self.sender.send_modify(|readiness| {
+            // A completed scan is the last successful one
+            if status == ServerStatus::Ready {
+                readiness.last_scan_at = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .ok()
+                    .map(|elapsed| elapsed.as_secs());
+            }
+            readiness.status = status;
+        });
+    }
+
+    /// Wait until the server is no longer initializing
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        // The sender lives as long as `self`, so this can't fail
+        let _ = receiver
+            .wait_for(|readiness| readiness.status != ServerStatus::Initializing)
+            .await;
+    }
+}
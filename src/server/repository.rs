@@ -0,0 +1,34 @@
+use serde::Serialize;
+
+/// The GitHub repository documents are served from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepositoryInfo {
+    pub owner: String,
+    pub repo: String,
+    /// Branch, tag or commit scanned, `HEAD` if the default branch isn't known
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    /// Scanned subfolders, the whole repository if empty
+    pub subfolders: Vec<String>,
+}
+
+impl RepositoryInfo {
+    /// A repository scanned on its default branch
+    pub fn new(owner: &str, repo: &str, subfolders: &[String]) -> Self {
+        Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            git_ref: "HEAD".to_string(),
+            subfolders: subfolders.to_vec(),
+        }
+    }
+
+    /// Short description of the repository, such as `owner/repo (docs, guides)`
+    pub fn descriptor(&self) -> String {
+        if self.subfolders.is_empty() {
+            format!("{}/{}", self.owner, self.repo)
+        } else {
+            format!("{}/{} ({})", self.owner, self.repo, self.subfolders.join(", "))
+        }
+    }
+}
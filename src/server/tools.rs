@@ -1,83 +1,1137 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use rmcp::model::{AnnotateAble, CallToolResult, Content, Implementation, ListResourcesResult, PaginatedRequestParam, ProtocolVersion, RawResource, Resource, ServerCapabilities, ServerInfo};
 use tokio::sync::{RwLock};
 use serde_json::{json};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use sha2::{Digest, Sha256};
 
 use rmcp::{
-    Error as McpError, RoleServer, ServerHandler, model::*, schemars,
+    Error as McpError, RoleServer, ServerHandler, handler::server::tool::ToolCallContext, model::*, schemars,
     service::RequestContext, tool,
 };
 
-use crate::storage::DocumentStorage;
+use super::freshness::Freshness;
+use super::metrics::ToolMetrics;
+use super::paths::validate_path;
+use super::readiness::{NotReadyPolicy, Readiness, ReadinessState, ServerStatus};
+use super::repository::RepositoryInfo;
+use super::truncate::{truncate, Truncated, TRUNCATION_MARKER};
+use crate::document::{DocumentScanner, RenderFormat, Visibility};
+use crate::error::KodeError;
+use crate::github::FetchFailure;
+use crate::storage::{sort_documents, DocumentStorage, ExportFormat, MatchOptions, SortField, SortOrder, StoredDocument};
 
 
 type DocumentStore = Arc<RwLock<DocumentStorage>>;
 
+/// Scheme of the resource URIs of stored documents, followed by the document path
+const DOCUMENT_URI_SCHEME: &str = "doc://";
+
+/// Maximum number of values of a completion, as set by the MCP specification
+const MAX_COMPLETIONS: usize = 100;
+
+/// Maximum number of keywords suggested by `autocomplete_query`
+const MAX_SUGGESTIONS: usize = 100;
+
+/// Directory of the storage `export_index` writes files to, so clients can't write anywhere else
+pub const EXPORTS_DIRECTORY: &str = "exports";
+
 #[derive(Clone)]
 pub struct Documents{
     pub store: DocumentStore,
+    // Paths that failed during the last scan, so clients can tell the index is incomplete
+    scan_failures: Arc<RwLock<Vec<FetchFailure>>>,
+    // Whether the initial scan has completed, and what search tools do until then
+    readiness: Arc<ReadinessState>,
+    not_ready_policy: NotReadyPolicy,
+    // The repository documents are scanned from, none when only serving local documents
+    repository: Option<RepositoryInfo>,
+    // Maximum size of the content returned by a tool call, in bytes, unlimited if not set
+    max_response_bytes: Option<usize>,
+    // Whether titles shared by several documents are prefixed with their directory
+    qualify_titles: bool,
+    // Builds the documents added by clients, with the settings of the scanned documents
+    scanner: Arc<DocumentScanner>,
+    // Whether internal documents are hidden from every tool and resource
+    public_only: bool,
+    // The token a client presents to `unlock_internal_documents`, none to never unlock them
+    internal_token: Option<Arc<str>>,
+    // Whether the client presented the internal token, serving internal documents despite `public_only`
+    // until it locks them again, shared by the clones of the server as it serves a single client over stdio
+    internal_unlocked: Arc<AtomicBool>,
+    // Calls and latency of each tool, recorded with the `metrics` feature
+    metrics: Arc<ToolMetrics>,
+    // Rescans the index when queried after it got stale, none to never rescan on queries
+    freshness: Option<Arc<Freshness>>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct GetAllDocsRequest {
     #[schemars(description = "the maximum number of documents to return", default)]
     pub limit: i32,
+
+    #[schemars(description = "only return documents written in this language, as an ISO 639-1 code (e.g. en, fr)")]
+    pub language: Option<String>,
+
+    #[schemars(description = "whether to leave out the content of the documents, only listing their metadata (default true)")]
+    pub metadata_only: Option<bool>,
+
+    #[schemars(description = "only return documents whose path starts with this prefix (e.g. api/)")]
+    pub path_prefix: Option<String>,
+
+    #[schemars(description = "the field to sort documents by: path, title, size or last_modified (default path)")]
+    pub sort_by: Option<String>,
+
+    #[schemars(description = "the order to sort documents in: asc or desc (default asc)")]
+    pub sort_order: Option<String>,
+}
+
+/// A document listed by `get_all_docs` or `get_documents`, borrowing from the
+/// storage so that listing doesn't copy every document
+#[derive(Serialize)]
+struct DocumentListing<'a> {
+    path: &'a str,
+    title: Cow<'a, str>,
+    summary: Option<&'a str>,
+    aliases: &'a [String],
+    language: Option<&'a str>,
+    format: &'static str,
+    mime_type: &'static str,
+    visibility: &'static str,
+    fetched_at: Option<u64>,
+    last_modified: Option<u64>,
+    word_count: usize,
+    token_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<Cow<'a, str>>,
+}
+
+impl<'a> DocumentListing<'a> {
+    fn new(doc: &'a StoredDocument, title: Cow<'a, str>, content: Option<Cow<'a, str>>) -> Self {
+        Self {
+            path: &doc.path,
+            title,
+            summary: doc.summary.as_deref(),
+            aliases: &doc.aliases,
+            language: doc.language.as_deref(),
+            format: doc.format().name(),
+            mime_type: doc.mime_type(),
+            visibility: doc.visibility.name(),
+            fetched_at: doc.fetched_at,
+            last_modified: doc.last_modified,
+            word_count: doc.word_count,
+            token_count: doc.token_count,
+            content,
+        }
+    }
+}
+
+/// The response of `get_all_docs`
+#[derive(Serialize)]
+struct DocumentList<'a> {
+    total: usize,
+    returned: usize,
+    documents: Vec<DocumentListing<'a>>,
+}
+
+/// The response of `get_documents`
+#[derive(Serialize)]
+struct DocumentBatch<'a> {
+    documents: Vec<DocumentListing<'a>>,
+    /// Requested paths matching no document
+    not_found: Vec<String>,
+    /// Paths of the documents whose content was cut to the maximum response size
+    truncated: Vec<&'a str>,
+}
+
+/// Reject a document path that could escape the repository, with an
+/// `invalid_params` error telling why
+fn check_path(path: &str) -> Result<(), McpError> {
+    validate_path(path).map_err(|invalid| {
+        McpError::invalid_params("invalid_path", Some(json!({ "path": path, "reason": invalid.reason() })))
+    })
+}
+
+/// Resolve the file an export is written to, below the exports directory of
+/// the storage, creating its parent directories
+///
+/// Paths escaping the directory are rejected, through `..` or links alike, and
+/// so are links in place of the file, which would be written through.
+fn export_path(storage_path: &Path, path: &str) -> Result<PathBuf, McpError> {
+    check_path(path)?;
+    let invalid = |reason: &str| McpError::invalid_params("invalid_path", Some(json!({ "path": path, "reason": reason })));
+    let io_error = |e: std::io::Error| McpError::internal_error("export_error", Some(json!({ "path": path, "error": e.to_string() })));
+
+    let exports = storage_path.join(EXPORTS_DIRECTORY);
+    let target = exports.join(path);
+    let parent = match target.parent() {
+        Some(parent) if target != exports && !path.ends_with(['/', '\\']) => parent,
+        _ => return Err(invalid("not a file")),
+    };
+    std::fs::create_dir_all(parent).map_err(io_error)?;
+
+    let exports = exports.canonicalize().map_err(io_error)?;
+    if !parent.canonicalize().map_err(io_error)?.starts_with(&exports) {
+        return Err(invalid("outside of the exports directory"));
+    }
+    if target.symlink_metadata().is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        return Err(invalid("a link"));
+    }
+    Ok(target)
+}
+
+/// Check a presented token against the expected one in constant time
+///
+/// Both are hashed first, so the time taken doesn't depend on their length
+/// nor on where they differ.
+fn token_matches(expected: &str, presented: &str) -> bool {
+    let expected = Sha256::digest(expected.as_bytes());
+    let presented = Sha256::digest(presented.as_bytes());
+    expected.iter().zip(presented.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Take the content of a document out of the size budget shared by the documents
+/// of a response, cutting it if it doesn't fit, and only copying it if so
+fn take_content<'a>(content: &'a str, budget: &mut usize) -> Cow<'a, str> {
+    let content = if content.len() <= *budget {
+        Cow::Borrowed(content)
+    } else {
+        Cow::Owned(truncate(content, *budget).text)
+    };
+    *budget -= content.len();
+    content
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
-struct GetDocumentRequest {
+pub struct GetDocumentRequest {
     #[schemars(description = "the path of the document to retrieve")]
-    path: String
+    pub path: String,
+
+    #[schemars(description = "the byte offset to return the content from, to read the rest of a truncated document (default 0)")]
+    pub offset: Option<usize>,
+
+    #[schemars(description = "the representation of the content to return: raw as stored (the default), text without markup, or html")]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetDocumentsRequest {
+    #[schemars(description = "the paths of the documents to retrieve")]
+    pub paths: Vec<String>,
+
+    #[schemars(description = "whether to leave out the content of the documents, only returning their metadata (default false)")]
+    pub metadata_only: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetDocumentByTitleRequest {
+    #[schemars(description = "the title of the document to retrieve, matched case-insensitively")]
+    pub title: String,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct FindRelevantDocsRequest {
-    #[schemars(description = "the query to search for relevant documents")]
+    #[schemars(description = "the query to search for relevant documents. Use \"quotes\" for exact phrases, +term for required terms and -term to exclude documents containing a term")]
     pub query: String,
 
     #[schemars(description = "the maximum number of documents to return", default)]
     pub limit: i32,
+
+    #[schemars(description = "whether to include the full content of each document instead of only a snippet", default)]
+    #[serde(default)]
+    pub include_full_content: bool,
+
+    #[schemars(description = "whether to mark matched query terms in snippets with **", default)]
+    #[serde(default)]
+    pub mark_terms: bool,
+
+    #[schemars(description = "whether to include the [start, end) byte offsets of matched query terms within each snippet", default)]
+    #[serde(default)]
+    pub with_highlights: bool,
+
+    #[schemars(description = "only return documents written in this language, as an ISO 639-1 code (e.g. en, fr)")]
+    pub language: Option<String>,
+
+    #[schemars(description = "the minimum score of returned documents, from 0 to 1. By default, documents only sharing a single query keyword in their body are left out")]
+    pub min_score: Option<f64>,
+
+    #[schemars(description = "whether query terms must match with the same case, e.g. IO but not io", default)]
+    #[serde(default)]
+    pub case_sensitive: bool,
+
+    #[schemars(description = "whether query terms must match whole words, e.g. IO but not IOError", default)]
+    #[serde(default)]
+    pub whole_word: bool,
+
+    #[schemars(description = "only search documents whose path starts with this prefix (e.g. api/)")]
+    pub path_prefix: Option<String>,
+
+    #[schemars(description = "leave out documents whose approximate token count exceeds this budget")]
+    pub max_tokens: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ExplainRelevanceRequest {
+    #[schemars(description = "the query to explain the score of, as given to find_relevant_docs")]
+    pub query: String,
+
+    #[schemars(description = "the path of the document to explain the score of")]
+    pub path: String,
+
+    #[schemars(description = "whether query terms must match with the same case, as in find_relevant_docs", default)]
+    #[serde(default)]
+    pub case_sensitive: bool,
+
+    #[schemars(description = "whether query terms must match whole words, as in find_relevant_docs", default)]
+    #[serde(default)]
+    pub whole_word: bool,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SearchInDocumentRequest {
+    #[schemars(description = "the path of the document to search in")]
+    pub path: String,
+
+    #[schemars(description = "the query to search for within the document")]
+    pub query: String,
+
+    #[schemars(description = "the number of context lines to include before and after each match (default 2)")]
+    pub context_lines: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct AutocompleteQueryRequest {
+    #[schemars(description = "the partial query term to complete, e.g. auth")]
+    pub partial: String,
+
+    #[schemars(description = "the maximum number of suggestions to return (default 10, at most 100)", default)]
+    #[serde(default)]
+    pub limit: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct UnlockInternalDocumentsRequest {
+    #[schemars(description = "the internal token the server was configured with")]
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct LookupTermRequest {
+    #[schemars(description = "the term to look up the definition of, e.g. a glossary entry, ignoring case")]
+    pub term: String,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetDocumentImagesRequest {
+    #[schemars(description = "the path of the document to list the images of")]
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct RelatedDocumentsRequest {
+    #[schemars(description = "the path of the document to find related documents for")]
+    pub path: String,
+
+    #[schemars(description = "the maximum number of documents to return (default 5)", default)]
+    #[serde(default)]
+    pub limit: i32,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct AddDocumentRequest {
+    #[schemars(description = "the path to store the document under")]
+    pub path: String,
+
+    #[schemars(description = "the content of the document")]
+    pub content: String,
+
+    #[schemars(description = "the title of the document (extracted from the content if omitted)")]
+    pub title: Option<String>,
+
+    #[schemars(description = "whether to replace an existing document stored under the same path", default)]
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ExportIndexRequest {
+    #[schemars(description = "the export format: jsonl for one JSON document per line, or markdown for a single file with each document under its title")]
+    pub format: String,
+
+    #[schemars(description = "a file to write the export to instead of returning it, relative to the exports directory of the storage")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct DiffDocumentsRequest {
+    #[schemars(description = "the path of the original document")]
+    pub old_path: String,
+
+    #[schemars(description = "the path of the changed document")]
+    pub new_path: String,
+
+    #[schemars(description = "the number of unchanged lines to include around each change (default 3)")]
+    pub context_lines: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct SummarizeDocumentRequest {
+    #[schemars(description = "the path of the document to summarize")]
+    pub path: String,
+
+    #[schemars(description = "the number of sentences of the summary (default 3)", default)]
+    #[serde(default)]
+    pub sentences: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetStaleDocumentsRequest {
+    #[schemars(description = "return documents fetched more than this many seconds ago")]
+    pub older_than_seconds: u64,
+
+    #[schemars(description = "the maximum number of documents to return", default)]
+    #[serde(default)]
+    pub limit: i32,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ListTopicsRequest {
+    #[schemars(description = "the maximum number of keywords to return (default 50)", default)]
+    #[serde(default)]
+    pub limit: usize,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct GetTableOfContentsRequest {
+    #[schemars(description = "the deepest heading level to list, 1 being the document titles (default 6)", default)]
+    #[serde(default)]
+    pub max_heading_level: Option<usize>,
 }
 
 #[tool(tool_box)]
 impl Documents {
 
-    pub fn new(store: DocumentStore) -> Self {
-        Self {
-            store,
+    /// Create the tools over an already built index
+    pub fn new(store: DocumentStore) -> Self {
+        Self {
+            store,
+            scan_failures: Arc::new(RwLock::new(Vec::new())),
+            readiness: Arc::new(ReadinessState::new(ServerStatus::Ready)),
+            not_ready_policy: NotReadyPolicy::default(),
+            repository: None,
+            max_response_bytes: None,
+            qualify_titles: false,
+            scanner: Arc::new(DocumentScanner::new()),
+            public_only: false,
+            internal_token: None,
+            internal_unlocked: Arc::new(AtomicBool::new(false)),
+            metrics: Arc::new(ToolMetrics::default()),
+            freshness: None,
+        }
+    }
+
+    /// Set the repository the documents are scanned from, reported to clients
+    pub fn with_repository(mut self, repository: RepositoryInfo) -> Self {
+        self.repository = Some(repository);
+        self
+    }
+
+    /// Set the maximum size of the content returned by a tool call, in bytes
+    ///
+    /// Longer content is cut at a character boundary and ends with a `[truncated]` marker.
+    pub fn with_max_response_bytes(mut self, max_response_bytes: Option<usize>) -> Self {
+        self.max_response_bytes = max_response_bytes;
+        self
+    }
+
+    /// Prefix the titles shared by several documents with their directory in
+    /// tool responses, e.g. `api / Overview`, without changing stored titles
+    pub fn with_qualified_titles(mut self, qualify_titles: bool) -> Self {
+        self.qualify_titles = qualify_titles;
+        self
+    }
+
+    /// Set the scanner building the documents added with `add_document`, so
+    /// their titles and summaries are extracted like those of scanned documents
+    ///
+    /// A scanner with the default settings is used if not set.
+    pub fn with_scanner(mut self, scanner: DocumentScanner) -> Self {
+        self.scanner = Arc::new(scanner);
+        self
+    }
+
+    /// Only serve public documents, as if internal documents weren't stored,
+    /// until the client unlocks them with the internal token
+    ///
+    /// Every document is served by default.
+    pub fn with_public_only(mut self, public_only: bool) -> Self {
+        self.public_only = public_only;
+        self
+    }
+
+    /// Set the token clients present to `unlock_internal_documents` to be
+    /// served internal documents despite `with_public_only`
+    ///
+    /// Internal documents can't be unlocked without a token. Once unlocked, they
+    /// are served by every clone of the server until `lock_internal_documents`.
+    pub fn with_internal_token(mut self, internal_token: Option<String>) -> Self {
+        self.internal_token = internal_token.map(Arc::from);
+        self
+    }
+
+    /// Check whether internal documents may be served
+    fn serves_internal(&self) -> bool {
+        !self.public_only || self.internal_unlocked.load(Ordering::Relaxed)
+    }
+
+    /// Check whether a document may be served
+    fn visible(&self, doc: &StoredDocument) -> bool {
+        doc.visibility == Visibility::Public || self.serves_internal()
+    }
+
+    /// Fail as if the document at a path didn't exist when it may not be served
+    fn check_visible(&self, store: &DocumentStorage, path: &str) -> Result<(), McpError> {
+        match store.get_document(path) {
+            Some(doc) if !self.visible(doc) => {
+                Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": path }))))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Count the documents that may be served
+    fn visible_count(&self, store: &DocumentStorage) -> usize {
+        store.get_all_documents().into_iter().filter(|doc| self.visible(doc)).count()
+    }
+
+    /// Get the title of a document to return, qualified with its directory if
+    /// enabled and the title is shared
+    fn title<'a>(&self, store: &DocumentStorage, doc: &'a StoredDocument) -> Cow<'a, str> {
+        if self.qualify_titles {
+            store.qualified_title(doc)
+        } else {
+            Cow::Borrowed(&doc.title)
+        }
+    }
+
+    /// Rescan the index in the background with the given function when a tool
+    /// is called and the last scan is older than `ttl`
+    ///
+    /// The call is answered from the current documents, so the rescan only
+    /// benefits the next ones. A single rescan runs at a time.
+    pub fn with_stale_ttl<F, Fut>(mut self, ttl: Duration, rescan: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.freshness = Some(Arc::new(Freshness::new(ttl, rescan)));
+        self
+    }
+
+    /// Cut text to the maximum response size
+    fn limit(&self, text: &str) -> Truncated {
+        truncate(text, self.max_response_bytes.unwrap_or(usize::MAX))
+    }
+
+    /// Create the tools over an index that is still being built by the initial scan
+    ///
+    /// Search tools follow the given policy until `set_status` reports the scan as done.
+    pub fn initializing(store: DocumentStore, not_ready_policy: NotReadyPolicy) -> Self {
+        Self {
+            readiness: Arc::new(ReadinessState::new(ServerStatus::Initializing)),
+            not_ready_policy,
+            ..Self::new(store)
+        }
+    }
+
+    /// Update the status of the index, recording the scan time when it becomes ready
+    pub fn set_status(&self, status: ServerStatus) {
+        if let (ServerStatus::Ready, Some(freshness)) = (&status, &self.freshness) {
+            freshness.mark_scanned();
+        }
+        self.readiness.set_status(status);
+    }
+
+    /// Get the status of the index and the time of the last successful scan
+    pub fn readiness(&self) -> Readiness {
+        self.readiness.get()
+    }
+
+    /// Check that the index can be searched, waiting for the initial scan or
+    /// failing while it runs, depending on the policy
+    ///
+    /// A failed scan doesn't block searches, as documents may have been loaded
+    /// from a persistent storage. Once scanned, a stale index is rescanned in
+    /// the background, if a time to live is set.
+    pub async fn ensure_ready(&self) -> Result<(), McpError> {
+        if self.readiness.get().status != ServerStatus::Initializing {
+            if let Some(freshness) = &self.freshness
+                && freshness.rescan_if_stale()
+            {
+                tracing::info!("The index is stale, rescanning it in the background");
+            }
+            return Ok(());
+        }
+
+        match self.not_ready_policy {
+            NotReadyPolicy::Wait => {
+                self.readiness.wait().await;
+                Ok(())
+            }
+            NotReadyPolicy::Partial => Ok(()),
+            NotReadyPolicy::Reject => Err(McpError::invalid_request(
+                "index_not_ready",
+                Some(json!({ "status": "initializing", "message": "The initial repository scan is still running, retry later" })),
+            )),
+        }
+    }
+
+    /// Record the failures of the last repository scan
+    pub async fn set_scan_failures(&self, failures: Vec<FetchFailure>) {
+        *self.scan_failures.write().await = failures;
+    }
+
+    /// Templates of the resource URIs clients can build, one per addressable kind of resource
+    pub fn resource_templates(&self) -> Vec<ResourceTemplate> {
+        vec![RawResourceTemplate {
+            uri_template: format!("{}{{path}}", DOCUMENT_URI_SCHEME),
+            name: "document".to_string(),
+            description: Some("A stored document, by its path in the repository, e.g. doc://docs/intro.md".to_string()),
+            mime_type: Some("text/markdown".to_string()),
+        }
+        .no_annotation()]
+    }
+
+    /// Read a stored document from its `doc://{path}` resource URI
+    pub async fn read_document_resource(&self, uri: &str) -> Result<ReadResourceResult, McpError> {
+        let Some(path) = uri.strip_prefix(DOCUMENT_URI_SCHEME) else {
+            return Err(McpError::resource_not_found("resource_not_found", Some(json!({ "uri": uri }))));
+        };
+        check_path(path)?;
+
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let Some(doc) = store.get_document(path).filter(|doc| self.visible(doc)) else {
+            return Err(McpError::resource_not_found("document_not_found", Some(json!({ "uri": uri, "path": path }))));
+        };
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some(doc.mime_type().to_string()),
+                text: doc.content.clone(),
+            }],
+        })
+    }
+
+    /// Complete an argument of a resource template or prompt
+    ///
+    /// The `path` of the `doc://{path}` template completes to the stored document
+    /// paths, aliases included, starting with the partial input, ignoring case.
+    /// Other arguments have no completions.
+    pub async fn complete_argument(&self, request: CompleteRequestParam) -> Result<CompleteResult, McpError> {
+        let template = format!("{}{{path}}", DOCUMENT_URI_SCHEME);
+        let values = match &request.r#ref {
+            Reference::Resource(resource) if resource.uri == template && request.argument.name == "path" => {
+                let prefix = request.argument.value.to_lowercase();
+                let store = self.store.read().await;
+                let mut paths: Vec<String> = store
+                    .get_all_documents()
+                    .into_iter()
+                    .filter(|doc| self.visible(doc))
+                    .flat_map(|doc| std::iter::once(&doc.path).chain(&doc.aliases))
+                    .filter(|path| path.to_lowercase().starts_with(&prefix))
+                    .cloned()
+                    .collect();
+                paths.sort();
+                paths
+            }
+            _ => Vec::new(),
+        };
+
+        let total = values.len();
+        Ok(CompleteResult {
+            completion: CompletionInfo {
+                values: values.into_iter().take(MAX_COMPLETIONS).collect(),
+                total: Some(total as u32),
+                has_more: Some(total > MAX_COMPLETIONS),
+            },
+        })
+    }
+
+    fn _create_resource_text(&self, uri: &str, name: &str) -> Resource {
+        RawResource::new(uri, name.to_string()).no_annotation()
+    }
+
+    #[tool(description = "Get all documents in the storage, with only their metadata unless metadata_only is false")]
+    pub async fn get_all_docs(&self, #[tool(aggr)] GetAllDocsRequest { limit, language, metadata_only, path_prefix, sort_by, sort_order } : GetAllDocsRequest) -> Result<CallToolResult, McpError> {
+        let sort_field = match sort_by.as_deref() {
+            Some(field) => field.parse().map_err(|_| {
+                McpError::invalid_params("unsupported_sort_field", Some(json!({ "sort_by": field, "supported": ["path", "title", "size", "last_modified"] })))
+            })?,
+            None => SortField::default(),
+        };
+        let sort_order = match sort_order.as_deref() {
+            Some(order) => order.parse().map_err(|_| {
+                McpError::invalid_params("unsupported_sort_order", Some(json!({ "sort_order": order, "supported": ["asc", "desc"] })))
+            })?,
+            None => SortOrder::default(),
+        };
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let mut docs = store.get_all_documents();
+        docs.retain(|doc| self.visible(doc));
+        if let Some(language) = &language {
+            docs.retain(|doc| doc.is_language(language));
+        }
+        if let Some(path_prefix) = &path_prefix {
+            let path_prefix = path_prefix.trim_start_matches('/');
+            docs.retain(|doc| doc.path.starts_with(path_prefix));
+        }
+        sort_documents(&mut docs, sort_field, sort_order);
+
+        // Limit the number of documents returned
+        let returned = if limit > 0 { (limit as usize).min(docs.len()) } else { docs.len() };
+
+        // Contents share the maximum response size, and are only copied when truncated
+        let include_content = metadata_only == Some(false);
+        let mut content_budget = self.max_response_bytes.unwrap_or(usize::MAX);
+
+        let records: Vec<_> = docs
+            .iter()
+            .take(returned)
+            .map(|doc| {
+                let content = include_content.then(|| take_content(&doc.content, &mut content_budget));
+                DocumentListing::new(doc, self.title(&store, doc), content)
+            })
+            .collect();
+
+        let response = DocumentList {
+            total: docs.len(),
+            returned: records.len(),
+            documents: records,
+        };
+        let response = serde_json::to_string(&response).map_err(|e| {
+            McpError::internal_error("serialization_error", Some(json!({ "error": e.to_string() })))
+        })?;
+
+        Ok(CallToolResult::success(vec![Content::text(response)]))
+    }
+
+    #[tool(description = "Get a specific document by path. Content over the maximum response size is truncated, and the rest can be read from the returned next_offset")]
+    pub async fn get_document(
+        &self,
+        #[tool(aggr)] GetDocumentRequest { path, offset, format }: GetDocumentRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let render_format = match format.as_deref() {
+            Some(format) => format.parse().map_err(|_| {
+                McpError::invalid_params("unsupported_format", Some(json!({ "format": format, "supported": ["raw", "text", "html"] })))
+            })?,
+            None => RenderFormat::default(),
+        };
+        check_path(&path)?;
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        if let Some(doc) = store.get_document(&path).filter(|doc| self.visible(doc)) {
+            // Offsets are within the requested representation of the content
+            let rendered = doc.render(render_format);
+            
+            // Start at a character boundary at or before the requested offset
+            let mut offset = offset.unwrap_or(0).min(rendered.len());
+            while !rendered.is_char_boundary(offset) {
+                offset -= 1;
+            }
+            let content = self.limit(&rendered[offset..]);
+            let mime_type = match render_format {
+                RenderFormat::Raw => doc.mime_type(),
+                RenderFormat::Text => "text/plain",
+                RenderFormat::Html => "text/html",
+            };
+
+            let mut response = json!({
+                "path": doc.path,
+                "title": self.title(&store, doc),
+                "summary": doc.summary,
+                "aliases": doc.aliases,
+                "language": doc.language,
+                "format": doc.format().name(),
+                "content_format": render_format.name(),
+                "mime_type": mime_type,
+                "visibility": doc.visibility,
+                "fetched_at": doc.fetched_at,
+                "last_modified": doc.last_modified,
+                "word_count": doc.word_count,
+                "token_count": doc.token_count,
+                "links": doc.links,
+                "content": content.text,
+                "offset": offset,
+                "truncated": content.cut_at.is_some(),
+            });
+            if let Some(cut_at) = content.cut_at {
+                let next_offset = offset + cut_at;
+                response["next_offset"] = json!(next_offset);
+                response["message"] = json!(format!(
+                    "The content was truncated to the maximum response size, call get_document with offset {} for the rest",
+                    next_offset
+                ));
+            }
+            Ok(CallToolResult::success(vec![Content::text(response.to_string())]))
+        } else {
+            Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": path }))))
+        }
+    }
+
+    #[tool(description = "Get several documents by path in one call, listing the paths that match no document. Contents share the maximum response size")]
+    pub async fn get_documents(
+        &self,
+        #[tool(aggr)] GetDocumentsRequest { paths, metadata_only }: GetDocumentsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        for path in &paths {
+            check_path(path)?;
+        }
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let include_content = metadata_only != Some(true);
+        let mut content_budget = self.max_response_bytes.unwrap_or(usize::MAX);
+
+        let mut response = DocumentBatch {
+            documents: Vec::new(),
+            not_found: Vec::new(),
+            truncated: Vec::new(),
+        };
+        let mut returned = HashSet::new();
+        for path in paths {
+            let Some(doc) = store.get_document(&path).filter(|doc| self.visible(doc)) else {
+                response.not_found.push(path);
+                continue;
+            };
+            // Aliases resolve to the same document, which is only returned once
+            if !returned.insert(doc.path.as_str()) {
+                continue;
+            }
+            let content = include_content.then(|| take_content(&doc.content, &mut content_budget));
+            // Contents are only copied when cut
+            if content.as_ref().is_some_and(|content| matches!(content, Cow::Owned(_))) {
+                response.truncated.push(&doc.path);
+            }
+            response.documents.push(DocumentListing::new(doc, self.title(&store, doc), content));
+        }
+
+        let response = serde_json::to_string(&response).map_err(|e| {
+            McpError::internal_error("serialization_error", Some(json!({ "error": e.to_string() })))
+        })?;
+        Ok(CallToolResult::success(vec![Content::text(response)]))
+    }
+
+    #[tool(description = "Get a specific document by title. If several documents have the title, lists them so one can be fetched by path")]
+    async fn get_document_by_title(
+        &self,
+        #[tool(aggr)] GetDocumentByTitleRequest { title }: GetDocumentByTitleRequest,
+    ) -> Result<CallToolResult, McpError> {
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let mut docs = store.get_documents_by_title(&title);
+        docs.retain(|doc| self.visible(doc));
+
+        let response = match docs.as_slice() {
+            [] => {
+                return Err(McpError::resource_not_found("document_not_found", Some(json!({ "title": title }))));
+            }
+            [doc] => {
+                let content = self.limit(&doc.content);
+                let mut response = json!({
+                    "path": doc.path,
+                    "title": self.title(&store, doc),
+                    "summary": doc.summary,
+                    "aliases": doc.aliases,
+                    "language": doc.language,
+                    "fetched_at": doc.fetched_at,
+                    "last_modified": doc.last_modified,
+                    "content": content.text,
+                    "truncated": content.cut_at.is_some(),
+                });
+                if let Some(next_offset) = content.cut_at {
+                    response["next_offset"] = json!(next_offset);
+                    response["message"] = json!(format!(
+                        "The content was truncated to the maximum response size, call get_document with this path and offset {} for the rest",
+                        next_offset
+                    ));
+                }
+                response
+            }
+            // Let the client pick the document among the candidates
+            candidates => {
+                let records: Vec<_> = candidates
+                    .iter()
+                    .map(|doc| {
+                        json!({
+                            "path": doc.path,
+                            "title": self.title(&store, doc),
+                            "summary": doc.summary,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "title": title,
+                    "ambiguous": true,
+                    "message": "Several documents have this title, use get_document with one of the candidate paths",
+                    "candidates": records,
+                })
+            }
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(response.to_string())]))
+    }
+
+    #[tool(description = "Find documents relevant to a query")]
+    pub async fn find_relevant_docs(
+        &self,
+        #[tool(aggr)] FindRelevantDocsRequest { query, limit, include_full_content, mark_terms, with_highlights, language, min_score, case_sensitive, whole_word, path_prefix, max_tokens }: FindRelevantDocsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let options = MatchOptions { case_sensitive, whole_word };
+        let mut docs = store.score_relevant_documents_in(&query, options, path_prefix.as_deref().unwrap_or_default());
+        docs.retain(|relevant| self.visible(relevant.document));
+        if let Some(language) = &language {
+            docs.retain(|relevant| relevant.document.is_language(language));
+        }
+        if let Some(max_tokens) = max_tokens {
+            docs.retain(|relevant| relevant.document.token_count <= max_tokens);
+        }
+
+        // Leave out weak matches, which add noise rather than context
+        match min_score {
+            Some(min_score) => docs.retain(|relevant| relevant.score >= min_score),
+            None => {
+                if let Some(weak_score) = store.weak_match_score_with(&query, options) {
+                    docs.retain(|relevant| relevant.relevance > weak_score);
+                }
+            }
+        }
+
+        if docs.is_empty() {
+            return Ok(CallToolResult::success(vec![Content::text(
+                json!({
+                    "documents": [],
+                    "message": "No relevant documents found for the query"
+                }).to_string(),
+            )]));
+        }
+
+        // Limit the number of documents returned
+        let returned = if limit > 0 { (limit as usize).min(docs.len()) } else { docs.len() };
+
+        // Snippets and full contents share the maximum response size, best matches first
+        let mut content_budget = self.max_response_bytes.unwrap_or(usize::MAX);
+
+        let records: Vec<_> = docs
+            .iter()
+            .take(returned)
+            .map(|relevant| {
+                let doc = relevant.document;
+                let snippet = store
+                    .extract_snippet(doc, &query, mark_terms)
+                    .map(|snippet| take_content(&snippet, &mut content_budget).into_owned());
+                let mut record = json!({
+                    "path": doc.path,
+                    "title": self.title(&store, doc),
+                    "summary": doc.summary,
+                    "aliases": doc.aliases,
+                    "language": doc.language,
+                    "format": doc.format().name(),
+                    "mime_type": doc.mime_type(),
+                    "fetched_at": doc.fetched_at,
+                    "last_modified": doc.last_modified,
+                    "word_count": doc.word_count,
+                    "token_count": doc.token_count,
+                    "score": relevant.score,
+                    "matched_terms": relevant.matched_terms,
+                    "snippet": snippet,
+                });
+                if with_highlights {
+                    let highlights = snippet
+                        .as_deref()
+                        .map(|snippet| store.highlight_terms(doc, snippet.strip_suffix(TRUNCATION_MARKER).unwrap_or(snippet), &query))
+                        .unwrap_or_default();
+                    record["highlights"] = json!(highlights);
+                }
+                if include_full_content {
+                    let content = truncate(&doc.content, content_budget);
+                    content_budget -= content.text.len();
+                    record["content"] = json!(content.text);
+                    if let Some(next_offset) = content.cut_at {
+                        record["truncated"] = json!(true);
+                        record["next_offset"] = json!(next_offset);
+                    }
+                }
+                record
+            })
+            .collect();
+
+        let response = json!({
+            "total": docs.len(),
+            "returned": records.len(),
+            "documents": records
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Explain the score of a document for a query: the weight of each query term by where it matched, the combined score and the recency factor, to understand why a document ranks where it does")]
+    pub async fn explain_relevance(
+        &self,
+        #[tool(aggr)] ExplainRelevanceRequest { query, path, case_sensitive, whole_word }: ExplainRelevanceRequest,
+    ) -> Result<CallToolResult, McpError> {
+        check_path(&path)?;
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        self.check_visible(&store, &path)?;
+        let options = MatchOptions { case_sensitive, whole_word };
+        let Some(explanation) = store.explain_relevance(&query, &path, options) else {
+            return Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": path }))));
+        };
+
+        Ok(CallToolResult::success(vec![Content::text(
+            json!(explanation).to_string(),
+        )]))
+    }
+
+    #[tool(description = "Search for passages matching a query within a specific document. Snippets share the maximum response size")]
+    pub async fn search_in_document(
+        &self,
+        #[tool(aggr)] SearchInDocumentRequest { path, query, context_lines }: SearchInDocumentRequest,
+    ) -> Result<CallToolResult, McpError> {
+        check_path(&path)?;
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        self.check_visible(&store, &path)?;
+        let Some(matches) = store.search_in_document(&path, &query, context_lines.unwrap_or(2)) else {
+            return Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": path }))));
+        };
+
+        // Snippets share the maximum response size, and matches past it are left out
+        let mut content_budget = self.max_response_bytes.unwrap_or(usize::MAX);
+        let total = matches.len();
+        let mut matches = matches.into_iter();
+        let mut records = Vec::new();
+        while content_budget > 0 && let Some(mut found) = matches.next() {
+            found.snippet = take_content(&found.snippet, &mut content_budget).into_owned();
+            records.push(found);
+        }
+        let truncated = records.len() < total || content_budget == 0;
+
+        let response = json!({
+            "path": path,
+            "total": total,
+            "returned": records.len(),
+            "matches": records,
+            "truncated": truncated,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "List the images referenced by a document, such as diagrams and screenshots, with their alt text and whether they are local or remote")]
+    pub async fn get_document_images(
+        &self,
+        #[tool(aggr)] GetDocumentImagesRequest { path }: GetDocumentImagesRequest,
+    ) -> Result<CallToolResult, McpError> {
+        check_path(&path)?;
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let Some(doc) = store.get_document(&path).filter(|doc| self.visible(doc)) else {
+            return Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": path }))));
+        };
+
+        let response = json!({
+            "path": doc.path,
+            "total": doc.images.len(),
+            "images": doc.images,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Look up the definition of a term, such as a glossary entry, across all documents, from definition lists and `Term — definition` bullets")]
+    pub async fn lookup_term(
+        &self,
+        #[tool(aggr)] LookupTermRequest { term }: LookupTermRequest,
+    ) -> Result<CallToolResult, McpError> {
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let mut definitions = store.lookup_term(&term);
+        definitions.retain(|(doc, _)| self.visible(doc));
+        if definitions.is_empty() {
+            return Err(McpError::resource_not_found("term_not_found", Some(json!({ "term": term }))));
         }
-    }
 
-    fn _create_resource_text(&self, uri: &str, name: &str) -> Resource {
-        RawResource::new(uri, name.to_string()).no_annotation()
+        let records: Vec<_> = definitions
+            .iter()
+            .map(|(doc, definition)| {
+                json!({
+                    "term": definition.term,
+                    "definition": definition.definition,
+                    "path": doc.path,
+                    "title": self.title(&store, doc),
+                })
+            })
+            .collect();
+
+        let response = json!({
+            "term": term,
+            "total": records.len(),
+            "definitions": records,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
     }
 
-    #[tool(description = "Get all documents in the storage")]
-    async fn get_all_docs(&self, #[tool(aggr)] GetAllDocsRequest {limit} : GetAllDocsRequest) -> Result<CallToolResult, McpError> {
+    #[tool(description = "Find documents similar to a given document")]
+    async fn related_documents(
+        &self,
+        #[tool(aggr)] RelatedDocumentsRequest { path, limit }: RelatedDocumentsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        check_path(&path)?;
+        self.ensure_ready().await?;
         let store = self.store.read().await;
-        let docs = store.get_all_documents();
+        let limit = if limit > 0 { limit as usize } else { 5 };
+        self.check_visible(&store, &path)?;
+        // Hidden documents are left out before limiting, so as many documents are returned
+        let Some(mut related) = store.related_documents(&path, usize::MAX) else {
+            return Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": path }))));
+        };
+        related.retain(|(doc, _)| self.visible(doc));
+        related.truncate(limit);
 
-        let records: Vec<_> = docs
+        let records: Vec<_> = related
             .iter()
-            .map(|doc| {
+            .map(|(doc, similarity)| {
                 json!({
                     "path": doc.path,
-                    "title": doc.title,
+                    "title": self.title(&store, doc),
                     "summary": doc.summary,
+                    "similarity": similarity,
                 })
             })
             .collect();
 
-        // Limit the number of documents returned
-        let records: Vec<_> = if limit > 0 && limit < records.len() as i32 {
-            records.into_iter().take(limit as usize).collect()
-        } else {
-            records
-        };
-
         let response = json!({
-            "total": docs.len(),
+            "path": path,
             "returned": records.len(),
             "documents": records
         });
@@ -87,65 +1141,413 @@ impl Documents {
         )]))
     }
 
-    #[tool(description = "Get a specific document by path")]
-    async fn get_document(
+    #[tool(description = "Summarize a document with its most salient sentences, picked by how frequent their keywords are in the document")]
+    pub async fn summarize_document(
         &self,
-        #[tool(aggr)] GetDocumentRequest { path }: GetDocumentRequest,
+        #[tool(aggr)] SummarizeDocumentRequest { path, sentences }: SummarizeDocumentRequest,
     ) -> Result<CallToolResult, McpError> {
+        check_path(&path)?;
+        self.ensure_ready().await?;
         let store = self.store.read().await;
-        if let Some(doc) = store.get_document(&path) {
-            let response = json!({
-                "path": doc.path,
-                "title": doc.title,
-                "summary": doc.summary,
-                "content": doc.content,
-            });
-            Ok(CallToolResult::success(vec![Content::text(response.to_string())]))
-        } else {
-            Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": path }))))
+        let sentence_count = if sentences > 0 { sentences } else { 3 };
+        self.check_visible(&store, &path)?;
+        let Some(summary) = store.summarize_document(&path, sentence_count) else {
+            return Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": path }))));
+        };
+
+        // The summary is cut to the maximum response size, keeping the whole sentences within it
+        let joined = self.limit(&summary.join(" "));
+        let kept = joined.cut_at.unwrap_or(usize::MAX);
+        let mut end = 0;
+        let sentences: Vec<_> = summary
+            .iter()
+            .take_while(|sentence| {
+                end += sentence.len();
+                let within = end <= kept;
+                end += 1;
+                within
+            })
+            .collect();
+
+        let response = json!({
+            "path": path,
+            "summary": joined.text,
+            "sentences": sentences,
+            "truncated": joined.cut_at.is_some(),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "List the most frequent keywords of the stored documents, with the number of documents containing each, to pick better query terms")]
+    pub async fn list_topics(
+        &self,
+        #[tool(aggr)] ListTopicsRequest { limit }: ListTopicsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let limit = if limit > 0 { limit } else { 50 };
+
+        let topics: Vec<_> = store
+            .list_topics_matching(limit, |doc| self.visible(doc))
+            .into_iter()
+            .map(|(keyword, documents)| json!({ "keyword": keyword, "documents": documents }))
+            .collect();
+
+        let response = json!({
+            "documents": self.visible_count(&store),
+            "returned": topics.len(),
+            "topics": topics,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Suggest keywords of the documents completing a partial query term, those starting with it first, ranked by the number of documents containing them")]
+    pub async fn autocomplete_query(
+        &self,
+        #[tool(aggr)] AutocompleteQueryRequest { partial, limit }: AutocompleteQueryRequest,
+    ) -> Result<CallToolResult, McpError> {
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let limit = if limit > 0 { limit.min(MAX_SUGGESTIONS) } else { 10 };
+
+        let suggestions: Vec<_> = store
+            .suggest_keywords_matching(&partial, limit, |doc| self.visible(doc))
+            .into_iter()
+            .map(|(keyword, documents)| json!({ "keyword": keyword, "documents": documents }))
+            .collect();
+
+        let response = json!({
+            "partial": partial,
+            "returned": suggestions.len(),
+            "suggestions": suggestions,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Serve internal documents to this client despite the public-only filter, by presenting the internal token the server was configured with")]
+    pub async fn unlock_internal_documents(
+        &self,
+        #[tool(aggr)] UnlockInternalDocumentsRequest { token }: UnlockInternalDocumentsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        if !self.internal_token.as_deref().is_some_and(|expected| token_matches(expected, &token)) {
+            return Err(McpError::invalid_params("invalid_internal_token", None));
         }
+        self.internal_unlocked.store(true, Ordering::Relaxed);
+
+        let response = json!({
+            "unlocked": true,
+            "documents": self.visible_count(&*self.store.read().await),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
     }
 
-    #[tool(description = "Find documents relevant to a query")]
-    async fn find_relevant_docs(
+    #[tool(description = "Hide internal documents again after unlock_internal_documents, serving public documents only")]
+    pub async fn lock_internal_documents(&self) -> Result<CallToolResult, McpError> {
+        self.internal_unlocked.store(false, Ordering::Relaxed);
+
+        let response = json!({
+            "unlocked": false,
+            "documents": self.visible_count(&*self.store.read().await),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Get a table of contents of all documents, grouped by directory, with the headings of each document, as a map of the documentation before reading it")]
+    pub async fn get_table_of_contents(
         &self,
-        #[tool(aggr)] FindRelevantDocsRequest { query, limit }: FindRelevantDocsRequest,
+        #[tool(aggr)] GetTableOfContentsRequest { max_heading_level }: GetTableOfContentsRequest,
     ) -> Result<CallToolResult, McpError> {
+        self.ensure_ready().await?;
         let store = self.store.read().await;
-        let docs = store.find_relevant_documents(&query);
+        let max_heading_level = max_heading_level.unwrap_or(6);
+        if max_heading_level == 0 {
+            return Err(McpError::invalid_params(
+                "invalid_max_heading_level",
+                Some(json!({ "max_heading_level": max_heading_level })),
+            ));
+        }
 
-        if docs.is_empty() {
-            return Ok(CallToolResult::success(vec![Content::text(
-                json!({
-                    "documents": [],
-                    "message": "No relevant documents found for the query"
-                }).to_string(),
-            )]));
+        let response = json!({
+            "documents": self.visible_count(&store),
+            "table_of_contents": store.table_of_contents_matching(max_heading_level, |doc| self.visible(doc)),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Compare the content of two documents, such as two versions of a file, as a unified diff")]
+    pub async fn diff_documents(
+        &self,
+        #[tool(aggr)] DiffDocumentsRequest { old_path, new_path, context_lines }: DiffDocumentsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        check_path(&old_path)?;
+        check_path(&new_path)?;
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let Some(old) = store.get_document(&old_path).filter(|doc| self.visible(doc)) else {
+            return Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": old_path }))));
+        };
+        let Some(new) = store.get_document(&new_path).filter(|doc| self.visible(doc)) else {
+            return Err(McpError::resource_not_found("document_not_found", Some(json!({ "path": new_path }))));
+        };
+
+        let diff = TextDiff::from_lines(&old.content, &new.content);
+        let text = diff
+            .unified_diff()
+            .context_radius(context_lines.unwrap_or(3))
+            .header(&old_path, &new_path)
+            .to_string();
+
+        let text = self.limit(&text);
+        let response = json!({
+            "old_path": old_path,
+            "new_path": new_path,
+            "identical": diff.ratio() == 1.0,
+            "diff": text.text,
+            "truncated": text.cut_at.is_some(),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Add a document to the storage from its content, once internal documents are unlocked if the server only serves public documents")]
+    pub async fn add_document(
+        &self,
+        #[tool(aggr)] AddDocumentRequest { path, content, title, overwrite }: AddDocumentRequest,
+    ) -> Result<CallToolResult, McpError> {
+        // Whether adding succeeds would tell which paths and contents internal documents
+        // have, so hidden documents have to be unlocked first, whatever the path
+        if !self.serves_internal() {
+            return Err(McpError::invalid_request("internal_documents_locked", None));
+        }
+        if path.trim().is_empty() {
+            return Err(McpError::invalid_params("empty_path", None));
+        }
+        check_path(&path)?;
+        if content.trim().is_empty() {
+            return Err(McpError::invalid_params("empty_content", Some(json!({ "path": path }))));
         }
 
+        // Extract the title and summary the same way as scanned documents
+        let mut document = self.scanner.process_content(&path, content).map_err(|e| {
+            McpError::invalid_params("invalid_content", Some(json!({ "path": path, "error": e.to_string() })))
+        })?;
+        if let Some(title) = title {
+            document.title = title;
+        }
+
+        let mut store = self.store.write().await;
+        if !overwrite && store.get_document(&path).is_some() {
+            return Err(McpError::invalid_params("document_already_exists", Some(json!({ "path": path }))));
+        }
+
+        store.store_document(document).map_err(|e| {
+            McpError::internal_error("storage_error", Some(json!({ "error": e.to_string() })))
+        })?;
+
+        // Identical content is stored once, so the document may resolve to another path
+        let Some(stored) = store.get_document(&path) else {
+            return Err(McpError::internal_error("document_not_stored", Some(json!({ "path": path }))));
+        };
+
+        let response = json!({
+            "path": path,
+            "stored_as": stored.path,
+            "title": stored.title,
+            "keyword_count": stored.keywords.len(),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Recompute the keywords of all stored documents, to apply keyword extraction changes without fetching the documents again")]
+    async fn reindex_keywords(&self) -> Result<CallToolResult, McpError> {
+        let mut store = self.store.write().await;
+        let reindexed = store.reindex_keywords().map_err(|e| {
+            McpError::internal_error("storage_error", Some(json!({ "error": e.to_string() })))
+        })?;
+
+        let response = json!({
+            "reindexed": reindexed,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Load the saved document storage again, to pick up changes made to it outside of the server. Only available with a persistent storage")]
+    pub async fn reload_from_disk(&self) -> Result<CallToolResult, McpError> {
+        // Searches wait for the write lock, so they see either the old or the new documents
+        let mut store = self.store.write().await;
+        let documents = store.reload_from_disk().map_err(|e| match e {
+            KodeError::InvalidArgument(reason) => McpError::invalid_request("not_persistent", Some(json!({ "error": reason }))),
+            KodeError::NotFound(what) => McpError::invalid_request("index_not_found", Some(json!({ "error": format!("no {}", what) }))),
+            e => McpError::internal_error("storage_error", Some(json!({ "error": e.to_string() }))),
+        })?;
+
+        let response = json!({
+            "documents": documents,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Export all stored documents as JSONL or a single Markdown file, returned as text or written to a file in the exports directory of the storage")]
+    pub async fn export_index(
+        &self,
+        #[tool(aggr)] ExportIndexRequest { format, path }: ExportIndexRequest,
+    ) -> Result<CallToolResult, McpError> {
+        let export_format: ExportFormat = format.parse().map_err(|_| {
+            McpError::invalid_params("unsupported_format", Some(json!({ "format": format, "supported": ["jsonl", "markdown"] })))
+        })?;
+
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let export = store.export_documents_matching(export_format, |doc| self.visible(doc)).map_err(|e| {
+            McpError::internal_error("export_error", Some(json!({ "error": e.to_string() })))
+        })?;
+
+        let Some(path) = path else {
+            // A truncated export can't be paged, a path is needed to get all of it
+            return Ok(CallToolResult::success(vec![Content::text(self.limit(&export).text)]));
+        };
+
+        let Some(storage_path) = store.storage_path() else {
+            return Err(McpError::invalid_request(
+                "not_persistent",
+                Some(json!({ "error": "the storage is kept in memory only, there's no exports directory to write to" })),
+            ));
+        };
+        let export_path = export_path(storage_path, &path)?;
+        std::fs::write(&export_path, &export).map_err(|e| {
+            McpError::internal_error("export_error", Some(json!({ "path": path, "error": e.to_string() })))
+        })?;
+
+        let response = json!({
+            "path": export_path,
+            "format": format,
+            "documents": self.visible_count(&store),
+            "bytes": export.len(),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "List the documents not fetched again for a given duration, oldest first, with when they were fetched and last changed in the repository")]
+    pub async fn get_stale_documents(
+        &self,
+        #[tool(aggr)] GetStaleDocumentsRequest { older_than_seconds, limit }: GetStaleDocumentsRequest,
+    ) -> Result<CallToolResult, McpError> {
+        self.ensure_ready().await?;
+        let store = self.store.read().await;
+        let mut docs = store.get_stale_documents(Duration::from_secs(older_than_seconds));
+        docs.retain(|doc| self.visible(doc));
+
+        // Limit the number of documents returned
+        let returned = if limit > 0 { (limit as usize).min(docs.len()) } else { docs.len() };
+
         let records: Vec<_> = docs
             .iter()
+            .take(returned)
             .map(|doc| {
                 json!({
                     "path": doc.path,
-                    "title": doc.title,
-                    "summary": doc.summary,
-                    "content": doc.content,
+                    "title": self.title(&store, doc),
+                    "fetched_at": doc.fetched_at,
+                    "last_modified": doc.last_modified,
                 })
             })
             .collect();
 
-        // Limit the number of documents returned
-        let records: Vec<_> = if limit > 0 && limit < records.len() as i32 {
-            records.into_iter().take(limit as usize).collect()
-        } else {
-            records
-        };
-
         let response = json!({
             "total": docs.len(),
             "returned": records.len(),
-            "documents": records
+            "documents": records,
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Check whether the server is ready, with the document count and the time of the last successful scan")]
+    pub async fn health(&self) -> Result<CallToolResult, McpError> {
+        let store = self.store.read().await;
+        let readiness = self.readiness.get();
+
+        let mut response = json!(readiness.status);
+        response["documents"] = json!(self.visible_count(&store));
+        response["last_scan_at"] = json!(readiness.last_scan_at);
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Get the repository the documents are served from: owner, repository, ref and subfolders, with the document count and the time of the last scan")]
+    pub async fn get_repository_info(&self) -> Result<CallToolResult, McpError> {
+        let store = self.store.read().await;
+
+        let mut response = match &self.repository {
+            Some(repository) => json!(repository),
+            None => json!({ "owner": null, "repo": null, "ref": null, "subfolders": [] }),
+        };
+        response["documents"] = json!(self.visible_count(&store));
+        response["last_scan_at"] = json!(self.readiness.get().last_scan_at);
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Get the number of calls, errors and latency of each tool since the server started. Only recorded when the server is built with the metrics feature")]
+    pub async fn get_server_metrics(&self) -> Result<CallToolResult, McpError> {
+        let response = json!({
+            "enabled": cfg!(feature = "metrics"),
+            "tools": self.metrics.snapshot(),
+        });
+
+        Ok(CallToolResult::success(vec![Content::text(
+            response.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Get a report of the paths that failed during the last repository scan")]
+    pub async fn get_scan_report(&self) -> Result<CallToolResult, McpError> {
+        let store = self.store.read().await;
+        let failures = self.scan_failures.read().await;
+
+        let response = json!({
+            "indexed": self.visible_count(&store),
+            "failed": failures.len(),
+            "complete": failures.is_empty(),
+            "failures": *failures,
         });
 
         Ok(CallToolResult::success(vec![Content::text(
@@ -155,10 +1557,48 @@ impl Documents {
 }
 
 
-#[tool(tool_box)]
 impl ServerHandler for Documents {
+    async fn list_tools(
+        &self,
+        _request: PaginatedRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools: Self::tool_box().list(),
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        #[cfg(feature = "metrics")]
+        let (tool, start) = (request.name.clone(), std::time::Instant::now());
+
+        let context = ToolCallContext::new(self, request, context);
+        let result = Self::tool_box().call(context).await;
+
+        // Time every tool the same way, failures included
+        #[cfg(feature = "metrics")]
+        {
+            let elapsed = start.elapsed();
+            let failed = result.as_ref().ok().is_none_or(|result| result.is_error == Some(true));
+            tracing::debug!(tool = %tool, elapsed_ms = elapsed.as_secs_f64() * 1000.0, failed, "Tool call");
+            self.metrics.record(&tool, elapsed, failed);
+        }
+
+        result
+    }
+
     fn get_info(&self) -> ServerInfo {
-        let instructions: &str = "This server provides tools to access documentation from a GitHub repository. \
+        // Name the repository, so clients connected to several servers can tell them apart
+        let source = match &self.repository {
+            Some(repository) => format!("the {} GitHub repository", repository.descriptor()),
+            None => "a GitHub repository".to_string(),
+        };
+        let instructions = format!("This server provides tools to access documentation from {}. \
         In this server, you can access documentation stored in a document storage system. \
         The document storage contains documentation, best practices, insights and how to guides. \
         It can help you find relevant information based on your queries. \
@@ -169,7 +1609,32 @@ impl ServerHandler for Documents {
         teaching them how to write better code. \
         \n\n\
         Available tools include:\n\
-        'get_all_docs' to retrieve all available documents, 'get_document' to fetch a specific document by path, or 'find_relevant_docs' to search for documents relevant to a query.";
+        - 'get_all_docs' to retrieve all available documents\n\
+        - 'get_document' to fetch a specific document by path\n\
+        - 'get_documents' to fetch several documents by path in one call\n\
+        - 'get_document_by_title' to fetch a specific document by title\n\
+        - 'find_relevant_docs' to search for documents relevant to a query\n\
+        - 'explain_relevance' to understand why a document scores the way it does for a query\n\
+        - 'search_in_document' to find matching passages within a specific document\n\
+        - 'related_documents' to find documents similar to a given document\n\
+        - 'get_document_images' to list the images and diagrams referenced by a document\n\
+        - 'lookup_term' to get the definition of a glossary term\n\
+        - 'summarize_document' to summarize a document with its most salient sentences\n\
+        - 'list_topics' to discover the most frequent keywords of the documents before searching\n\
+        - 'autocomplete_query' to complete a partial query term with the keywords of the documents\n\
+        - 'get_table_of_contents' to get a map of all documents and their headings, grouped by directory\n\
+        - 'diff_documents' to compare the content of two documents as a unified diff\n\
+        - 'add_document' to add a document to the storage from its content\n\
+        - 'unlock_internal_documents' to be served internal documents by presenting the internal token\n\
+        - 'lock_internal_documents' to hide internal documents again after unlocking them\n\
+        - 'get_scan_report' to check whether the last repository scan was complete\n\
+        - 'reindex_keywords' to recompute the keywords of all stored documents\n\
+        - 'reload_from_disk' to pick up changes made to the saved storage outside of the server\n\
+        - 'export_index' to export all stored documents as JSONL or Markdown\n\
+        - 'get_stale_documents' to list documents not fetched again for a given duration\n\
+        - 'get_repository_info' to check which repository the documents are served from\n\
+        - 'get_server_metrics' to get the number of calls and latency of each tool\n\
+        - 'health' to check whether the initial scan is done and the index is ready", source);
         
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
@@ -177,9 +1642,11 @@ impl ServerHandler for Documents {
                 .enable_prompts()
                 .enable_resources()
                 .enable_tools()
+                // Servers built on the 2024-11-05 protocol have no completions capability field yet
+                .enable_experimental_with([("completions".to_string(), JsonObject::new())].into())
                 .build(),
             server_info: Implementation::from_build_env(),
-            instructions: Some(instructions.to_string()),
+            instructions: Some(instructions),
         }
     }
 
@@ -202,6 +1669,10 @@ impl ServerHandler for Documents {
         ReadResourceRequestParam { uri }: ReadResourceRequestParam,
         _: RequestContext<RoleServer>,
     ) -> Result<ReadResourceResult, McpError> {
+        if uri.starts_with(DOCUMENT_URI_SCHEME) {
+            return self.read_document_resource(&uri).await;
+        }
+
         match uri.as_str() {
             "str:////Users/to/some/path/" => {
                 let cwd = "/Users/to/some/path/";
@@ -263,6 +1734,14 @@ impl ServerHandler for Documents {
         }
     }
 
+    async fn complete(
+        &self,
+        request: CompleteRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, McpError> {
+        self.complete_argument(request).await
+    }
+
     async fn list_resource_templates(
         &self,
         _request: PaginatedRequestParam,
@@ -270,7 +1749,7 @@ impl ServerHandler for Documents {
     ) -> Result<ListResourceTemplatesResult, McpError> {
         Ok(ListResourceTemplatesResult {
             next_cursor: None,
-            resource_templates: Vec::new(),
+            resource_templates: self.resource_templates(),
         })
     }
 }
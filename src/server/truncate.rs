@@ -0,0 +1,35 @@
+/// Marker ending text cut to fit the maximum response size
+pub const TRUNCATION_MARKER: &str = "\n[truncated]";
+
+/// A piece of text cut to fit a size limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Truncated {
+    /// The text kept, followed by the truncation marker if it was cut
+    pub text: String,
+    /// Byte offset in the original text where the kept part ends, if it was cut
+    pub cut_at: Option<usize>,
+}
+
+/// Cut text to at most `max_bytes` bytes, marker included, at a character boundary
+///
+/// Text within the limit is returned whole. When the limit is smaller than
+/// the marker, the text is cut to the limit without it.
+pub fn truncate(text: &str, max_bytes: usize) -> Truncated {
+    if text.len() <= max_bytes {
+        return Truncated {
+            text: text.to_string(),
+            cut_at: None,
+        };
+    }
+
+    let marker = if max_bytes >= TRUNCATION_MARKER.len() { TRUNCATION_MARKER } else { "" };
+    let mut end = max_bytes - marker.len();
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    Truncated {
+        text: format!("{}{}", &text[..end], marker),
+        cut_at: Some(end),
+    }
+}
@@ -0,0 +1,50 @@
+use serde::Serialize;
+
+/// Where a query term was found in a document, which sets the weight of the match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchLocation {
+    Title,
+    Heading,
+    /// The summary, for terms restricted to it, weighing as much as headings
+    Summary,
+    Body,
+}
+
+/// How a query term contributed to the score of a document
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TermScore {
+    /// The query term, as a keyword unless matching exactly
+    pub term: String,
+    /// Field the term is restricted to with a `field:` prefix, if any
+    pub field: Option<&'static str>,
+    /// What matched in the document: the term itself, one of its synonyms, or nothing
+    pub matched: Option<String>,
+    /// Whether the term only matched through a synonym, which weighs less
+    pub synonym: bool,
+    /// The most significant place the match was found in
+    pub location: Option<MatchLocation>,
+    /// Weight added to the score of the document
+    pub weight: f64,
+}
+
+/// Breakdown of the score of a document for a query, to understand its ranking
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelevanceExplanation {
+    pub path: String,
+    /// Every scored term of the query, matched or not
+    pub terms: Vec<TermScore>,
+    /// Whether the document has the required phrases and terms of the query,
+    /// and none of its excluded terms; it isn't returned otherwise
+    pub satisfies_operators: bool,
+    /// Sum of the weights of the terms
+    pub total_weight: f64,
+    /// Weight of a document with every term in its title, which scores 1
+    pub max_weight: f64,
+    /// Keyword score of the document, the total weight over the maximum weight
+    pub relevance: f64,
+    /// Factor the relevance is multiplied by for the recency of the document, 1 if disabled
+    pub recency_factor: f64,
+    /// The score search results are ranked by
+    pub score: f64,
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use serde_json::json;
+use std::str::FromStr;
+
+use super::StoredDocument;
+
+/// Format of an exported corpus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One JSON document object per line
+    Jsonl,
+    /// A single markdown file with each document under its title
+    Markdown,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format.trim().to_lowercase().as_str() {
+            "jsonl" => Ok(ExportFormat::Jsonl),
+            "markdown" | "md" => Ok(ExportFormat::Markdown),
+            _ => anyhow::bail!("Unsupported export format: {}", format),
+        }
+    }
+}
+
+/// Serialize documents in the given format, in the order given
+pub fn export(documents: &[&StoredDocument], format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Jsonl => to_jsonl(documents),
+        ExportFormat::Markdown => Ok(to_markdown(documents)),
+    }
+}
+
+/// One object per line, without the search indexes which are specific to this server
+fn to_jsonl(documents: &[&StoredDocument]) -> Result<String> {
+    let mut output = String::new();
+    for document in documents {
+        let record = json!({
+            "path": document.path,
+            "title": document.title,
+            "summary": document.summary,
+            "language": document.language,
+            "aliases": document.aliases,
+            "links": document.links,
+            "content": document.content,
+        });
+        output.push_str(&serde_json::to_string(&record)?);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Each document under a top level heading with its title, followed by its path and content
+fn to_markdown(documents: &[&StoredDocument]) -> String {
+    documents
+        .iter()
+        .map(|document| {
+            format!(
+                "# {}\n\nPath: `{}`\n\n{}\n",
+                document.title,
+                document.path,
+                document.content.trim_end()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n---\n\n")
+}
@@ -1,16 +1,187 @@
-use anyhow::Result;
+use crate::error::{KodeError, Result};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::document::Document;
+use crate::document::{self, Definition, Document, DocumentFormat, DocumentScanner, ImageRef, RenderFormat, Visibility};
+
+mod explain;
+mod export;
+mod language;
+mod normalize;
+mod query;
+mod search;
+mod sort;
+mod stem;
+mod stopwords;
+mod summarize;
+mod synonyms;
+mod toc;
+mod tokenizer;
+mod tokens;
+mod weighting;
+
+pub use explain::{MatchLocation, RelevanceExplanation, TermScore};
+pub use export::ExportFormat;
+pub use query::MatchOptions;
+pub use search::DocumentMatch;
+pub use sort::{sort_documents, SortField, SortOrder};
+pub use stopwords::Stopwords;
+pub use synonyms::Synonyms;
+pub use toc::{TocDirectory, TocDocument, TocHeading};
+pub use tokens::{count_words, estimate_tokens};
+pub use tokenizer::{CodeTokenizer, DefaultTokenizer, Tokenizer, TokenizerKind};
+pub use weighting::{KeywordField, WeightedKeyword};
+
+use query::Field;
+
+/// Approximate maximum length of search result snippets, in bytes
+const SNIPPET_LENGTH: usize = 300;
+
+/// Share of the weight of a keyword matched through one of its synonyms, so
+/// exact matches rank first
+const SYNONYM_WEIGHT: f64 = 0.5;
+
+/// Share of the body weight a keyword of the body scores at least, the rest
+/// depending on how significant it is in the document
+const MIN_BODY_SHARE: f64 = 0.75;
+
+/// File names of the document index, uncompressed and gzip compressed
+const INDEX_FILE: &str = "documents.json";
+const COMPRESSED_INDEX_FILE: &str = "documents.json.gz";
+
+/// Extension appended to the name of a corrupt index when it's set aside
+const BACKUP_EXTENSION: &str = "bak";
+
+/// A saved index that couldn't be parsed when the storage was opened, and was
+/// set aside so the storage could start empty
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexRecovery {
+    /// Where the corrupt index was moved, e.g. `documents.json.bak`
+    pub backup_path: PathBuf,
+    /// Why the index couldn't be parsed
+    pub reason: String,
+}
+
+/// Multipliers applied to keyword matches depending on where the keyword appears
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ScoringWeights {
+    pub title: f64,
+    pub heading: f64,
+    pub body: f64,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            title: 3.0,
+            heading: 2.0,
+            body: 1.0,
+        }
+    }
+}
+
+/// Boost of recently modified documents in search results
+///
+/// The keyword score of a document is multiplied by `1 - weight + weight * decay`,
+/// where the decay halves every `half_life_days` since the document was last
+/// modified, so newer documents rank first among documents matching as well.
+/// Documents without a known modification time get no boost.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RecencyBoost {
+    /// Share of the score depending on recency, from 0 to 1
+    pub weight: f64,
+    /// Age, in days, at which a document gets half of the boost
+    pub half_life_days: f64,
+}
+
+impl Default for RecencyBoost {
+    fn default() -> Self {
+        Self {
+            weight: 0.2,
+            half_life_days: 90.0,
+        }
+    }
+}
+
+impl RecencyBoost {
+    /// Factor applied to the score of a document last modified at the given time, from `1 - weight` to 1
+    pub fn factor(&self, last_modified: Option<u64>, now: u64) -> f64 {
+        let weight = self.weight.clamp(0.0, 1.0);
+        let decay = match last_modified {
+            Some(last_modified) => {
+                let age_days = now.saturating_sub(last_modified) as f64 / 86_400.0;
+                0.5_f64.powf(age_days / self.half_life_days.max(f64::MIN_POSITIVE))
+            }
+            None => 0.0,
+        };
+        1.0 - weight + weight * decay
+    }
+}
+
+/// How documents merged from another storage are stored when their path is already taken
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the most recently changed document, by last modification or fetch time
+    #[default]
+    KeepNewest,
+    /// Keep the document already stored
+    KeepExisting,
+    /// Store every merged document under the given directory, so no path collides
+    Namespace(String),
+}
 
 /// Document storage that handles storing and retrieving documents
 pub struct DocumentStorage {
-    storage_path: PathBuf,
+    // Directory the index is saved in, none for a storage kept in memory only
+    storage_path: Option<PathBuf>,
     documents: HashMap<String, StoredDocument>,
+    weights: ScoringWeights,
+    // Boost of recently modified documents, none if scores only depend on keywords
+    recency: Option<RecencyBoost>,
+    // Whether the index is saved gzip compressed
+    compress: bool,
+    // Whether keywords are reduced to their stem
+    stemming: bool,
+    // Whether diacritics are removed from keywords
+    fold_diacritics: bool,
+    // Whether the HTML of documents is rendered when they're stored, instead of when requested
+    prerender_html: bool,
+    // Words left out of keywords, per document language
+    stopwords: Stopwords,
+    // Synonyms query keywords are expanded to, none if queries aren't expanded
+    synonyms: Option<Synonyms>,
+    // Maximum number of content keywords kept per document, all of them if not set
+    max_keywords: Option<usize>,
+    // Splits text into the words keywords are extracted from
+    tokenizer: Box<dyn Tokenizer>,
+    // Whether saving is deferred until the current batch is committed
+    in_batch: bool,
+    // Whether documents changed since the index was last saved, within a batch
+    dirty: bool,
+    // Number of times the index was saved
+    saves: usize,
+    // Content hash to the path of the canonical document with that content
+    content_hashes: HashMap<String, String>,
+    // Alias path to the path of the canonical document it duplicates
+    aliases: HashMap<String, String>,
+    // Normalized title to the paths of the documents with that title
+    titles: HashMap<String, Vec<String>>,
+    // The corrupt index set aside when the storage was opened, if any
+    recovery: Option<IndexRecovery>,
+    // Paths of the documents internal unless their front matter says otherwise
+    internal_paths: Option<GlobSet>,
 }
 
 /// Stored document with additional metadata for retrieval
@@ -20,11 +191,150 @@ pub struct StoredDocument {
     pub content: String,
     pub title: String,
     pub summary: Option<String>,
+    /// Keywords of the whole content, most significant first
+    ///
+    /// A view of the weighted keywords of the content, rebuilt when the index
+    /// is loaded rather than saved. Only read from indexes saved before
+    /// keywords were weighted.
+    #[serde(default, skip_serializing)]
     pub keywords: Vec<String>,
+    /// The keywords of the title, headings and content, each stored once with
+    /// the most significant field it's found in, and weighed in the content by
+    /// frequency and position, most significant first
+    ///
+    /// Title and heading matches weigh more than content matches when scoring.
+    #[serde(default)]
+    pub weighted_keywords: Vec<WeightedKeyword>,
+    // Position of each keyword in the weighted keywords, to look them up while scoring
+    #[serde(skip)]
+    keyword_positions: HashMap<String, usize>,
+    /// SHA-256 of the normalized content, used to detect duplicates
+    #[serde(default)]
+    pub content_hash: String,
+    /// Other paths with identical content, which resolve to this document
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// ISO 639-1 code of the language the document prose is written in
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Repository-relative paths of the documents linked from this document
+    #[serde(default)]
+    pub links: Vec<String>,
+    /// Images referenced by this document, with their alt text
+    #[serde(default)]
+    pub images: Vec<ImageRef>,
+    /// Terms defined by this document, from definition lists and glossary bullets
+    #[serde(default)]
+    pub definitions: Vec<Definition>,
+    /// Who the document may be shown to, from its front matter or the internal path patterns
+    #[serde(default)]
+    pub visibility: Visibility,
+    /// When the document was last stored, as a Unix timestamp
+    #[serde(default)]
+    pub fetched_at: Option<u64>,
+    /// When the document was last changed in its repository, as a Unix timestamp, if known
+    #[serde(default)]
+    pub last_modified: Option<u64>,
+    /// The content rendered as HTML, if rendered when the document was stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+    /// Number of whitespace-separated words of the content
+    #[serde(default)]
+    pub word_count: usize,
+    /// Approximate number of LLM tokens of the content, to fit documents in a context budget
+    #[serde(default)]
+    pub token_count: usize,
+    /// The source the document was fetched from apart from the repository files,
+    /// such as the wiki, none for repository and local documents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// A document relevant to a query, with how well it matches
+#[derive(Debug, Clone)]
+pub struct RelevantDocument<'a> {
+    pub document: &'a StoredDocument,
+    /// Weighted keyword score, from 0 to 1 when every query keyword is in the
+    /// title, multiplied by the recency boost if enabled
+    pub score: f64,
+    /// Weighted keyword score, before the recency boost
+    pub relevance: f64,
+    /// Query keywords found in the document
+    pub matched_terms: Vec<String>,
+}
+
+impl StoredDocument {
+    /// Check whether the document is written in the given language
+    ///
+    /// Accepts ISO 639-1 (`en`) and ISO 639-3 (`eng`) codes. Documents whose
+    /// language couldn't be detected never match.
+    pub fn is_language(&self, language: &str) -> bool {
+        self.language.as_deref() == Some(language::normalize(language).as_str())
+    }
+    
+    /// Format of the document, from the extension of its path, plain text if it's unknown
+    pub fn format(&self) -> DocumentFormat {
+        DocumentFormat::detect(&self.path).unwrap_or(DocumentFormat::PlainText)
+    }
+    
+    /// MIME type of the content of the document, so clients can render it
+    pub fn mime_type(&self) -> &'static str {
+        self.format().mime_type()
+    }
+    
+    /// Get the weight of a keyword of the document, from 0 to 1
+    ///
+    /// Keywords of documents without weights all weigh 1, and keywords the
+    /// document doesn't have weigh 0.
+    pub fn keyword_weight(&self, keyword: &str) -> f64 {
+        if self.weighted_keywords.is_empty() {
+            return if self.keywords.iter().any(|k| k == keyword) { 1.0 } else { 0.0 };
+        }
+        self.weighted_keyword(keyword).map_or(0.0, |weighted| f64::from(weighted.weight))
+    }
+    
+    /// Get the most significant field a keyword of the document is found in,
+    /// none if the document doesn't have it
+    pub fn keyword_field(&self, keyword: &str) -> Option<KeywordField> {
+        match self.weighted_keyword(keyword) {
+            Some(weighted) => Some(weighted.field),
+            None => self.keywords.iter().any(|k| k == keyword).then_some(KeywordField::Body),
+        }
+    }
+    
+    /// Find a weighted keyword of the document
+    fn weighted_keyword(&self, keyword: &str) -> Option<&WeightedKeyword> {
+        self.keyword_positions.get(keyword).map(|&position| &self.weighted_keywords[position])
+    }
+    
+    /// Replace the weighted keywords of the document, and the keywords derived from them
+    fn set_weighted_keywords(&mut self, weighted_keywords: Vec<WeightedKeyword>) {
+        self.keywords = weighting::body_keywords(&weighted_keywords);
+        self.keyword_positions = weighted_keywords
+            .iter()
+            .enumerate()
+            .map(|(position, weighted)| (weighted.keyword.clone(), position))
+            .collect();
+        self.weighted_keywords = weighted_keywords;
+    }
+    
+    /// Get the content of the document in the given format, using the HTML
+    /// rendered when it was stored if any
+    pub fn render(&self, format: RenderFormat) -> Cow<'_, str> {
+        match (&self.html, format) {
+            (Some(html), RenderFormat::Html) => Cow::Borrowed(html),
+            _ => document::render(&self.path, &self.content, format),
+        }
+    }
 }
 
 impl DocumentStorage {
     /// Create a new document storage with the given storage path
+    ///
+    /// A saved index that can't be parsed, such as one truncated by a crash, is
+    /// moved to `documents.json.bak` and the storage starts empty, with a
+    /// warning logged and returned by `recovery`. Failing to read the index
+    /// otherwise, e.g. for lack of permissions, is an error.
     pub fn new(storage_path: impl AsRef<Path>) -> Result<Self> {
         let storage_path = storage_path.as_ref().to_path_buf();
         
@@ -33,123 +343,1392 @@ impl DocumentStorage {
             fs::create_dir_all(&storage_path)?;
         }
         
-        // Try to load existing documents
-        let documents = Self::load_documents(&storage_path).unwrap_or_default();
+        // Load existing documents, setting a corrupt index aside
+        let (documents, recovery) = match Self::load_documents(&storage_path) {
+            Ok(documents) => (documents, None),
+            Err(KodeError::Parse(reason)) => (HashMap::new(), Some(Self::set_aside_index(&storage_path, reason)?)),
+            Err(e) => return Err(e),
+        };
+        
+        let mut storage = Self {
+            storage_path: Some(storage_path),
+            recovery,
+            ..Self::new_in_memory()
+        };
+        storage.documents = documents;
+        storage.rebuild_indexes();
         
-        Ok(Self {
-            storage_path,
-            documents,
+        Ok(storage)
+    }
+    
+    /// Move a corrupt index out of the way, so it's kept for inspection but not
+    /// loaded or overwritten again
+    fn set_aside_index(storage_path: &Path, reason: String) -> Result<IndexRecovery> {
+        let index_path = [COMPRESSED_INDEX_FILE, INDEX_FILE]
+            .iter()
+            .map(|file| storage_path.join(file))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| storage_path.join(INDEX_FILE));
+        let mut backup_path = index_path.clone().into_os_string();
+        backup_path.push(".");
+        backup_path.push(BACKUP_EXTENSION);
+        let backup_path = PathBuf::from(backup_path);
+        
+        fs::rename(&index_path, &backup_path)?;
+        tracing::warn!(
+            "The document index {} is corrupt ({}), starting with an empty storage. It was moved to {}",
+            index_path.display(),
+            reason,
+            backup_path.display()
+        );
+        Ok(IndexRecovery { backup_path, reason })
+    }
+    
+    /// The directory the storage is saved in, none if it's kept in memory only
+    pub fn storage_path(&self) -> Option<&Path> {
+        self.storage_path.as_deref()
+    }
+    
+    /// The corrupt index set aside when the storage was opened, if it couldn't be parsed
+    pub fn recovery(&self) -> Option<&IndexRecovery> {
+        self.recovery.as_ref()
+    }
+    
+    /// Create a storage kept in memory only, which never reads or writes files
+    ///
+    /// It behaves like a storage on disk, except its documents are lost when it
+    /// is dropped.
+    pub fn new_in_memory() -> Self {
+        Self {
+            storage_path: None,
+            documents: HashMap::new(),
+            weights: ScoringWeights::default(),
+            recency: None,
+            compress: false,
+            stemming: false,
+            fold_diacritics: false,
+            prerender_html: false,
+            stopwords: Stopwords::default(),
+            synonyms: None,
+            max_keywords: None,
+            tokenizer: Box::new(DefaultTokenizer),
+            in_batch: false,
+            dirty: false,
+            saves: 0,
+            content_hashes: HashMap::new(),
+            aliases: HashMap::new(),
+            titles: HashMap::new(),
+            recovery: None,
+            internal_paths: None,
+        }
+    }
+    
+    /// Set whether the index is saved gzip compressed, from the next save on
+    ///
+    /// Loading detects the format of the existing index, so it can be changed
+    /// between runs.
+    pub fn set_compression(&mut self, compress: bool) {
+        self.compress = compress;
+    }
+    
+    /// Set whether keywords are reduced to their stem, so that inflected forms
+    /// of a word (`configured`, `configuring`) match each other
+    ///
+    /// Documents already stored keep their keywords until `reindex_keywords` is called.
+    pub fn set_stemming(&mut self, stemming: bool) {
+        self.stemming = stemming;
+    }
+    
+    /// Set whether diacritics are removed from keywords, so accented and
+    /// unaccented forms of a word (`résumé`, `resume`) match each other
+    ///
+    /// Keep it disabled to tell words apart by their diacritics. Documents
+    /// already stored keep their keywords until `reindex_keywords` is called.
+    pub fn set_diacritic_folding(&mut self, fold_diacritics: bool) {
+        self.fold_diacritics = fold_diacritics;
+    }
+    
+    /// Set whether the HTML of documents is rendered when they're stored, and
+    /// kept with them, instead of every time it's requested
+    ///
+    /// It makes HTML requests cheaper, at the cost of about twice the memory
+    /// and index size. Documents already stored are rendered when requested.
+    pub fn set_html_prerendering(&mut self, prerender_html: bool) {
+        self.prerender_html = prerender_html;
+    }
+    
+    /// Set the stopwords left out of keywords, per document language
+    ///
+    /// Documents already stored keep their keywords until `reindex_keywords` is called.
+    pub fn set_stopwords(&mut self, stopwords: Stopwords) {
+        self.stopwords = stopwords;
+    }
+    
+    /// Set the synonyms query keywords are expanded to, or disable query expansion
+    ///
+    /// Documents matching a query keyword only through a synonym score less
+    /// than documents containing the keyword itself.
+    pub fn set_synonyms(&mut self, synonyms: Option<Synonyms>) {
+        self.synonyms = synonyms;
+    }
+    
+    /// Set the maximum number of content keywords kept per document, the most
+    /// frequent ones, or keep all of them
+    ///
+    /// Documents already stored keep their keywords until `reindex_keywords` is called.
+    pub fn set_max_keywords(&mut self, max_keywords: Option<usize>) {
+        self.max_keywords = max_keywords;
+    }
+    
+    /// Set the tokenizer splitting text into the words keywords are extracted from
+    ///
+    /// Documents already stored keep their keywords until `reindex_keywords` is called.
+    pub fn set_tokenizer(&mut self, tokenizer: Box<dyn Tokenizer>) {
+        self.tokenizer = tokenizer;
+    }
+    
+    /// Set the glob patterns of the paths of internal documents, e.g. `internal/**`
+    ///
+    /// A `visibility` set in the front matter of a document takes precedence,
+    /// so `visibility: public` publishes a document of an internal directory.
+    /// Documents already stored are tagged again right away.
+    pub fn set_internal_paths(&mut self, patterns: &[String]) -> Result<()> {
+        self.internal_paths = if patterns.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                let glob = Glob::new(pattern)
+                    .map_err(|e| KodeError::InvalidArgument(format!("invalid internal path pattern {}: {}", pattern, e)))?;
+                builder.add(glob);
+            }
+            Some(builder.build().map_err(|e| KodeError::InvalidArgument(e.to_string()))?)
+        };
+        
+        let mut documents = std::mem::take(&mut self.documents);
+        for document in documents.values_mut() {
+            document.visibility = self.visibility(&document.path, &document.content);
+        }
+        self.documents = documents;
+        Ok(())
+    }
+    
+    /// Get the visibility of a document, from its front matter or else the internal path patterns
+    fn visibility(&self, path: &str, content: &str) -> Visibility {
+        document::front_matter_visibility(content).unwrap_or_else(|| {
+            match self.internal_paths.as_ref().is_some_and(|internal| internal.is_match(path)) {
+                true => Visibility::Internal,
+                false => Visibility::Public,
+            }
         })
     }
     
+    /// Load the saved index again, replacing the documents in memory, to pick
+    /// up changes made to it by another process or by hand
+    ///
+    /// The index is read in full before anything is replaced, so the documents
+    /// in memory are kept if it can't be read, or if there's no index anymore.
+    /// Changes of an open batch not committed yet are lost. Returns the number
+    /// of documents loaded.
+    pub fn reload_from_disk(&mut self) -> Result<usize> {
+        let Some(storage_path) = &self.storage_path else {
+            return Err(KodeError::InvalidArgument("the storage is kept in memory only, there's nothing to reload".to_string()));
+        };
+        // An index deleted or moved away isn't an empty index
+        if [INDEX_FILE, COMPRESSED_INDEX_FILE].iter().all(|file| !storage_path.join(file).exists()) {
+            return Err(KodeError::NotFound(format!("document index in {}", storage_path.display())));
+        }
+        
+        self.documents = Self::load_documents(storage_path)?;
+        self.rebuild_indexes();
+        self.dirty = false;
+        Ok(self.documents.len())
+    }
+    
+    /// Recompute the keywords of every stored document from its content, and save them
+    ///
+    /// Used to apply changes to keyword extraction, such as stemming, without
+    /// fetching the documents again. Returns the number of documents reindexed.
+    pub fn reindex_keywords(&mut self) -> Result<usize> {
+        let mut documents = std::mem::take(&mut self.documents);
+        let reindex = |document: &mut StoredDocument| {
+            let language = document.language.as_deref();
+            let text = document::keyword_text(&document.path, &document.content);
+            let mut keywords = self.extract_content_keywords(&text, language);
+            self.add_image_keywords(&mut keywords, &document.images, language);
+            document.set_weighted_keywords(weighting::consolidate(
+                weighting::weigh(&keywords, &self.keyword_occurrences(&text)),
+                &self.extract_keywords(&document.title, language),
+                &self.extract_heading_keywords(&document.path, &document.content, language),
+            ));
+        };
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            documents.par_iter_mut().for_each(|(_, document)| reindex(document));
+        }
+        #[cfg(not(feature = "parallel"))]
+        documents.values_mut().for_each(reindex);
+        let count = documents.len();
+        self.documents = documents;
+        
+        // Save to disk
+        self.persist()?;
+        
+        Ok(count)
+    }
+    
+    /// Start a batch of changes, saved to disk once when the batch is committed
+    ///
+    /// Until `commit` is called, storing and removing documents only updates the
+    /// in-memory storage, so a batch of N documents stored one by one is written
+    /// once instead of N times. Starting a batch while one is open has no effect.
+    pub fn begin_batch(&mut self) {
+        self.in_batch = true;
+    }
+    
+    /// End the current batch, saving the documents if they changed during it
+    pub fn commit(&mut self) -> Result<()> {
+        self.in_batch = false;
+        if self.dirty {
+            self.save_documents()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Number of times the index was saved to disk since the storage was opened
+    pub fn save_count(&self) -> usize {
+        self.saves
+    }
+    
+    /// Set the boost of recently modified documents in search results, or
+    /// score documents on their keywords only
+    pub fn set_recency_boost(&mut self, recency: Option<RecencyBoost>) {
+        self.recency = recency;
+    }
+    
+    /// Set the multipliers applied to title, heading and body keyword matches
+    pub fn set_scoring_weights(&mut self, weights: ScoringWeights) {
+        self.weights = weights;
+    }
+    
     /// Store a document
     pub fn store_document(&mut self, document: Document) -> Result<()> {
-        // Extract keywords from the document content
-        let keywords = self.extract_keywords(&document.content);
+        self.insert_document(document);
         
-        // Create a stored document
-        let stored_document = StoredDocument {
-            path: document.path.clone(),
-            content: document.content,
-            title: document.title,
-            summary: document.summary,
-            keywords,
-        };
+        // Save to disk
+        self.persist()?;
+        
+        Ok(())
+    }
+    
+    /// Replace the documents of a source, such as the wiki, with its current
+    /// documents, removing those it no longer has
+    ///
+    /// Only documents stored from the same source are removed, so repository
+    /// files under the same directory are kept. The documents are stored under
+    /// their own paths rather than as aliases, so they can be told apart.
+    pub fn replace_source(&mut self, source: &str, documents: Vec<Document>) -> Result<()> {
+        let removed: Vec<String> = self
+            .documents
+            .values()
+            .filter(|stored| stored.source.as_deref() == Some(source))
+            .filter(|stored| !documents.iter().any(|document| document.path == stored.path))
+            .map(|stored| stored.path.clone())
+            .collect();
+        for path in &removed {
+            // No content hash matches the empty hash, so the path is always detached
+            self.remove_path(path, "");
+        }
         
-        // Add to the in-memory storage
-        self.documents.insert(document.path, stored_document);
+        for mut document in self.analyze_documents(documents) {
+            document.source = Some(source.to_string());
+            self.insert_analyzed(document, false);
+        }
         
         // Save to disk
-        self.save_documents()?;
+        self.persist()?;
         
         Ok(())
     }
     
     /// Store multiple documents
     pub fn store_documents(&mut self, documents: Vec<Document>) -> Result<()> {
-        for document in documents {
-            // Extract keywords from the document content
-            let keywords = self.extract_keywords(&document.content);
-            
-            // Create a stored document
-            let stored_document = StoredDocument {
-                path: document.path.clone(),
-                content: document.content,
-                title: document.title,
-                summary: document.summary,
-                keywords,
-            };
-            
-            // Add to the in-memory storage
-            self.documents.insert(document.path, stored_document);
+        self.insert_documents(documents);
+        
+        // Save to disk
+        self.persist()?;
+        
+        Ok(())
+    }
+    
+    /// Merge the documents of another storage into this one, such as the scan
+    /// of another repository or ref, and save the result
+    ///
+    /// Aliases are merged as documents of their own, so duplicates across both
+    /// storages are detected. Keywords are extracted again with the settings of
+    /// this storage, and the content hash, alias and title indexes are updated,
+    /// so the merged index is searched as if the documents were stored here.
+    /// Path collisions are resolved with `policy`. Returns the number of documents
+    /// added or replaced.
+    pub fn merge(&mut self, other: &DocumentStorage, policy: MergePolicy) -> Result<usize> {
+        let namespace = match &policy {
+            MergePolicy::Namespace(namespace) => namespace.trim_matches('/'),
+            _ => "",
+        };
+        
+        let mut incoming: Vec<&StoredDocument> = other.documents.values().collect();
+        incoming.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut merged = Vec::new();
+        for stored in incoming {
+            for path in std::iter::once(&stored.path).chain(&stored.aliases) {
+                let path = if namespace.is_empty() { path.clone() } else { format!("{}/{}", namespace, path) };
+                let keep_existing = match (&policy, self.get_document(&path)) {
+                    (_, None) | (MergePolicy::Namespace(_), _) => false,
+                    (MergePolicy::KeepExisting, Some(_)) => true,
+                    (MergePolicy::KeepNewest, Some(existing)) => {
+                        existing.last_modified.or(existing.fetched_at) >= stored.last_modified.or(stored.fetched_at)
+                    }
+                };
+                if keep_existing {
+                    continue;
+                }
+                
+                let mut document = self.analyze_document(Document {
+                    path,
+                    content: stored.content.clone(),
+                    title: stored.title.clone(),
+                    summary: stored.summary.clone(),
+                });
+                document.fetched_at = stored.fetched_at;
+                document.last_modified = stored.last_modified;
+                merged.push(document);
+            }
+        }
+        
+        let count = merged.len();
+        for document in merged {
+            self.insert_analyzed(document, true);
         }
         
         // Save to disk
-        self.save_documents()?;
+        self.persist()?;
+        
+        Ok(count)
+    }
+    
+    /// Record when documents were last changed in their repository, by path or alias
+    ///
+    /// Unknown paths are ignored.
+    pub fn set_last_modified(&mut self, last_modified: &HashMap<String, u64>) -> Result<()> {
+        for (path, modified) in last_modified {
+            let path = self.aliases.get(path).unwrap_or(path).clone();
+            if let Some(document) = self.documents.get_mut(&path) {
+                // Aliases share the document, which is as recent as its latest copy
+                document.last_modified = document.last_modified.max(Some(*modified));
+            }
+        }
+        
+        // Save to disk
+        self.persist()?;
         
         Ok(())
     }
     
+    /// Remove documents, given by path or alias
+    ///
+    /// A removed document with aliases is kept under its first alias, since the
+    /// aliases still have the same content. Unknown paths are ignored.
+    pub fn remove_documents(&mut self, paths: &[String]) -> Result<()> {
+        for path in paths {
+            // No content hash matches the empty hash, so the path is always detached
+            self.remove_path(path, "");
+        }
+        
+        // Save to disk
+        self.persist()?;
+        
+        Ok(())
+    }
+    
+    /// Add a document to the in-memory storage
+    fn insert_document(&mut self, document: Document) {
+        let document = self.analyze_document(document);
+        self.insert_analyzed(document, true);
+    }
+    
+    /// Add documents to the in-memory storage, in order
+    ///
+    /// Documents are analyzed in parallel with the `parallel` feature, then
+    /// inserted one by one in their original order, so the stored result
+    /// doesn't depend on thread scheduling.
+    fn insert_documents(&mut self, documents: Vec<Document>) {
+        for document in self.analyze_documents(documents) {
+            self.insert_analyzed(document, true);
+        }
+    }
+    
+    /// Analyze documents, in parallel with the `parallel` feature, keeping their order
+    fn analyze_documents(&self, documents: Vec<Document>) -> Vec<StoredDocument> {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            documents.into_par_iter().map(|document| self.analyze_document(document)).collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        documents.into_iter().map(|document| self.analyze_document(document)).collect()
+    }
+    
+    /// Extract the language, keywords and links of a document, without touching the storage
+    fn analyze_document(&self, document: Document) -> StoredDocument {
+        let content_hash = Self::hash_content(&document.content);
+        
+        // Detect the language from the prose only, as code would skew detection
+        let language = language::detect(&document::prose(&document.path, &document.content));
+        
+        // Extract keywords from the document content, title and headings, without the stopwords of its language
+        let text = document::keyword_text(&document.path, &document.content);
+        let mut keywords = self.extract_content_keywords(&text, language.as_deref());
+        let title_keywords = self.extract_keywords(&document.title, language.as_deref());
+        let heading_keywords = self.extract_heading_keywords(&document.path, &document.content, language.as_deref());
+        
+        // Resolve relative links so they match the paths documents are stored under
+        let links = document::extract_links(&document.path, &document.content);
+        
+        // Alt text describes diagrams and screenshots, so it counts as content even when keywords are capped
+        let images = document::extract_images(&document.path, &document.content);
+        self.add_image_keywords(&mut keywords, &images, language.as_deref());
+        let definitions = document::extract_definitions(&document.content);
+        let visibility = self.visibility(&document.path, &document.content);
+        let weighted_keywords = weighting::consolidate(
+            weighting::weigh(&keywords, &self.keyword_occurrences(&text)),
+            &title_keywords,
+            &heading_keywords,
+        );
+        
+        let html = self
+            .prerender_html
+            .then(|| document::render(&document.path, &document.content, RenderFormat::Html).into_owned());
+        
+        let word_count = tokens::count_words(&document.content);
+        let token_count = tokens::estimate_tokens(&document.content);
+        
+        let mut stored = StoredDocument {
+            path: document.path,
+            content: document.content,
+            title: document.title,
+            summary: document.summary,
+            keywords: Vec::new(),
+            weighted_keywords: Vec::new(),
+            keyword_positions: HashMap::new(),
+            content_hash,
+            aliases: Vec::new(),
+            language,
+            links,
+            images,
+            definitions,
+            visibility,
+            fetched_at: Some(Self::now()),
+            last_modified: None,
+            html,
+            word_count,
+            token_count,
+            source: None,
+        };
+        stored.set_weighted_keywords(weighted_keywords);
+        stored
+    }
+    
+    /// Add an analyzed document to the in-memory storage
+    ///
+    /// A document whose content duplicates an already stored document is recorded
+    /// as an alias of that document instead of being stored again, unless
+    /// `alias_duplicates` is false.
+    fn insert_analyzed(&mut self, mut document: StoredDocument, alias_duplicates: bool) {
+        let content_hash = document.content_hash.clone();
+        
+        // The path is about to point to new content, so detach it from its old entry
+        self.remove_path(&document.path, &content_hash);
+        
+        // Record duplicates as aliases of the canonical document, unless the path already
+        // holds the same content apart from it, as a duplicate stored from another source
+        if alias_duplicates
+            && !self.documents.contains_key(&document.path)
+            && let Some(canonical) = self.content_hashes.get(&content_hash)
+            && *canonical != document.path
+        {
+            let canonical = canonical.clone();
+            if let Some(stored) = self.documents.get_mut(&canonical) {
+                stored.aliases.push(document.path.clone());
+            }
+            self.aliases.insert(document.path, canonical);
+            return;
+        }
+        
+        // Keep the aliases of a document being refreshed with the same content
+        let previous = self.documents.get(&document.path).map(|stored| (stored.aliases.clone(), stored.title.clone()));
+        if let Some((aliases, title)) = previous {
+            self.unindex_title(&title, &document.path);
+            document.aliases = aliases;
+        }
+        
+        // Add to the in-memory storage, leaving duplicates stored apart to the canonical document
+        self.content_hashes.entry(content_hash).or_insert_with(|| document.path.clone());
+        self.index_title(&document.title, &document.path);
+        self.documents.insert(document.path.clone(), document);
+    }
+    
+    /// Detach a path from the document it currently resolves to, unless that
+    /// document already has the given content hash
+    fn remove_path(&mut self, path: &str, content_hash: &str) {
+        // An alias only needs to be unlinked from its canonical document
+        if let Some(canonical) = self.aliases.remove(path) {
+            if let Some(stored) = self.documents.get_mut(&canonical) {
+                stored.aliases.retain(|alias| alias != path);
+            }
+            return;
+        }
+        
+        let Some(stored) = self.documents.get(path) else {
+            return;
+        };
+        if stored.content_hash == content_hash {
+            return;
+        }
+        
+        // Promote the first alias, if any, to hold the previous content
+        let mut previous = self.documents.remove(path).unwrap();
+        let previous_hash = previous.content_hash.clone();
+        if self.content_hashes.get(&previous.content_hash).is_some_and(|canonical| canonical == path) {
+            self.content_hashes.remove(&previous.content_hash);
+        }
+        self.unindex_title(&previous.title, path);
+        if !previous.aliases.is_empty() {
+            let promoted = previous.aliases.remove(0);
+            self.aliases.remove(&promoted);
+            for alias in &previous.aliases {
+                self.aliases.insert(alias.clone(), promoted.clone());
+            }
+            previous.path = promoted.clone();
+            // Aliases are only stored from the repository, other sources being stored apart
+            previous.source = None;
+            self.content_hashes.entry(previous.content_hash.clone()).or_insert_with(|| promoted.clone());
+            self.index_title(&previous.title, &promoted);
+            self.documents.insert(promoted, previous);
+        }
+        
+        // Without aliases, a document stored apart from it with the same content becomes canonical
+        if !self.content_hashes.contains_key(&previous_hash)
+            && let Some(other) = self.documents.values().find(|stored| stored.content_hash == previous_hash)
+        {
+            self.content_hashes.insert(previous_hash, other.path.clone());
+        }
+    }
+    
+    /// Rebuild the content hash, alias and title indexes from the stored documents
+    fn rebuild_indexes(&mut self) {
+        self.content_hashes.clear();
+        self.aliases.clear();
+        self.titles.clear();
+        
+        let mut documents = std::mem::take(&mut self.documents);
+        for document in documents.values_mut() {
+            // Documents stored before hashing was introduced have no hash yet
+            if document.content_hash.is_empty() {
+                document.content_hash = Self::hash_content(&document.content);
+            }
+            // Nor a language, if stored before language detection was introduced
+            if document.language.is_none() {
+                document.language = language::detect(&document::prose(&document.path, &document.content));
+            }
+            // Or links, if stored before links were resolved
+            if document.links.is_empty() {
+                document.links = document::extract_links(&document.path, &document.content);
+            }
+            // Or images, if stored before images were extracted
+            if document.images.is_empty() {
+                document.images = document::extract_images(&document.path, &document.content);
+            }
+            // Or definitions, if stored before they were extracted
+            if document.definitions.is_empty() {
+                document.definitions = document::extract_definitions(&document.content);
+            }
+            // Or word and token counts, if stored before they were computed
+            if document.token_count == 0 && !document.content.trim().is_empty() {
+                document.word_count = tokens::count_words(&document.content);
+                document.token_count = tokens::estimate_tokens(&document.content);
+            }
+            // Or title and heading keywords, if stored before they were consolidated with the others
+            let mut weighted_keywords = std::mem::take(&mut document.weighted_keywords);
+            if weighted_keywords.iter().all(|weighted| weighted.field == KeywordField::Body) {
+                // Indexes saved before keywords were weighted only have the flat keywords
+                let keywords = match weighted_keywords.is_empty() {
+                    true => std::mem::take(&mut document.keywords),
+                    false => weighting::body_keywords(&weighted_keywords),
+                };
+                let language = document.language.as_deref();
+                let text = document::keyword_text(&document.path, &document.content);
+                weighted_keywords = weighting::consolidate(
+                    weighting::weigh(&keywords, &self.keyword_occurrences(&text)),
+                    &self.extract_keywords(&document.title, language),
+                    &self.extract_heading_keywords(&document.path, &document.content, language),
+                );
+            }
+            // Keywords aren't saved, but derived from the weighted keywords
+            document.set_weighted_keywords(weighted_keywords);
+            // Unlike the fields migrated above, visibility is always computed again, as it
+            // depends on the internal path patterns of this run rather than on the saved index
+            document.visibility = self.visibility(&document.path, &document.content);
+            self.content_hashes.insert(document.content_hash.clone(), document.path.clone());
+            self.index_title(&document.title, &document.path);
+            for alias in &document.aliases {
+                self.aliases.insert(alias.clone(), document.path.clone());
+            }
+        }
+        self.documents = documents;
+    }
+    
+    /// Add a document path to the title index
+    fn index_title(&mut self, title: &str, path: &str) {
+        let paths = self.titles.entry(Self::normalize_title(title)).or_default();
+        paths.push(path.to_string());
+        paths.sort();
+    }
+    
+    /// Remove a document path from the title index
+    fn unindex_title(&mut self, title: &str, path: &str) {
+        let key = Self::normalize_title(title);
+        if let Some(paths) = self.titles.get_mut(&key) {
+            paths.retain(|indexed| indexed != path);
+            if paths.is_empty() {
+                self.titles.remove(&key);
+            }
+        }
+    }
+    
+    /// Normalize a title for lookups, ignoring case and whitespace differences
+    fn normalize_title(title: &str) -> String {
+        title.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+    }
+    
+    /// Hash the content of a document, ignoring line ending and trailing whitespace differences
+    fn hash_content(content: &str) -> String {
+        let mut hasher = Sha256::new();
+        for line in content.trim().lines() {
+            hasher.update(line.trim_end().as_bytes());
+            hasher.update(b"\n");
+        }
+        format!("{:x}", hasher.finalize())
+    }
+    
     /// Get all stored documents
     pub fn get_all_documents(&self) -> Vec<&StoredDocument> {
         self.documents.values().collect()
     }
     
+    /// Get the documents not stored again for more than `max_age`, oldest first
+    ///
+    /// Documents stored before fetch times were recorded are always stale.
+    pub fn get_stale_documents(&self, max_age: Duration) -> Vec<&StoredDocument> {
+        let threshold = Self::now().saturating_sub(max_age.as_secs());
+        let mut documents: Vec<_> = self
+            .documents
+            .values()
+            .filter(|document| document.fetched_at.is_none_or(|fetched_at| fetched_at < threshold))
+            .collect();
+        documents.sort_by(|a, b| a.fetched_at.cmp(&b.fetched_at).then_with(|| a.path.cmp(&b.path)));
+        documents
+    }
+    
+    /// Get the most frequent keywords of the stored documents, with the number of
+    /// documents containing each of them, most frequent first
+    ///
+    /// Frequencies are computed on each call from the keywords of the documents,
+    /// title and heading keywords included. Keywords as frequent as each other
+    /// are sorted alphabetically.
+    pub fn list_topics(&self, limit: usize) -> Vec<(String, usize)> {
+        self.list_topics_matching(limit, |_| true)
+    }
+    
+    /// Get the most frequent keywords of the stored documents matching a filter,
+    /// such as the public ones, like `list_topics`
+    pub fn list_topics_matching(&self, limit: usize, filter: impl Fn(&StoredDocument) -> bool) -> Vec<(String, usize)> {
+        let mut topics: Vec<(&str, usize)> = self.keyword_frequencies(filter).into_iter().collect();
+        topics.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        topics.into_iter().take(limit).map(|(keyword, count)| (keyword.to_string(), count)).collect()
+    }
+    
+    /// Suggest keywords of the stored documents completing a partial query
+    /// term, with the number of documents containing each of them
+    ///
+    /// Keywords starting with the term come first, then keywords containing it,
+    /// each ranked by document frequency, then alphabetically. The term is
+    /// normalized like keywords are, except for stemming, which would cut it.
+    pub fn suggest_keywords(&self, partial: &str, limit: usize) -> Vec<(String, usize)> {
+        self.suggest_keywords_matching(partial, limit, |_| true)
+    }
+    
+    /// Suggest keywords of the stored documents matching a filter, such as the
+    /// public ones, like `suggest_keywords`
+    pub fn suggest_keywords_matching(&self, partial: &str, limit: usize, filter: impl Fn(&StoredDocument) -> bool) -> Vec<(String, usize)> {
+        let partial = normalize::normalize_text(partial.trim());
+        let partial = if self.fold_diacritics { normalize::fold_diacritics(&partial) } else { partial };
+        if partial.is_empty() {
+            return Vec::new();
+        }
+        
+        let mut suggestions: Vec<(&str, usize)> = self
+            .keyword_frequencies(filter)
+            .into_iter()
+            .filter(|(keyword, _)| keyword.contains(partial.as_str()))
+            .collect();
+        suggestions.sort_by(|a, b| {
+            let prefix = |keyword: &str| !keyword.starts_with(partial.as_str());
+            prefix(a.0).cmp(&prefix(b.0)).then_with(|| b.1.cmp(&a.1)).then_with(|| a.0.cmp(b.0))
+        });
+        suggestions.into_iter().take(limit).map(|(keyword, count)| (keyword.to_string(), count)).collect()
+    }
+    
+    /// Count the documents matching a filter containing each keyword, title and heading keywords included
+    fn keyword_frequencies(&self, filter: impl Fn(&StoredDocument) -> bool) -> HashMap<&str, usize> {
+        let mut frequencies: HashMap<&str, usize> = HashMap::new();
+        for document in self.documents.values().filter(|document| filter(document)) {
+            let keywords: HashSet<&str> = document
+                .keywords
+                .iter()
+                .chain(document.weighted_keywords.iter().map(|weighted| &weighted.keyword))
+                .map(String::as_str)
+                .collect();
+            for keyword in keywords {
+                *frequencies.entry(keyword).or_default() += 1;
+            }
+        }
+        frequencies
+    }
+    
+    /// Build a table of contents of the stored documents, grouped by directory,
+    /// with the outline of each document down to `max_heading_level`
+    ///
+    /// Directories and documents are sorted by name, and headings are nested
+    /// under the closest preceding heading of a lower level.
+    pub fn table_of_contents(&self, max_heading_level: usize) -> TocDirectory {
+        self.table_of_contents_matching(max_heading_level, |_| true)
+    }
+    
+    /// Build a table of contents of the stored documents matching a filter, such
+    /// as the public ones, like `table_of_contents`
+    pub fn table_of_contents_matching(&self, max_heading_level: usize, filter: impl Fn(&StoredDocument) -> bool) -> TocDirectory {
+        let scanner = DocumentScanner::new();
+        let mut root = TocDirectory::default();
+        for document in self.documents.values().filter(|document| filter(document)) {
+            let outline = scanner.extract_outline(&document.path, &document.content);
+            root.insert(TocDocument {
+                path: document.path.clone(),
+                title: document.title.clone(),
+                headings: toc::nest_headings(outline, max_heading_level),
+            });
+        }
+        root.sort();
+        root
+    }
+    
+    /// Current time as a Unix timestamp
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or_default()
+    }
+    
+    /// Serialize all stored documents, sorted by path, in the given format
+    pub fn export_documents(&self, format: ExportFormat) -> Result<String> {
+        self.export_documents_matching(format, |_| true)
+    }
+    
+    /// Serialize the stored documents matching a filter, such as the public
+    /// ones, sorted by path, in the given format
+    pub fn export_documents_matching(&self, format: ExportFormat, filter: impl Fn(&StoredDocument) -> bool) -> Result<String> {
+        let mut documents = self.get_all_documents();
+        documents.retain(|document| filter(document));
+        documents.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(export::export(&documents, format)?)
+    }
+    
     /// Get a specific document by path
+    ///
+    /// Alias paths resolve to the canonical document with the same content.
     pub fn get_document(&self, path: &str) -> Option<&StoredDocument> {
-        self.documents.get(path)
+        self.documents
+            .get(path)
+            .or_else(|| self.aliases.get(path).and_then(|canonical| self.documents.get(canonical)))
+    }
+    
+    /// Get the documents with the given title, ignoring case, sorted by path
+    pub fn get_documents_by_title(&self, title: &str) -> Vec<&StoredDocument> {
+        self.titles
+            .get(&Self::normalize_title(title))
+            .map(|paths| paths.iter().filter_map(|path| self.documents.get(path)).collect())
+            .unwrap_or_default()
+    }
+    
+    /// Look up the definitions of a term across documents, ignoring case and
+    /// whitespace differences, sorted by document path
+    pub fn lookup_term(&self, term: &str) -> Vec<(&StoredDocument, &Definition)> {
+        let term = Self::normalize_title(term);
+        let mut definitions: Vec<_> = self
+            .documents
+            .values()
+            .flat_map(|document| document.definitions.iter().map(move |definition| (document, definition)))
+            .filter(|(_, definition)| Self::normalize_title(&definition.term) == term)
+            .collect();
+        definitions.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+        definitions
+    }
+    
+    /// Get the title of a document to display, prefixed with its directory when
+    /// other documents have the same title, e.g. `api / Overview`
+    ///
+    /// Titles are compared like `get_documents_by_title` does. Documents at the
+    /// root of the repository, and unique titles, keep their title.
+    pub fn qualified_title<'a>(&self, document: &'a StoredDocument) -> Cow<'a, str> {
+        let shared = self.titles.get(&Self::normalize_title(&document.title)).is_some_and(|paths| paths.len() > 1);
+        let directory = Path::new(&document.path).parent().and_then(|parent| parent.to_str()).unwrap_or_default();
+        if !shared || directory.is_empty() {
+            return Cow::Borrowed(&document.title);
+        }
+        Cow::Owned(format!("{} / {}", directory.split('/').collect::<Vec<_>>().join(" / "), document.title))
     }
     
     /// Find documents relevant to a query
+    ///
+    /// Keywords matching the title or headings of a document count more than
+    /// keywords only found in its body, according to the scoring weights.
+    ///
+    /// The query may use operators: documents must contain every `"quoted phrase"`
+    /// and `+term`, and must not contain any `-term`. Other words are only scored.
     pub fn find_relevant_documents(&self, query: &str) -> Vec<&StoredDocument> {
-        let query_keywords = self.extract_keywords(query);
+        self.score_relevant_documents(query)
+            .into_iter()
+            .map(|relevant| relevant.document)
+            .collect()
+    }
+    
+    /// Find documents relevant to a query, like `find_relevant_documents`, along
+    /// with their score and the query keywords they contain
+    ///
+    /// Scores are normalized by the best possible score for the query, so a
+    /// document containing every query keyword in its title scores 1.
+    pub fn score_relevant_documents(&self, query: &str) -> Vec<RelevantDocument<'_>> {
+        self.score_relevant_documents_with(query, MatchOptions::default())
+    }
+    
+    /// Find and score documents relevant to a query, like `score_relevant_documents`,
+    /// matching terms as given by the options
+    ///
+    /// With case sensitive or whole word matching, query terms are looked up in
+    /// the original title, headings and content of documents instead of their
+    /// keywords, so short terms such as acronyms are kept.
+    pub fn score_relevant_documents_with(&self, query: &str, options: MatchOptions) -> Vec<RelevantDocument<'_>> {
+        self.score_relevant_documents_in(query, options, "")
+    }
+    
+    /// Find and score documents relevant to a query, like `score_relevant_documents_with`,
+    /// among the documents whose path starts with the given prefix
+    ///
+    /// An empty prefix searches every document.
+    pub fn score_relevant_documents_in(&self, query: &str, options: MatchOptions, path_prefix: &str) -> Vec<RelevantDocument<'_>> {
+        let path_prefix = path_prefix.trim_start_matches('/');
+        let query = query::parse_with_case(query, options.case_sensitive);
+        let query_terms = self.query_terms(&query, options);
+        let query_synonyms = self.query_synonyms(&query_terms, options);
+        let field_terms = self.field_terms(&query, options);
+        let max_score = self.max_weight(query_terms.len() + field_terms.len());
+        let now = Self::now();
         
-        // Score documents based on keyword matches
-        let mut scored_documents: Vec<(&StoredDocument, usize)> = self
+        // Score documents based on weighted keyword matches
+        let mut scored_documents: Vec<RelevantDocument> = self
             .documents
             .values()
-            .map(|doc| {
-                let score = query_keywords
-                    .iter()
-                    .filter(|kw| doc.keywords.contains(kw))
-                    .count();
-                (doc, score)
+            .filter(|doc| doc.path.starts_with(path_prefix))
+            .filter(|doc| self.satisfies_operators(doc, &query, options))
+            .filter_map(|doc| {
+                let terms = self.score_terms(doc, &query_terms, &query_synonyms, &field_terms, options);
+                let score: f64 = terms.iter().map(|term| term.weight).sum();
+                let relevance = score / max_score;
+                let boost = self.recency.map_or(1.0, |recency| recency.factor(doc.last_modified, now));
+                (score > 0.0).then(|| RelevantDocument {
+                    document: doc,
+                    score: relevance * boost,
+                    relevance,
+                    matched_terms: terms.into_iter().filter_map(|term| term.matched).collect(),
+                })
             })
-            .filter(|(_, score)| *score > 0)
             .collect();
         
         // Sort by score (descending)
-        scored_documents.sort_by(|(_, score1), (_, score2)| score2.cmp(score1));
+        scored_documents.sort_by(|a, b| b.score.total_cmp(&a.score));
+        
+        scored_documents
+    }
+    
+    /// Explain the score of a document for a query, term by term, with the same
+    /// scoring as `score_relevant_documents_with`
+    ///
+    /// Returns `None` if no document is stored under the given path or alias.
+    pub fn explain_relevance(&self, query: &str, path: &str, options: MatchOptions) -> Option<RelevanceExplanation> {
+        let document = self.get_document(path)?;
+        let query = query::parse_with_case(query, options.case_sensitive);
+        let query_terms = self.query_terms(&query, options);
+        let field_terms = self.field_terms(&query, options);
+        
+        let query_synonyms = self.query_synonyms(&query_terms, options);
+        let terms = self.score_terms(document, &query_terms, &query_synonyms, &field_terms, options);
+        let satisfies_operators = self.satisfies_operators(document, &query, options);
+        let total_weight: f64 = terms.iter().map(|term| term.weight).sum();
+        let max_weight = self.max_weight(query_terms.len() + field_terms.len());
+        let relevance = if satisfies_operators && total_weight > 0.0 { total_weight / max_weight } else { 0.0 };
+        let recency_factor = self.recency.map_or(1.0, |recency| recency.factor(document.last_modified, Self::now()));
+        
+        Some(RelevanceExplanation {
+            path: document.path.clone(),
+            terms,
+            satisfies_operators,
+            total_weight,
+            max_weight,
+            relevance,
+            recency_factor,
+            score: relevance * recency_factor,
+        })
+    }
+    
+    /// Weight of a document with every one of the given number of terms in its title
+    fn max_weight(&self, term_count: usize) -> f64 {
+        let max_weight = self.weights.title.max(self.weights.heading).max(self.weights.body);
+        max_weight * term_count as f64
+    }
+    
+    /// Check whether a document has the required phrases and terms of a query, and none of its excluded terms
+    fn satisfies_operators(&self, document: &StoredDocument, query: &query::ParsedQuery, options: MatchOptions) -> bool {
+        !query.has_constraints()
+            || query.matches(&query::normalize_with_case(
+                &format!("{}\n{}", document.title, document.content),
+                options.case_sensitive,
+            ))
+    }
+    
+    /// Score each term of a query in a document, by the most significant place it's found in
+    ///
+    /// Query terms not found fall back to their best synonym among
+    /// `query_synonyms`, those of each term, exact matching aside. Terms
+    /// restricted to a field only count when found there.
+    fn score_terms(
+        &self,
+        document: &StoredDocument,
+        query_terms: &[String],
+        query_synonyms: &[Vec<String>],
+        field_terms: &[(Field, String)],
+        options: MatchOptions,
+    ) -> Vec<TermScore> {
+        let text = options.is_exact().then(|| self.match_text(document, options.case_sensitive));
+        let mut terms = Vec::with_capacity(query_terms.len() + field_terms.len());
+        for (keyword, synonyms) in query_terms.iter().zip(query_synonyms) {
+            let (location, weight) = match &text {
+                Some(text) => {
+                    let location = Self::term_location(text, keyword, options.whole_word);
+                    (location, location.map_or(0.0, |location| self.location_weight(location)))
+                }
+                None => {
+                    let location = Self::keyword_location(document, keyword);
+                    (location, location.map_or(0.0, |location| self.keyword_match_weight(document, keyword, location)))
+                }
+            };
+            let mut score = TermScore {
+                term: keyword.clone(),
+                field: None,
+                matched: location.map(|_| keyword.clone()),
+                synonym: false,
+                location,
+                weight,
+            };
+            
+            if location.is_none() && text.is_none() {
+                let best_synonym = synonyms
+                    .iter()
+                    .filter_map(|synonym| {
+                        let location = Self::keyword_location(document, synonym)?;
+                        Some((location, self.keyword_match_weight(document, synonym, location), synonym))
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((location, weight, synonym)) = best_synonym {
+                    score.matched = Some(synonym.clone());
+                    score.synonym = true;
+                    score.location = Some(location);
+                    score.weight = weight * SYNONYM_WEIGHT;
+                }
+            }
+            terms.push(score);
+        }
+        
+        for (field, term) in field_terms {
+            let location = self.field_location(document, *field, term, options);
+            terms.push(TermScore {
+                term: term.clone(),
+                field: Some(field.name()),
+                matched: location.map(|_| term.clone()),
+                synonym: false,
+                location,
+                weight: location.map_or(0.0, |location| self.location_weight(location)),
+            });
+        }
+        terms
+    }
+    
+    /// Weight of a keyword found in a document: that of the place it was found
+    /// in, scaled by the weight of the keyword in the document for the body
+    fn keyword_match_weight(&self, document: &StoredDocument, keyword: &str, location: MatchLocation) -> f64 {
+        match location {
+            MatchLocation::Body => {
+                self.weights.body * (MIN_BODY_SHARE + (1.0 - MIN_BODY_SHARE) * document.keyword_weight(keyword))
+            }
+            location => self.location_weight(location),
+        }
+    }
+    
+    /// Weight of a match by the place it was found in
+    fn location_weight(&self, location: MatchLocation) -> f64 {
+        match location {
+            MatchLocation::Title => self.weights.title,
+            MatchLocation::Heading | MatchLocation::Summary => self.weights.heading,
+            MatchLocation::Body => self.weights.body,
+        }
+    }
+    
+    /// Normalized score of a document only containing one keyword of a query, in its body
+    ///
+    /// Returns `None` for queries with a single keyword, where such a match isn't weak.
+    pub fn weak_match_score(&self, query: &str) -> Option<f64> {
+        self.weak_match_score_with(query, MatchOptions::default())
+    }
+    
+    /// Normalized score of a weak match, like `weak_match_score`, for a query
+    /// matched as given by the options
+    pub fn weak_match_score_with(&self, query: &str, options: MatchOptions) -> Option<f64> {
+        let query = query::parse_with_case(query, options.case_sensitive);
+        let term_count = self.query_terms(&query, options).len() + self.field_terms(&query, options).len();
+        if term_count < 2 {
+            return None;
+        }
         
-        // Return the documents
-        scored_documents.into_iter().map(|(doc, _)| doc).collect()
+        let max_weight = self.weights.title.max(self.weights.heading).max(self.weights.body);
+        Some(self.weights.body / (max_weight * term_count as f64))
     }
     
-    /// Extract keywords from text
-    fn extract_keywords(&self, text: &str) -> Vec<String> {
-        let text = text.to_lowercase();
+    /// The terms of a query documents are scored on
+    ///
+    /// These are the query keywords, or with exact matching the query terms as
+    /// written, phrases whole, without stopwords.
+    fn query_terms(&self, query: &query::ParsedQuery, options: MatchOptions) -> Vec<String> {
+        if !options.is_exact() {
+            return self.extract_keywords(&query.positive_terms(), None);
+        }
+        
+        query
+            .positive_term_list()
+            .into_iter()
+            .filter(|term| !self.stopwords.contains(None, &term.to_lowercase()))
+            .collect()
+    }
+    
+    /// The terms of a query restricted to a field, split into keywords like
+    /// `query_terms` unless matching exactly
+    fn field_terms(&self, query: &query::ParsedQuery, options: MatchOptions) -> Vec<(Field, String)> {
+        let mut terms = Vec::new();
+        for (field, term) in &query.fielded {
+            let field_terms = if options.is_exact() {
+                let term = term.trim_matches(|c: char| !c.is_alphanumeric());
+                if term.is_empty() || self.stopwords.contains(None, &term.to_lowercase()) {
+                    continue;
+                }
+                vec![term.to_string()]
+            } else {
+                self.extract_keywords(term, None)
+            };
+            terms.extend(field_terms.into_iter().map(|term| (*field, term)));
+        }
+        terms
+    }
+    
+    /// Where a term restricted to a field of a document is found, none if the field doesn't contain it
+    fn field_location(&self, document: &StoredDocument, field: Field, term: &str, options: MatchOptions) -> Option<MatchLocation> {
+        let summary = document.summary.as_deref().unwrap_or_default();
+        let found = if options.is_exact() {
+            let text = match field {
+                Field::Title => &document.title,
+                Field::Summary => summary,
+                Field::Content => &document.content,
+            };
+            query::contains_term(&query::normalize_with_case(text, options.case_sensitive), term, options.whole_word)
+        } else {
+            let contains = |keywords: &[String]| keywords.iter().any(|keyword| keyword == term);
+            match field {
+                Field::Title => document.keyword_field(term) == Some(KeywordField::Title),
+                Field::Summary => contains(&self.extract_keywords(summary, document.language.as_deref())),
+                Field::Content => contains(&document.keywords),
+            }
+        };
         
-        // Split by non-alphanumeric characters
-        let words: Vec<&str> = text
-            .split(|c: char| !c.is_alphanumeric())
-            .filter(|s| !s.is_empty())
+        found.then_some(match field {
+            Field::Title => MatchLocation::Title,
+            Field::Summary => MatchLocation::Summary,
+            Field::Content => MatchLocation::Body,
+        })
+    }
+    
+    /// Normalized title, headings and content of a document, to match terms exactly
+    fn match_text(&self, document: &StoredDocument, case_sensitive: bool) -> [String; 3] {
+        let headings: Vec<String> = DocumentScanner::new()
+            .extract_outline(&document.path, &document.content)
+            .into_iter()
+            .map(|heading| heading.text)
             .collect();
+        [&document.title, &headings.join("\n"), &document.content]
+            .map(|text| query::normalize_with_case(text, case_sensitive))
+    }
+    
+    /// The most significant place a term appears in among the title, headings and content of a document
+    fn term_location([title, headings, content]: &[String; 3], term: &str, whole_word: bool) -> Option<MatchLocation> {
+        if query::contains_term(title, term, whole_word) {
+            Some(MatchLocation::Title)
+        } else if query::contains_term(headings, term, whole_word) {
+            Some(MatchLocation::Heading)
+        } else if query::contains_term(content, term, whole_word) {
+            Some(MatchLocation::Body)
+        } else {
+            None
+        }
+    }
+    
+    /// Synonyms of each query keyword, expanded once per query rather than per
+    /// document, none when matching exactly
+    fn query_synonyms(&self, query_terms: &[String], options: MatchOptions) -> Vec<Vec<String>> {
+        query_terms
+            .iter()
+            .map(|keyword| if options.is_exact() { Vec::new() } else { self.expand_keyword(keyword) })
+            .collect()
+    }
+    
+    /// Synonyms of a query keyword, normalized like keywords, none if queries aren't expanded
+    fn expand_keyword(&self, keyword: &str) -> Vec<String> {
+        let Some(synonyms) = &self.synonyms else {
+            return Vec::new();
+        };
         
-        // Filter out common words and short words
-        let stopwords = [
-            "the", "a", "an", "and", "or", "but", "if", "then", "else", "when",
-            "at", "from", "by", "for", "with", "about", "against", "between",
-            "into", "through", "during", "before", "after", "above", "below",
-            "to", "of", "in", "on", "is", "are", "was", "were", "be", "been",
-            "being", "have", "has", "had", "do", "does", "did", "will", "would",
-            "shall", "should", "can", "could", "may", "might", "must", "this",
-            "that", "these", "those", "i", "you", "he", "she", "it", "we", "they",
-        ];
+        let mut expanded: Vec<String> = Vec::new();
+        for group in synonyms.groups() {
+            let words: Vec<String> = group
+                .iter()
+                .map(|word| self.normalize_keyword(&normalize::normalize_text(word)))
+                .collect();
+            if !words.iter().any(|word| word == keyword) {
+                continue;
+            }
+            for word in words {
+                if word != keyword && !expanded.contains(&word) {
+                    expanded.push(word);
+                }
+            }
+        }
+        expanded
+    }
+    
+    /// The most significant place a keyword appears in among the keywords of a document
+    fn keyword_location(document: &StoredDocument, keyword: &str) -> Option<MatchLocation> {
+        document.keyword_field(keyword).map(|field| match field {
+            KeywordField::Title => MatchLocation::Title,
+            KeywordField::Heading => MatchLocation::Heading,
+            KeywordField::Body => MatchLocation::Body,
+        })
+    }
+    
+    /// Find the documents most similar to the document at the given path
+    ///
+    /// Similarity is the weighted share of keywords two documents have in common,
+    /// each keyword weighing as it does when searching, so title and heading terms
+    /// count more than body ones and long documents don't rank higher just by having
+    /// more keywords. Documents as similar are ordered by path. Returns `None` if no
+    /// document is stored under the given path.
+    pub fn related_documents(&self, path: &str, limit: usize) -> Option<Vec<(&StoredDocument, f64)>> {
+        let document = self.get_document(path)?;
+        let weights = self.keyword_weights(document);
+        let total: f64 = weights.values().sum();
+        
+        let mut related: Vec<(&StoredDocument, f64)> = self
+            .documents
+            .values()
+            .filter(|other| other.path != document.path)
+            .filter_map(|other| {
+                let other_weights = self.keyword_weights(other);
+                let shared: f64 = weights
+                    .iter()
+                    .filter_map(|(keyword, weight)| other_weights.get(keyword).map(|other| weight.min(*other)))
+                    .sum();
+                if shared == 0.0 {
+                    return None;
+                }
+                let union = total + other_weights.values().sum::<f64>() - shared;
+                Some((other, shared / union))
+            })
+            .collect();
+        
+        // Sort by similarity (descending), then by path
+        related.sort_by(|(a, x), (b, y)| y.total_cmp(x).then_with(|| a.path.cmp(&b.path)));
+        related.truncate(limit);
+        
+        Some(related)
+    }
+    
+    /// Weight of each keyword of a document, as found in it when searching
+    fn keyword_weights<'a>(&self, document: &'a StoredDocument) -> HashMap<&'a str, f64> {
+        document
+            .weighted_keywords
+            .iter()
+            .filter_map(|weighted| {
+                let location = Self::keyword_location(document, &weighted.keyword)?;
+                Some((weighted.keyword.as_str(), self.keyword_match_weight(document, &weighted.keyword, location)))
+            })
+            .collect()
+    }
+    
+    /// Find the passages of a single document matching a query
+    ///
+    /// Returns `None` if no document is stored under the given path.
+    pub fn search_in_document(&self, path: &str, query: &str, context_lines: usize) -> Option<Vec<DocumentMatch>> {
+        let document = self.get_document(path)?;
+
+        // Match on query keywords, falling back to the raw query for short terms
+        let mut terms = self.extract_keywords(query, document.language.as_deref());
+        if terms.is_empty() && !query.trim().is_empty() {
+            terms.push(query.trim().to_lowercase());
+        }
+
+        Some(search::find_line_matches(&document.content, &terms, context_lines))
+    }
+    
+    /// Summarize a document with its most salient sentences, in document order
+    ///
+    /// Sentences are taken from the prose of the document, headings and code
+    /// left out, and ranked by how frequent their keywords are in the whole
+    /// document. Returns `None` if no document is stored under the given path.
+    pub fn summarize_document(&self, path: &str, sentence_count: usize) -> Option<Vec<String>> {
+        let document = self.get_document(path)?;
+        let language = document.language.as_deref();
         
+        // Headings and list fragments don't end like sentences, so they are left out
+        let prose = document::prose(&document.path, &document.content);
+        let sentences: Vec<String> = prose
+            .lines()
+            .flat_map(document::split_sentences)
+            .filter(|sentence| sentence.trim_end_matches(['"', '\'', ')', ']', '”', '’']).ends_with(['.', '!', '?']))
+            .map(|sentence| sentence.split_whitespace().collect::<Vec<_>>().join(" "))
+            .collect();
+        
+        let keywords: Vec<Vec<String>> = sentences
+            .iter()
+            .map(|sentence| self.extract_keywords(sentence, language))
+            .collect();
+        
+        Some(
+            summarize::rank_sentences(&keywords, sentence_count)
+                .into_iter()
+                .map(|index| sentences[index].clone())
+                .collect(),
+        )
+    }
+    
+    /// Extract the passage of a document most relevant to a query
+    ///
+    /// Matched query terms are wrapped in `**` when `mark_terms` is set.
+    pub fn extract_snippet(&self, document: &StoredDocument, query: &str, mark_terms: bool) -> Option<String> {
+        // Excluded terms can't be in the document, and operators aren't part of the terms
+        let terms = self.extract_keywords(&query::parse(query).positive_terms(), document.language.as_deref());
+        search::extract_snippet(&document.content, &terms, SNIPPET_LENGTH, mark_terms)
+    }
+    
+    /// Find the byte ranges of the query terms within a snippet of a document,
+    /// for clients highlighting them themselves
+    ///
+    /// Ranges are sorted, don't overlap and fall on UTF-8 character boundaries.
+    pub fn highlight_terms(&self, document: &StoredDocument, snippet: &str, query: &str) -> Vec<(usize, usize)> {
+        let terms = self.extract_keywords(&query::parse(query).positive_terms(), document.language.as_deref());
+        search::find_terms(snippet, &terms)
+    }
+    
+    /// Extract keywords from the section headings of a document
+    fn extract_heading_keywords(&self, path: &str, content: &str, language: Option<&str>) -> Vec<String> {
+        let headings: Vec<String> = DocumentScanner::new()
+            .extract_outline(path, content)
+            .into_iter()
+            .map(|heading| heading.text)
+            .collect();
+        self.extract_keywords(&headings.join("\n"), language)
+    }
+    
+    /// Extract the keywords of the content of a document, keeping only the most
+    /// frequent ones if their number is capped
+    ///
+    /// Keywords as frequent as each other are kept in order of first appearance.
+    fn extract_content_keywords(&self, content: &str, language: Option<&str>) -> Vec<String> {
+        let keywords = self.extract_keywords(content, language);
+        let Some(max_keywords) = self.max_keywords.filter(|max| keywords.len() > *max) else {
+            return keywords;
+        };
+        
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in self.keyword_occurrences(content) {
+            *counts.entry(word).or_default() += 1;
+        }
+        
+        // The sort is stable, so ties keep their order of first appearance
+        let mut keywords = keywords;
+        keywords.sort_by_key(|keyword| std::cmp::Reverse(counts.get(keyword).copied().unwrap_or_default()));
+        keywords.truncate(max_keywords);
+        keywords
+    }
+    
+    /// The words of content long enough to be keywords, in order and in their
+    /// keyword form, stopwords included
+    fn keyword_occurrences(&self, content: &str) -> Vec<String> {
+        let content = normalize::normalize_text(content);
+        self.tokenizer
+            .tokenize(&content)
+            .into_iter()
+            .filter(|word| word.len() > 2)
+            .map(|word| self.normalize_keyword(word))
+            .collect()
+    }
+    
+    /// Add the keywords of the alt text of images to the keywords of a document
+    fn add_image_keywords(&self, keywords: &mut Vec<String>, images: &[ImageRef], language: Option<&str>) {
+        let alt_text: Vec<&str> = images.iter().map(|image| image.alt.as_str()).collect();
+        for keyword in self.extract_keywords(&alt_text.join("\n"), language) {
+            if !keywords.contains(&keyword) {
+                keywords.push(keyword);
+            }
+        }
+    }
+    
+    /// Extract keywords from text, without the stopwords of its language
+    ///
+    /// Text of unknown language, such as queries, uses the default stopwords.
+    fn extract_keywords(&self, text: &str, language: Option<&str>) -> Vec<String> {
+        let text = normalize::normalize_text(text);
+        
+        // Split into words with the configured tokenizer
+        let words = self.tokenizer.tokenize(&text);
+        
+        // Filter out common words and short words
         let keywords: Vec<String> = words
             .into_iter()
-            .filter(|word| word.len() > 2 && !stopwords.contains(word))
-            .map(|s| s.to_string())
+            .filter(|word| word.len() > 2 && !self.stopwords.contains(language, word))
+            .map(|s| self.normalize_keyword(s))
             .collect();
         
         // Deduplicate
@@ -163,20 +1742,86 @@ impl DocumentStorage {
         unique_keywords
     }
     
+    /// Normalize a lowercase word to the form keywords are stored in
+    fn normalize_keyword(&self, word: &str) -> String {
+        let word = if self.fold_diacritics { normalize::fold_diacritics(word) } else { word.to_string() };
+        if self.stemming { stem::stem(&word) } else { word }
+    }
+    
+    /// Save documents to disk, or mark them as changed if a batch is open
+    fn persist(&mut self) -> Result<()> {
+        if self.in_batch {
+            self.dirty = true;
+            return Ok(());
+        }
+        
+        self.save_documents()
+    }
+    
     /// Save documents to disk
-    fn save_documents(&self) -> Result<()> {
-        let index_path = self.storage_path.join("documents.json");
-        let file = File::create(index_path)?;
-        let writer = BufWriter::new(file);
+    ///
+    /// The index is written to a temporary file first and then renamed over the
+    /// previous one, so an interrupted save never leaves a truncated index.
+    /// Storages kept in memory only are never saved.
+    fn save_documents(&mut self) -> Result<()> {
+        let Some(storage_path) = &self.storage_path else {
+            self.dirty = false;
+            return Ok(());
+        };
         
-        serde_json::to_writer(writer, &self.documents)?;
+        let (index_file, stale_file) = if self.compress {
+            (COMPRESSED_INDEX_FILE, INDEX_FILE)
+        } else {
+            (INDEX_FILE, COMPRESSED_INDEX_FILE)
+        };
+        
+        let temp_path = storage_path.join(format!("{}.tmp", index_file));
+        if let Err(e) = self.write_index(&temp_path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+        fs::rename(&temp_path, storage_path.join(index_file))?;
+        self.dirty = false;
+        self.saves += 1;
+        
+        // Remove the index in the other format, so it isn't loaded instead of this one
+        let stale_path = storage_path.join(stale_file);
+        if stale_path.exists() {
+            fs::remove_file(stale_path)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Write the index to a file, flushed to disk before returning
+    fn write_index(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+        
+        if self.compress {
+            let mut encoder = GzEncoder::new(&mut writer, Compression::default());
+            serde_json::to_writer(&mut encoder, &self.documents)?;
+            encoder.finish()?;
+        } else {
+            serde_json::to_writer(&mut writer, &self.documents)?;
+        }
+        
+        let file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
         
         Ok(())
     }
     
-    /// Load documents from disk
+    /// Load documents from disk, from whichever of the compressed or uncompressed index exists
     fn load_documents(storage_path: &Path) -> Result<HashMap<String, StoredDocument>> {
-        let index_path = storage_path.join("documents.json");
+        let index_path = storage_path.join(INDEX_FILE);
+        let compressed_index_path = storage_path.join(COMPRESSED_INDEX_FILE);
+        
+        if compressed_index_path.exists() {
+            let file = File::open(compressed_index_path)?;
+            let reader = BufReader::new(GzDecoder::new(BufReader::new(file)));
+            return Ok(serde_json::from_reader(reader)?);
+        }
         
         if !index_path.exists() {
             return Ok(HashMap::new());
@@ -0,0 +1,15 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalize text before it's split into keywords: to its NFKC form, so
+/// composed and decomposed characters (`é` and `e` followed by a combining
+/// accent) and compatibility forms (`ﬁ` and `fi`) compare equal, and lowercase
+pub fn normalize_text(text: &str) -> String {
+    text.nfkc().collect::<String>().to_lowercase()
+}
+
+/// Remove the diacritics of a word, so accented and unaccented forms (`résumé`
+/// and `resume`) compare equal
+pub fn fold_diacritics(word: &str) -> String {
+    word.nfd().filter(|c| !is_combining_mark(*c)).nfc().collect()
+}
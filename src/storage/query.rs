@@ -0,0 +1,216 @@
+use std::iter::Peekable;
+use std::str::Chars;
+use unicode_normalization::UnicodeNormalization;
+
+/// How query terms are matched against documents
+///
+/// By default, terms are matched on the lowercase keywords extracted from
+/// documents. Either option matches them against the original text instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchOptions {
+    /// Only match terms with the same case, e.g. `IO` but not `io`
+    pub case_sensitive: bool,
+    /// Only match terms as whole words, e.g. `IO` but not `IOError`
+    pub whole_word: bool,
+}
+
+impl MatchOptions {
+    /// Check whether terms are matched against the original text rather than keywords
+    pub fn is_exact(&self) -> bool {
+        self.case_sensitive || self.whole_word
+    }
+}
+
+/// A field of documents query terms can be restricted to, with a `field:` prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Summary,
+    Content,
+}
+
+impl Field {
+    /// The field of a prefix name, ignoring case
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "title" => Some(Field::Title),
+            "summary" => Some(Field::Summary),
+            "content" => Some(Field::Content),
+            _ => None,
+        }
+    }
+
+    /// The prefix name of the field
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Summary => "summary",
+            Field::Content => "content",
+        }
+    }
+}
+
+/// A search query parsed into its operators
+///
+/// Plain queries only have optional terms, which are scored as a bag of words.
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedQuery {
+    /// Terms that make a document more relevant
+    pub optional: Vec<String>,
+    /// Terms (`+term`) every matching document must contain
+    pub required: Vec<String>,
+    /// Terms (`-term`) no matching document may contain
+    pub excluded: Vec<String>,
+    /// Phrases (`"some phrase"`) every matching document must contain word for word
+    pub phrases: Vec<String>,
+    /// Phrases (`-"some phrase"`) no matching document may contain
+    pub excluded_phrases: Vec<String>,
+    /// Terms and phrases (`title:term`, `summary:"some phrase"`) that make a
+    /// document more relevant only when found in the given field
+    pub fielded: Vec<(Field, String)>,
+}
+
+impl ParsedQuery {
+    /// Check whether the query constrains which documents match, beyond scoring
+    pub fn has_constraints(&self) -> bool {
+        !self.required.is_empty()
+            || !self.excluded.is_empty()
+            || !self.phrases.is_empty()
+            || !self.excluded_phrases.is_empty()
+    }
+
+    /// The terms that make a document relevant, excluded terms left out
+    pub fn positive_terms(&self) -> String {
+        self.optional
+            .iter()
+            .chain(&self.required)
+            .chain(&self.phrases)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The terms that make a document relevant, each phrase as a single term,
+    /// without the punctuation around them
+    pub fn positive_term_list(&self) -> Vec<String> {
+        let mut terms: Vec<String> = Vec::new();
+        for term in self.optional.iter().chain(&self.required).chain(&self.phrases) {
+            let term = term.trim_matches(|c: char| !c.is_alphanumeric());
+            if !term.is_empty() && !terms.iter().any(|t| t == term) {
+                terms.push(term.to_string());
+            }
+        }
+        terms
+    }
+
+    /// Check whether a text, normalized with `normalize_with_case`, satisfies the constraints of the query
+    pub fn matches(&self, text: &str) -> bool {
+        self.required.iter().all(|term| contains_phrase(text, term))
+            && self.phrases.iter().all(|phrase| contains_phrase(text, phrase))
+            && !self.excluded.iter().any(|term| contains_phrase(text, term))
+            && !self.excluded_phrases.iter().any(|phrase| contains_phrase(text, phrase))
+    }
+}
+
+/// Parse a query into quoted phrases, `+required` and `-excluded` terms, terms
+/// restricted to a field with a `title:`, `summary:` or `content:` prefix, and
+/// optional terms
+///
+/// An unterminated quote extends the phrase to the end of the query. Field
+/// prefixes don't combine with the `+` and `-` operators, and an operator
+/// followed by another `-` is part of a plain term, like a `--flag`.
+pub fn parse(query: &str) -> ParsedQuery {
+    parse_with_case(query, false)
+}
+
+/// Parse a query like `parse`, keeping the case of its terms if `case_sensitive`
+pub fn parse_with_case(query: &str, case_sensitive: bool) -> ParsedQuery {
+    let mut parsed = ParsedQuery::default();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let operator = match c {
+            '+' | '-' if chars.clone().nth(1) != Some('-') => chars.next(),
+            _ => None,
+        };
+        let field = if operator.is_none() { take_field(&mut chars) } else { None };
+
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            let phrase = normalize_with_case(&chars.by_ref().take_while(|c| *c != '"').collect::<String>(), case_sensitive);
+            if phrase.is_empty() {
+                continue;
+            }
+            if let Some(field) = field {
+                parsed.fielded.push((field, phrase));
+                continue;
+            }
+            match operator {
+                Some('-') => parsed.excluded_phrases.push(phrase),
+                _ => parsed.phrases.push(phrase),
+            }
+        } else {
+            let term = normalize_with_case(&chars.by_ref().take_while(|c| !c.is_whitespace()).collect::<String>(), case_sensitive);
+            // A lone operator or field prefix is not a term
+            if term.is_empty() {
+                continue;
+            }
+            if let Some(field) = field {
+                parsed.fielded.push((field, term));
+                continue;
+            }
+            match operator {
+                Some('+') => parsed.required.push(term),
+                Some('-') => parsed.excluded.push(term),
+                _ => parsed.optional.push(term),
+            }
+        }
+    }
+
+    parsed
+}
+
+/// Consume a `field:` prefix at the start of a term, if it names a field
+fn take_field(chars: &mut Peekable<Chars>) -> Option<Field> {
+    let name: String = chars.clone().take_while(|c| c.is_alphabetic()).collect();
+    let field = Field::from_name(&name)?;
+
+    let length = name.chars().count();
+    if chars.clone().nth(length) != Some(':') {
+        return None;
+    }
+    chars.nth(length);
+    Some(field)
+}
+
+/// Normalize text for phrase matching: in NFKC form, with whitespace runs
+/// collapsed to single spaces, and lowercase unless `case_sensitive`
+pub fn normalize_with_case(text: &str, case_sensitive: bool) -> String {
+    let text = text.nfkc().collect::<String>();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if case_sensitive { text } else { text.to_lowercase() }
+}
+
+/// Check whether a normalized text contains a normalized term, as whole words
+/// only if `whole_word`
+pub fn contains_term(text: &str, term: &str, whole_word: bool) -> bool {
+    if whole_word {
+        contains_phrase(text, term)
+    } else {
+        text.contains(term)
+    }
+}
+
+/// Check whether a normalized text contains a normalized phrase as whole words
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    text.match_indices(phrase).any(|(start, matched)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + matched.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
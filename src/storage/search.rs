@@ -0,0 +1,389 @@
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// A passage of a document matching a query, with surrounding context
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentMatch {
+    /// First line of the snippet (1-based)
+    pub start_line: usize,
+    /// Last line of the snippet (1-based, inclusive)
+    pub end_line: usize,
+    /// Lines within the snippet that matched the query (1-based)
+    pub matched_lines: Vec<usize>,
+    /// The snippet text, including context lines
+    pub snippet: String,
+}
+
+/// Find the lines of `content` containing any of the given terms (case-insensitive),
+/// grouping hits whose context windows overlap into a single match
+pub fn find_line_matches(content: &str, terms: &[String], context_lines: usize) -> Vec<DocumentMatch> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    // Collect the indices of all lines containing at least one term
+    let hits: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.to_lowercase();
+            terms.iter().any(|term| line.contains(term.as_str()))
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut matches: Vec<DocumentMatch> = Vec::new();
+    let mut current: Option<(usize, usize, Vec<usize>)> = None;
+
+    for hit in hits {
+        let start = hit.saturating_sub(context_lines);
+        let end = (hit + context_lines).min(lines.len() - 1);
+
+        current = match current {
+            // Extend the current window if it overlaps with this hit's context
+            Some((window_start, window_end, mut window_hits)) if start <= window_end + 1 => {
+                window_hits.push(hit);
+                Some((window_start, end.max(window_end), window_hits))
+            }
+            Some(window) => {
+                matches.push(build_match(&lines, window));
+                Some((start, end, vec![hit]))
+            }
+            None => Some((start, end, vec![hit])),
+        };
+    }
+
+    if let Some(window) = current {
+        matches.push(build_match(&lines, window));
+    }
+
+    matches
+}
+
+/// Build a match from a window of lines (0-based indices)
+fn build_match(lines: &[&str], (start, end, hits): (usize, usize, Vec<usize>)) -> DocumentMatch {
+    DocumentMatch {
+        start_line: start + 1,
+        end_line: end + 1,
+        matched_lines: hits.into_iter().map(|hit| hit + 1).collect(),
+        snippet: lines[start..=end].join("\n"),
+    }
+}
+
+/// Find the byte ranges of all case-insensitive occurrences of `needle` in `haystack`
+///
+/// The returned ranges always fall on UTF-8 character boundaries of `haystack`.
+pub fn find_case_insensitive(haystack: &str, needle: &str) -> Vec<(usize, usize)> {
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let mut ranges = Vec::new();
+    let mut next_start = 0;
+
+    for (start, _) in haystack.char_indices() {
+        if start < next_start {
+            continue;
+        }
+
+        // Compare the lowercased characters from this position against the needle
+        let mut matched = 0;
+        let mut end = start;
+        for (offset, c) in haystack[start..].char_indices() {
+            let lowered: Vec<char> = c.to_lowercase().collect();
+            if needle[matched..].starts_with(&lowered) {
+                matched += lowered.len();
+                end = start + offset + c.len_utf8();
+                if matched == needle.len() {
+                    break;
+                }
+            } else {
+                break;
+            }
+        }
+
+        if matched == needle.len() {
+            ranges.push((start, end));
+            next_start = end;
+        }
+    }
+
+    ranges
+}
+
+/// Find the ranges of all terms in `text`, sorted and with overlaps merged
+pub fn find_terms(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = terms
+        .iter()
+        .flat_map(|term| find_case_insensitive(text, term))
+        .collect();
+    ranges.sort();
+
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    merged
+}
+
+/// Extract the passage of `content` most relevant to the given terms
+///
+/// The block containing the most distinct terms is selected: a paragraph, or a
+/// whole fenced code block. Long paragraphs are cut down to roughly `max_length`
+/// bytes around their first match, at sentence boundaries when possible and
+/// never within inline code, links or emphasis. Long code blocks are cut at line
+/// boundaries and fenced again, so the snippet is well-formed markdown. Matched
+/// terms are wrapped in `**` when `mark_terms` is set, except in code.
+pub fn extract_snippet(content: &str, terms: &[String], max_length: usize, mark_terms: bool) -> Option<String> {
+    // Pick the block matching the most distinct terms
+    let (block, _) = split_blocks(content)
+        .into_iter()
+        .map(|block| {
+            let distinct = terms
+                .iter()
+                .filter(|term| !find_case_insensitive(block, term).is_empty())
+                .count();
+            (block, distinct)
+        })
+        .filter(|(_, distinct)| *distinct > 0)
+        .fold(None, |best: Option<(&str, usize)>, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        })?;
+
+    match fence_marker(block) {
+        Some(marker) => Some(code_snippet(block, &marker, terms, max_length)),
+        None => Some(paragraph_snippet(block, terms, max_length, mark_terms)),
+    }
+}
+
+/// Split markdown content into trimmed blocks separated by blank lines, keeping
+/// each fenced code block whole, blank lines included
+fn split_blocks(content: &str) -> Vec<&str> {
+    let mut blocks = Vec::new();
+    let mut push = |start: usize, end: usize| {
+        let block = content[start..end].trim();
+        if !block.is_empty() {
+            blocks.push(block);
+        }
+    };
+
+    let mut block_start: Option<usize> = None;
+    let mut fence: Option<String> = None;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let (line_start, line_end) = (offset, offset + line.len());
+        offset = line_end;
+
+        if let Some(marker) = &fence {
+            if is_fence_close(line, marker) {
+                push(block_start.take().unwrap_or(line_start), line_end);
+                fence = None;
+            }
+            continue;
+        }
+
+        if let Some(marker) = fence_marker(line) {
+            // A fence opening right below a paragraph still starts a block of its own
+            if let Some(start) = block_start.take() {
+                push(start, line_start);
+            }
+            block_start = Some(line_start);
+            fence = Some(marker);
+        } else if line.trim().is_empty() {
+            if let Some(start) = block_start.take() {
+                push(start, line_start);
+            }
+        } else if block_start.is_none() {
+            block_start = Some(line_start);
+        }
+    }
+    if let Some(start) = block_start {
+        push(start, content.len());
+    }
+
+    blocks
+}
+
+/// The fence a line opens a code block with, such as ` ``` ` or `~~~~`
+fn fence_marker(line: &str) -> Option<String> {
+    let line = line.trim_start();
+    let fence_char = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let length = line.chars().take_while(|c| *c == fence_char).count();
+    (length >= 3).then(|| fence_char.to_string().repeat(length))
+}
+
+/// Check whether a line closes the code block opened with `marker`
+fn is_fence_close(line: &str, marker: &str) -> bool {
+    let line = line.trim();
+    line.starts_with(marker) && line.chars().all(|c| marker.starts_with(c))
+}
+
+/// Cut a fenced code block down to the lines around its first match, fenced
+/// again, with `...` outside the fences where lines were left out
+fn code_snippet(block: &str, marker: &str, terms: &[String], max_length: usize) -> String {
+    let mut lines: Vec<&str> = block.lines().collect();
+    let opening = lines.remove(0);
+    if lines.last().is_some_and(|line| is_fence_close(line, marker)) {
+        lines.pop();
+    }
+
+    // Take a few lines of context before the first match, then the lines after it while they fit
+    let first_match = lines
+        .iter()
+        .position(|line| terms.iter().any(|term| !find_case_insensitive(line, term).is_empty()))
+        .unwrap_or(0);
+    let line_length = |index: usize| lines.get(index).map_or(0, |line| line.len() + 1);
+    let (mut start, mut end) = (first_match, first_match + 1);
+    let mut length = opening.len() + marker.len() + line_length(first_match);
+    while start > 0 && length + line_length(start - 1) <= max_length / 4 {
+        start -= 1;
+        length += line_length(start);
+    }
+    while end < lines.len() && length + line_length(end) <= max_length {
+        length += line_length(end);
+        end += 1;
+    }
+    while start > 0 && length + line_length(start - 1) <= max_length {
+        start -= 1;
+        length += line_length(start);
+    }
+
+    let mut snippet = String::with_capacity(length + 8);
+    if start > 0 {
+        snippet.push_str("...\n");
+    }
+    snippet.push_str(opening);
+    snippet.push('\n');
+    for line in &lines[start..end.min(lines.len())] {
+        snippet.push_str(line);
+        snippet.push('\n');
+    }
+    snippet.push_str(marker);
+    if end < lines.len() {
+        snippet.push_str("\n...");
+    }
+    snippet
+}
+
+/// Cut a paragraph down to roughly `max_length` bytes around its first match
+fn paragraph_snippet(paragraph: &str, terms: &[String], max_length: usize, mark_terms: bool) -> String {
+    let inline = inline_constructs(paragraph);
+    let matches = find_terms(paragraph, terms);
+
+    // Center a window around the first match if the paragraph is too long
+    let (mut start, mut end) = (0, paragraph.len());
+    if paragraph.len() > max_length {
+        let (first_match, first_match_end) = matches.first().copied().unwrap_or_default();
+
+        // Start at the sentence of the match if it's close enough, else avoid cutting words in half
+        let lookback = floor_char_boundary(paragraph, first_match.saturating_sub(max_length / 2));
+        match sentence_ends(&paragraph[lookback..first_match]).last() {
+            Some(boundary) => {
+                let sentence = &paragraph[lookback + boundary..];
+                start = paragraph.len() - sentence.trim_start().len();
+            }
+            None => {
+                start = floor_char_boundary(paragraph, first_match.saturating_sub(max_length / 4));
+                if start > 0
+                    && let Some(space) = paragraph[start..first_match].find(char::is_whitespace)
+                {
+                    start += space + 1;
+                }
+            }
+        }
+        end = floor_char_boundary(paragraph, (start + max_length).min(paragraph.len()));
+
+        // End at the last sentence of the window, else avoid cutting words in half
+        if end < paragraph.len() {
+            let search_from = first_match_end.clamp(start, end);
+            match sentence_ends(&paragraph[search_from..end]).last() {
+                Some(boundary) => end = search_from + boundary,
+                None => {
+                    if let Some(space) = paragraph[start..end].rfind(char::is_whitespace) {
+                        end = start + space;
+                    }
+                }
+            }
+        }
+
+        // Keep inline code, links and emphasis whole, or leave them out
+        for &(construct_start, construct_end) in &inline {
+            if construct_start < start && start < construct_end {
+                start = construct_start;
+            }
+            if construct_start < end && end < construct_end {
+                end = if construct_start > start { construct_start } else { construct_end };
+            }
+        }
+        end = paragraph[..end].trim_end().len().max(start);
+    }
+
+    let window = &paragraph[start..end];
+    let mut snippet = String::with_capacity(window.len() + 8);
+
+    if start > 0 {
+        snippet.push_str("...");
+    }
+
+    if mark_terms {
+        // Markers would show literally within code, and break link destinations
+        let code: Vec<(usize, usize)> = inline_code(window);
+        let mut last = 0;
+        for (match_start, match_end) in find_terms(window, terms) {
+            if code.iter().any(|&(code_start, code_end)| match_start < code_end && code_start < match_end) {
+                continue;
+            }
+            snippet.push_str(&window[last..match_start]);
+            snippet.push_str("**");
+            snippet.push_str(&window[match_start..match_end]);
+            snippet.push_str("**");
+            last = match_end;
+        }
+        snippet.push_str(&window[last..]);
+    } else {
+        snippet.push_str(window);
+    }
+
+    if end < paragraph.len() {
+        snippet.push_str("...");
+    }
+
+    snippet
+}
+
+/// Find the ends of the sentences of `text`, as the byte offsets right after their punctuation
+fn sentence_ends(text: &str) -> impl Iterator<Item = usize> + '_ {
+    text.char_indices()
+        .zip(text.chars().skip(1))
+        .filter(|((_, c), next)| matches!(c, '.' | '!' | '?') && next.is_whitespace())
+        .map(|((index, c), _)| index + c.len_utf8())
+}
+
+/// Find the byte ranges of the inline code spans, links, images and emphasis of a paragraph
+fn inline_constructs(text: &str) -> Vec<(usize, usize)> {
+    static INLINE: OnceLock<Regex> = OnceLock::new();
+    let inline = INLINE.get_or_init(|| {
+        Regex::new(r"`[^`]*`|!?\[[^\]]*\]\([^)]*\)|\*\*[^*]+\*\*|__[^_]+__|\*[^*\s][^*]*\*").unwrap()
+    });
+    inline.find_iter(text).map(|m| (m.start(), m.end())).collect()
+}
+
+/// Find the byte ranges of the inline code spans and link destinations of a paragraph
+fn inline_code(text: &str) -> Vec<(usize, usize)> {
+    static CODE: OnceLock<Regex> = OnceLock::new();
+    let code = CODE.get_or_init(|| Regex::new(r"`[^`]*`|\]\([^)]*\)").unwrap());
+    code.find_iter(text).map(|m| (m.start(), m.end())).collect()
+}
+
+/// Find the closest UTF-8 character boundary at or before `index`
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use super::StoredDocument;
+
+/// Field documents are listed by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortField {
+    #[default]
+    Path,
+    /// The title, ignoring case
+    Title,
+    /// The size of the content, in bytes
+    Size,
+    /// The time of the last commit changing the document, documents without one first
+    LastModified,
+}
+
+impl FromStr for SortField {
+    type Err = anyhow::Error;
+
+    fn from_str(field: &str) -> Result<Self> {
+        match field.trim().to_lowercase().as_str() {
+            "path" => Ok(SortField::Path),
+            "title" => Ok(SortField::Title),
+            "size" => Ok(SortField::Size),
+            "last_modified" => Ok(SortField::LastModified),
+            _ => anyhow::bail!("Unsupported sort field: {}", field),
+        }
+    }
+}
+
+/// Direction documents are listed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl FromStr for SortOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(order: &str) -> Result<Self> {
+        match order.trim().to_lowercase().as_str() {
+            "asc" => Ok(SortOrder::Asc),
+            "desc" => Ok(SortOrder::Desc),
+            _ => anyhow::bail!("Unsupported sort order: {}", order),
+        }
+    }
+}
+
+/// Sort documents by a field, in the given order
+///
+/// Documents with the same value of the field are listed by path, so the order
+/// is the same on every call.
+pub fn sort_documents(documents: &mut [&StoredDocument], field: SortField, order: SortOrder) {
+    documents.sort_by(|a, b| {
+        let ordering = match field {
+            SortField::Path => Ordering::Equal,
+            SortField::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            SortField::Size => a.content.len().cmp(&b.content.len()),
+            SortField::LastModified => a.last_modified.cmp(&b.last_modified),
+        }
+        .then_with(|| a.path.cmp(&b.path));
+        match order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+}
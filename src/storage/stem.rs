@@ -0,0 +1,56 @@
+/// Reduce an English word to a stem by stripping common inflection suffixes
+///
+/// This is a light stemmer, not a full Porter implementation: it only needs to
+/// map the usual forms of a word (`configure`, `configured`, `configuring`,
+/// `configures`) to the same keyword. The word is expected in lowercase.
+pub fn stem(word: &str) -> String {
+    // Short words are mostly stopwords or acronyms, which don't inflect the same way
+    if word.chars().count() <= 4 || !word.chars().all(|c| c.is_ascii_alphabetic()) {
+        return word.to_string();
+    }
+
+    let mut stem = strip_plural(word).to_string();
+
+    for suffix in ["ing", "ed", "ly"] {
+        if let Some(stripped) = stem.strip_suffix(suffix)
+            && stripped.len() >= 3
+            && stripped.contains(is_vowel)
+        {
+            stem = undouble(stripped).to_string();
+            break;
+        }
+    }
+
+    // `configure` and `configur(ed)` should share a stem
+    if stem.len() > 4 && stem.ends_with('e') {
+        stem.pop();
+    }
+
+    stem
+}
+
+fn strip_plural(word: &str) -> std::borrow::Cow<'_, str> {
+    if let Some(stripped) = word.strip_suffix("ies") {
+        return format!("{}y", stripped).into();
+    }
+    if let Some(stripped) = word.strip_suffix("sses") {
+        return format!("{}ss", stripped).into();
+    }
+    if word.ends_with('s') && !(word.ends_with("ss") || word.ends_with("us") || word.ends_with("is")) {
+        return word[..word.len() - 1].into();
+    }
+    word.into()
+}
+
+/// Remove the doubled consonant left by suffixes (`running` -> `runn` -> `run`)
+fn undouble(stem: &str) -> &str {
+    let bytes = stem.as_bytes();
+    match bytes {
+        [.., a, b] if a == b && !is_vowel(*a as char) && !matches!(a, b'l' | b's' | b'z') => &stem[..stem.len() - 1],
+        _ => stem,
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y')
+}
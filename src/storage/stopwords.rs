@@ -0,0 +1,91 @@
+use std::collections::{HashMap, HashSet};
+
+use super::language;
+
+/// Language whose stopwords are used when a document language is unknown or has no list
+const DEFAULT_LANGUAGE: &str = "en";
+
+const ENGLISH: &[&str] = &[
+    "the", "a", "an", "and", "or", "but", "if", "then", "else", "when",
+    "at", "from", "by", "for", "with", "about", "against", "between",
+    "into", "through", "during", "before", "after", "above", "below",
+    "to", "of", "in", "on", "is", "are", "was", "were", "be", "been",
+    "being", "have", "has", "had", "do", "does", "did", "will", "would",
+    "shall", "should", "can", "could", "may", "might", "must", "this",
+    "that", "these", "those", "i", "you", "he", "she", "it", "we", "they",
+];
+
+const FRENCH: &[&str] = &[
+    "le", "la", "les", "un", "une", "des", "du", "de", "et", "ou", "mais",
+    "donc", "car", "ni", "que", "qui", "quoi", "dont", "où", "si", "ce",
+    "cet", "cette", "ces", "dans", "sur", "sous", "avec", "sans", "pour",
+    "par", "entre", "vers", "chez", "avant", "après", "est", "sont", "été",
+    "être", "avoir", "ont", "était", "sera", "peut", "doit", "fait", "aux",
+    "au", "il", "elle", "ils", "elles", "nous", "vous", "je", "tu", "on",
+    "son", "sa", "ses", "leur", "leurs", "notre", "votre", "pas", "plus",
+    "comme", "aussi", "tout", "tous", "toutes", "même", "lors",
+];
+
+const GERMAN: &[&str] = &[
+    "der", "die", "das", "den", "dem", "des", "ein", "eine", "einen",
+    "einem", "einer", "eines", "und", "oder", "aber", "wenn", "dann",
+    "als", "wie", "dass", "mit", "von", "aus", "bei", "nach", "für",
+    "über", "unter", "vor", "zwischen", "durch", "gegen", "ohne", "ist",
+    "sind", "war", "waren", "sein", "hat", "haben", "wird", "werden",
+    "kann", "können", "muss", "soll", "sich", "nicht", "auch", "noch",
+    "nur", "ich", "du", "er", "sie", "es", "wir", "ihr", "dies", "diese",
+    "dieser", "dieses", "auf", "zum", "zur", "im", "am",
+];
+
+const SPANISH: &[&str] = &[
+    "el", "la", "los", "las", "un", "una", "unos", "unas", "y", "o",
+    "pero", "si", "que", "de", "del", "al", "en", "con", "sin", "por",
+    "para", "entre", "hacia", "desde", "sobre", "antes", "después", "es",
+    "son", "fue", "ser", "estar", "está", "están", "ha", "han", "hay",
+    "puede", "debe", "se", "su", "sus", "este", "esta", "estos", "estas",
+    "ese", "esa", "como", "más", "también", "todo", "todos", "yo", "tú",
+    "él", "ella", "nosotros", "ellos", "ellas", "lo", "le", "les", "cuando",
+];
+
+/// Stopwords left out of keywords, per document language
+///
+/// Built-in lists are provided for English, French, German and Spanish.
+/// Documents in other languages, or whose language is unknown, use the
+/// English list.
+#[derive(Debug, Clone)]
+pub struct Stopwords {
+    // ISO 639-1 code to the stopwords of that language
+    lists: HashMap<String, HashSet<String>>,
+}
+
+impl Stopwords {
+    /// Replace the stopwords of a language, given as an ISO 639-1 or ISO 639-3 code
+    pub fn set(&mut self, language: &str, words: &[String]) {
+        let words = words.iter().map(|word| word.trim().to_lowercase()).collect();
+        self.lists.insert(language::normalize(language), words);
+    }
+
+    /// Check whether a lowercase word is a stopword in the given language
+    pub fn contains(&self, language: Option<&str>, word: &str) -> bool {
+        let list = language
+            .and_then(|language| self.lists.get(language))
+            .or_else(|| self.lists.get(DEFAULT_LANGUAGE));
+        list.is_some_and(|list| list.contains(word))
+    }
+
+    fn list(words: &[&str]) -> HashSet<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+}
+
+impl Default for Stopwords {
+    fn default() -> Self {
+        let lists = HashMap::from([
+            ("en".to_string(), Self::list(ENGLISH)),
+            ("fr".to_string(), Self::list(FRENCH)),
+            ("de".to_string(), Self::list(GERMAN)),
+            ("es".to_string(), Self::list(SPANISH)),
+        ]);
+        Self { lists }
+    }
+}
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+/// Pick the `count` most salient sentences, given the keywords of each sentence
+///
+/// A sentence scores the frequencies of its keywords across the whole document,
+/// damped by the square root of its keyword count so long sentences don't win
+/// by length alone. Ties go to the earliest sentence. Returns the indices of
+/// the picked sentences in document order.
+pub fn rank_sentences(sentence_keywords: &[Vec<String>], count: usize) -> Vec<usize> {
+    // Number of sentences each keyword appears in
+    let mut frequencies: HashMap<&str, usize> = HashMap::new();
+    for keywords in sentence_keywords {
+        for keyword in keywords {
+            *frequencies.entry(keyword.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut scored: Vec<(usize, f64)> = sentence_keywords
+        .iter()
+        .enumerate()
+        .filter(|(_, keywords)| !keywords.is_empty())
+        .map(|(index, keywords)| {
+            let total: usize = keywords.iter().map(|keyword| frequencies[keyword.as_str()]).sum();
+            (index, total as f64 / (keywords.len() as f64).sqrt())
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut picked: Vec<usize> = scored.into_iter().take(count).map(|(index, _)| index).collect();
+    picked.sort_unstable();
+    picked
+}
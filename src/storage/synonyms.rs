@@ -0,0 +1,66 @@
+/// Groups of words with the same meaning in technical documentation, acronyms
+/// and abbreviations along with the words they stand for
+///
+/// Words of two characters or less are never keywords, so abbreviations such
+/// as `db` or `js` can't be listed.
+const DEFAULT_GROUPS: &[&[&str]] = &[
+    &["authentication", "auth", "login", "signin"],
+    &["authorization", "authz", "permissions"],
+    &["kubernetes", "k8s"],
+    &["configuration", "config", "settings"],
+    &["repository", "repo"],
+    &["documentation", "docs"],
+    &["environment", "env"],
+    &["dependency", "dependencies", "deps"],
+    &["installation", "install", "setup"],
+    &["error", "exception", "failure"],
+    &["directory", "folder"],
+    &["delete", "remove"],
+    &["internationalization", "i18n"],
+    &["localization", "l10n"],
+    &["accessibility", "a11y"],
+    &["application", "app"],
+    &["specification", "spec"],
+];
+
+/// Length of the longest words not kept as keywords
+const MIN_WORD_LENGTH: usize = 2;
+
+/// Words a query keyword is expanded to, so searches also find documents using
+/// another word for the same thing
+///
+/// A built-in list of common technical terms is provided. Every word of a group
+/// expands to the others.
+#[derive(Debug, Clone)]
+pub struct Synonyms {
+    groups: Vec<Vec<String>>,
+}
+
+impl Synonyms {
+    /// Add a word and its synonyms, in addition to the built-in groups
+    ///
+    /// Words too short to be keywords are left out, as they'd never match.
+    pub fn add(&mut self, word: &str, synonyms: &[String]) {
+        let group = std::iter::once(word)
+            .chain(synonyms.iter().map(String::as_str))
+            .map(|word| word.trim().to_lowercase())
+            .filter(|word| word.chars().count() > MIN_WORD_LENGTH)
+            .collect();
+        self.groups.push(group);
+    }
+
+    /// Get the groups of words with the same meaning
+    pub fn groups(&self) -> &[Vec<String>] {
+        &self.groups
+    }
+}
+
+impl Default for Synonyms {
+    fn default() -> Self {
+        let groups = DEFAULT_GROUPS
+            .iter()
+            .map(|group| group.iter().map(|word| word.to_string()).collect())
+            .collect();
+        Self { groups }
+    }
+}
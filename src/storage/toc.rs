@@ -0,0 +1,91 @@
+use serde::Serialize;
+
+use crate::document::Heading;
+
+/// A directory of the table of contents, with its subdirectories and documents sorted by name
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TocDirectory {
+    /// Name of the directory, empty for the root
+    pub name: String,
+    /// Path of the directory, empty for the root
+    pub path: String,
+    pub directories: Vec<TocDirectory>,
+    pub documents: Vec<TocDocument>,
+}
+
+/// A document of the table of contents, with its headings nested by level
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TocDocument {
+    pub path: String,
+    pub title: String,
+    pub headings: Vec<TocHeading>,
+}
+
+/// A heading of the table of contents, with the headings of its section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TocHeading {
+    pub level: usize,
+    pub text: String,
+    pub headings: Vec<TocHeading>,
+}
+
+impl TocDirectory {
+    /// Add a document under the directories of its path, creating them as needed
+    pub fn insert(&mut self, document: TocDocument) {
+        let directories: Vec<&str> = document.path.split('/').collect();
+        let directories = &directories[..directories.len() - 1];
+
+        let mut directory = self;
+        for name in directories {
+            let path = if directory.path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{}", directory.path, name)
+            };
+            let index = match directory.directories.iter().position(|child| child.name == *name) {
+                Some(index) => index,
+                None => {
+                    directory.directories.push(TocDirectory {
+                        name: name.to_string(),
+                        path,
+                        ..Default::default()
+                    });
+                    directory.directories.len() - 1
+                }
+            };
+            directory = &mut directory.directories[index];
+        }
+        directory.documents.push(document);
+    }
+
+    /// Sort the subdirectories and documents by name, recursively
+    pub fn sort(&mut self) {
+        self.directories.sort_by(|a, b| a.name.cmp(&b.name));
+        self.documents.sort_by(|a, b| a.path.cmp(&b.path));
+        for directory in &mut self.directories {
+            directory.sort();
+        }
+    }
+}
+
+/// Nest the headings of an outline under the closest preceding heading of a lower level
+///
+/// Headings deeper than `max_level` are left out.
+pub fn nest_headings(outline: Vec<Heading>, max_level: usize) -> Vec<TocHeading> {
+    let mut headings: Vec<TocHeading> = Vec::new();
+    for heading in outline.into_iter().filter(|heading| heading.level <= max_level) {
+        let heading = TocHeading {
+            level: heading.level,
+            text: heading.text,
+            headings: Vec::new(),
+        };
+
+        // Walk down the last headings while they're higher than the new one
+        let mut siblings = &mut headings;
+        while siblings.last().is_some_and(|last| last.level < heading.level) {
+            siblings = &mut siblings.last_mut().unwrap().headings;
+        }
+        siblings.push(heading);
+    }
+    headings
+}
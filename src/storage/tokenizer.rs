@@ -0,0 +1,79 @@
+use serde::Deserialize;
+use std::fmt::Debug;
+
+/// Splits text into the words keywords are extracted from
+///
+/// Implementations get lowercase text and return its words in order of
+/// appearance, duplicates included. Stopwords and short words are left out
+/// afterwards.
+pub trait Tokenizer: Debug + Send + Sync {
+    fn tokenize<'a>(&self, text: &'a str) -> Vec<&'a str>;
+}
+
+/// Which tokenizer keywords are extracted with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerKind {
+    /// Split on every character that isn't a letter or a digit
+    #[default]
+    Default,
+    /// Keep identifiers such as `std::vec`, `--flag` or `file.rs` whole
+    Code,
+}
+
+impl TokenizerKind {
+    /// Create the tokenizer of this kind
+    pub fn tokenizer(self) -> Box<dyn Tokenizer> {
+        match self {
+            TokenizerKind::Default => Box::new(DefaultTokenizer),
+            TokenizerKind::Code => Box::new(CodeTokenizer),
+        }
+    }
+}
+
+/// Splits text on every character that isn't a letter or a digit
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultTokenizer;
+
+impl Tokenizer for DefaultTokenizer {
+    fn tokenize<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect()
+    }
+}
+
+/// Keeps technical identifiers whole: paths such as `std::vec::vec`, command
+/// line flags such as `--verbose`, and dotted or hyphenated names such as
+/// `file.rs`, `snake_case` or `kebab-case`
+///
+/// The parts of an identifier are words as well, so `std::vec` is found by
+/// searching for `std::vec` or for `vec`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CodeTokenizer;
+
+impl CodeTokenizer {
+    fn is_identifier_char(c: char) -> bool {
+        c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.')
+    }
+}
+
+impl Tokenizer for CodeTokenizer {
+    fn tokenize<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut words = Vec::new();
+        for token in text.split(|c: char| !Self::is_identifier_char(c)) {
+            // Punctuation around an identifier isn't part of it, leading dashes of flags aside
+            let token = token.trim_end_matches([':', '.', '-']).trim_start_matches([':', '.']);
+            if !token.chars().any(char::is_alphanumeric) {
+                continue;
+            }
+
+            let parts = DefaultTokenizer.tokenize(token);
+            if parts.len() > 1 || token.len() != parts.first().map_or(0, |part| part.len()) {
+                words.push(token);
+            }
+            words.extend(parts);
+        }
+        words
+    }
+}
@@ -0,0 +1,19 @@
+/// Average number of LLM tokens per word of English prose
+const TOKENS_PER_WORD: f64 = 1.3;
+
+/// Average number of characters per token, lower bound of texts with few spaces such as code
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Count the whitespace-separated words of a text
+pub fn count_words(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+/// Estimate the number of tokens a text takes in an LLM context
+///
+/// Prose is estimated from its word count, but code and URLs pack many tokens
+/// in few words, so the estimate is at least a token per four characters.
+pub fn estimate_tokens(text: &str) -> usize {
+    let from_words = (count_words(text) as f64 * TOKENS_PER_WORD).ceil() as usize;
+    from_words.max(text.chars().count().div_ceil(CHARS_PER_TOKEN))
+}
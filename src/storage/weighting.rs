@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How much earlier keywords weigh more: a keyword first appearing at the very
+/// start of a document weighs this much more than one first appearing at its end
+const POSITION_BOOST: f64 = 1.0;
+
+/// A field of a document keywords are found in, the most significant first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordField {
+    Title,
+    Heading,
+    #[default]
+    Body,
+}
+
+/// A keyword of a document, with how significant it is in the document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeightedKeyword {
+    pub keyword: String,
+    /// Weight in the body, from 0 to 1, the most significant keyword of the
+    /// body weighing 1 and keywords only in the title or headings 0, single
+    /// precision being enough and reloading exactly from JSON
+    pub weight: f32,
+    /// The most significant field the keyword is found in
+    #[serde(default)]
+    pub field: KeywordField,
+}
+
+/// Weigh the keywords of a document by how often they appear in it and how
+/// early they first appear, most significant first
+///
+/// `occurrences` are the words of the document in order, in their keyword
+/// form. Keywords found elsewhere, such as in the alt text of images, weigh as
+/// much as a single occurrence at the end. Ties keep the order of `keywords`.
+pub fn weigh(keywords: &[String], occurrences: &[String]) -> Vec<WeightedKeyword> {
+    // Number of occurrences of each word, and the position of its first one
+    let mut stats: HashMap<&str, (usize, usize)> = HashMap::new();
+    for (position, word) in occurrences.iter().enumerate() {
+        stats.entry(word.as_str()).or_insert((0, position)).0 += 1;
+    }
+
+    let length = occurrences.len().max(1) as f64;
+    let weights: Vec<f64> = keywords
+        .iter()
+        .map(|keyword| {
+            let (count, first) = stats.get(keyword.as_str()).copied().unwrap_or((1, occurrences.len()));
+            let frequency = 1.0 + (count as f64).ln();
+            let position = 1.0 + POSITION_BOOST * (1.0 - first as f64 / length);
+            frequency * position
+        })
+        .collect();
+
+    // Normalize so the most significant keyword weighs 1
+    let max_weight = weights.iter().copied().fold(0.0, f64::max);
+    let mut weighted: Vec<WeightedKeyword> = keywords
+        .iter()
+        .zip(weights)
+        .map(|(keyword, weight)| WeightedKeyword {
+            keyword: keyword.clone(),
+            weight: if max_weight > 0.0 { (weight / max_weight) as f32 } else { 0.0 },
+            field: KeywordField::Body,
+        })
+        .collect();
+    weighted.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+    weighted
+}
+
+/// Merge the keywords of the title and headings into the weighted keywords of
+/// the body, so each keyword is stored once, with the most significant field
+/// it's found in and its weight in the body
+///
+/// Keywords only in the title or headings are added after the others, weighing 0.
+pub fn consolidate(mut weighted: Vec<WeightedKeyword>, title: &[String], headings: &[String]) -> Vec<WeightedKeyword> {
+    let mut positions: HashMap<String, usize> = weighted
+        .iter()
+        .enumerate()
+        .map(|(position, existing)| (existing.keyword.clone(), position))
+        .collect();
+    for (field, keywords) in [(KeywordField::Title, title), (KeywordField::Heading, headings)] {
+        for keyword in keywords {
+            match positions.get(keyword) {
+                Some(&position) => weighted[position].field = weighted[position].field.min(field),
+                None => {
+                    positions.insert(keyword.clone(), weighted.len());
+                    weighted.push(WeightedKeyword {
+                        keyword: keyword.clone(),
+                        weight: 0.0,
+                        field,
+                    });
+                }
+            }
+        }
+    }
+    weighted
+}
+
+/// The keywords of the body among weighted keywords, most significant first,
+/// keywords only in the title or headings weighing nothing in the body
+pub fn body_keywords(weighted: &[WeightedKeyword]) -> Vec<String> {
+    weighted
+        .iter()
+        .filter(|weighted| weighted.weight > 0.0)
+        .map(|weighted| weighted.keyword.clone())
+        .collect()
+}
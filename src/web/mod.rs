@@ -0,0 +1,152 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::CONTENT_TYPE;
+use tokio::sync::Semaphore;
+
+use crate::document::{html_to_markdown, Document, DocumentScanner};
+use crate::error::{KodeError, Result};
+use crate::github::FetchFailure;
+
+/// Default number of pages fetched at the same time
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 5;
+
+/// Options for fetching web pages
+#[derive(Debug, Clone)]
+pub struct WebOptions {
+    /// Maximum duration of a single request before it is aborted
+    pub request_timeout: Duration,
+    /// Maximum number of pages fetched at the same time
+    pub max_concurrent_requests: usize,
+}
+
+impl Default for WebOptions {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(30),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+        }
+    }
+}
+
+/// The outcome of fetching a list of pages
+///
+/// Fetching keeps going when individual pages fail; `failures` lists the URLs
+/// that were skipped.
+#[derive(Debug, Clone, Default)]
+pub struct FetchResult {
+    pub documents: Vec<Document>,
+    pub failures: Vec<FetchFailure>,
+}
+
+/// Fetches standalone web pages, such as documentation hosted outside of a
+/// repository, and converts them to documents stored under their URL
+#[derive(Clone)]
+pub struct WebFetcher {
+    client: reqwest::Client,
+    // Semaphore to limit concurrent requests
+    request_semaphore: Arc<Semaphore>,
+    // Maximum duration of a single request
+    request_timeout: Duration,
+    // Scanner extracting the title and summary of fetched pages
+    scanner: Arc<DocumentScanner>,
+}
+
+impl WebFetcher {
+    /// Create a fetcher with custom options, processing pages with the given scanner
+    pub fn new(options: WebOptions, scanner: DocumentScanner) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(options.request_timeout)
+            .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(|e| KodeError::Other(e.into()))?;
+
+        Ok(Self {
+            client,
+            request_semaphore: Arc::new(Semaphore::new(options.max_concurrent_requests.max(1))),
+            request_timeout: options.request_timeout,
+            scanner: Arc::new(scanner),
+        })
+    }
+
+    /// Fetch pages and convert them to documents, stored under their URL
+    ///
+    /// HTML pages are converted to markdown and titled by their `<title>`, or
+    /// their first heading. Markdown and plain text are kept as they are.
+    pub async fn fetch_pages(&self, urls: &[String]) -> FetchResult {
+        // Spawn a task for each page, the semaphore limiting how many run at once
+        let tasks: Vec<_> = urls
+            .iter()
+            .map(|url| {
+                let fetcher = self.clone();
+                let url = url.clone();
+                (url.clone(), tokio::spawn(async move { fetcher.fetch_page(&url).await }))
+            })
+            .collect();
+
+        let mut result = FetchResult::default();
+        for (url, task) in tasks {
+            match task.await {
+                Ok(Ok(document)) => result.documents.push(document),
+                Ok(Err(e)) => {
+                    tracing::error!("Failed to fetch {}: {}", url, e);
+                    result.failures.push(FetchFailure {
+                        path: url,
+                        reason: format!("failed to fetch page: {}", e),
+                    });
+                }
+                Err(e) => {
+                    tracing::error!("Fetch task for {} failed: {}", url, e);
+                    result.failures.push(FetchFailure {
+                        path: url,
+                        reason: format!("fetch task failed: {}", e),
+                    });
+                }
+            }
+        }
+        result
+    }
+
+    /// Fetch a single page and convert it to a document
+    pub async fn fetch_page(&self, url: &str) -> Result<Document> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| KodeError::InvalidArgument(format!("invalid URL {}: {}", url, e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(KodeError::InvalidArgument(format!("unsupported URL scheme of {}", url)));
+        }
+
+        let _permit = self.request_semaphore.clone().acquire_owned().await.map_err(anyhow::Error::from)?;
+        let response = self.client.get(parsed).send().await.map_err(|e| self.request_error(e))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(KodeError::from_status(status.as_u16(), None, url));
+        }
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase();
+        let body = response.text().await.map_err(|e| self.request_error(e))?;
+
+        let is_html = content_type.contains("html") || (content_type.is_empty() && body.trim_start().starts_with('<'));
+        if !is_html {
+            return self.scanner.process_content(url, body).map_err(KodeError::Other);
+        }
+
+        let page = html_to_markdown(&body);
+        let mut document = self.scanner.process_content(url, page.markdown).map_err(KodeError::Other)?;
+        if let Some(title) = page.title {
+            document.title = title;
+        }
+        Ok(document)
+    }
+
+    /// Convert a failed request to the matching error
+    fn request_error(&self, error: reqwest::Error) -> KodeError {
+        if error.is_timeout() {
+            return KodeError::Timeout(self.request_timeout);
+        }
+        KodeError::Other(error.into())
+    }
+}
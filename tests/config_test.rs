@@ -0,0 +1,156 @@
+use std::path::Path;
+
+use clap::Parser;
+use kode_ai_rs::config::{Cli, ServerConfig};
+use kode_ai_rs::document::{DocumentScanner, ExtensionGroup};
+use kode_ai_rs::server::NotReadyPolicy;
+
+#[test]
+fn test_config_file_merged_with_cli() {
+    // Create a config file overriding some of the defaults
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    let config = r#"{
+        "github_repo": "handbook",
+        "github_owner": "acme",
+        "github_subfolder": "docs",
+        "include": ["docs/**/*.md"],
+        "exclude": ["docs/drafts/**"],
+        "storage_path": "/var/lib/kode-ai",
+        "scoring": { "title": 5.0 }
+    }"#;
+    std::fs::write(&config_path, config).unwrap();
+    
+    // Load it with a command line flag overriding one of its values
+    let cli = Cli::try_parse_from([
+        "kode-ai-rs",
+        "--config", config_path.to_str().unwrap(),
+        "--github-repo", "wiki",
+        "--request-timeout", "10",
+    ]).unwrap();
+    let config = ServerConfig::load(&cli).unwrap();
+    
+    // Verify command line flags take precedence over the config file
+    assert_eq!(config.github_repo, "wiki");
+    assert_eq!(config.request_timeout, 10);
+    
+    // Verify the other values come from the config file
+    assert_eq!(config.github_owner, "acme");
+    assert_eq!(config.github_subfolder, vec!["docs"]);
+    assert_eq!(config.include, vec!["docs/**/*.md"]);
+    assert_eq!(config.exclude, vec!["docs/drafts/**"]);
+    assert_eq!(config.storage_path.unwrap().to_str(), Some("/var/lib/kode-ai"));
+    assert_eq!(config.scoring.title, 5.0);
+    
+    // Verify missing values keep their defaults
+    assert_eq!(config.github_pat, None);
+    assert_eq!(config.scoring.body, 1.0);
+}
+
+#[test]
+fn test_default_config_without_file() {
+    // Load the configuration without a config file or flags
+    let cli = Cli::try_parse_from(["kode-ai-rs"]).unwrap();
+    let config = ServerConfig::load(&cli).unwrap();
+    
+    // Verify the defaults are used
+    assert_eq!(config, ServerConfig::default());
+    assert_eq!(config.github_repo, "rust-sdk");
+    assert_eq!(config.github_owner, "modelcontextprotocol");
+}
+
+#[test]
+fn test_multiple_subfolders() {
+    // Give subfolders both comma separated and with a repeated flag
+    let cli = Cli::try_parse_from([
+        "kode-ai-rs",
+        "--github-subfolder", "docs,guides",
+        "--github-subfolder", "examples",
+    ]).unwrap();
+    let config = ServerConfig::load(&cli).unwrap();
+    
+    // Verify every subfolder is kept, in order
+    assert_eq!(config.github_subfolder, vec!["docs", "guides", "examples"]);
+    
+    // Verify the config file accepts a list of subfolders too
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    std::fs::write(&config_path, r#"{ "github_subfolder": ["docs", "guides"] }"#).unwrap();
+    let config = ServerConfig::from_file(&config_path).unwrap();
+    assert_eq!(config.github_subfolder, vec!["docs", "guides"]);
+}
+
+#[test]
+fn test_local_path_replaces_default_repository() {
+    // Give a local directory without a repository
+    let cli = Cli::try_parse_from(["kode-ai-rs", "--local-path", "./docs"]).unwrap();
+    let config = ServerConfig::load(&cli).unwrap();
+    
+    // Verify the default repository is not scanned
+    assert_eq!(config.local_path.unwrap().to_str(), Some("./docs"));
+    assert!(config.github_repo.is_empty());
+    
+    // Verify an explicit repository is scanned in addition to the local directory
+    let cli = Cli::try_parse_from(["kode-ai-rs", "--local-path", "./docs", "--github-repo", "handbook"]).unwrap();
+    let config = ServerConfig::load(&cli).unwrap();
+    assert_eq!(config.github_repo, "handbook");
+}
+
+#[test]
+fn test_config_file_rejects_unknown_options() {
+    // Create a config file with a misspelled option
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    std::fs::write(&config_path, r#"{ "github_rep": "handbook" }"#).unwrap();
+    
+    // Verify loading it fails instead of silently ignoring the option
+    assert!(ServerConfig::from_file(&config_path).is_err());
+}
+
+#[test]
+fn test_extensions_group_enables_a_category() {
+    // Enable the markdown group only
+    let cli = Cli::try_parse_from(["kode-ai-rs", "--extensions-group", "markdown"]).unwrap();
+    let config = ServerConfig::load(&cli).unwrap();
+    assert_eq!(config.extension_groups, vec![ExtensionGroup::Markdown]);
+    
+    // Verify the scanner picks up every markdown extension, and nothing else
+    let mut scanner = DocumentScanner::new();
+    scanner.set_extension_groups(&config.extension_groups);
+    for path in ["guide.md", "component.mdx", "notes.markdown"] {
+        assert!(scanner.is_supported_file(Path::new(path)), "{} should be supported", path);
+    }
+    for path in ["notes.txt", "api.rst", "analysis.ipynb"] {
+        assert!(!scanner.is_supported_file(Path::new(path)), "{} should not be supported", path);
+    }
+    
+    // Verify single extensions can still be added, and groups can be combined
+    scanner.add_extension("txt");
+    assert!(scanner.is_supported_file(Path::new("notes.txt")));
+    let cli = Cli::try_parse_from(["kode-ai-rs", "--extensions-group", "text,docs"]).unwrap();
+    assert_eq!(ServerConfig::load(&cli).unwrap().extension_groups, vec![ExtensionGroup::Text, ExtensionGroup::Docs]);
+    
+    // Verify unknown groups are rejected
+    assert!(Cli::try_parse_from(["kode-ai-rs", "--extensions-group", "images"]).is_err());
+}
+
+#[test]
+fn test_not_ready_policy_flag() {
+    // Verify search tools wait for the initial scan by default
+    let cli = Cli::try_parse_from(["kode-ai-rs"]).unwrap();
+    assert_eq!(ServerConfig::load(&cli).unwrap().not_ready_policy, NotReadyPolicy::Wait);
+    
+    // Create a config file rejecting searches until the scan completes
+    let temp_dir = tempfile::tempdir().unwrap();
+    let config_path = temp_dir.path().join("config.json");
+    std::fs::write(&config_path, r#"{ "not_ready_policy": "reject" }"#).unwrap();
+    let cli = Cli::try_parse_from(["kode-ai-rs", "--config", config_path.to_str().unwrap()]).unwrap();
+    assert_eq!(ServerConfig::load(&cli).unwrap().not_ready_policy, NotReadyPolicy::Reject);
+    
+    // Verify the flag takes precedence over the config file
+    let cli = Cli::try_parse_from(["kode-ai-rs", "--config", config_path.to_str().unwrap(), "--not-ready-policy", "partial"]).unwrap();
+    assert_eq!(ServerConfig::load(&cli).unwrap().not_ready_policy, NotReadyPolicy::Partial);
+    
+    // Verify unknown policies are rejected
+    assert!(Cli::try_parse_from(["kode-ai-rs", "--not-ready-policy", "block"]).is_err());
+}
@@ -0,0 +1,487 @@
+use std::sync::Arc;
+
+use kode_ai_rs::document::{extract_links, resolve_link, ContentNormalization, DefaultProcessor, Document, DocumentProcessor, DocumentScanner};
+use kode_ai_rs::storage::DocumentStorage;
+
+#[test]
+fn test_process_notebook() {
+    let scanner = DocumentScanner::new();
+    
+    // Create a minimal notebook with a code cell before the first heading
+    let notebook = r##"{
+        "cells": [
+            {
+                "cell_type": "code",
+                "execution_count": 1,
+                "source": ["# load the data\n", "import pandas as pd"],
+                "outputs": [{"output_type": "stream", "text": ["hidden output"]}]
+            },
+            {
+                "cell_type": "markdown",
+                "source": ["# Data Analysis\n", "\n", "Exploring the sales dataset."]
+            }
+        ],
+        "metadata": {"language_info": {"name": "python"}},
+        "nbformat": 4,
+        "nbformat_minor": 5
+    }"##;
+    
+    // Process the notebook
+    let doc = scanner.process_content("analysis.ipynb", notebook.to_string()).unwrap();
+    
+    // Verify the title comes from the markdown cell, not the code comment
+    assert_eq!(doc.title, "Data Analysis");
+    
+    // Verify both markdown and code cells are captured
+    assert!(doc.content.contains("Exploring the sales dataset."));
+    assert!(doc.content.contains("```python\n# load the data\nimport pandas as pd\n```"));
+    
+    // Verify outputs are skipped
+    assert!(!doc.content.contains("hidden output"));
+}
+
+#[test]
+fn test_extract_title_closed_heading() {
+    let scanner = DocumentScanner::new();
+    
+    // Verify the closing `#` run is removed, but not a `#` ending a word
+    assert_eq!(scanner.extract_title("# Installation Guide ##\n\nText."), Some("Installation Guide".to_string()));
+    assert_eq!(scanner.extract_title("# Using C#\n\nText."), Some("Using C#".to_string()));
+    assert_eq!(scanner.extract_title("# C# Guide #\n\nText."), Some("C# Guide".to_string()));
+}
+
+#[test]
+fn test_extract_title_skips_code_blocks_and_subheadings() {
+    let scanner = DocumentScanner::new();
+    
+    // Create a document with a shell comment and a subheading before the title
+    let content = "```bash\n# install the tool\ncargo install kode\n```\n\n## Overview\n\n# Real Title\n";
+    
+    // Verify the title is the first level 1 heading outside code blocks
+    assert_eq!(scanner.extract_title(content), Some("Real Title".to_string()));
+    assert_eq!(scanner.extract_title("## Only a subheading\n"), None);
+}
+
+#[test]
+fn test_extract_title_with_bom() {
+    let scanner = DocumentScanner::new();
+    
+    // Process a file starting with a byte order mark
+    let doc = scanner.process_content("guide.md", "\u{feff}# Guide\n\nSome text.".to_string()).unwrap();
+    
+    // Verify the title is found despite the byte order mark
+    assert_eq!(doc.title, "Guide");
+}
+
+#[test]
+fn test_summary_ends_on_sentence_boundary() {
+    let scanner = DocumentScanner::new();
+    
+    // Create a document whose prose is longer than a summary
+    let content = "# Deployment\n\n\
+        The server is deployed as a single binary, e.g. on a container platform. \
+        It reads its configuration from the environment at startup. \
+        Logs are written to standard output, so they can be collected by the platform. \
+        Metrics are exposed on a separate port for scraping.\n\n\
+        ```bash\nkode-ai-rs --github-repo docs\n```\n";
+    
+    // Verify the summary is made of whole sentences, keeping abbreviations intact
+    let summary = scanner.generate_summary(content).unwrap();
+    assert_eq!(
+        summary,
+        "The server is deployed as a single binary, e.g. on a container platform. \
+        It reads its configuration from the environment at startup."
+    );
+    assert!(summary.len() <= 200);
+}
+
+#[test]
+fn test_summary_truncates_long_sentence() {
+    let scanner = DocumentScanner::new();
+    
+    // Create a document with a single sentence longer than a summary
+    let sentence = "This sentence keeps going ".repeat(12);
+    let summary = scanner.generate_summary(&format!("# Long\n\n{}", sentence)).unwrap();
+    
+    // Verify it is cut between words
+    let cut = summary.strip_suffix("...").unwrap();
+    assert!(sentence.starts_with(&format!("{} ", cut)));
+    assert!(summary.len() <= 200);
+}
+
+#[test]
+fn test_summary_length_is_configurable() {
+    let mut scanner = DocumentScanner::new();
+    scanner.set_summary_length(40);
+    
+    // Create a document with several short sentences
+    let content = "# Guide\n\nFirst step is short. Second step is short too. Third step ends it.";
+    let summary = scanner.generate_summary(content).unwrap();
+    
+    // Verify only the sentences fitting in the configured length are kept
+    assert_eq!(summary, "First step is short.");
+}
+
+#[test]
+fn test_process_content_normalizes_line_endings() {
+    let mut scanner = DocumentScanner::new();
+    
+    // Process a markdown file written on Windows, with a tab in a code block
+    let raw = "# Setup\r\n\r\nInstall the server first.\r\n\r\n```\r\n\tcargo install\r\n```\r\n";
+    let doc = scanner.process_content("docs/setup.md", raw.to_string()).unwrap();
+    
+    // Verify the stored content, title and summary have no carriage returns
+    assert!(!doc.content.contains('\r'));
+    assert_eq!(doc.content, "# Setup\n\nInstall the server first.\n\n```\n\tcargo install\n```\n");
+    assert_eq!(doc.title, "Setup");
+    assert_eq!(doc.summary.as_deref(), Some("Install the server first."));
+    
+    // Verify tabs are expanded when a tab width is set
+    scanner.set_normalization(ContentNormalization { tab_width: Some(4), ..ContentNormalization::default() });
+    let doc = scanner.process_content("docs/setup.md", raw.to_string()).unwrap();
+    assert!(doc.content.contains("\n    cargo install\n"));
+    
+    // Verify the original content is kept when preserved
+    scanner.set_normalization(ContentNormalization::preserve());
+    let doc = scanner.process_content("docs/setup.md", raw.to_string()).unwrap();
+    assert_eq!(doc.content, raw);
+}
+
+#[test]
+fn test_fallback_title_uses_directory_of_generic_filenames() {
+    let mut scanner = DocumentScanner::new();
+    
+    // Process two untitled index files in different directories
+    let guides = scanner.process_content("docs/guides/index.md", "Read these first.".to_string()).unwrap();
+    let reference = scanner.process_content("docs/api-reference/index.md", "Every endpoint.".to_string()).unwrap();
+    
+    // Verify they get distinct titles derived from their directories
+    assert_eq!(guides.title, "Guides");
+    assert_eq!(reference.title, "Api Reference");
+    
+    // Verify other filenames, and generic ones at the root, keep their name
+    let install = scanner.process_content("docs/install.md", "Run it.".to_string()).unwrap();
+    assert_eq!(install.title, "install");
+    let readme = scanner.process_content("README.md", "A project.".to_string()).unwrap();
+    assert_eq!(readme.title, "README");
+    
+    // Verify the generic filenames are configurable, ignoring case
+    scanner.set_generic_filenames(&["Overview".to_string()]);
+    let overview = scanner.process_content("docs/setup/overview.md", "Steps.".to_string()).unwrap();
+    assert_eq!(overview.title, "Setup");
+    let index = scanner.process_content("docs/guides/index.md", "Read these first.".to_string()).unwrap();
+    assert_eq!(index.title, "index");
+}
+
+#[test]
+fn test_process_asciidoc_file() {
+    let scanner = DocumentScanner::new();
+    
+    // Create an AsciiDoc file with a source block containing `=` lines
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file_path = temp_dir.path().join("guide.adoc");
+    let content = "= Document Title\n:toc:\n\nAn introduction to the guide.\n\n== Installation\n\n[source,ruby]\n----\n= not a heading\n----\n\n=== Requirements\n";
+    std::fs::write(&file_path, content).unwrap();
+    
+    // Process the file
+    let doc = scanner.process_file(&file_path).unwrap();
+    
+    // Verify the title is extracted from the `=` header
+    assert_eq!(doc.title, "Document Title");
+    
+    // Verify the sections are extracted, skipping the source block
+    let outline = scanner.extract_outline(&doc.path, &doc.content);
+    let headings: Vec<_> = outline.iter().map(|h| (h.level, h.text.as_str())).collect();
+    assert_eq!(headings, vec![(1, "Document Title"), (2, "Installation"), (3, "Requirements")]);
+}
+
+#[test]
+fn test_process_openapi_spec() {
+    let scanner = DocumentScanner::new();
+    
+    // Create a minimal OpenAPI spec with one operation and one schema
+    let spec = r##"openapi: 3.0.0
+info:
+  title: Petstore API
+  description: Manage the pets of the store.
+paths:
+  /pets/{petId}:
+    get:
+      summary: Fetch a single pet by its identifier
+      operationId: showPetById
+      parameters:
+        - name: petId
+          in: path
+          description: The id of the pet
+      responses:
+        200:
+          description: The pet
+components:
+  schemas:
+    Pet:
+      description: An animal of the store
+      properties:
+        name:
+          description: The name of the pet
+"##;
+    
+    // Process the spec
+    let doc = scanner.process_content("api/openapi.yaml", spec.to_string()).unwrap();
+    
+    // Verify the title comes from `info.title`, and each operation and schema gets a section
+    assert_eq!(doc.title, "Petstore API");
+    assert!(doc.content.contains("## GET /pets/{petId}\n\nFetch a single pet by its identifier"));
+    assert!(doc.content.contains("- `petId`: The id of the pet"));
+    assert!(doc.content.contains("### Pet\n\nAn animal of the store"));
+    
+    // Verify the endpoint summary is searchable once stored
+    let mut storage = DocumentStorage::new_in_memory();
+    storage.store_document(doc).unwrap();
+    assert_eq!(storage.find_relevant_documents("identifier")[0].path, "api/openapi.yaml");
+    
+    // Verify YAML files that aren't specs are rejected, and other YAML files aren't scanned
+    assert!(scanner.process_content("config/openapi.yml", "name: build".to_string()).is_err());
+    assert!(!scanner.is_supported_file(std::path::Path::new(".github/workflows/ci.yml")));
+}
+
+#[test]
+fn test_scan_directory_max_depth() {
+    let scanner = DocumentScanner::new();
+    
+    // Create documentation nested two levels deep
+    let temp_dir = tempfile::tempdir().unwrap();
+    let nested = temp_dir.path().join("guides").join("advanced");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(temp_dir.path().join("intro.md"), "# Intro").unwrap();
+    std::fs::write(temp_dir.path().join("guides").join("setup.md"), "# Setup").unwrap();
+    std::fs::write(nested.join("tuning.md"), "# Tuning").unwrap();
+    
+    // Verify depth 0 only scans the given directory
+    let docs = scanner.scan_directory(temp_dir.path(), Some(0)).unwrap();
+    let titles: Vec<_> = docs.iter().map(|doc| doc.title.as_str()).collect();
+    assert_eq!(titles, vec!["Intro"]);
+    
+    // Verify files beyond the configured depth aren't indexed
+    let docs = scanner.scan_directory(temp_dir.path(), Some(1)).unwrap();
+    let mut titles: Vec<_> = docs.iter().map(|doc| doc.title.as_str()).collect();
+    titles.sort();
+    assert_eq!(titles, vec!["Intro", "Setup"]);
+    
+    // Verify no limit scans everything
+    assert_eq!(scanner.scan_directory(temp_dir.path(), None).unwrap().len(), 3);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_scan_directory_symlinks() {
+    // Create documentation with a link cycle, and a link to a file outside of it
+    let temp_dir = tempfile::tempdir().unwrap();
+    let docs_dir = temp_dir.path().join("docs");
+    let guides = docs_dir.join("guides");
+    std::fs::create_dir_all(&guides).unwrap();
+    std::fs::write(guides.join("setup.md"), "# Setup").unwrap();
+    std::fs::write(temp_dir.path().join("secret.md"), "# Secret").unwrap();
+    std::os::unix::fs::symlink(&docs_dir, guides.join("loop")).unwrap();
+    std::os::unix::fs::symlink(temp_dir.path().join("secret.md"), docs_dir.join("secret.md")).unwrap();
+    
+    // Verify linked files are indexed by default, but linked directories aren't scanned
+    let mut scanner = DocumentScanner::new();
+    let paths = |docs: Vec<Document>| {
+        let mut paths: Vec<String> = docs.into_iter().map(|doc| doc.path).collect();
+        paths.sort();
+        paths
+    };
+    assert_eq!(paths(scanner.scan_directory(&docs_dir, None).unwrap()), vec!["guides/setup.md", "secret.md"]);
+    
+    // Verify following links terminates, scanning the cycle once and skipping the outside file
+    scanner.set_follow_symlinks(true, false);
+    assert_eq!(paths(scanner.scan_directory(&docs_dir, None).unwrap()), vec!["guides/setup.md"]);
+    
+    // Verify the outside file is indexed when allowed
+    scanner.set_follow_symlinks(true, true);
+    assert_eq!(paths(scanner.scan_directory(&docs_dir, None).unwrap()), vec!["guides/setup.md", "secret.md"]);
+}
+
+#[test]
+fn test_scan_directory_skips_binary_files() {
+    // Create a text file and a binary file mislabeled as text
+    let temp_dir = tempfile::tempdir().unwrap();
+    std::fs::write(temp_dir.path().join("notes.txt"), "Release notes\n\nFixed the installer.").unwrap();
+    std::fs::write(temp_dir.path().join("data.txt"), [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0, 0, 0x0d, 0x02, 0x01]).unwrap();
+    
+    // Scan the directory
+    let scanner = DocumentScanner::new();
+    let docs = scanner.scan_directory(temp_dir.path(), None).unwrap();
+    
+    // Verify the binary file is skipped rather than stored
+    let paths: Vec<&str> = docs.iter().map(|doc| doc.path.as_str()).collect();
+    assert_eq!(paths, vec!["notes.txt"]);
+    
+    // Verify binary content that happens to be valid UTF-8 is rejected too
+    let control_bytes = "\u{1}\u{2}\u{3}\u{4}\u{5}\u{6}".repeat(10);
+    assert!(scanner.process_content("dump.txt", control_bytes).is_err());
+    assert!(scanner.process_content("tabs.txt", "a\tb\r\nc\n".to_string()).is_ok());
+}
+
+#[test]
+fn test_index_local_directory() {
+    // Create a local documentation folder with a draft and a non-documentation file
+    let temp_dir = tempfile::tempdir().unwrap();
+    let docs_dir = temp_dir.path().join("docs");
+    std::fs::create_dir_all(docs_dir.join("guides")).unwrap();
+    std::fs::create_dir_all(docs_dir.join("drafts")).unwrap();
+    std::fs::write(docs_dir.join("intro.md"), "# Intro\n\nSee the [setup guide](guides/setup.md).").unwrap();
+    std::fs::write(docs_dir.join("guides").join("setup.md"), "# Setup\n\nInstall the toolchain.").unwrap();
+    std::fs::write(docs_dir.join("drafts").join("wip.md"), "# Work in progress").unwrap();
+    std::fs::write(docs_dir.join("logo.png"), "not a document").unwrap();
+    
+    // Scan it with an exclude pattern and store the documents
+    let mut scanner = DocumentScanner::new();
+    scanner.set_patterns(&[], &["drafts/**".to_string()]).unwrap();
+    let docs = scanner.scan_directory(&docs_dir, None).unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path().join("storage")).unwrap();
+    storage.store_documents(docs).unwrap();
+    
+    // Verify documents are stored under their path relative to the directory
+    let mut paths: Vec<&str> = storage.get_all_documents().iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["guides/setup.md", "intro.md"]);
+    
+    // Verify they can be searched, and their links resolve to stored paths
+    assert_eq!(storage.find_relevant_documents("toolchain")[0].path, "guides/setup.md");
+    assert_eq!(storage.get_document("intro.md").unwrap().links, vec!["guides/setup.md"]);
+}
+
+#[test]
+fn test_markdown_to_text_table() {
+    let scanner = DocumentScanner::new();
+    
+    // Convert a table with columns of different widths
+    let markdown = "| Name | Value |\n|------|-------|\n| a | 1 |\n| long name | 22 |";
+    let text = scanner.markdown_to_text(markdown);
+    
+    // Verify the rows are aligned
+    assert_eq!(text, "Name      | Value\n----------|------\na         | 1\nlong name | 22");
+}
+
+#[test]
+fn test_markdown_to_text_nested_list() {
+    let scanner = DocumentScanner::new();
+    
+    // Convert an ordered list with a nested bullet list
+    let markdown = "1. First\n2. Second\n   - nested a\n   - nested b\n3. Third";
+    let text = scanner.markdown_to_text(markdown);
+    
+    // Verify the numbering and nesting are preserved
+    assert_eq!(text, "1. First\n2. Second\n  - nested a\n  - nested b\n3. Third");
+}
+
+#[test]
+fn test_markdown_to_text_link() {
+    let scanner = DocumentScanner::new();
+    
+    // Convert a paragraph with a link and an autolink
+    let markdown = "See [the docs](https://example.com/docs) or <https://example.com>.";
+    let text = scanner.markdown_to_text(markdown);
+    
+    // Verify the link URL is kept, without repeating autolinks
+    assert_eq!(text, "See the docs (https://example.com/docs) or https://example.com.");
+}
+
+#[test]
+fn test_resolve_relative_links() {
+    // Verify parent and current directory segments resolve against the document directory
+    assert_eq!(resolve_link("guide/intro.md", "../api/index.md").as_deref(), Some("api/index.md"));
+    assert_eq!(resolve_link("guide/intro.md", "./setup.md").as_deref(), Some("guide/setup.md"));
+    assert_eq!(resolve_link("guide/intro.md", "setup.md#requirements").as_deref(), Some("guide/setup.md"));
+    assert_eq!(resolve_link("guide/intro.md", "/docs/faq.md").as_deref(), Some("docs/faq.md"));
+    assert_eq!(resolve_link("guide/intro.md", "getting%20started.md").as_deref(), Some("guide/getting started.md"));
+    
+    // Verify links that don't point to a repository file are ignored
+    assert_eq!(resolve_link("guide/intro.md", "https://example.com/api/index.md"), None);
+    assert_eq!(resolve_link("guide/intro.md", "mailto:docs@example.com"), None);
+    assert_eq!(resolve_link("guide/intro.md", "#usage"), None);
+    assert_eq!(resolve_link("guide/intro.md", "../../outside.md"), None);
+}
+
+#[test]
+fn test_extract_links() {
+    // Extract the links of a document with duplicate, external and anchor links
+    let content = "# Intro\n\nSee the [API](../api/index.md), the [setup](./setup.md#install) \
+        and the [API again](../api/index.md#top). Also [the site](https://example.com) and [below](#next).";
+    let links = extract_links("guide/intro.md", content);
+    
+    // Verify each linked document is listed once with its repository path
+    assert_eq!(links, vec!["api/index.md", "guide/setup.md"]);
+}
+
+/// Uppercases the title of the documents built by the default processor
+#[derive(Debug)]
+struct UppercaseTitles(DefaultProcessor);
+
+impl DocumentProcessor for UppercaseTitles {
+    fn process(&self, raw: &str, path: &str) -> anyhow::Result<Document> {
+        self.postprocess(self.0.process(raw, path)?)
+    }
+
+    fn postprocess(&self, mut document: Document) -> anyhow::Result<Document> {
+        document.title = document.title.to_uppercase();
+        Ok(document)
+    }
+}
+
+/// Expands `{% include intro %}` directives before documents are built
+#[derive(Debug)]
+struct ExpandIncludes;
+
+impl DocumentProcessor for ExpandIncludes {
+    fn process(&self, raw: &str, path: &str) -> anyhow::Result<Document> {
+        Ok(Document {
+            path: path.to_string(),
+            content: raw.replace("{% include intro %}", "# Introduction\n\nShared introduction."),
+            title: String::new(),
+            summary: None,
+        })
+    }
+}
+
+#[test]
+fn test_custom_document_processors() {
+    // Replace the default processor with one wrapping it
+    let mut scanner = DocumentScanner::new();
+    scanner.set_processors(vec![Arc::new(UppercaseTitles(scanner.default_processor()))]);
+    
+    // Verify it runs on the document built by the default processor
+    let doc = scanner.process_content("guide.md", "# Getting Started\n\nInstall the server.".to_string()).unwrap();
+    assert_eq!(doc.title, "GETTING STARTED");
+    assert_eq!(doc.summary.as_deref(), Some("Install the server."));
+    
+    // Verify processors placed first rewrite the raw content the next ones build from
+    scanner.set_processors(vec![
+        Arc::new(ExpandIncludes),
+        Arc::new(scanner.default_processor()),
+        Arc::new(UppercaseTitles(DefaultProcessor::default())),
+    ]);
+    let doc = scanner.process_content("intro.md", "{% include intro %}".to_string()).unwrap();
+    assert_eq!(doc.title, "INTRODUCTION");
+    assert_eq!(doc.content, "# Introduction\n\nShared introduction.");
+    
+    // Verify processors added to the default chain rework the document built by the default processor
+    let mut scanner = DocumentScanner::new();
+    scanner.add_processor(Arc::new(UppercaseTitles(DefaultProcessor::default())));
+    let notebook = r##"{"cells": [{"cell_type": "markdown", "source": ["# Data Analysis\n", "\n", "Exploring the sales dataset."]}], "metadata": {}, "nbformat": 4, "nbformat_minor": 5}"##;
+    let doc = scanner.process_content("analysis.ipynb", notebook.to_string()).unwrap();
+    assert_eq!(doc.title, "DATA ANALYSIS");
+    assert_eq!(doc.summary.as_deref(), Some("Exploring the sales dataset."));
+    assert!(doc.content.starts_with("# Data Analysis"));
+    
+    // Verify processors not overriding the rework build the document again from its content
+    let mut scanner = DocumentScanner::new();
+    scanner.add_processor(Arc::new(ExpandIncludes));
+    let doc = scanner.process_content("intro.md", "{% include intro %}".to_string()).unwrap();
+    assert_eq!(doc.content, "# Introduction\n\nShared introduction.");
+    assert_eq!(scanner.processors().len(), 2);
+    
+    // Verify documents without a title are titled like the default processor does
+    assert_eq!(scanner.fallback_title("getting-started/index.md"), "Getting Started");
+}
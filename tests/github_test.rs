@@ -0,0 +1,1451 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use kode_ai_rs::document::{Document, DocumentProcessor, ListedFile};
+use kode_ai_rs::error::KodeError;
+use kode_ai_rs::github::{Backoff, ConnectorOptions, GitHubConnector, ScanReport, Watcher, SCAN_REPORT_FILE};
+use kode_ai_rs::server::{Documents, GetAllDocsRequest};
+use kode_ai_rs::storage::DocumentStorage;
+use serde_json::json;
+use wiremock::matchers::{method, path, path_regex, query_param};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Create a connector pointing at the mock server
+async fn connector(server: &MockServer, request_timeout: Duration) -> GitHubConnector {
+    let options = ConnectorOptions {
+        request_timeout,
+        base_uri: Some(server.uri()),
+        ..ConnectorOptions::default()
+    };
+    GitHubConnector::with_options("owner", "repo", None, options).await.unwrap()
+}
+
+/// Build a contents API response for a file
+fn file_response(content: &str) -> serde_json::Value {
+    json!({
+        "type": "file",
+        "encoding": "base64",
+        "content": STANDARD.encode(content),
+    })
+}
+
+#[tokio::test]
+async fn test_request_timeout_aborts_slow_request() {
+    // Create a mock endpoint that never answers in time
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/slow.md"))
+        .respond_with(ResponseTemplate::new(200)
+            .set_body_json(file_response("# Slow"))
+            .set_delay(Duration::from_secs(30)))
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_millis(200)).await;
+    
+    // Fetch the file
+    let start = Instant::now();
+    let result = connector.get_file_contents("slow.md").await;
+    
+    // Verify the request was aborted and retried instead of hanging
+    assert!(result.is_err());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_retry_after_is_honored() {
+    // Create a mock endpoint that is rate limited once, then succeeds
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/guide.md"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/guide.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Guide")))
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Fetch the file
+    let start = Instant::now();
+    let content = connector.get_file_contents("guide.md").await.unwrap();
+    
+    // Verify the retry waited for the requested delay
+    assert_eq!(content, "# Guide");
+    assert!(start.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn test_exhausted_quota_is_retried_at_reset() {
+    // Create a mock endpoint over the primary rate limit once, which only tells when the quota resets
+    let server = MockServer::start().await;
+    let reset = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() + 1;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/guide.md"))
+        .respond_with(
+            ResponseTemplate::new(403)
+                .insert_header("x-ratelimit-remaining", "0")
+                .insert_header("x-ratelimit-reset", reset.to_string().as_str()),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/guide.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Guide")))
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Verify the request is retried once the quota resets, rather than failing as forbidden
+    let content = connector.get_file_contents("guide.md").await.unwrap();
+    assert_eq!(content, "# Guide");
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+#[test]
+fn test_retryable_errors() {
+    // Verify parse errors aren't retried, since the same content won't parse either
+    assert!(!KodeError::Parse("invalid JSON".to_string()).is_retryable());
+    assert!(KodeError::Timeout(Duration::from_secs(1)).is_retryable());
+    
+    // Verify a 403 is a rate limit when it says how long to wait, and a permission error otherwise
+    let error = KodeError::from_status(403, Some(Duration::from_secs(1)), "/repos/owner/repo");
+    assert!(matches!(error, KodeError::RateLimited { .. }) && error.is_retryable());
+    let error = KodeError::from_status(403, None, "/repos/owner/repo");
+    assert!(matches!(error, KodeError::AuthenticationFailed(_)) && !error.is_retryable());
+}
+
+#[test]
+fn test_backoff_delays_are_jittered() {
+    // Create the backoffs of several requests retrying at the same time
+    let mut backoffs: Vec<Backoff> = (0..8).map(Backoff::with_seed).collect();
+    
+    // Verify each delay stays under the exponential bound of its retry
+    let delays: Vec<Vec<Duration>> = backoffs
+        .iter_mut()
+        .map(|backoff| (0..3).map(|_| backoff.next_delay()).collect())
+        .collect();
+    for retry in delays.iter() {
+        for (attempt, delay) in retry.iter().enumerate() {
+            assert!(*delay <= Duration::from_millis(100 << attempt));
+        }
+    }
+    
+    // Verify the requests don't all wait the same delay before their first retry
+    let first: std::collections::HashSet<_> = delays.iter().map(|retry| retry[0]).collect();
+    assert!(first.len() > 1);
+    
+    // Verify a seed always gives the same delays
+    let mut replay = Backoff::with_seed(3);
+    assert_eq!((0..3).map(|_| replay.next_delay()).collect::<Vec<_>>(), delays[3]);
+}
+
+#[tokio::test]
+async fn test_failures_are_typed() {
+    // Create mock endpoints for a missing file and a file the token can't read
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/missing.md"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/private.md"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Verify a missing file is reported as such, without retrying
+    let error = connector.get_file_contents("missing.md").await.unwrap_err();
+    assert!(matches!(error, KodeError::NotFound(_)), "unexpected error: {:?}", error);
+    
+    // Verify rejected credentials are told apart
+    let error = connector.get_file_contents("private.md").await.unwrap_err();
+    assert!(matches!(error, KodeError::AuthenticationFailed(_)), "unexpected error: {:?}", error);
+    
+    // Verify invalid arguments are reported before any request
+    let result = GitHubConnector::new("", "repo", None).await;
+    assert!(matches!(result, Err(KodeError::InvalidArgument(_))));
+}
+
+#[tokio::test]
+async fn test_binary_file_fetched_once() {
+    // Create a mock endpoint for a binary file with a documentation extension
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/logo.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "type": "file",
+            "encoding": "base64",
+            "content": STANDARD.encode([0x89, b'P', b'N', b'G', 0, 0, 0, 0x0d]),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Verify the file is rejected without being fetched again
+    let error = connector.get_file_contents("logo.md").await.unwrap_err();
+    assert!(matches!(error, KodeError::BinaryContent(_)), "unexpected error: {:?}", error);
+}
+
+#[tokio::test]
+async fn test_binary_file_skipped() {
+    // Create a mock repository with a guide and a binary file with a text extension
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "truncated": false,
+            "tree": [
+                { "path": "docs/guide.md", "type": "blob" },
+                { "path": "docs/dump.txt", "type": "blob" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/guide.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Guide")))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/dump.txt"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "type": "file",
+            "encoding": "base64",
+            "content": STANDARD.encode([0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0]),
+        })))
+        .mount(&server)
+        .await;
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    let store = tokio::sync::RwLock::new(DocumentStorage::new_in_memory());
+    
+    // Scan the repository
+    let failures = connector.scan_into(&[], None, &store, |_| {}).await.unwrap();
+    
+    // Verify the binary file was skipped rather than failed, and the guide indexed
+    assert!(failures.is_empty());
+    let report = connector.scan_report().await.unwrap();
+    assert_eq!(report.files_indexed, 1);
+    assert!(report.failures.is_empty());
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].path, "docs/dump.txt");
+    assert_eq!(report.skipped[0].reason, "binary content");
+}
+
+#[tokio::test]
+async fn test_large_file_downloaded_raw() {
+    // Create a mock contents response for a file over the 1 MB limit, and its raw content
+    let server = MockServer::start().await;
+    let content = format!("# Reference\n\n{}", "A very long reference page.\n".repeat(40_000));
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/reference.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "type": "file",
+            "encoding": "none",
+            "content": "",
+            "size": content.len(),
+            "download_url": format!("{}/raw/owner/repo/HEAD/docs/reference.md", server.uri()),
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/raw/owner/repo/HEAD/docs/reference.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(content.clone()))
+        .expect(1)
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Fetch the file
+    let fetched = connector.get_file_contents("docs/reference.md").await.unwrap();
+    
+    // Verify the raw content was downloaded instead of failing
+    assert_eq!(fetched, content);
+}
+
+#[tokio::test]
+async fn test_large_binary_file_downloaded_raw_fetched_once() {
+    // Create a mock contents response for a binary file over the 1 MB limit, with a documentation extension
+    let server = MockServer::start().await;
+    let mut content = vec![0x89, b'P', b'N', b'G', 0, 0, 0, 0x0d];
+    content.extend(std::iter::repeat_n(0xff, 1_200_000));
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/diagram.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "type": "file",
+            "encoding": "none",
+            "content": "",
+            "size": content.len(),
+            "download_url": format!("{}/raw/owner/repo/HEAD/docs/diagram.md", server.uri()),
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/raw/owner/repo/HEAD/docs/diagram.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(content))
+        .expect(1)
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Verify the file is rejected as binary, like small files, without being fetched again
+    let error = connector.get_file_contents("docs/diagram.md").await.unwrap_err();
+    assert!(matches!(error, KodeError::BinaryContent(_)), "unexpected error: {:?}", error);
+}
+
+#[tokio::test]
+async fn test_file_cache_evicts_least_recently_used() {
+    // Create mock files, the first one expected to be fetched again once evicted
+    let server = MockServer::start().await;
+    for (name, expected) in [("a.md", 2), ("b.md", 1), ("c.md", 1)] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/{}", name)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response(name)))
+            .expect(expected)
+            .mount(&server)
+            .await;
+    }
+    
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        cache_capacity: 2,
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    
+    // Fetch more files than the cache holds
+    for name in ["a.md", "b.md", "c.md"] {
+        assert_eq!(connector.get_file_contents(name).await.unwrap(), name);
+    }
+    
+    // Verify the least recently used file was evicted and fetched again, the others still cached
+    assert_eq!(connector.get_file_contents("a.md").await.unwrap(), "a.md");
+    assert_eq!(connector.get_file_contents("c.md").await.unwrap(), "c.md");
+}
+
+#[tokio::test]
+async fn test_list_files_uses_git_tree() {
+    // Create a mock repository tree with nested documentation
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sha": "abc123",
+            "truncated": false,
+            "tree": [
+                { "path": "README.md", "type": "blob" },
+                { "path": "docs", "type": "tree" },
+                { "path": "docs/intro.md", "type": "blob" },
+                { "path": "docs/guides", "type": "tree" },
+                { "path": "docs/guides/setup.md", "type": "blob" },
+                { "path": "docs/logo.png", "type": "blob" },
+            ],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/intro.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Intro")))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/guides/setup.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Setup")))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/README.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Project")))
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Scan the docs folder
+    let result = connector.list_files(&["docs".to_string()], None).await.unwrap();
+    
+    // Verify only the supported files under the folder, and the root README, were fetched
+    let mut paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["README.md", "docs/guides/setup.md", "docs/intro.md"]);
+    assert!(result.failures.is_empty());
+    
+    // Verify no directory was listed through the contents API, nor the quota requested, the default branch lookup aside
+    let requests = server.received_requests().await.unwrap();
+    let requests: Vec<_> = requests
+        .iter()
+        .filter(|request| request.url.path() != "/repos/owner/repo")
+        .collect();
+    assert_eq!(requests.len(), 4);
+}
+
+#[tokio::test]
+async fn test_scan_into_stores_priority_files_first() {
+    // Create a mock repository with a getting started guide and a slow reference page
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sha": "abc123",
+            "truncated": false,
+            "tree": [
+                { "path": "docs/reference.md", "type": "blob" },
+                { "path": "docs/start.md", "type": "blob" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/reference.md"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(file_response("# Reference"))
+                .set_delay(Duration::from_secs(1)),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/start.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Getting Started")))
+        .mount(&server)
+        .await;
+    
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        priority: vec!["docs/start.md".to_string()],
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    let store = Arc::new(tokio::sync::RwLock::new(DocumentStorage::new_in_memory()));
+    
+    // Scan the repository in the background
+    let scan_store = store.clone();
+    let scan = tokio::spawn(async move { connector.scan_into(&[], None, &scan_store, |_| {}).await });
+    
+    // Verify the priority file is stored while the other one is still being fetched
+    tokio::time::timeout(Duration::from_millis(800), async {
+        while store.read().await.get_document("docs/start.md").is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert!(store.read().await.get_document("docs/reference.md").is_none());
+    
+    // Verify both files are stored once the scan completes
+    assert!(scan.await.unwrap().unwrap().is_empty());
+    assert!(store.read().await.get_document("docs/reference.md").is_some());
+}
+
+#[tokio::test]
+async fn test_scan_report_matches_stored_documents() {
+    // Create a mock repository with two guides, a broken notebook and a file that can't be fetched
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sha": "abc123",
+            "truncated": false,
+            "tree": [
+                { "path": "docs/install.md", "type": "blob" },
+                { "path": "docs/usage.md", "type": "blob" },
+                { "path": "docs/broken.ipynb", "type": "blob" },
+                { "path": "docs/missing.md", "type": "blob" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    for (file, content) in [("install.md", "# Install\n\nRun it."), ("usage.md", "# Usage\n"), ("broken.ipynb", "not a notebook")] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/docs/{}", file)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response(content)))
+            .mount(&server)
+            .await;
+    }
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    let store = tokio::sync::RwLock::new(DocumentStorage::new_in_memory());
+    
+    // No report is available before a scan
+    assert!(connector.scan_report().await.is_none());
+    
+    // Scan the repository
+    let failures = connector.scan_into(&[], None, &store, |_| {}).await.unwrap();
+    assert_eq!(failures.len(), 2);
+    
+    // Verify the report counts the stored documents, and tells skipped files from failed ones
+    let report = connector.scan_report().await.unwrap();
+    assert_eq!(report.owner, "owner");
+    assert_eq!(report.repo, "repo");
+    assert_eq!(report.git_ref, "HEAD");
+    assert_eq!(report.files_discovered, 4);
+    assert_eq!(report.files_indexed, store.read().await.get_all_documents().len());
+    assert_eq!(report.files_indexed, 2);
+    assert_eq!(report.total_bytes, ("# Install\n\nRun it.".len() + "# Usage\n".len()) as u64);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].path, "docs/broken.ipynb");
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].path, "docs/missing.md");
+    
+    // Verify the report is written as JSON to a directory, and read back
+    let temp_dir = tempfile::tempdir().unwrap();
+    report.save(temp_dir.path()).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(temp_dir.path().join(SCAN_REPORT_FILE)).unwrap()).unwrap();
+    assert_eq!(json["files_indexed"], 2);
+    assert_eq!(ScanReport::load(temp_dir.path()).unwrap(), report);
+}
+
+#[tokio::test]
+async fn test_validate_access_rejects_token_without_read_access() {
+    // Create a mock repository that the token is forbidden to read
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({ "message": "Resource not accessible by personal access token" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", Some("github_pat_test"), options.clone()).await.unwrap();
+    
+    // Verify the error tells which repository the token can't read, and why
+    let error = connector.validate_access().await.unwrap_err();
+    assert!(matches!(error, KodeError::AuthenticationFailed(_)));
+    assert!(error.to_string().contains("token cannot access owner/repo (403)"), "{}", error);
+    
+    // Verify anonymous connectors aren't checked
+    let anonymous = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    assert!(anonymous.validate_access().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_validate_access_retries_transient_failures() {
+    // Create a mock repository that is unavailable, then over the rate limit, then readable
+    let server = MockServer::start().await;
+    let reset = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo"))
+        .respond_with(ResponseTemplate::new(502))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo"))
+        .respond_with(
+            ResponseTemplate::new(403)
+                .insert_header("x-ratelimit-remaining", "0")
+                .insert_header("x-ratelimit-reset", reset.to_string().as_str()),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "default_branch": "main" })))
+        .mount(&server)
+        .await;
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", Some("github_pat_test"), options).await.unwrap();
+    
+    // Verify the access is confirmed once the failures pass, the rate limit not being taken for missing access
+    connector.validate_access().await.unwrap();
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_preview_scan_lists_files_without_fetching_them() {
+    // Create a mock repository tree with file sizes, and file contents that must not be fetched
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sha": "abc123",
+            "truncated": false,
+            "tree": [
+                { "path": "docs", "type": "tree" },
+                { "path": "docs/guide.md", "type": "blob", "size": 1200 },
+                { "path": "docs/logo.png", "type": "blob", "size": 5000 },
+                { "path": "docs/api.rst", "type": "blob", "size": 340 },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/guide.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Guide")))
+        .expect(0)
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Preview the scan of the whole repository
+    let preview = connector.preview_scan(&[], None).await;
+    
+    // Verify the supported files are listed with their sizes, and no content is fetched
+    assert!(preview.failures.is_empty());
+    assert_eq!(
+        preview.files,
+        vec![
+            ListedFile { path: "docs/guide.md".to_string(), size: 1200 },
+            ListedFile { path: "docs/api.rst".to_string(), size: 340 },
+        ]
+    );
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests.iter().all(|request| !request.url.path().contains("/contents/")));
+}
+
+#[tokio::test]
+async fn test_default_branch_is_the_scan_baseline() {
+    // Create a mock repository whose default branch is main, with its tree listed on that branch
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "name": "repo",
+            "default_branch": "main",
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/main"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sha": "abc123",
+            "truncated": false,
+            "tree": [{ "path": "docs/guide.md", "type": "blob" }],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/guide.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Guide")))
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Verify the default branch is detected
+    assert_eq!(connector.default_branch().await.unwrap(), "main");
+    assert_eq!(connector.baseline_ref().await, "main");
+    
+    // Verify the scan lists the tree of the default branch, reusing the detected branch
+    let result = connector.list_files(&[], None).await.unwrap();
+    assert!(result.failures.is_empty());
+    assert_eq!(result.documents.len(), 1);
+    assert_eq!(result.documents[0].path, "docs/guide.md");
+}
+
+#[tokio::test]
+async fn test_list_files_follows_directory_pages() {
+    // Create a mock repository without a tree, whose directory listing spans two pages
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "path": "docs/second.md", "type": "file" },
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs"))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("Link", format!("<{}/repos/owner/repo/contents/docs?page=2>; rel=\"next\"", server.uri()).as_str())
+            .set_body_json(json!([
+                { "path": "docs/first.md", "type": "file" },
+            ])))
+        .mount(&server)
+        .await;
+    for file in ["first.md", "second.md"] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/docs/{}", file)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Page")))
+            .mount(&server)
+            .await;
+    }
+    
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        include_root_readme: false,
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    
+    // Walk the docs directory
+    let result = connector.list_files(&["docs".to_string()], None).await.unwrap();
+    
+    // Verify the files of the second page were indexed too
+    let mut paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["docs/first.md", "docs/second.md"]);
+    assert!(result.failures.is_empty());
+}
+
+#[tokio::test]
+async fn test_list_files_max_depth() {
+    // Create a mock repository tree with a nested guide
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "truncated": false,
+            "tree": [
+                { "path": "docs/intro.md", "type": "blob" },
+                { "path": "docs/guides/setup.md", "type": "blob" },
+                { "path": "docs/guides/advanced/tuning.md", "type": "blob" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    for (file, content) in [("intro.md", "# Intro"), ("guides/setup.md", "# Setup"), ("guides/advanced/tuning.md", "# Tuning")] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/docs/{}", file)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response(content)))
+            .mount(&server)
+            .await;
+    }
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Scan the docs folder without subdirectories
+    let result = connector.list_files(&["docs".to_string()], Some(0)).await.unwrap();
+    let paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    assert_eq!(paths, vec!["docs/intro.md"]);
+    
+    // Scan the docs folder one level deep
+    let result = connector.list_files(&["docs".to_string()], Some(1)).await.unwrap();
+    let mut paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["docs/guides/setup.md", "docs/intro.md"]);
+}
+
+#[tokio::test]
+async fn test_list_files_multiple_subfolders() {
+    // Create a mock repository tree with documentation in several folders
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "truncated": false,
+            "tree": [
+                { "path": "README.md", "type": "blob" },
+                { "path": "docs/intro.md", "type": "blob" },
+                { "path": "docs/guides/setup.md", "type": "blob" },
+                { "path": "guides/usage.md", "type": "blob" },
+                { "path": "src/notes.md", "type": "blob" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    for (file, content) in [("docs/intro.md", "# Intro"), ("docs/guides/setup.md", "# Setup"), ("guides/usage.md", "# Usage")] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/{}", file)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response(content)))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Scan two distinct folders, and a folder nested in one of them
+    let roots = vec!["docs".to_string(), "./guides/".to_string(), "docs/guides".to_string()];
+    let result = connector.list_files(&roots, None).await.unwrap();
+    
+    // Verify the files of both folders were fetched, each of them once
+    let mut paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["docs/guides/setup.md", "docs/intro.md", "guides/usage.md"]);
+}
+
+#[tokio::test]
+async fn test_list_files_rewrites_paths() {
+    // Create a mock monorepo tree with documentation in a package
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "truncated": false,
+            "tree": [
+                { "path": "packages/foo/docs/intro.md", "type": "blob" },
+                { "path": "packages/foo/docs/guides/setup.md", "type": "blob" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    for (file, content) in [("intro.md", "# Intro"), ("guides/setup.md", "# Setup")] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/packages/foo/docs/{}", file)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response(content)))
+            .mount(&server)
+            .await;
+    }
+    
+    // Scan the package documentation, stripping the package path
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        strip_prefix: Some("packages/foo/docs/".to_string()),
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    let result = connector.list_files(&["packages/foo/docs".to_string()], None).await.unwrap();
+    
+    // Verify the documents are stored without the prefix
+    let mut paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["guides/setup.md", "intro.md"]);
+    
+    // Scan it again, namespacing the stripped paths
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        strip_prefix: Some("packages/foo/docs".to_string()),
+        path_prefix: Some("foo".to_string()),
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    let result = connector.list_files(&["packages/foo/docs".to_string()], None).await.unwrap();
+    
+    // Verify the documents are stored under the namespace
+    let mut paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["foo/guides/setup.md", "foo/intro.md"]);
+}
+
+/// Titles documents after their path, in uppercase
+#[derive(Debug)]
+struct PathTitles;
+
+impl DocumentProcessor for PathTitles {
+    fn process(&self, raw: &str, path: &str) -> anyhow::Result<Document> {
+        Ok(Document {
+            path: path.to_string(),
+            content: raw.to_string(),
+            title: path.to_uppercase(),
+            summary: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_list_files_runs_custom_processors() {
+    // Create a mock repository with a single guide
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "truncated": false,
+            "tree": [{ "path": "docs/intro.md", "type": "blob" }],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/intro.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Intro")))
+        .mount(&server)
+        .await;
+    
+    // Scan it with a custom processor replacing the default one
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        include_root_readme: false,
+        processors: vec![Arc::new(PathTitles)],
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    let result = connector.list_files(&["docs".to_string()], None).await.unwrap();
+    
+    // Verify the document was built by the custom processor
+    assert_eq!(result.documents.len(), 1);
+    assert_eq!(result.documents[0].title, "DOCS/INTRO.MD");
+    assert_eq!(result.documents[0].content, "# Intro");
+}
+
+#[tokio::test]
+async fn test_list_files_includes_root_readme() {
+    // Create a mock repository tree with a root README and READMEs in other folders
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "truncated": false,
+            "tree": [
+                { "path": "README.rst", "type": "blob" },
+                { "path": "CHANGELOG.md", "type": "blob" },
+                { "path": "docs/intro.md", "type": "blob" },
+                { "path": "examples/README.md", "type": "blob" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    for (file, content) in [("README.rst", "Project\n======="), ("docs/intro.md", "# Intro")] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/{}", file)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response(content)))
+            .mount(&server)
+            .await;
+    }
+    
+    // Scan the docs folder
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    let result = connector.list_files(&["docs".to_string()], None).await.unwrap();
+    
+    // Verify the root README was indexed too, but not other root files or READMEs
+    let mut paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["README.rst", "docs/intro.md"]);
+    
+    // Verify the root README is left out when disabled
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        include_root_readme: false,
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    let result = connector.list_files(&["docs".to_string()], None).await.unwrap();
+    let paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    assert_eq!(paths, vec!["docs/intro.md"]);
+}
+
+#[tokio::test]
+async fn test_list_files_reports_progress() {
+    // Create a mock repository tree with twenty files
+    let server = MockServer::start().await;
+    let files: Vec<String> = (0..20).map(|i| format!("docs/page{}.md", i)).collect();
+    let tree: Vec<_> = files.iter().map(|file| json!({ "path": file, "type": "blob" })).collect();
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "truncated": false, "tree": tree })))
+        .mount(&server)
+        .await;
+    for file in &files {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/{}", file)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Page")))
+            .mount(&server)
+            .await;
+    }
+    
+    for max_concurrent_requests in [1, 3, 5] {
+        let options = ConnectorOptions {
+            base_uri: Some(server.uri()),
+            max_concurrent_requests,
+            ..ConnectorOptions::default()
+        };
+        let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+        
+        // Scan the docs folder, recording progress reports
+        let reports = Mutex::new(Vec::new());
+        let result = connector
+            .list_files_with_progress(&["docs".to_string()], None, |progress| reports.lock().unwrap().push(progress))
+            .await
+            .unwrap();
+        assert_eq!(result.documents.len(), 20);
+        
+        // Verify progress was reported every tenth of the files, whatever the number of requests in flight
+        let reports = reports.into_inner().unwrap();
+        let fetched: Vec<_> = reports.iter().map(|progress| progress.fetched).collect();
+        assert_eq!(fetched, vec![2, 4, 6, 8, 10, 12, 14, 16, 18, 20]);
+        assert!(reports.iter().all(|progress| progress.total == 20));
+    }
+}
+
+/// Answers file requests after a delay depending on the file, recording when each one arrived
+struct DelayedFiles {
+    delays: HashMap<String, Duration>,
+    arrivals: Arc<Mutex<Vec<(String, Instant)>>>,
+}
+
+impl Respond for DelayedFiles {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let file = request.url.path().trim_start_matches("/repos/owner/repo/contents/").to_string();
+        let delay = self.delays[&file];
+        self.arrivals.lock().unwrap().push((file, Instant::now()));
+        ResponseTemplate::new(200).set_body_json(file_response("# Page")).set_delay(delay)
+    }
+}
+
+#[tokio::test]
+async fn test_list_files_keeps_requests_in_flight() {
+    // Create a mock repository tree with two slow files among fast ones
+    let server = MockServer::start().await;
+    let files: Vec<String> = (0..6).map(|i| format!("docs/page{}.md", i)).collect();
+    let tree: Vec<_> = files.iter().map(|file| json!({ "path": file, "type": "blob" })).collect();
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "truncated": false, "tree": tree })))
+        .mount(&server)
+        .await;
+    let delays: HashMap<String, Duration> = files
+        .iter()
+        .enumerate()
+        .map(|(i, file)| {
+            let delay = if i == 0 || i == 3 { Duration::from_millis(1000) } else { Duration::from_millis(100) };
+            (file.clone(), delay)
+        })
+        .collect();
+    let arrivals = Arc::new(Mutex::new(Vec::new()));
+    Mock::given(method("GET"))
+        .and(path_regex("^/repos/owner/repo/contents/"))
+        .respond_with(DelayedFiles { delays: delays.clone(), arrivals: arrivals.clone() })
+        .mount(&server)
+        .await;
+    
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        max_concurrent_requests: 2,
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    
+    // Scan the docs folder, two files at a time
+    let result = connector.list_files(&["docs".to_string()], None).await.unwrap();
+    
+    // Verify the files are listed in tree order, whatever order they completed in
+    let paths: Vec<&str> = result.documents.iter().map(|doc| doc.path.as_str()).collect();
+    assert_eq!(paths, files.iter().map(String::as_str).collect::<Vec<_>>());
+    
+    // Count the requests in flight when each one arrived: a request is in flight until its delay is over,
+    // and the next one only starts once a response was received
+    let arrivals = arrivals.lock().unwrap();
+    assert_eq!(arrivals.len(), 6);
+    let in_flight_at = |at: Instant| {
+        arrivals
+            .iter()
+            .filter(|(file, arrived)| *arrived <= at && at < *arrived + delays[file])
+            .count()
+    };
+    let max_in_flight = arrivals.iter().map(|(_, arrived)| in_flight_at(*arrived)).max().unwrap();
+    
+    // Verify requests were kept in flight up to the limit, and never more
+    assert_eq!(max_in_flight, 2);
+    
+    // Verify fast files were fetched alongside the first slow one, instead of waiting for it
+    // with the file it was fetched with
+    let (slow_file, slow_arrived) = arrivals.iter().find(|(file, _)| file == "docs/page0.md").unwrap();
+    let started_alongside = arrivals
+        .iter()
+        .filter(|(file, arrived)| file != slow_file && *arrived < *slow_arrived + delays[slow_file])
+        .count();
+    assert!(started_alongside >= 3, "{} files started while the slow one was fetched", started_alongside);
+}
+
+#[tokio::test]
+async fn test_list_changed_files() {
+    // Create a mock comparison with added, modified, renamed, removed and unsupported files
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/compare/v1.0...main"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "status": "ahead",
+            "files": [
+                { "filename": "docs/new.md", "status": "added" },
+                { "filename": "docs/intro.md", "status": "modified" },
+                { "filename": "docs/guide.md", "status": "renamed", "previous_filename": "docs/old-guide.md" },
+                { "filename": "docs/obsolete.md", "status": "removed" },
+                { "filename": "docs/api/v1/deep.md", "status": "added" },
+                { "filename": "docs/api/v1/gone.md", "status": "removed" },
+                { "filename": "src/main.rs", "status": "modified" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    for (file, content) in [("new.md", "# New"), ("intro.md", "# Intro"), ("guide.md", "# Guide")] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/docs/{}", file)))
+            .and(query_param("ref", "main"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response(content)))
+            .mount(&server)
+            .await;
+    }
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // List the changes between the two refs, at most one directory below the repository root
+    let changes = connector.list_changed_files("v1.0", "main", &[], Some(1)).await.unwrap();
+    
+    // Verify only the changed documents were fetched, at the head ref
+    let mut paths: Vec<&str> = changes.documents.iter().map(|doc| doc.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["docs/guide.md", "docs/intro.md", "docs/new.md"]);
+    assert!(changes.failures.is_empty());
+    
+    // Verify removed files and the old paths of renamed files are reported
+    let mut removed = changes.removed.clone();
+    removed.sort();
+    assert_eq!(removed, vec!["docs/obsolete.md", "docs/old-guide.md"]);
+    
+    // Verify files deeper than the maximum depth are left out, like a full scan does
+    assert!(!changes.documents.iter().any(|doc| doc.path.starts_with("docs/api/")));
+    assert!(!changes.removed.iter().any(|path| path.starts_with("docs/api/")));
+}
+
+#[tokio::test]
+async fn test_watcher_updates_store_when_head_moves() {
+    // Create a mock repository whose head moves after the first poll, adding a document
+    let server = MockServer::start().await;
+    mount_moving_head(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/compare/abc123...def456"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "files": [{ "filename": "docs/new.md", "status": "added" }],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/new.md"))
+        .and(query_param("ref", "def456"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# New\n\nA new guide.")))
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    let store = Arc::new(tokio::sync::RwLock::new(DocumentStorage::new_in_memory()));
+    let mut watcher = Watcher::new(connector, store.clone(), &[], None, None);
+    
+    // Verify the first poll only records the head
+    assert!(!watcher.poll().await.unwrap());
+    assert_eq!(watcher.head(), Some("abc123"));
+    assert!(store.read().await.get_all_documents().is_empty());
+    
+    // Verify the next poll sees the new head and indexes the changed file
+    assert!(watcher.poll().await.unwrap());
+    assert_eq!(watcher.head(), Some("def456"));
+    assert_eq!(store.read().await.get_document("docs/new.md").unwrap().title, "New");
+    
+    // Verify polling again without changes doesn't compare anything
+    assert!(!watcher.poll().await.unwrap());
+}
+
+/// Mount the commit list of a mock repository, whose head is `abc123` once, then `def456`
+async fn mount_moving_head(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{ "sha": "abc123", "commit": {} }])))
+        .up_to_n_times(1)
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{ "sha": "def456", "commit": {} }])))
+        .mount(server)
+        .await;
+}
+
+/// Mount a mock repository with a guide and a page at `abc123`, the guide being
+/// changed and the page deleted at `def456`, whose changes can't be compared
+async fn mount_uncomparable_changes(server: &MockServer) {
+    mount_moving_head(server).await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/compare/abc123...def456"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(server)
+        .await;
+    let both = json!({ "truncated": false, "tree": [
+        { "path": "docs/guide.md", "type": "blob" },
+        { "path": "docs/page.md", "type": "blob" },
+    ] });
+    for tree in ["HEAD", "abc123"] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/git/trees/{}", tree)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(both.clone()))
+            .mount(server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/def456"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "truncated": false, "tree": [
+            { "path": "docs/guide.md", "type": "blob" },
+        ] })))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/guide.md"))
+        .and(query_param("ref", "def456"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Updated guide")))
+        .mount(server)
+        .await;
+    for (file, content) in [("guide.md", "# Guide"), ("page.md", "# Page")] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/repo/contents/docs/{}", file)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(file_response(content)))
+            .mount(server)
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn test_watcher_rescans_uncomparable_changes() {
+    // Create a mock repository whose changes can't be compared, scanned once, next to a document from elsewhere
+    let server = MockServer::start().await;
+    mount_uncomparable_changes(&server).await;
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    let store = Arc::new(tokio::sync::RwLock::new(DocumentStorage::new_in_memory()));
+    connector.scan_into(&[], None, &store, |_| {}).await.unwrap();
+    store.write().await.store_document(Document {
+        path: "notes/local.md".to_string(),
+        content: "# Local notes".to_string(),
+        title: "Local notes".to_string(),
+        summary: None,
+    }).unwrap();
+    assert_eq!(store.read().await.get_document("docs/guide.md").unwrap().title, "Guide");
+    let mut watcher = Watcher::new(connector, store.clone(), &[], None, None);
+    
+    // Poll the repository until its head moves
+    assert!(!watcher.poll().await.unwrap());
+    assert!(watcher.poll().await.unwrap());
+    
+    // Verify the changed file was fetched again at the new head rather than from the cache
+    assert_eq!(store.read().await.get_document("docs/guide.md").unwrap().title, "Updated guide");
+    
+    // Verify the deleted file was removed, and the document from elsewhere kept
+    assert!(store.read().await.get_document("docs/page.md").is_none());
+    assert!(store.read().await.get_document("notes/local.md").is_some());
+    assert_eq!(watcher.head(), Some("def456"));
+}
+
+#[tokio::test]
+async fn test_stale_index_rescans_uncomparable_changes() {
+    // Create a mock repository whose changes can't be compared, scanned once, with its head recorded
+    let server = MockServer::start().await;
+    mount_uncomparable_changes(&server).await;
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    let store = Arc::new(tokio::sync::RwLock::new(DocumentStorage::new_in_memory()));
+    connector.scan_into(&[], None, &store, |_| {}).await.unwrap();
+    let mut watcher = Watcher::new(connector, store.clone(), &[], None, None);
+    assert!(!watcher.poll().await.unwrap());
+    
+    // Serve the index, rescanning it with the watcher once stale, as the server does
+    let watcher = Arc::new(tokio::sync::Mutex::new(watcher));
+    let documents = Documents::new(store.clone()).with_stale_ttl(Duration::from_millis(50), move || {
+        let watcher = watcher.clone();
+        async move {
+            watcher.lock().await.poll().await.unwrap();
+        }
+    });
+    
+    // Query the index once it's stale, and wait for the background rescan
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: None, sort_by: None, sort_order: None };
+    documents.get_all_docs(request).await.unwrap();
+    let start = Instant::now();
+    while store.read().await.get_document("docs/page.md").is_some() && start.elapsed() < Duration::from_secs(5) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    
+    // Verify the rescan fetched the changed file at the new head, and removed the deleted one
+    assert_eq!(store.read().await.get_document("docs/guide.md").unwrap().title, "Updated guide");
+    assert!(store.read().await.get_document("docs/page.md").is_none());
+}
+
+#[tokio::test]
+async fn test_rate_limit_status() {
+    // Create a mock rate limit response
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/rate_limit"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "resources": {
+                "core": { "limit": 5000, "remaining": 4321, "reset": 1750000000, "used": 679 },
+                "search": { "limit": 30, "remaining": 30, "reset": 1750000060, "used": 0 },
+            },
+            "rate": { "limit": 5000, "remaining": 4321, "reset": 1750000000, "used": 679 },
+        })))
+        .mount(&server)
+        .await;
+    
+    let connector = connector(&server, Duration::from_secs(5)).await;
+    
+    // Get the quota
+    let status = connector.rate_limit_status().await.unwrap();
+    
+    // Verify the core API quota was parsed
+    assert_eq!(status.limit, 5000);
+    assert_eq!(status.remaining, 4321);
+    assert_eq!(status.reset, 1750000000);
+    assert_eq!(status.seconds_until_reset(), 0);
+}
+
+#[tokio::test]
+async fn test_list_files_tracks_last_modified() {
+    // Create a mock repository with a document and the last commit changing it
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "truncated": false,
+            "tree": [
+                { "path": "docs/intro.md", "type": "blob" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/intro.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(file_response("# Intro")))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/commits"))
+        .and(query_param("path", "docs/intro.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "sha": "abc123",
+                "commit": {
+                    "author": { "name": "Author", "date": "2024-04-30T08:00:00Z" },
+                    "committer": { "name": "Committer", "date": "2024-05-01T12:00:00Z" },
+                },
+            },
+        ])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    
+    // Scan the docs folder, fetching the last commit of each file
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        track_last_modified: true,
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    let result = connector.list_files(&["docs".to_string()], None).await.unwrap();
+    
+    // Verify the committer date was recorded for the document
+    assert_eq!(result.documents.len(), 1);
+    assert_eq!(result.last_modified.get("docs/intro.md"), Some(&1714564800));
+}
+
+#[tokio::test]
+async fn test_list_releases() {
+    // Create a mock releases endpoint with a named release, a release only
+    // identified by its tag, a draft and a release without notes
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            { "tag_name": "v2.0.0", "name": "Version 2", "body": "## Breaking changes\n\nThe frobnicate option was removed.", "draft": false },
+            { "tag_name": "v1.1.0", "name": "", "body": "Added the widget exporter.", "draft": false },
+            { "tag_name": "v3.0.0", "name": "Next", "body": "Unreleased notes.", "draft": true },
+            { "tag_name": "v1.0.0", "name": "First", "body": null, "draft": false },
+        ])))
+        .mount(&server)
+        .await;
+    
+    // Fetch the release notes
+    let releases = connector(&server, Duration::from_secs(5)).await.list_releases().await.unwrap();
+    
+    // Verify published releases with notes are stored under the releases directory, titled by name or tag
+    let listed: Vec<(&str, &str)> = releases.iter().map(|release| (release.path.as_str(), release.title.as_str())).collect();
+    assert_eq!(listed, vec![("releases/v2.0.0.md", "Version 2"), ("releases/v1.1.0.md", "v1.1.0")]);
+    
+    // Verify the release notes are searchable once stored
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut store = DocumentStorage::new(temp_dir.path()).unwrap();
+    store.store_documents(releases).unwrap();
+    let found = store.find_relevant_documents("frobnicate");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path, "releases/v2.0.0.md");
+    
+    // Verify a repository without releases has none instead of failing
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+    let releases = connector(&server, Duration::from_secs(5)).await.list_releases().await.unwrap();
+    assert!(releases.is_empty());
+}
+
+/// Run a git command in a directory, failing the test if it fails
+fn git(directory: &std::path::Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(directory)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[tokio::test]
+async fn test_list_wiki_pages() {
+    // Create a mock wiki repository with two pages and a non-documentation file
+    let wikis = tempfile::tempdir().unwrap();
+    let wiki = wikis.path().join("owner").join("repo.wiki.git");
+    std::fs::create_dir_all(&wiki).unwrap();
+    std::fs::write(wiki.join("Home.md"), "# Home\n\nWelcome to the wiki.").unwrap();
+    std::fs::write(wiki.join("Setup-Guide.md"), "# Setup guide\n\nInstall the server.").unwrap();
+    std::fs::write(wiki.join("logo.png"), [0u8; 4]).unwrap();
+    git(&wiki, &["init", "--quiet"]);
+    git(&wiki, &["add", "."]);
+    git(&wiki, &["commit", "--quiet", "-m", "Add pages"]);
+    
+    // Fetch the wiki pages
+    let server = MockServer::start().await;
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        wiki_base_uri: Some(wikis.path().to_string_lossy().to_string()),
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options.clone()).await.unwrap();
+    let pages = connector.list_wiki_pages().await.unwrap();
+    
+    // Verify the pages are stored under the wiki directory
+    let mut paths: Vec<&str> = pages.iter().map(|page| page.path.as_str()).collect();
+    paths.sort();
+    assert_eq!(paths, vec!["wiki/Home.md", "wiki/Setup-Guide.md"]);
+    assert!(pages.iter().any(|page| page.title == "Setup guide"));
+    
+    // Verify a repository without a wiki has no pages instead of failing
+    let connector = GitHubConnector::with_options("owner", "other", None, options).await.unwrap();
+    assert!(connector.list_wiki_pages().await.unwrap().is_empty());
+    
+    // Serve a wiki asking for credentials, as GitHub does for a token without access to it
+    Mock::given(method("GET"))
+        .and(path("/owner/private.wiki.git/info/refs"))
+        .respond_with(ResponseTemplate::new(401).insert_header("WWW-Authenticate", "Basic realm=\"GitHub\""))
+        .mount(&server)
+        .await;
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        wiki_base_uri: Some(server.uri()),
+        ..ConnectorOptions::default()
+    };
+    
+    // Verify the authentication failure is raised instead of being taken for a missing wiki
+    for token in [None, Some("invalid-token")] {
+        let connector = GitHubConnector::with_options("owner", "private", token, options.clone()).await.unwrap();
+        let error = connector.list_wiki_pages().await.unwrap_err();
+        assert!(matches!(error, KodeError::AuthenticationFailed(_)), "{}", error);
+    }
+}
@@ -0,0 +1,1245 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use kode_ai_rs::document::{Document, DocumentProcessor, DocumentScanner};
+use kode_ai_rs::github::{ConnectorOptions, GitHubConnector};
+use kode_ai_rs::server::{AddDocumentRequest, AutocompleteQueryRequest, DiffDocumentsRequest, Documents, EXPORTS_DIRECTORY, ExplainRelevanceRequest, ExportIndexRequest, FindRelevantDocsRequest, GetAllDocsRequest, GetDocumentRequest, GetDocumentsRequest, GetStaleDocumentsRequest, GetTableOfContentsRequest, ListTopicsRequest, LookupTermRequest, NotReadyPolicy, RepositoryInfo, SearchInDocumentRequest, ServerStatus, SummarizeDocumentRequest, TRUNCATION_MARKER, UnlockInternalDocumentsRequest};
+use kode_ai_rs::storage::DocumentStorage;
+use rmcp::model::{ArgumentInfo, CompleteRequestParam, Reference, ResourceContents, ResourceReference};
+use tokio::sync::RwLock;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Create an empty document store
+fn store(temp_dir: &tempfile::TempDir) -> Arc<RwLock<DocumentStorage>> {
+    Arc::new(RwLock::new(DocumentStorage::new(temp_dir.path()).unwrap()))
+}
+
+/// Get the JSON payload of a tool result
+fn payload(result: rmcp::model::CallToolResult) -> serde_json::Value {
+    let text = result.content[0].as_text().unwrap().text.clone();
+    serde_json::from_str(&text).unwrap()
+}
+
+#[tokio::test]
+async fn test_ready_transition() {
+    // Create a server whose initial scan is still running
+    let temp_dir = tempfile::tempdir().unwrap();
+    let documents = Documents::initializing(store(&temp_dir), NotReadyPolicy::Reject);
+    
+    // Verify the server reports it is initializing and rejects searches
+    let health = payload(documents.health().await.unwrap());
+    assert_eq!(health["status"], "initializing");
+    assert!(health["last_scan_at"].is_null());
+    assert!(documents.ensure_ready().await.is_err());
+    
+    // Verify exports and stale document listings are rejected too, rather than using the documents scanned so far
+    let request = ExportIndexRequest { format: "jsonl".to_string(), path: None };
+    assert_eq!(documents.export_index(request).await.unwrap_err().message, "index_not_ready");
+    let request = GetStaleDocumentsRequest { older_than_seconds: 0, limit: 0 };
+    assert_eq!(documents.get_stale_documents(request).await.unwrap_err().message, "index_not_ready");
+    
+    // Complete the scan
+    documents.set_status(ServerStatus::Ready);
+    
+    // Verify the server reports it is ready, with the scan time, and accepts searches
+    let health = payload(documents.health().await.unwrap());
+    assert_eq!(health["status"], "ready");
+    assert_eq!(health["documents"], 0);
+    assert!(health["last_scan_at"].is_u64());
+    assert!(documents.ensure_ready().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_wait_policy_blocks_until_ready() {
+    // Create a server whose initial scan is still running
+    let temp_dir = tempfile::tempdir().unwrap();
+    let documents = Documents::initializing(store(&temp_dir), NotReadyPolicy::Wait);
+    
+    // Start a search while the scan runs
+    let waiting = tokio::spawn({
+        let documents = documents.clone();
+        async move { documents.ensure_ready().await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!waiting.is_finished());
+    
+    // Verify the search proceeds once the scan fails, reporting the error
+    documents.set_status(ServerStatus::Error("rate limited".to_string()));
+    assert!(waiting.await.unwrap().is_ok());
+    
+    let health = payload(documents.health().await.unwrap());
+    assert_eq!(health["status"], "error");
+    assert_eq!(health["error"], "rate limited");
+    assert!(health["last_scan_at"].is_null());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_export_index_to_file() {
+    // Create a server with a stored document
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_document(Document {
+        path: "intro.md".to_string(),
+        content: "# Intro\n\nWelcome.".to_string(),
+        title: "Intro".to_string(),
+        summary: None,
+    }).unwrap();
+    let documents = Documents::new(store);
+    
+    // Export the index to a file
+    let request = ExportIndexRequest {
+        format: "jsonl".to_string(),
+        path: Some("exports/docs.jsonl".to_string()),
+    };
+    let response = payload(documents.export_index(request).await.unwrap());
+    
+    // Verify the export was written to the exports directory of the storage instead of returned
+    let export_path = temp_dir.path().join(EXPORTS_DIRECTORY).join("exports/docs.jsonl");
+    assert_eq!(response["documents"], 1);
+    assert_eq!(response["path"], export_path.to_string_lossy().as_ref());
+    let export = std::fs::read_to_string(&export_path).unwrap();
+    assert_eq!(export.lines().count(), 1);
+    assert_eq!(response["bytes"], export.len());
+    
+    // Verify exports can't be written anywhere else, the index in particular
+    let outside = temp_dir.path().join("outside.jsonl");
+    std::os::unix::fs::symlink(temp_dir.path(), temp_dir.path().join(EXPORTS_DIRECTORY).join("storage")).unwrap();
+    for path in [outside.to_string_lossy().to_string(), "../documents.json".to_string(), "storage/documents.json".to_string()] {
+        let request = ExportIndexRequest {
+            format: "jsonl".to_string(),
+            path: Some(path.clone()),
+        };
+        let error = documents.export_index(request).await.unwrap_err();
+        assert_eq!(error.message, "invalid_path", "{}", path);
+    }
+    assert!(!outside.exists());
+    assert!(DocumentStorage::new(temp_dir.path()).unwrap().get_document("intro.md").is_some());
+    
+    // Verify unknown formats are rejected
+    let request = ExportIndexRequest {
+        format: "csv".to_string(),
+        path: None,
+    };
+    assert!(documents.export_index(request).await.is_err());
+}
+
+/// Build a search request with default options
+fn search_request(query: &str, min_score: Option<f64>) -> FindRelevantDocsRequest {
+    FindRelevantDocsRequest {
+        query: query.to_string(),
+        limit: 0,
+        include_full_content: false,
+        mark_terms: false,
+        with_highlights: false,
+        language: None,
+        min_score,
+        case_sensitive: false,
+        whole_word: false,
+        path_prefix: None,
+        max_tokens: None,
+    }
+}
+
+#[tokio::test]
+async fn test_find_relevant_docs_min_score() {
+    // Create a server with a relevant document and one barely matching the query
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "deploy.md".to_string(),
+            content: "# Deploying the server\n\nBuild a container image and deploy the server.".to_string(),
+            title: "Deploying the server".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "changelog.md".to_string(),
+            content: "# Changelog\n\nFixed a crash in the server logs.".to_string(),
+            title: "Changelog".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store);
+    
+    // Verify the barely matching document is left out by default
+    let response = payload(documents.find_relevant_docs(search_request("deploy server container", None)).await.unwrap());
+    let paths: Vec<&str> = response["documents"].as_array().unwrap().iter().map(|doc| doc["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, vec!["deploy.md"]);
+    
+    // Verify it is left out at a reasonable threshold too
+    let response = payload(documents.find_relevant_docs(search_request("deploy server container", Some(0.3))).await.unwrap());
+    assert_eq!(response["total"], 1);
+    
+    // Verify it is included without a threshold
+    let response = payload(documents.find_relevant_docs(search_request("deploy server container", Some(0.0))).await.unwrap());
+    let paths: Vec<&str> = response["documents"].as_array().unwrap().iter().map(|doc| doc["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, vec!["deploy.md", "changelog.md"]);
+}
+
+#[tokio::test]
+async fn test_find_relevant_docs_path_prefix() {
+    // Create a server with authentication docs in the API reference and the guides
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "api/auth.md".to_string(),
+            content: "# Authentication endpoint\n\nExchange a token for a session.".to_string(),
+            title: "Authentication endpoint".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "guides/auth.md".to_string(),
+            content: "# Authentication guide\n\nSign in with a token from the dashboard.".to_string(),
+            title: "Authentication guide".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store);
+    
+    // Search the API reference only
+    let request = FindRelevantDocsRequest {
+        path_prefix: Some("api/".to_string()),
+        ..search_request("authentication token", None)
+    };
+    let response = payload(documents.find_relevant_docs(request).await.unwrap());
+    
+    // Verify the matching guide is left out
+    let paths: Vec<&str> = response["documents"].as_array().unwrap().iter().map(|doc| doc["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, vec!["api/auth.md"]);
+    
+    // Verify listing is scoped the same way, and no prefix searches everything
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: Some("guides/".to_string()), sort_by: None, sort_order: None };
+    let response = payload(documents.get_all_docs(request).await.unwrap());
+    assert_eq!(response["documents"][0]["path"], "guides/auth.md");
+    assert_eq!(response["total"], 1);
+    let response = payload(documents.find_relevant_docs(search_request("authentication token", None)).await.unwrap());
+    assert_eq!(response["total"], 2);
+}
+
+#[tokio::test]
+async fn test_find_relevant_docs_max_tokens() {
+    // Create a server with a short deployment note and a long deployment guide
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "deploy.md".to_string(),
+            content: "# Deploying\n\nDeploy the server with a container.".to_string(),
+            title: "Deploying".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "guide.md".to_string(),
+            content: format!("# Deployment guide\n\n{}", "Deploy the server on every node of the cluster. ".repeat(100)),
+            title: "Deployment guide".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store);
+    
+    // Verify word and token counts are listed with the documents
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: None, sort_by: None, sort_order: None };
+    let response = payload(documents.get_all_docs(request).await.unwrap());
+    assert_eq!(response["documents"][0]["path"], "deploy.md");
+    assert_eq!(response["documents"][0]["word_count"], 8);
+    assert_eq!(response["documents"][0]["token_count"], 12);
+    assert_eq!(response["documents"][1]["word_count"], 903);
+    assert_eq!(response["documents"][1]["token_count"], 1205);
+    
+    // Verify both documents are found without a budget
+    let response = payload(documents.find_relevant_docs(search_request("deploy server", Some(0.0))).await.unwrap());
+    assert_eq!(response["total"], 2);
+    assert!(response["documents"].as_array().unwrap().iter().all(|doc| doc["token_count"].as_u64().unwrap() > 0));
+    
+    // Verify the guide is left out of a small budget
+    let request = FindRelevantDocsRequest {
+        max_tokens: Some(100),
+        ..search_request("deploy server", Some(0.0))
+    };
+    let response = payload(documents.find_relevant_docs(request).await.unwrap());
+    let paths: Vec<&str> = response["documents"].as_array().unwrap().iter().map(|doc| doc["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, vec!["deploy.md"]);
+}
+
+#[tokio::test]
+async fn test_document_mime_type() {
+    // Create a server with a reStructuredText guide and a file of unknown format
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "docs/install.rst".to_string(),
+            content: "Installation\n============\n\nRun the installer.".to_string(),
+            title: "Installation".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "docs/NOTICE".to_string(),
+            content: "Third party installer notices.".to_string(),
+            title: "NOTICE".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store);
+    
+    // Verify the reStructuredText MIME type is reported by get_document
+    let request = GetDocumentRequest { path: "docs/install.rst".to_string(), offset: None, format: None };
+    let response = payload(documents.get_document(request).await.unwrap());
+    assert_eq!(response["format"], "restructuredtext");
+    assert_eq!(response["mime_type"], "text/x-rst");
+    
+    // Verify search results report it too, and unknown formats default to plain text
+    let response = payload(documents.find_relevant_docs(search_request("installer", Some(0.0))).await.unwrap());
+    for doc in response["documents"].as_array().unwrap() {
+        let expected = if doc["path"] == "docs/install.rst" { "text/x-rst" } else { "text/plain" };
+        assert_eq!(doc["mime_type"], expected);
+    }
+    assert_eq!(response["total"], 2);
+}
+
+#[tokio::test]
+async fn test_get_document_formats() {
+    // Create a server with a markdown document, rendering HTML when storing documents
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.set_html_prerendering(true);
+    store.write().await.store_document(Document {
+        path: "docs/intro.md".to_string(),
+        content: "# Intro\n\nUse `a < b` with **care**.\n\n- one\n- two\n".to_string(),
+        title: "Intro".to_string(),
+        summary: None,
+    }).unwrap();
+    assert!(store.read().await.get_document("docs/intro.md").unwrap().html.is_some());
+    let documents = Documents::new(store);
+    let request = |format: Option<&str>| GetDocumentRequest {
+        path: "docs/intro.md".to_string(),
+        offset: None,
+        format: format.map(str::to_string),
+    };
+    
+    // Verify the HTML format returns well-formed HTML for the markdown
+    let response = payload(documents.get_document(request(Some("html"))).await.unwrap());
+    assert_eq!(response["content_format"], "html");
+    assert_eq!(response["mime_type"], "text/html");
+    assert_eq!(
+        response["content"],
+        "<h1>Intro</h1>\n<p>Use <code>a &lt; b</code> with <strong>care</strong>.</p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n"
+    );
+    
+    // Verify the text format has no markup, and the raw format is the stored content
+    let response = payload(documents.get_document(request(Some("text"))).await.unwrap());
+    assert_eq!(response["mime_type"], "text/plain");
+    let text = response["content"].as_str().unwrap();
+    assert!(text.contains("Use `a < b` with care."));
+    assert!(!text.contains("**") && !text.contains('#'));
+    let response = payload(documents.get_document(request(None)).await.unwrap());
+    assert_eq!(response["content_format"], "raw");
+    assert_eq!(response["mime_type"], "text/markdown");
+    assert!(response["content"].as_str().unwrap().starts_with("# Intro"));
+    
+    // Verify unknown formats are rejected
+    assert!(documents.get_document(request(Some("pdf"))).await.is_err());
+}
+
+#[tokio::test]
+async fn test_get_document_rejects_path_traversal() {
+    // Create a server with a stored document
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_document(Document {
+        path: "docs/intro.md".to_string(),
+        content: "# Intro\n\nWelcome.".to_string(),
+        title: "Intro".to_string(),
+        summary: None,
+    }).unwrap();
+    let documents = Documents::new(store);
+    let request = |path: &str| GetDocumentRequest { path: path.to_string(), offset: None, format: None };
+    
+    // Verify parent components, absolute paths and null bytes are rejected as invalid parameters
+    for path in ["../../etc/passwd", "docs/../../etc/passwd", "..\\secrets.md", "/etc/passwd", "\\\\server\\share", "C:\\Windows\\win.ini", "docs/intro.md\0.png"] {
+        let error = documents.get_document(request(path)).await.unwrap_err();
+        assert_eq!(error.code, rmcp::model::ErrorCode::INVALID_PARAMS, "{}", path);
+        assert_eq!(error.message, "invalid_path");
+    }
+    
+    // Verify the other path arguments and document resources are checked too
+    let batch = GetDocumentsRequest { paths: vec!["docs/intro.md".to_string(), "../intro.md".to_string()], metadata_only: None };
+    assert_eq!(documents.get_documents(batch).await.unwrap_err().message, "invalid_path");
+    assert_eq!(documents.read_document_resource("doc:///etc/passwd").await.unwrap_err().message, "invalid_path");
+    
+    // Verify valid relative paths, including dotted file names, are still looked up
+    assert!(documents.get_document(request("docs/intro.md")).await.is_ok());
+    let error = documents.get_document(request("docs/..hidden.md")).await.unwrap_err();
+    assert_eq!(error.message, "document_not_found");
+}
+
+#[tokio::test]
+async fn test_document_resource_template() {
+    // Create a server with a stored document
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_document(Document {
+        path: "docs/intro.md".to_string(),
+        content: "# Intro\n\nWelcome.".to_string(),
+        title: "Intro".to_string(),
+        summary: None,
+    }).unwrap();
+    let documents = Documents::new(store);
+    
+    // Verify the document URI template is advertised
+    let templates = documents.resource_templates();
+    assert!(!templates.is_empty());
+    let template = templates.iter().find(|template| template.uri_template == "doc://{path}").unwrap();
+    assert_eq!(template.mime_type.as_deref(), Some("text/markdown"));
+    assert!(template.description.is_some());
+    
+    // Verify a URI built from the template reads the document
+    let result = documents.read_document_resource("doc://docs/intro.md").await.unwrap();
+    match &result.contents[0] {
+        ResourceContents::TextResourceContents { uri, mime_type, text } => {
+            assert_eq!(uri, "doc://docs/intro.md");
+            assert_eq!(mime_type.as_deref(), Some("text/markdown"));
+            assert_eq!(text, "# Intro\n\nWelcome.");
+        }
+        other => panic!("unexpected contents: {:?}", other),
+    }
+    
+    // Verify unknown documents are not found
+    assert!(documents.read_document_resource("doc://docs/missing.md").await.is_err());
+}
+
+#[tokio::test]
+async fn test_get_documents() {
+    // Create a server with two stored documents
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    for (path, content) in [("docs/intro.md", "# Intro\n\nWelcome."), ("docs/install.md", "# Install\n\nRun cargo install.")] {
+        store.write().await.store_document(Document {
+            path: path.to_string(),
+            content: content.to_string(),
+            title: path.to_string(),
+            summary: None,
+        }).unwrap();
+    }
+    let documents = Documents::new(store);
+    
+    // Fetch two existing paths and a missing one
+    let request = |metadata_only| GetDocumentsRequest {
+        paths: vec!["docs/intro.md".to_string(), "docs/missing.md".to_string(), "docs/install.md".to_string()],
+        metadata_only,
+    };
+    let response = payload(documents.get_documents(request(None)).await.unwrap());
+    
+    // Verify the found documents are returned in order, with their content, and the missing one listed apart
+    let found = response["documents"].as_array().unwrap();
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0]["path"], "docs/intro.md");
+    assert_eq!(found[0]["content"], "# Intro\n\nWelcome.");
+    assert_eq!(found[1]["path"], "docs/install.md");
+    assert_eq!(response["not_found"], serde_json::json!(["docs/missing.md"]));
+    assert_eq!(response["truncated"], serde_json::json!([]));
+    
+    // Verify contents are left out with metadata only
+    let response = payload(documents.get_documents(request(Some(true))).await.unwrap());
+    assert_eq!(response["documents"].as_array().unwrap().len(), 2);
+    assert!(response["documents"][0].get("content").is_none());
+}
+
+#[tokio::test]
+async fn test_explain_relevance() {
+    // Create a server with a document about installation
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_document(Document {
+        path: "docs/install.md".to_string(),
+        content: "# Install\n\nRun cargo install to build the binary.".to_string(),
+        title: "Install".to_string(),
+        summary: None,
+    }).unwrap();
+    let documents = Documents::new(store);
+    
+    // Explain the score of the document for a query with a missing term
+    let request = |path: &str| ExplainRelevanceRequest {
+        query: "install cargo kubernetes".to_string(),
+        path: path.to_string(),
+        case_sensitive: false,
+        whole_word: false,
+    };
+    let response = payload(documents.explain_relevance(request("docs/install.md")).await.unwrap());
+    
+    // Verify the matched terms are listed with where they matched, and the missing one weighs nothing
+    let terms = response["terms"].as_array().unwrap();
+    assert_eq!(terms.len(), 3);
+    let matched: Vec<&str> = terms.iter().filter_map(|term| term["matched"].as_str()).collect();
+    assert_eq!(matched, vec!["install", "cargo"]);
+    assert_eq!(terms[0]["location"], "title");
+    assert_eq!(terms[1]["location"], "body");
+    assert!(terms[2]["matched"].is_null());
+    assert_eq!(terms[2]["weight"], 0.0);
+    
+    // Verify the combined score is non-zero and matches the ranking of find_relevant_docs
+    let score = response["score"].as_f64().unwrap();
+    assert!(score > 0.0);
+    let store = documents.store.read().await;
+    let ranked = store.score_relevant_documents("install cargo kubernetes");
+    assert!((ranked[0].score - score).abs() < 1e-9);
+    drop(store);
+    
+    // Verify unknown documents are reported as not found
+    assert!(documents.explain_relevance(request("docs/missing.md")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_complete_document_paths() {
+    // Create a server with documents in two directories
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    for (path, content) in [
+        ("docs/intro.md", "# Intro\n\nWelcome."),
+        ("docs/install.md", "# Install\n\nRun cargo install."),
+        ("guides/deploy.md", "# Deploy\n\nShip it."),
+    ] {
+        store.write().await.store_document(Document {
+            path: path.to_string(),
+            content: content.to_string(),
+            title: path.to_string(),
+            summary: None,
+        }).unwrap();
+    }
+    let documents = Documents::new(store);
+    
+    // Verify the completions capability is advertised
+    let info = rmcp::ServerHandler::get_info(&documents);
+    assert!(info.capabilities.experimental.unwrap().contains_key("completions"));
+    
+    // Complete a partial path of the document resource template
+    let complete = |value: &str| CompleteRequestParam {
+        r#ref: Reference::Resource(ResourceReference { uri: "doc://{path}".to_string() }),
+        argument: ArgumentInfo { name: "path".to_string(), value: value.to_string() },
+    };
+    let result = documents.complete_argument(complete("docs/in")).await.unwrap();
+    
+    // Verify the matching stored paths are suggested, sorted
+    assert_eq!(result.completion.values, vec!["docs/install.md", "docs/intro.md"]);
+    assert_eq!(result.completion.total, Some(2));
+    assert_eq!(result.completion.has_more, Some(false));
+    
+    // Verify an empty input suggests every path, and unknown prefixes none
+    assert_eq!(documents.complete_argument(complete("")).await.unwrap().completion.values.len(), 3);
+    assert!(documents.complete_argument(complete("api/")).await.unwrap().completion.values.is_empty());
+}
+
+#[tokio::test]
+async fn test_get_repository_info() {
+    // Create a server for a repository subfolder
+    let temp_dir = tempfile::tempdir().unwrap();
+    let repository = RepositoryInfo::new("modelcontextprotocol", "rust-sdk", &["docs".to_string()]);
+    let documents = Documents::new(store(&temp_dir)).with_repository(repository);
+    
+    // Get the repository info
+    let info = payload(documents.get_repository_info().await.unwrap());
+    
+    // Verify the configured repository is reported
+    assert_eq!(info["owner"], "modelcontextprotocol");
+    assert_eq!(info["repo"], "rust-sdk");
+    assert_eq!(info["ref"], "HEAD");
+    assert_eq!(info["subfolders"], serde_json::json!(["docs"]));
+    assert_eq!(info["documents"], 0);
+    
+    // Verify the instructions name the repository
+    let instructions = rmcp::ServerHandler::get_info(&documents).instructions.unwrap();
+    assert!(instructions.contains("modelcontextprotocol/rust-sdk (docs)"));
+}
+
+#[tokio::test]
+async fn test_get_all_docs_metadata_only() {
+    // Create a server with a document
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_document(Document {
+        path: "intro.md".to_string(),
+        content: "# Intro\n\nWelcome to the project.".to_string(),
+        title: "Intro".to_string(),
+        summary: None,
+    }).unwrap();
+    let documents = Documents::new(store);
+    
+    // Verify documents are listed without their content by default
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: None, sort_by: None, sort_order: None };
+    let response = payload(documents.get_all_docs(request).await.unwrap());
+    assert_eq!(response["returned"], 1);
+    assert_eq!(response["documents"][0]["title"], "Intro");
+    assert!(response["documents"][0].get("content").is_none());
+    
+    // Verify the content is included when asked for
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: Some(false), path_prefix: None, sort_by: None, sort_order: None };
+    let response = payload(documents.get_all_docs(request).await.unwrap());
+    assert_eq!(response["documents"][0]["content"], "# Intro\n\nWelcome to the project.");
+}
+
+#[tokio::test]
+async fn test_get_all_docs_sorted() {
+    // Create a server with documents whose titles and paths are in different orders
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "a.md".to_string(),
+            content: "# Zebra\n\nStripes.".to_string(),
+            title: "Zebra".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "b.md".to_string(),
+            content: "# apple\n\nA fruit with a long description.".to_string(),
+            title: "apple".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "c.md".to_string(),
+            content: "# Mango\n\nSweet.".to_string(),
+            title: "Mango".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store);
+    let listed_paths = |response: serde_json::Value| -> Vec<String> {
+        response["documents"].as_array().unwrap().iter().map(|doc| doc["path"].as_str().unwrap().to_string()).collect()
+    };
+    
+    // Verify documents are listed by path by default
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: None, sort_by: None, sort_order: None };
+    let response = payload(documents.get_all_docs(request).await.unwrap());
+    assert_eq!(listed_paths(response), vec!["a.md", "b.md", "c.md"]);
+    
+    // Verify sorting by title is alphabetical, ignoring case
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: None, sort_by: Some("title".to_string()), sort_order: None };
+    let response = payload(documents.get_all_docs(request).await.unwrap());
+    let titles: Vec<&str> = response["documents"].as_array().unwrap().iter().map(|doc| doc["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, vec!["apple", "Mango", "Zebra"]);
+    
+    // Verify the order can be reversed, and the limit applies after sorting
+    let request = GetAllDocsRequest { limit: 1, language: None, metadata_only: None, path_prefix: None, sort_by: Some("size".to_string()), sort_order: Some("desc".to_string()) };
+    let response = payload(documents.get_all_docs(request).await.unwrap());
+    assert_eq!(listed_paths(response), vec!["b.md"]);
+    
+    // Verify unknown fields are rejected
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: None, sort_by: Some("popularity".to_string()), sort_order: None };
+    assert!(documents.get_all_docs(request).await.is_err());
+}
+
+#[tokio::test]
+async fn test_large_document_truncated() {
+    // Create a server with a huge document of multibyte characters and a response size limit
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    let content = format!("# Reference\n\n{}", "Référence détaillée. ".repeat(10_000));
+    store.write().await.store_document(Document {
+        path: "reference.md".to_string(),
+        content: content.clone(),
+        title: "Reference".to_string(),
+        summary: None,
+    }).unwrap();
+    let documents = Documents::new(store).with_max_response_bytes(Some(1000));
+    
+    // Fetch the document
+    let request = GetDocumentRequest { path: "reference.md".to_string(), offset: None, format: None };
+    let response = payload(documents.get_document(request).await.unwrap());
+    
+    // Verify the content was cut to the limit with a marker, at a character boundary
+    let text = response["content"].as_str().unwrap();
+    assert!(text.len() <= 1000);
+    assert!(text.ends_with("[truncated]"));
+    assert_eq!(response["truncated"], true);
+    let next_offset = response["next_offset"].as_u64().unwrap() as usize;
+    assert_eq!(text.strip_suffix(TRUNCATION_MARKER).unwrap(), &content[..next_offset]);
+    
+    // Verify the rest can be paged from the next offset
+    let request = GetDocumentRequest { path: "reference.md".to_string(), offset: Some(next_offset), format: None };
+    let response = payload(documents.get_document(request).await.unwrap());
+    assert!(content[next_offset..].starts_with(response["content"].as_str().unwrap().strip_suffix(TRUNCATION_MARKER).unwrap()));
+}
+
+#[tokio::test]
+async fn test_passages_truncated() {
+    // Create a server with a long document matching the query on every line, and a small response size limit
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    let content = format!("# Caching\n\n{}", "The cache keeps every response in memory.\n\n".repeat(500));
+    store.write().await.store_document(Document {
+        path: "cache.md".to_string(),
+        content,
+        title: "Caching".to_string(),
+        summary: None,
+    }).unwrap();
+    let documents = Documents::new(store).with_max_response_bytes(Some(200));
+    
+    // Verify the passages found in the document share the limit
+    let request = SearchInDocumentRequest { path: "cache.md".to_string(), query: "cache".to_string(), context_lines: Some(0) };
+    let response = payload(documents.search_in_document(request).await.unwrap());
+    let matches = response["matches"].as_array().unwrap();
+    let snippet_bytes: usize = matches.iter().map(|found| found["snippet"].as_str().unwrap().len()).sum();
+    assert!(snippet_bytes <= 200);
+    assert!(matches.len() < response["total"].as_u64().unwrap() as usize);
+    assert_eq!(response["returned"], matches.len());
+    assert_eq!(response["truncated"], true);
+    
+    // Verify the summary is cut to the limit, keeping the whole sentences within it
+    let request = SummarizeDocumentRequest { path: "cache.md".to_string(), sentences: 100 };
+    let response = payload(documents.summarize_document(request).await.unwrap());
+    let summary = response["summary"].as_str().unwrap();
+    assert!(summary.len() <= 200);
+    assert!(summary.ends_with(TRUNCATION_MARKER));
+    assert_eq!(response["truncated"], true);
+    let sentences = response["sentences"].as_array().unwrap();
+    assert!(!sentences.is_empty());
+    assert!(sentences.iter().all(|sentence| sentence == "The cache keeps every response in memory."));
+    
+    // Verify snippets of relevant documents are cut to the limit, with highlights within them
+    let documents = documents.with_max_response_bytes(Some(30));
+    let request = FindRelevantDocsRequest { with_highlights: true, ..search_request("cache", None) };
+    let response = payload(documents.find_relevant_docs(request).await.unwrap());
+    let record = &response["documents"][0];
+    let snippet = record["snippet"].as_str().unwrap();
+    assert!(snippet.len() <= 30);
+    assert!(snippet.ends_with(TRUNCATION_MARKER));
+    let kept = snippet.strip_suffix(TRUNCATION_MARKER).unwrap().len() as u64;
+    assert!(record["highlights"].as_array().unwrap().iter().all(|highlight| highlight[1].as_u64().unwrap() <= kept));
+}
+
+#[tokio::test]
+async fn test_list_topics() {
+    // Create a server with a small corpus about caching
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "cache.md".to_string(),
+            content: "# Cache\n\nThe cache keeps responses in memory.".to_string(),
+            title: "Cache".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "eviction.md".to_string(),
+            content: "# Eviction\n\nOld entries leave the cache first.".to_string(),
+            title: "Eviction".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "warmup.md".to_string(),
+            content: "# Warmup\n\nFill the cache at startup.".to_string(),
+            title: "Warmup".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store);
+    
+    // List the two most frequent keywords
+    let response = payload(documents.list_topics(ListTopicsRequest { limit: 2 }).await.unwrap());
+    
+    // Verify the theme of the corpus comes first, with its document frequency
+    assert_eq!(response["returned"], 2);
+    assert_eq!(response["topics"][0]["keyword"], "cache");
+    assert_eq!(response["topics"][0]["documents"], 3);
+}
+
+#[tokio::test]
+async fn test_get_table_of_contents() {
+    // Create a server with two guides in a common directory and a root README
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "guides/setup.md".to_string(),
+            content: "# Setup\n\n## Requirements\n\n### Toolchain\n\n## Installation\n".to_string(),
+            title: "Setup".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "guides/deploy.md".to_string(),
+            content: "# Deploy\n\n## Release\n".to_string(),
+            title: "Deploy".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "README.md".to_string(),
+            content: "# Overview\n".to_string(),
+            title: "Overview".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store);
+    
+    // Get the table of contents down to second level headings
+    let request = GetTableOfContentsRequest { max_heading_level: Some(2) };
+    let response = payload(documents.get_table_of_contents(request).await.unwrap());
+    let toc = &response["table_of_contents"];
+    
+    // Verify the README stays at the root and both guides are grouped under their directory
+    assert_eq!(response["documents"], 3);
+    assert_eq!(toc["documents"][0]["path"], "README.md");
+    assert_eq!(toc["directories"][0]["path"], "guides");
+    let guides = toc["directories"][0]["documents"].as_array().unwrap();
+    let paths: Vec<_> = guides.iter().map(|doc| doc["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, vec!["guides/deploy.md", "guides/setup.md"]);
+    
+    // Verify headings are nested under the title, without the third level
+    let sections: Vec<_> = guides[1]["headings"][0]["headings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|heading| heading["text"].as_str().unwrap())
+        .collect();
+    assert_eq!(guides[1]["headings"][0]["text"], "Setup");
+    assert_eq!(sections, vec!["Requirements", "Installation"]);
+    assert_eq!(guides[1]["headings"][0]["headings"][0]["headings"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_diff_documents() {
+    // Create a server with two versions of a migration guide
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "v1/migration.md".to_string(),
+            content: "# Migration\n\nUpdate the client.\nRestart the server.\nCheck the logs.\n".to_string(),
+            title: "Migration".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "v2/migration.md".to_string(),
+            content: "# Migration\n\nUpdate the client.\nRun the database migrations.\nCheck the logs.\n".to_string(),
+            title: "Migration".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store);
+    
+    // Diff the two versions without context
+    let request = DiffDocumentsRequest {
+        old_path: "v1/migration.md".to_string(),
+        new_path: "v2/migration.md".to_string(),
+        context_lines: Some(0),
+    };
+    let response = payload(documents.diff_documents(request).await.unwrap());
+    
+    // Verify only the changed line is reported, as a unified diff
+    assert_eq!(response["identical"], false);
+    assert_eq!(
+        response["diff"],
+        "--- v1/migration.md\n+++ v2/migration.md\n@@ -4 +4 @@\n-Restart the server.\n+Run the database migrations.\n"
+    );
+    
+    // Verify unknown documents are reported
+    let request = DiffDocumentsRequest {
+        old_path: "v1/migration.md".to_string(),
+        new_path: "v3/migration.md".to_string(),
+        context_lines: None,
+    };
+    assert!(documents.diff_documents(request).await.is_err());
+}
+
+#[cfg(feature = "metrics")]
+#[tokio::test]
+async fn test_server_metrics_count_tool_calls() {
+    use rmcp::ServiceExt;
+    use rmcp::model::CallToolRequestParam;
+    
+    // Serve the tools to a client over an in-memory transport
+    let temp_dir = tempfile::tempdir().unwrap();
+    let documents = Documents::new(store(&temp_dir));
+    let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+    let server = documents.clone();
+    tokio::spawn(async move {
+        let service = server.serve(tokio::io::split(server_io)).await.unwrap();
+        service.waiting().await.unwrap();
+    });
+    let client = ().serve(tokio::io::split(client_io)).await.unwrap();
+    
+    // Call get_all_docs twice through the client
+    for _ in 0..2 {
+        let request = CallToolRequestParam {
+            name: "get_all_docs".into(),
+            arguments: Some(serde_json::json!({ "limit": 0 }).as_object().unwrap().clone()),
+        };
+        client.call_tool(request).await.unwrap();
+    }
+    
+    // Verify the calls were counted and timed
+    let metrics = payload(documents.get_server_metrics().await.unwrap());
+    assert_eq!(metrics["enabled"], true);
+    assert_eq!(metrics["tools"]["get_all_docs"]["calls"], 2);
+    assert_eq!(metrics["tools"]["get_all_docs"]["errors"], 0);
+    assert!(metrics["tools"]["get_all_docs"]["max_ms"].as_f64().unwrap() >= 0.0);
+    
+    client.cancel().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_stale_index_rescanned_on_query() {
+    // Create a server whose index gets stale after 50 milliseconds, counting rescans
+    let temp_dir = tempfile::tempdir().unwrap();
+    let rescans = Arc::new(AtomicUsize::new(0));
+    let documents = Documents::new(store(&temp_dir)).with_stale_ttl(Duration::from_millis(50), {
+        let rescans = rescans.clone();
+        move || {
+            let rescans = rescans.clone();
+            async move {
+                rescans.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    });
+    let request = || GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: None, sort_by: None, sort_order: None };
+    
+    // Verify a query on a fresh index doesn't rescan
+    documents.get_all_docs(request()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(rescans.load(Ordering::SeqCst), 0);
+    
+    // Verify the first query after the time to live schedules a rescan, answered meanwhile
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    documents.get_all_docs(request()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(rescans.load(Ordering::SeqCst), 1);
+    
+    // Verify the rescan made the index fresh again
+    documents.get_all_docs(request()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(rescans.load(Ordering::SeqCst), 1);
+    
+    // Verify listing stale documents schedules a rescan as well
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    documents.get_stale_documents(GetStaleDocumentsRequest { older_than_seconds: 0, limit: 0 }).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(rescans.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_shared_titles_qualified_with_directory() {
+    // Create a server with an overview in two folders, and a unique title
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "api/overview.md".to_string(),
+            content: "# Overview\n\nThe endpoints of the server API.".to_string(),
+            title: "Overview".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "guides/deploy/overview.md".to_string(),
+            content: "# Overview\n\nHow to deploy the server.".to_string(),
+            title: "Overview".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "guides/server.md".to_string(),
+            content: "# Server\n\nConfigure the server.".to_string(),
+            title: "Server".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store.clone()).with_qualified_titles(true);
+    
+    // Verify both overviews get their directory in search results, but not the unique title
+    let response = payload(documents.find_relevant_docs(search_request("server", Some(0.0))).await.unwrap());
+    let mut titles: Vec<&str> = response["documents"].as_array().unwrap().iter().map(|doc| doc["title"].as_str().unwrap()).collect();
+    titles.sort();
+    assert_eq!(titles, vec!["Server", "api / Overview", "guides / deploy / Overview"]);
+    
+    // Verify listings are qualified the same way, while stored titles are unchanged
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: None, sort_by: None, sort_order: None };
+    let response = payload(documents.get_all_docs(request).await.unwrap());
+    assert_eq!(response["documents"][0]["title"], "api / Overview");
+    assert_eq!(store.read().await.get_document("api/overview.md").unwrap().title, "Overview");
+    
+    // Verify titles are returned as stored by default
+    let response = payload(Documents::new(store).find_relevant_docs(search_request("endpoints", None)).await.unwrap());
+    assert_eq!(response["documents"][0]["title"], "Overview");
+}
+
+#[tokio::test]
+async fn test_lookup_glossary_term() {
+    // Create a server with a glossary using both definition syntaxes, and a code block
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_document(Document {
+        path: "docs/glossary.md".to_string(),
+        content: "# Glossary\n\nShard\n: A horizontal partition of the index,\n    served by a single node.\n\n\
+            - **Replica** — A copy of a shard.\n- Node -- A server of the cluster.\n\n\
+            ```\nTerm\n: not a definition\n```".to_string(),
+        title: "Glossary".to_string(),
+        summary: None,
+    }).unwrap();
+    let documents = Documents::new(store);
+    
+    // Verify a definition list term is found, ignoring case, with its continued definition
+    let response = payload(documents.lookup_term(LookupTermRequest { term: "shard".to_string() }).await.unwrap());
+    assert_eq!(response["total"], 1);
+    assert_eq!(response["definitions"][0]["term"], "Shard");
+    assert_eq!(response["definitions"][0]["definition"], "A horizontal partition of the index, served by a single node.");
+    assert_eq!(response["definitions"][0]["path"], "docs/glossary.md");
+    
+    // Verify bullet definitions are found, without their emphasis
+    let response = payload(documents.lookup_term(LookupTermRequest { term: "Replica".to_string() }).await.unwrap());
+    assert_eq!(response["definitions"][0]["definition"], "A copy of a shard.");
+    let response = payload(documents.lookup_term(LookupTermRequest { term: "node".to_string() }).await.unwrap());
+    assert_eq!(response["definitions"][0]["definition"], "A server of the cluster.");
+    
+    // Verify code blocks are ignored, and unknown terms aren't found
+    assert!(documents.lookup_term(LookupTermRequest { term: "Term".to_string() }).await.is_err());
+    assert!(documents.lookup_term(LookupTermRequest { term: "cluster".to_string() }).await.is_err());
+}
+
+#[tokio::test]
+async fn test_internal_documents_hidden_when_public_only() {
+    // Create a storage with a public guide, a guide tagged internal in its front matter, and an internal directory
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.set_internal_paths(&["ops/**".to_string()]).unwrap();
+    store.write().await.store_documents(vec![
+        Document {
+            path: "guides/deploy.md".to_string(),
+            content: "# Deploying\n\nDeploy the server with a container.".to_string(),
+            title: "Deploying".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "guides/rollback.md".to_string(),
+            content: "---\nvisibility: internal\n---\n# Rolling back\n\nRoll the server back to the last release.".to_string(),
+            title: "Rolling back".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "ops/oncall.md".to_string(),
+            content: "# On call\n\nRestart the server when paged.".to_string(),
+            title: "On call".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let paths = |response: serde_json::Value| -> Vec<String> {
+        let mut paths: Vec<String> = response["documents"].as_array().unwrap().iter().map(|doc| doc["path"].as_str().unwrap().to_string()).collect();
+        paths.sort();
+        paths
+    };
+    
+    // Verify every document is served by default
+    let documents = Documents::new(store.clone());
+    let response = payload(documents.find_relevant_docs(search_request("server", Some(0.0))).await.unwrap());
+    assert_eq!(paths(response), vec!["guides/deploy.md", "guides/rollback.md", "ops/oncall.md"]);
+    
+    // Verify internal documents are left out of searches and listings with the public-only filter
+    let documents = Documents::new(store.clone()).with_public_only(true);
+    let response = payload(documents.find_relevant_docs(search_request("server", Some(0.0))).await.unwrap());
+    assert_eq!(paths(response), vec!["guides/deploy.md"]);
+    let request = GetAllDocsRequest { limit: 0, language: None, metadata_only: None, path_prefix: None, sort_by: None, sort_order: None };
+    let response = payload(documents.get_all_docs(request).await.unwrap());
+    assert_eq!(response["documents"][0]["visibility"], "public");
+    assert_eq!(paths(response), vec!["guides/deploy.md"]);
+    
+    // Verify they can't be fetched by path either
+    let request = GetDocumentRequest { path: "guides/rollback.md".to_string(), offset: None, format: None };
+    assert!(documents.get_document(request).await.is_err());
+    let request = GetDocumentsRequest { paths: vec!["guides/rollback.md".to_string()], metadata_only: None };
+    let response = payload(documents.get_documents(request).await.unwrap());
+    assert_eq!(response["not_found"], serde_json::json!(["guides/rollback.md"]));
+    
+    // Verify the other tools and the counts leave them out as well
+    let request = SearchInDocumentRequest { path: "guides/rollback.md".to_string(), query: "server".to_string(), context_lines: None };
+    assert_eq!(documents.search_in_document(request).await.unwrap_err().message, "document_not_found");
+    assert_eq!(payload(documents.health().await.unwrap())["documents"], 1);
+    assert_eq!(payload(documents.get_scan_report().await.unwrap())["indexed"], 1);
+    
+    // Verify documents can't be added until internal documents are unlocked, whether the path is hidden, unused or a copy
+    let add = |path: &str, content: &str| AddDocumentRequest { path: path.to_string(), content: content.to_string(), title: None, overwrite: true };
+    for (path, content) in [
+        ("ops/oncall.md", "# On call\n\nIgnore the pages."),
+        ("notes/new.md", "# New notes\n\nNothing yet."),
+        ("notes/oncall.md", "# On call\n\nRestart the server when paged."),
+    ] {
+        assert_eq!(documents.add_document(add(path, content)).await.unwrap_err().message, "internal_documents_locked");
+    }
+    assert!(store.read().await.get_document("ops/oncall.md").unwrap().content.contains("Restart the server"));
+    assert!(store.read().await.get_document("notes/new.md").is_none());
+    
+    // Verify a client can't unlock them without the token the server was configured with
+    let request = |token: &str| UnlockInternalDocumentsRequest { token: token.to_string() };
+    assert_eq!(documents.unlock_internal_documents(request("secret")).await.unwrap_err().message, "invalid_internal_token");
+    let documents = documents.with_internal_token(Some("secret".to_string()));
+    assert_eq!(documents.unlock_internal_documents(request("guess")).await.unwrap_err().message, "invalid_internal_token");
+    assert_eq!(paths(payload(documents.find_relevant_docs(search_request("server", Some(0.0))).await.unwrap())), vec!["guides/deploy.md"]);
+    
+    // Verify every tool serves them once unlocked
+    let response = payload(documents.unlock_internal_documents(request("secret")).await.unwrap());
+    assert_eq!(response["documents"], 3);
+    let response = payload(documents.find_relevant_docs(search_request("server", Some(0.0))).await.unwrap());
+    assert_eq!(paths(response), vec!["guides/deploy.md", "guides/rollback.md", "ops/oncall.md"]);
+    let request = GetDocumentRequest { path: "guides/rollback.md".to_string(), offset: None, format: None };
+    assert_eq!(payload(documents.get_document(request).await.unwrap())["visibility"], "internal");
+    let request = SearchInDocumentRequest { path: "guides/rollback.md".to_string(), query: "server".to_string(), context_lines: None };
+    assert!(documents.search_in_document(request).await.is_ok());
+    assert_eq!(payload(documents.health().await.unwrap())["documents"], 3);
+    let response = payload(documents.add_document(add("notes/new.md", "# New notes\n\nNothing yet.")).await.unwrap());
+    assert_eq!(response["stored_as"], "notes/new.md");
+    
+    // Verify locking hides them again
+    let response = payload(documents.lock_internal_documents().await.unwrap());
+    assert_eq!(response["documents"], 2);
+    assert_eq!(paths(payload(documents.find_relevant_docs(search_request("server", Some(0.0))).await.unwrap())), vec!["guides/deploy.md"]);
+}
+
+#[tokio::test]
+async fn test_autocomplete_query() {
+    // Create a server with a corpus about deployment
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    store.write().await.store_documents(vec![
+        Document {
+            path: "deploy/docker.md".to_string(),
+            content: "# Docker\n\nThe deployment runs in a container, deployable anywhere.".to_string(),
+            title: "Docker".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "deploy/kubernetes.md".to_string(),
+            content: "# Kubernetes\n\nThe deployment is scaled by the cluster.".to_string(),
+            title: "Kubernetes".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "deploy/rollback.md".to_string(),
+            content: "# Rollback\n\nRevert a deployment after a redeploy failed.".to_string(),
+            title: "Rollback".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    let documents = Documents::new(store);
+    
+    // Verify the most frequent keyword starting with the prefix comes first, then those containing it
+    let request = AutocompleteQueryRequest { partial: "Deploy".to_string(), limit: 0 };
+    let response = payload(documents.autocomplete_query(request).await.unwrap());
+    assert_eq!(response["suggestions"][0]["keyword"], "deployment");
+    assert_eq!(response["suggestions"][0]["documents"], 3);
+    assert_eq!(response["suggestions"][1]["keyword"], "deployable");
+    assert_eq!(response["suggestions"][2]["keyword"], "redeploy");
+    assert_eq!(response["returned"], 3);
+    
+    // Verify the number of suggestions is capped
+    let request = AutocompleteQueryRequest { partial: "deploy".to_string(), limit: 1 };
+    let response = payload(documents.autocomplete_query(request).await.unwrap());
+    assert_eq!(response["returned"], 1);
+}
+
+#[tokio::test]
+async fn test_get_scan_report() {
+    // Create a mock repository with a guide and a file that can't be fetched
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/git/trees/HEAD"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "truncated": false,
+            "tree": [
+                { "path": "docs/install.md", "type": "blob" },
+                { "path": "docs/missing.md", "type": "blob" },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/repo/contents/docs/install.md"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "type": "file",
+            "encoding": "base64",
+            "content": STANDARD.encode("# Install\n\nRun the installer."),
+        })))
+        .mount(&server)
+        .await;
+    let options = ConnectorOptions {
+        base_uri: Some(server.uri()),
+        ..ConnectorOptions::default()
+    };
+    let connector = GitHubConnector::with_options("owner", "repo", None, options).await.unwrap();
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store = store(&temp_dir);
+    let documents = Documents::new(store.clone());
+    
+    // Verify the report is complete before any scan failed
+    let report = payload(documents.get_scan_report().await.unwrap());
+    assert_eq!(report["complete"], true);
+    assert_eq!(report["failed"], 0);
+    
+    // Scan the repository, recording its failures as the server does at startup
+    let failures = connector.scan_into(&[], None, &store, |_| {}).await.unwrap();
+    documents.set_scan_failures(failures).await;
+    
+    // Verify the report lists the file that failed and why, next to the indexed count
+    let report = payload(documents.get_scan_report().await.unwrap());
+    assert_eq!(report["indexed"], 1);
+    assert_eq!(report["failed"], 1);
+    assert_eq!(report["complete"], false);
+    assert_eq!(report["failures"][0]["path"], "docs/missing.md");
+    assert!(!report["failures"][0]["reason"].as_str().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_add_document() {
+    // Create a server over an empty persistent storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let documents = Documents::new(store(&temp_dir));
+    let request = |path: &str, content: &str, overwrite| AddDocumentRequest {
+        path: path.to_string(),
+        content: content.to_string(),
+        title: None,
+        overwrite,
+    };
+    
+    // Verify an added document is titled from its content, and searchable
+    let response = payload(documents.add_document(request("notes/cache.md", "# Cache notes\n\nThe cache keeps responses in memory.", false)).await.unwrap());
+    assert_eq!(response["path"], "notes/cache.md");
+    assert_eq!(response["title"], "Cache notes");
+    assert!(response["keyword_count"].as_u64().unwrap() > 0);
+    let response = payload(documents.find_relevant_docs(search_request("cache responses", None)).await.unwrap());
+    assert_eq!(response["documents"][0]["path"], "notes/cache.md");
+    
+    // Verify empty content, paths outside of the storage and existing paths are rejected
+    assert!(documents.add_document(request("notes/empty.md", "  \n", false)).await.is_err());
+    assert!(documents.add_document(request("../outside.md", "# Outside", false)).await.is_err());
+    assert!(documents.add_document(request("notes/cache.md", "# Replaced\n\nNew notes.", false)).await.is_err());
+    
+    // Verify an existing document is replaced with the overwrite flag
+    let response = payload(documents.add_document(request("notes/cache.md", "# Replaced\n\nNew notes.", true)).await.unwrap());
+    assert_eq!(response["title"], "Replaced");
+    
+    // Verify the added document is saved, so a new storage over the same directory loads it
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    assert_eq!(reloaded.get_document("notes/cache.md").unwrap().title, "Replaced");
+    assert!(reloaded.get_document("notes/empty.md").is_none());
+    
+    // Verify added documents are built with the configured scanner, processors included
+    let mut scanner = DocumentScanner::new();
+    scanner.add_processor(Arc::new(DraftProcessor));
+    let documents = documents.with_scanner(scanner);
+    let response = payload(documents.add_document(request("notes/queue.md", "# Queue notes\n\nJobs wait in the queue.", false)).await.unwrap());
+    assert_eq!(response["title"], "Draft: Queue notes");
+}
+
+/// Marks the title of the documents built by the previous processors as a draft
+#[derive(Debug)]
+struct DraftProcessor;
+
+impl DocumentProcessor for DraftProcessor {
+    fn process(&self, raw: &str, path: &str) -> anyhow::Result<Document> {
+        self.postprocess(DocumentScanner::new().process_content(path, raw.to_string())?)
+    }
+
+    fn postprocess(&self, mut document: Document) -> anyhow::Result<Document> {
+        document.title = format!("Draft: {}", document.title);
+        Ok(document)
+    }
+}
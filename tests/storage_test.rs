@@ -1,5 +1,12 @@
-use kode_ai_rs::document::Document;
-use kode_ai_rs::storage::DocumentStorage;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use kode_ai_rs::document::{Document, DocumentScanner};
+use kode_ai_rs::error::KodeError;
+use kode_ai_rs::storage::{
+    CodeTokenizer, DocumentStorage, ExportFormat, KeywordField, MatchOptions, MergePolicy, RecencyBoost, Stopwords, Synonyms,
+    Tokenizer,
+};
 
 #[test]
 fn test_store_and_retrieve_document() {
@@ -115,4 +122,1625 @@ fn test_find_relevant_documents() {
     
     // Verify we found no documents
     assert!(no_results.is_empty());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_search_in_document() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create a test document with matches far apart
+    let doc = Document {
+        path: "guide.md".to_string(),
+        content: "# Guide\n\nInstall with Cargo.\n\nline 4\nline 5\nline 6\nline 7\n\nBuild with cargo build.".to_string(),
+        title: "Guide".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Search for a term appearing twice with different casing
+    let matches = storage.search_in_document("guide.md", "cargo", 1).unwrap();
+    
+    // Verify both hits are found with their line numbers and context
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0].matched_lines, vec![3]);
+    assert_eq!((matches[0].start_line, matches[0].end_line), (2, 4));
+    assert_eq!(matches[1].matched_lines, vec![10]);
+    assert!(matches[1].snippet.contains("cargo build"));
+    
+    // Verify a missing document is reported as such
+    assert!(storage.search_in_document("missing.md", "cargo", 1).is_none());
+}
+
+#[test]
+fn test_extract_snippet() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create a test document where the match is not in the first paragraph
+    let doc = Document {
+        path: "deploy.md".to_string(),
+        content: "# Deployment\n\nThis page covers shipping the service.\n\nContainers are built with Docker and pushed to the registry.".to_string(),
+        title: "Deployment".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Search and extract the snippet for the result
+    let results = storage.find_relevant_documents("docker");
+    assert_eq!(results.len(), 1);
+    let snippet = storage.extract_snippet(results[0], "docker", false).unwrap();
+    
+    // Verify the snippet is the passage containing the query term
+    assert_eq!(snippet, "Containers are built with Docker and pushed to the registry.");
+    
+    // Verify matched terms are marked when requested
+    let marked = storage.extract_snippet(results[0], "docker", true).unwrap();
+    assert!(marked.contains("**Docker**"));
+}
+
+#[test]
+fn test_extract_snippet_keeps_markdown_well_formed() {
+    let mut storage = DocumentStorage::new_in_memory();
+    
+    // Create a document whose only match is deep in a long code block with blank lines
+    let mut code = String::new();
+    for step in 0..30 {
+        code.push_str(&format!("let step_{step} = prepare({step});\n\n"));
+    }
+    code.push_str("let pool = connect_postgres(&config);\n");
+    for step in 30..60 {
+        code.push_str(&format!("let step_{step} = prepare({step});\n"));
+    }
+    let doc = Document {
+        path: "database.md".to_string(),
+        content: format!("# Database\n\nSetting up the storage.\n\n```rust\n{code}```\n\nThat's all."),
+        title: "Database".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Extract the snippet of the code block, with terms marked
+    let document = storage.get_document("database.md").unwrap();
+    let snippet = storage.extract_snippet(document, "postgres", true).unwrap();
+    
+    // Verify the snippet is the matching code, fenced, without markers in the code
+    assert!(snippet.contains("let pool = connect_postgres(&config);"));
+    assert!(!snippet.contains("**"));
+    let fences: Vec<&str> = snippet.lines().filter(|line| line.starts_with("```")).collect();
+    assert_eq!(fences, vec!["```rust", "```"]);
+    assert!(snippet.starts_with("...\n```rust\n"));
+    assert!(snippet.ends_with("```\n..."));
+    
+    // Create a long paragraph with a link and inline code around the match
+    let filler = "The service keeps running in the background. ".repeat(6);
+    let doc = Document {
+        path: "links.md".to_string(),
+        content: format!(
+            "# Links\n\n{filler}See [the deployment guide](https://example.com/guides/deploy) and run `make deploy` \
+            before the release. {filler}"
+        ),
+        title: "Links".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Extract the snippet of the paragraph
+    let document = storage.get_document("links.md").unwrap();
+    let snippet = storage.extract_snippet(document, "release", false).unwrap();
+    
+    // Verify the snippet starts at a sentence and keeps links and inline code whole
+    assert!(snippet.contains("release"));
+    assert!(snippet.starts_with("...See [the deployment guide](https://example.com/guides/deploy)"));
+    assert_eq!(snippet.matches('[').count(), snippet.matches("](").count());
+    assert_eq!(snippet.matches('`').count() % 2, 0);
+}
+
+#[test]
+fn test_deduplicate_identical_content() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create two documents with the same content under different paths
+    let content = "# Setup\n\nInstall the toolchain before building.";
+    let original = Document {
+        path: "docs/setup.md".to_string(),
+        content: content.to_string(),
+        title: "Setup".to_string(),
+        summary: None,
+    };
+    let mirror = Document {
+        path: "mirror/setup.md".to_string(),
+        content: content.replace('\n', "\r\n"),
+        title: "Setup".to_string(),
+        summary: None,
+    };
+    
+    // Store both documents
+    storage.store_documents(vec![original, mirror]).unwrap();
+    
+    // Verify only one canonical entry remains, reporting the other path as an alias
+    let all_docs = storage.get_all_documents();
+    assert_eq!(all_docs.len(), 1);
+    assert_eq!(all_docs[0].path, "docs/setup.md");
+    assert_eq!(all_docs[0].aliases, vec!["mirror/setup.md".to_string()]);
+    
+    // Verify the alias resolves to the canonical document
+    assert_eq!(storage.get_document("mirror/setup.md").unwrap().path, "docs/setup.md");
+    
+    // Verify searches return the canonical entry once
+    let results = storage.find_relevant_documents("toolchain");
+    assert_eq!(results.len(), 1);
+    
+    // Verify the aliases survive a reload from disk
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    assert_eq!(reloaded.get_document("mirror/setup.md").unwrap().path, "docs/setup.md");
+}
+
+#[test]
+fn test_related_documents() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create two documents on databases and one on frontend styling
+    let postgres = Document {
+        path: "postgres.md".to_string(),
+        content: "# Postgres\n\nConfigure database connections, migrations and indexes.".to_string(),
+        title: "Postgres".to_string(),
+        summary: None,
+    };
+    let mysql = Document {
+        path: "mysql.md".to_string(),
+        content: "# MySQL\n\nDatabase connections, schema migrations and query indexes.".to_string(),
+        title: "MySQL".to_string(),
+        summary: None,
+    };
+    let css = Document {
+        path: "css.md".to_string(),
+        content: "# Styling\n\nTheme colors, fonts and layout for connections page.".to_string(),
+        title: "Styling".to_string(),
+        summary: None,
+    };
+    storage.store_documents(vec![postgres, mysql, css]).unwrap();
+    
+    // Verify the two database documents are each other's most related document
+    let related = storage.related_documents("postgres.md", 5).unwrap();
+    assert_eq!(related[0].0.path, "mysql.md");
+    assert!(related.iter().all(|(doc, _)| doc.path != "postgres.md"));
+    
+    let related = storage.related_documents("mysql.md", 5).unwrap();
+    assert_eq!(related[0].0.path, "postgres.md");
+    
+    // Verify a missing document is reported as such
+    assert!(storage.related_documents("missing.md", 5).is_none());
+}
+
+#[test]
+fn test_language_filter() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create the same guide in English and French, with code that shouldn't affect detection
+    let english = Document {
+        path: "en/configuration.md".to_string(),
+        content: "# Configuration\n\nThe configuration file controls how the server starts and which \
+            repository it reads the documentation from.\n\n```rust\nlet configuration = load();\n```".to_string(),
+        title: "Configuration".to_string(),
+        summary: None,
+    };
+    let french = Document {
+        path: "fr/configuration.md".to_string(),
+        content: "# Configuration\n\nLe fichier de configuration contrôle comment le serveur démarre et \
+            depuis quel dépôt il lit la documentation.\n\n```rust\nlet configuration = load();\n```".to_string(),
+        title: "Configuration".to_string(),
+        summary: None,
+    };
+    storage.store_documents(vec![english, french]).unwrap();
+    
+    // Verify the languages were detected
+    assert_eq!(storage.get_document("en/configuration.md").unwrap().language.as_deref(), Some("en"));
+    assert_eq!(storage.get_document("fr/configuration.md").unwrap().language.as_deref(), Some("fr"));
+    
+    // Verify an English query matches both documents
+    let results = storage.find_relevant_documents("configuration documentation");
+    assert_eq!(results.len(), 2);
+    
+    // Verify filtering on English excludes the French document
+    let english_results: Vec<_> = results.into_iter().filter(|doc| doc.is_language("en")).collect();
+    assert_eq!(english_results.len(), 1);
+    assert_eq!(english_results[0].path, "en/configuration.md");
+}
+
+#[test]
+fn test_title_matches_outrank_body_matches() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create a document naming the topic only in its title, and one mentioning it once in a long body
+    let titled = Document {
+        path: "auth.md".to_string(),
+        content: "# Overview\n\nSign in with a token issued by the identity provider.".to_string(),
+        title: "Authentication".to_string(),
+        summary: None,
+    };
+    let body = "The deployment pipeline builds, tests and ships every release. ".repeat(20);
+    let buried = Document {
+        path: "deploy.md".to_string(),
+        content: format!("# Deployment\n\n{}\n\nThe pipeline also checks authentication settings.", body),
+        title: "Deployment".to_string(),
+        summary: None,
+    };
+    storage.store_documents(vec![buried, titled]).unwrap();
+    
+    // Verify the title match ranks first
+    let results = storage.find_relevant_documents("authentication");
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].path, "auth.md");
+    assert_eq!(results[1].path, "deploy.md");
+}
+
+#[test]
+fn test_get_documents_by_title() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create a uniquely titled document and two documents sharing a title
+    let guide = Document {
+        path: "docs/getting-started.md".to_string(),
+        content: "# Getting Started\n\nInstall the CLI.".to_string(),
+        title: "Getting Started".to_string(),
+        summary: None,
+    };
+    let server_faq = Document {
+        path: "server/faq.md".to_string(),
+        content: "# FAQ\n\nServer questions.".to_string(),
+        title: "FAQ".to_string(),
+        summary: None,
+    };
+    let client_faq = Document {
+        path: "client/faq.md".to_string(),
+        content: "# FAQ\n\nClient questions.".to_string(),
+        title: "FAQ".to_string(),
+        summary: None,
+    };
+    storage.store_documents(vec![guide, server_faq, client_faq]).unwrap();
+    
+    // Verify an unambiguous title matches regardless of case and spacing
+    let results = storage.get_documents_by_title("getting  STARTED");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "docs/getting-started.md");
+    
+    // Verify an ambiguous title returns every candidate
+    let results = storage.get_documents_by_title("faq");
+    let paths: Vec<_> = results.iter().map(|doc| doc.path.as_str()).collect();
+    assert_eq!(paths, vec!["client/faq.md", "server/faq.md"]);
+    
+    // Verify a missing title matches nothing
+    assert!(storage.get_documents_by_title("Troubleshooting").is_empty());
+    
+    // Verify the index follows title changes
+    let renamed = Document {
+        path: "docs/getting-started.md".to_string(),
+        content: "# Quickstart\n\nInstall the CLI.".to_string(),
+        title: "Quickstart".to_string(),
+        summary: None,
+    };
+    storage.store_document(renamed).unwrap();
+    assert!(storage.get_documents_by_title("Getting Started").is_empty());
+    assert_eq!(storage.get_documents_by_title("quickstart").len(), 1);
+}
+
+#[test]
+fn test_remove_documents() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create a document, a duplicate of it and an unrelated document
+    let content = "# Setup\n\nInstall the toolchain.".to_string();
+    let setup = Document {
+        path: "docs/setup.md".to_string(),
+        content: content.clone(),
+        title: "Setup".to_string(),
+        summary: None,
+    };
+    let mirror = Document {
+        path: "mirror/setup.md".to_string(),
+        content,
+        title: "Setup".to_string(),
+        summary: None,
+    };
+    let faq = Document {
+        path: "docs/faq.md".to_string(),
+        content: "# FAQ\n\nCommon questions.".to_string(),
+        title: "FAQ".to_string(),
+        summary: None,
+    };
+    storage.store_documents(vec![setup, mirror, faq]).unwrap();
+    
+    // Remove the canonical document, the unrelated one and a path that doesn't exist
+    storage.remove_documents(&["docs/setup.md".to_string(), "docs/faq.md".to_string(), "missing.md".to_string()]).unwrap();
+    
+    // Verify the removed documents are gone and the duplicate keeps the content
+    assert!(storage.get_document("docs/setup.md").is_none());
+    assert!(storage.get_document("docs/faq.md").is_none());
+    assert_eq!(storage.get_document("mirror/setup.md").unwrap().path, "mirror/setup.md");
+    
+    // Verify the removal was saved to disk
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    assert_eq!(reloaded.get_all_documents().len(), 1);
+}
+
+#[test]
+fn test_find_relevant_documents_phrase_query() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create documents with the same words, contiguous in only one of them
+    let contiguous = Document {
+        path: "errors.md".to_string(),
+        content: "# Errors\n\nHow to handle an Error\n   Boundary in the renderer.".to_string(),
+        title: "Errors".to_string(),
+        summary: None,
+    };
+    let scattered = Document {
+        path: "layout.md".to_string(),
+        content: "# Layout\n\nEach boundary of the layout logs an error when it overflows.".to_string(),
+        title: "Layout".to_string(),
+        summary: None,
+    };
+    storage.store_documents(vec![contiguous, scattered]).unwrap();
+    
+    // Verify a plain query matches both documents
+    assert_eq!(storage.find_relevant_documents("error boundary").len(), 2);
+    
+    // Verify a quoted phrase only matches the document containing it, ignoring case and line breaks
+    let results = storage.find_relevant_documents("\"error boundary\"");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "errors.md");
+}
+
+#[test]
+fn test_find_relevant_documents_exclusion_query() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create two documents about deployment, one of them deprecated
+    let current = Document {
+        path: "deploy.md".to_string(),
+        content: "# Deployment\n\nDeploy the service with the release pipeline.".to_string(),
+        title: "Deployment".to_string(),
+        summary: None,
+    };
+    let legacy = Document {
+        path: "deploy-legacy.md".to_string(),
+        content: "# Legacy Deployment\n\nDeploy the service by hand. This guide is deprecated.".to_string(),
+        title: "Legacy Deployment".to_string(),
+        summary: None,
+    };
+    storage.store_documents(vec![current, legacy]).unwrap();
+    
+    // Verify excluding a term filters out the otherwise matching document
+    let results = storage.find_relevant_documents("deploy service -deprecated");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "deploy.md");
+    
+    // Verify requiring a term keeps only the documents containing it
+    let results = storage.find_relevant_documents("deploy +hand");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "deploy-legacy.md");
+}
+
+#[test]
+fn test_find_relevant_documents_field_query() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Create a document about Rust, and one only mentioning it in its body
+    let rust = Document {
+        path: "rust.md".to_string(),
+        content: "# Rust Guide\n\nWrite safe systems code.".to_string(),
+        title: "Rust Guide".to_string(),
+        summary: Some("Write safe systems code.".to_string()),
+    };
+    let tooling = Document {
+        path: "tooling.md".to_string(),
+        content: "# Tooling\n\nThe build also compiles the rust bindings.".to_string(),
+        title: "Tooling".to_string(),
+        summary: Some("Build tools and rust bindings.".to_string()),
+    };
+    storage.store_documents(vec![rust, tooling]).unwrap();
+    
+    // Verify a title term only matches documents with it in their title
+    let results = storage.find_relevant_documents("title:rust");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "rust.md");
+    
+    // Verify other fields can be targeted, phrases included
+    let results = storage.find_relevant_documents("summary:\"rust bindings\"");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "tooling.md");
+    
+    // Verify unprefixed terms still search all fields
+    assert_eq!(storage.find_relevant_documents("rust").len(), 2);
+}
+
+#[test]
+fn test_compressed_storage_round_trip() {
+    // Create a temporary directory for storage, with an uncompressed index
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    let doc = Document {
+        path: "guide.md".to_string(),
+        content: "# Guide\n\nCompression keeps the index small.".to_string(),
+        title: "Guide".to_string(),
+        summary: Some("Compression keeps the index small.".to_string()),
+    };
+    storage.store_document(doc).unwrap();
+    assert!(temp_dir.path().join("documents.json").exists());
+    
+    // Save the same documents compressed
+    storage.set_compression(true);
+    let doc = Document {
+        path: "faq.md".to_string(),
+        content: "# FAQ\n\nCommon questions.".to_string(),
+        title: "FAQ".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Verify the compressed index replaced the uncompressed one
+    assert!(temp_dir.path().join("documents.json.gz").exists());
+    assert!(!temp_dir.path().join("documents.json").exists());
+    
+    // Verify the documents load back from the compressed index unchanged
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    assert_eq!(reloaded.get_all_documents().len(), 2);
+    for original in storage.get_all_documents() {
+        let loaded = reloaded.get_document(&original.path).unwrap();
+        assert_eq!(serde_json::to_value(loaded).unwrap(), serde_json::to_value(original).unwrap());
+    }
+}
+
+#[test]
+fn test_reindex_keywords_with_stemming() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store a document without stemming
+    let doc = Document {
+        path: "setup.md".to_string(),
+        content: "# Setup\n\nConfiguring the server after installing it.".to_string(),
+        title: "Setup".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    assert!(storage.get_document("setup.md").unwrap().keywords.contains(&"configuring".to_string()));
+    assert!(storage.find_relevant_documents("configured").is_empty());
+    
+    // Enable stemming and reindex the stored documents
+    storage.set_stemming(true);
+    let reindexed = storage.reindex_keywords().unwrap();
+    assert_eq!(reindexed, 1);
+    
+    // Verify the keywords were reduced to their stems
+    let keywords = &storage.get_document("setup.md").unwrap().keywords;
+    assert!(keywords.contains(&"configur".to_string()));
+    assert!(keywords.contains(&"install".to_string()));
+    assert!(!keywords.contains(&"configuring".to_string()));
+    
+    // Verify other forms of the words now match
+    assert_eq!(storage.find_relevant_documents("configured").len(), 1);
+    assert_eq!(storage.find_relevant_documents("installs").len(), 1);
+    
+    // Verify the new keywords were saved
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    assert!(reloaded.get_document("setup.md").unwrap().keywords.contains(&"configur".to_string()));
+}
+
+#[test]
+fn test_diacritic_insensitive_search() {
+    // Create an in-memory storage without diacritic folding
+    let mut storage = DocumentStorage::new_in_memory();
+    
+    // Store a document with accented words, the second one decomposed (NFD)
+    let doc = Document {
+        path: "careers.md".to_string(),
+        content: "# Careers\n\nSend your résumé to the team at the cafe\u{301}.".to_string(),
+        title: "Careers".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Verify diacritics are preserved, while composed and decomposed forms match
+    assert!(storage.find_relevant_documents("resume").is_empty());
+    assert_eq!(storage.find_relevant_documents("résumé").len(), 1);
+    assert_eq!(storage.find_relevant_documents("café").len(), 1);
+    
+    // Enable diacritic folding and reindex the stored documents
+    storage.set_diacritic_folding(true);
+    storage.reindex_keywords().unwrap();
+    
+    // Verify accented and unaccented forms match each other
+    let keywords = &storage.get_document("careers.md").unwrap().keywords;
+    assert!(keywords.contains(&"resume".to_string()));
+    assert_eq!(storage.find_relevant_documents("resume").len(), 1);
+    assert_eq!(storage.find_relevant_documents("résumé").len(), 1);
+    assert_eq!(storage.find_relevant_documents("cafe").len(), 1);
+}
+
+#[test]
+fn test_french_stopwords() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store a document written in French
+    let doc = Document {
+        path: "fr/installation.md".to_string(),
+        content: "# Installation\n\nPour installer le serveur, téléchargez les fichiers dans votre répertoire \
+            et lancez la commande avec les options nécessaires. Le serveur est ensuite disponible sur votre machine."
+            .to_string(),
+        title: "Installation".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Verify French stopwords were left out of the keywords
+    let stored = storage.get_document("fr/installation.md").unwrap();
+    assert_eq!(stored.language.as_deref(), Some("fr"));
+    for stopword in ["pour", "les", "dans", "votre", "avec", "est", "sur"] {
+        assert!(!stored.keywords.contains(&stopword.to_string()), "{} is a keyword", stopword);
+    }
+    assert!(stored.keywords.contains(&"serveur".to_string()));
+    assert!(stored.keywords.contains(&"commande".to_string()));
+    
+    // Replace the French list with a custom one and reindex
+    let mut stopwords = Stopwords::default();
+    stopwords.set("fra", &["serveur".to_string()]);
+    storage.set_stopwords(stopwords);
+    storage.reindex_keywords().unwrap();
+    
+    // Verify the custom list is used instead of the built-in one
+    let stored = storage.get_document("fr/installation.md").unwrap();
+    assert!(!stored.keywords.contains(&"serveur".to_string()));
+    assert!(stored.keywords.contains(&"pour".to_string()));
+}
+
+#[test]
+fn test_interrupted_save_keeps_previous_index() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store a first document
+    let doc = Document {
+        path: "intro.md".to_string(),
+        content: "# Intro\n\nWelcome to the documentation.".to_string(),
+        title: "Intro".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Verify no temporary file is left behind by a successful save
+    assert!(!temp_dir.path().join("documents.json.tmp").exists());
+    
+    // Make the next save fail before the index is replaced
+    std::fs::create_dir(temp_dir.path().join("documents.json.tmp")).unwrap();
+    let doc = Document {
+        path: "setup.md".to_string(),
+        content: "# Setup\n\nInstall the server.".to_string(),
+        title: "Setup".to_string(),
+        summary: None,
+    };
+    assert!(storage.store_document(doc).is_err());
+    
+    // Verify the previous index is still complete
+    let index = std::fs::read_to_string(temp_dir.path().join("documents.json")).unwrap();
+    let documents: serde_json::Value = serde_json::from_str(&index).unwrap();
+    assert!(documents.get("intro.md").is_some());
+    assert!(documents.get("setup.md").is_none());
+    
+    // Verify a truncated temporary file left by a crash is ignored when loading
+    std::fs::remove_dir(temp_dir.path().join("documents.json.tmp")).unwrap();
+    std::fs::write(temp_dir.path().join("documents.json.tmp"), "{\"setup.md\": {\"pa").unwrap();
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    assert!(reloaded.get_document("intro.md").is_some());
+    assert!(reloaded.get_document("setup.md").is_none());
+}
+
+#[test]
+fn test_relevant_documents_score_and_matched_terms() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store documents matching different words of the query
+    let docs = vec![
+        Document {
+            path: "docker.md".to_string(),
+            content: "# Docker Deployment\n\nBuild the image and deploy it to the cluster.".to_string(),
+            title: "Docker Deployment".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "cluster.md".to_string(),
+            content: "# Cluster\n\nThe cluster runs every service.".to_string(),
+            title: "Cluster".to_string(),
+            summary: None,
+        },
+    ];
+    storage.store_documents(docs).unwrap();
+    
+    // Search for words found in one or both documents
+    let results = storage.score_relevant_documents("docker cluster kubernetes");
+    assert_eq!(results.len(), 2);
+    
+    // Verify the matched terms are the query words found in each document
+    assert_eq!(results[0].document.path, "docker.md");
+    assert_eq!(results[0].matched_terms, vec!["docker", "cluster"]);
+    assert_eq!(results[1].document.path, "cluster.md");
+    assert_eq!(results[1].matched_terms, vec!["cluster"]);
+    
+    // Verify the scores are normalized and sorted in descending order
+    assert!(results[0].score > results[1].score);
+    assert!(results.iter().all(|result| result.score > 0.0 && result.score <= 1.0));
+    
+    // Verify a document with every query word in its title scores 1
+    let results = storage.score_relevant_documents("docker deployment");
+    assert_eq!(results[0].score, 1.0);
+}
+
+/// Store a few documents to export
+fn export_storage(temp_dir: &tempfile::TempDir) -> DocumentStorage {
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    let docs = vec![
+        Document {
+            path: "intro.md".to_string(),
+            content: "# Introduction\n\nWelcome to the documentation.".to_string(),
+            title: "Introduction".to_string(),
+            summary: Some("Welcome to the documentation.".to_string()),
+        },
+        Document {
+            path: "guides/setup.md".to_string(),
+            content: "# Setup\n\nInstall the server\nand run it.".to_string(),
+            title: "Setup".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "faq.md".to_string(),
+            content: "# FAQ\n\nFrequently asked questions.".to_string(),
+            title: "FAQ".to_string(),
+            summary: None,
+        },
+    ];
+    storage.store_documents(docs).unwrap();
+    storage
+}
+
+#[test]
+fn test_export_jsonl() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = export_storage(&temp_dir);
+    
+    // Export the documents as JSONL
+    let export = storage.export_documents(ExportFormat::Jsonl).unwrap();
+    
+    // Verify there is one JSON document per line, sorted by path
+    let lines: Vec<&str> = export.lines().collect();
+    assert_eq!(lines.len(), storage.get_all_documents().len());
+    let records: Vec<serde_json::Value> = lines.iter().map(|line| serde_json::from_str(line).unwrap()).collect();
+    let paths: Vec<&str> = records.iter().map(|record| record["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, vec!["faq.md", "guides/setup.md", "intro.md"]);
+    assert_eq!(records[1]["content"], "# Setup\n\nInstall the server\nand run it.");
+}
+
+#[test]
+fn test_export_markdown() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let storage = export_storage(&temp_dir);
+    
+    // Export the documents as a single markdown file
+    let export = storage.export_documents(ExportFormat::Markdown).unwrap();
+    
+    // Verify every document is under a heading with its title, followed by its path
+    for document in storage.get_all_documents() {
+        assert!(export.contains(&format!("# {}\n\nPath: `{}`", document.title, document.path)));
+        assert!(export.contains(&document.content));
+    }
+}
+
+#[test]
+fn test_batch_saves_once() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store 100 documents one by one within a batch
+    storage.begin_batch();
+    for i in 0..100 {
+        let doc = Document {
+            path: format!("page{}.md", i),
+            content: format!("# Page {}\n\nContent of page number {}.", i, i),
+            title: format!("Page {}", i),
+            summary: None,
+        };
+        storage.store_document(doc).unwrap();
+    }
+    
+    // Verify nothing was written before the batch is committed
+    assert_eq!(storage.save_count(), 0);
+    assert!(!temp_dir.path().join("documents.json").exists());
+    
+    // Commit the batch
+    storage.commit().unwrap();
+    
+    // Verify the index was written exactly once, with every document
+    assert_eq!(storage.save_count(), 1);
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    assert_eq!(reloaded.get_all_documents().len(), 100);
+    
+    // Verify single inserts outside a batch still save right away
+    storage.store_document(Document {
+        path: "extra.md".to_string(),
+        content: "# Extra\n\nOne more page.".to_string(),
+        title: "Extra".to_string(),
+        summary: None,
+    }).unwrap();
+    assert_eq!(storage.save_count(), 2);
+    
+    // Verify committing an unchanged batch doesn't save
+    storage.begin_batch();
+    storage.commit().unwrap();
+    assert_eq!(storage.save_count(), 2);
+}
+
+#[test]
+fn test_highlight_offsets() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store a document with multibyte characters before the matched terms
+    let doc = Document {
+        path: "cafe.md".to_string(),
+        content: "# Café\n\nLe café — déjà vu: configure the Server, then restart the server.".to_string(),
+        title: "Café".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Find the highlights of the query terms in the snippet
+    let document = storage.get_document("cafe.md").unwrap();
+    let snippet = storage.extract_snippet(document, "server configure", false).unwrap();
+    let highlights = storage.highlight_terms(document, &snippet, "server configure");
+    
+    // Verify every offset slices out exactly a matched term
+    let highlighted: Vec<&str> = highlights.iter().map(|(start, end)| &snippet[*start..*end]).collect();
+    assert_eq!(highlighted, vec!["configure", "Server", "server"]);
+    
+    // Verify the offsets also hold for snippets with marked terms
+    let snippet = storage.extract_snippet(document, "server", true).unwrap();
+    let highlights = storage.highlight_terms(document, &snippet, "server");
+    assert_eq!(&snippet[highlights[0].0..highlights[0].1], "Server");
+    assert_eq!(&snippet[highlights[0].0 - 2..highlights[0].0], "**");
+}
+
+#[test]
+fn test_fetch_and_modification_times() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store a document and record when it last changed in the repository
+    storage.store_document(Document {
+        path: "guide.md".to_string(),
+        content: "# Guide\n\nHow to use the project.".to_string(),
+        title: "Guide".to_string(),
+        summary: None,
+    }).unwrap();
+    storage.set_last_modified(&HashMap::from([("guide.md".to_string(), 1714564800)])).unwrap();
+    
+    // Verify the fetch time was set to now
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let fetched_at = storage.get_document("guide.md").unwrap().fetched_at.unwrap();
+    assert!(now - fetched_at < 60);
+    
+    // Verify both times are saved and reloaded
+    let index = std::fs::read_to_string(temp_dir.path().join("documents.json")).unwrap();
+    assert!(index.contains(&format!("\"fetched_at\":{}", fetched_at)));
+    assert!(index.contains("\"last_modified\":1714564800"));
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    let doc = reloaded.get_document("guide.md").unwrap();
+    assert_eq!(doc.fetched_at, Some(fetched_at));
+    assert_eq!(doc.last_modified, Some(1714564800));
+    
+    // Verify a freshly fetched document isn't stale
+    assert!(reloaded.get_stale_documents(Duration::from_secs(3600)).is_empty());
+    
+    // Make the document look fetched a day ago
+    let index = index.replace(&format!("\"fetched_at\":{}", fetched_at), &format!("\"fetched_at\":{}", fetched_at - 86400));
+    std::fs::write(temp_dir.path().join("documents.json"), index).unwrap();
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Verify it's stale after an hour, but not after two days
+    let stale = reloaded.get_stale_documents(Duration::from_secs(3600));
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].path, "guide.md");
+    assert!(reloaded.get_stale_documents(Duration::from_secs(2 * 86400)).is_empty());
+}
+
+#[test]
+fn test_case_sensitive_acronym_query() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store documents with the acronym, the lowercase word, and the acronym inside a word
+    for (path, title, content) in [
+        ("async-io.md", "Async IO", "# Async IO\n\nThe IO traits read and write bytes."),
+        ("scenarios.md", "Scenarios", "# Scenarios\n\nEach scenario describes an io bound workload."),
+        ("errors.md", "Errors", "# Errors\n\nAn IOError is returned when reading fails."),
+    ] {
+        storage.store_document(Document {
+            path: path.to_string(),
+            content: content.to_string(),
+            title: title.to_string(),
+            summary: None,
+        }).unwrap();
+    }
+    
+    let paths = |options: MatchOptions| {
+        let mut paths: Vec<String> = storage
+            .score_relevant_documents_with("IO", options)
+            .into_iter()
+            .map(|relevant| relevant.document.path.clone())
+            .collect();
+        paths.sort();
+        paths
+    };
+    
+    // Verify the default keyword search ignores the short term
+    assert!(paths(MatchOptions::default()).is_empty());
+    
+    // Verify a case sensitive search only matches the uppercase acronym
+    let case_sensitive = MatchOptions { case_sensitive: true, whole_word: false };
+    assert_eq!(paths(case_sensitive), vec!["async-io.md", "errors.md"]);
+    
+    // Verify a whole word search doesn't match it inside other words
+    let whole_word = MatchOptions { case_sensitive: false, whole_word: true };
+    assert_eq!(paths(whole_word), vec!["async-io.md", "scenarios.md"]);
+    
+    // Verify both options together only match the acronym itself, scored as a title match
+    let exact = MatchOptions { case_sensitive: true, whole_word: true };
+    let relevant = storage.score_relevant_documents_with("IO", exact);
+    assert_eq!(relevant.len(), 1);
+    assert_eq!(relevant[0].document.path, "async-io.md");
+    assert_eq!(relevant[0].matched_terms, vec!["IO"]);
+    assert_eq!(relevant[0].score, 1.0);
+}
+
+#[test]
+fn test_batch_analysis_matches_sequential_inserts() {
+    // Create documents with varied content, some of them duplicates
+    let documents: Vec<Document> = (0..200)
+        .map(|i| {
+            // The last 50 pages repeat the content of the first ones
+            let n = i % 150;
+            Document {
+                path: format!("docs/page{}.md", i),
+                content: format!(
+                    "# Page {}\n\n## Topic {}\n\nThis page covers configuration number {} and [the next page](page{}.md).",
+                    n, n % 7, n, n + 1
+                ),
+                title: format!("Page {}", n),
+                summary: None,
+            }
+        })
+        .collect();
+    
+    // Store them all at once in one storage, and one by one in another
+    let batch_dir = tempfile::tempdir().unwrap();
+    let mut batch = DocumentStorage::new(batch_dir.path()).unwrap();
+    batch.store_documents(documents.clone()).unwrap();
+    
+    let sequential_dir = tempfile::tempdir().unwrap();
+    let mut sequential = DocumentStorage::new(sequential_dir.path()).unwrap();
+    for document in documents {
+        sequential.store_document(document).unwrap();
+    }
+    
+    // Verify both storages hold the same documents, with the same keywords and aliases
+    let mut batch_documents = batch.get_all_documents();
+    batch_documents.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut sequential_documents = sequential.get_all_documents();
+    sequential_documents.sort_by(|a, b| a.path.cmp(&b.path));
+    assert_eq!(batch_documents.len(), 150);
+    assert_eq!(batch_documents.len(), sequential_documents.len());
+    for (batch_document, sequential_document) in batch_documents.iter().zip(&sequential_documents) {
+        assert_eq!(batch_document.path, sequential_document.path);
+        assert_eq!(batch_document.keywords, sequential_document.keywords);
+        assert_eq!(batch_document.weighted_keywords, sequential_document.weighted_keywords);
+        assert_eq!(batch_document.language, sequential_document.language);
+        assert_eq!(batch_document.links, sequential_document.links);
+        assert_eq!(batch_document.aliases, sequential_document.aliases);
+    }
+}
+
+#[test]
+fn test_in_memory_storage() {
+    // Create a storage kept in memory only
+    let mut storage = DocumentStorage::new_in_memory();
+    
+    // Store documents and remove one of them
+    storage.store_documents(vec![
+        Document {
+            path: "guide.md".to_string(),
+            content: "# Guide\n\nHow to configure the server.".to_string(),
+            title: "Guide".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "old.md".to_string(),
+            content: "# Old\n\nObsolete notes.".to_string(),
+            title: "Old".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    storage.remove_documents(&["old.md".to_string()]).unwrap();
+    
+    // Verify documents can be retrieved and searched
+    assert_eq!(storage.get_all_documents().len(), 1);
+    assert_eq!(storage.get_document("guide.md").unwrap().title, "Guide");
+    assert_eq!(storage.find_relevant_documents("configure")[0].path, "guide.md");
+    
+    // Verify nothing was written to disk
+    assert_eq!(storage.save_count(), 0);
+    assert!(!std::path::Path::new("documents.json").exists());
+    assert!(!std::path::Path::new("documents.json.gz").exists());
+}
+
+#[test]
+fn test_summarize_document_picks_salient_sentences() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store a document opening with generic sentences, the substance coming later
+    storage.store_document(Document {
+        path: "cache.md".to_string(),
+        content: "# Response cache\n\n\
+            Welcome to this page. We hope you enjoy reading it.\n\n\
+            ## Details\n\n\
+            The cache stores server responses in memory. \
+            Cache eviction removes the oldest responses when the cache is full. \
+            Some people prefer tea.\n\n\
+            ```rust\nlet cache = Cache::new();\n```\n\n\
+            Eviction of cached responses runs whenever the server stores a response.".to_string(),
+        title: "Response cache".to_string(),
+        summary: None,
+    }).unwrap();
+    
+    // Summarize it in two sentences
+    let summary = storage.summarize_document("cache.md", 2).unwrap();
+    
+    // Verify sentences about the main topic were picked rather than the first ones, in document order
+    assert_eq!(summary.len(), 2);
+    for generic in ["Welcome to this page.", "We hope you enjoy reading it.", "Some people prefer tea."] {
+        assert!(!summary.iter().any(|sentence| sentence == generic));
+    }
+    assert_eq!(summary[1], "Eviction of cached responses runs whenever the server stores a response.");
+    
+    // Verify unknown documents can't be summarized
+    assert!(storage.summarize_document("missing.md", 2).is_none());
+}
+
+#[test]
+fn test_query_expansion_with_synonyms() {
+    // Create a temporary directory for storage
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    
+    // Store a document only saying authentication, and one saying auth
+    storage.store_documents(vec![
+        Document {
+            path: "security.md".to_string(),
+            content: "# Security\n\nAuthentication uses tokens issued by the identity provider.".to_string(),
+            title: "Security".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "tokens.md".to_string(),
+            content: "# Tokens\n\nThe auth header carries the token.".to_string(),
+            title: "Tokens".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    
+    // Verify queries aren't expanded by default
+    let paths: Vec<String> = storage.find_relevant_documents("auth").iter().map(|doc| doc.path.clone()).collect();
+    assert_eq!(paths, vec!["tokens.md"]);
+    
+    // Expand queries with the built-in synonyms, and a custom one
+    let mut synonyms = Synonyms::default();
+    synonyms.add("idp", &["identity".to_string()]);
+    storage.set_synonyms(Some(synonyms));
+    
+    // Verify auth finds the document only saying authentication, below the exact match
+    let relevant = storage.score_relevant_documents("auth");
+    assert_eq!(relevant.len(), 2);
+    assert_eq!(relevant[0].document.path, "tokens.md");
+    assert_eq!(relevant[1].document.path, "security.md");
+    assert_eq!(relevant[1].matched_terms, vec!["authentication"]);
+    assert!(relevant[1].score < relevant[0].score);
+    
+    // Verify custom synonyms expand queries too
+    let paths: Vec<String> = storage.find_relevant_documents("idp").iter().map(|doc| doc.path.clone()).collect();
+    assert_eq!(paths, vec!["security.md"]);
+    
+    // Verify words too short to be keywords are left out of synonym groups
+    let mut synonyms = Synonyms::default();
+    synonyms.add("database", &["db".to_string(), "datastore".to_string()]);
+    assert!(synonyms.groups().iter().flatten().all(|word| word.len() > 2));
+    assert!(synonyms.groups().iter().any(|group| group == &["database", "datastore"]));
+}
+
+#[test]
+fn test_max_keywords_per_document() {
+    // Create a temporary directory for storage, keeping at most 10 keywords per document
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    storage.set_max_keywords(Some(10));
+    
+    // Store a long document with hundreds of distinct words, and a few repeated ones
+    let mut content = String::from("# Reference\n\n");
+    for i in 0..500 {
+        content.push_str(&format!("Parameter{} configures the server cache. ", i));
+    }
+    storage.store_document(Document {
+        path: "reference.md".to_string(),
+        content,
+        title: "Reference".to_string(),
+        summary: None,
+    }).unwrap();
+    
+    // Verify the keywords are capped, keeping the most frequent ones
+    let doc = storage.get_document("reference.md").unwrap();
+    assert_eq!(doc.keywords.len(), 10);
+    for keyword in ["configures", "server", "cache"] {
+        assert!(doc.keywords.contains(&keyword.to_string()));
+    }
+    assert!(storage.find_relevant_documents("cache").iter().any(|doc| doc.path == "reference.md"));
+}
+
+#[test]
+fn test_code_tokenizer_keeps_identifiers() {
+    // Verify identifiers are kept whole, along with their parts
+    let words = CodeTokenizer.tokenize("use std::vec, pass --verbose or edit main.rs.");
+    assert_eq!(words, vec!["use", "std::vec", "std", "vec", "pass", "--verbose", "verbose", "or", "edit", "main.rs", "main", "rs"]);
+    
+    // Create storages with the default and the code-aware tokenizer
+    let documents = vec![
+        Document {
+            path: "vectors.md".to_string(),
+            content: "# Vectors\n\nImport std::vec to build growable arrays.".to_string(),
+            title: "Vectors".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "unrelated.md".to_string(),
+            content: "# Standard library\n\nThe std crate, with vec and friends.".to_string(),
+            title: "Standard library".to_string(),
+            summary: None,
+        },
+    ];
+    let default_dir = tempfile::tempdir().unwrap();
+    let mut default_storage = DocumentStorage::new(default_dir.path()).unwrap();
+    default_storage.store_documents(documents.clone()).unwrap();
+    
+    let code_dir = tempfile::tempdir().unwrap();
+    let mut code_storage = DocumentStorage::new(code_dir.path()).unwrap();
+    code_storage.set_tokenizer(Box::new(CodeTokenizer));
+    code_storage.store_documents(documents).unwrap();
+    
+    // Verify std::vec is a single keyword with the code-aware tokenizer only
+    assert!(!default_storage.get_document("vectors.md").unwrap().keywords.contains(&"std::vec".to_string()));
+    assert!(code_storage.get_document("vectors.md").unwrap().keywords.contains(&"std::vec".to_string()));
+    
+    // Verify searching for it ranks the document using it above one with its parts apart
+    let relevant = code_storage.score_relevant_documents("std::vec");
+    assert_eq!(relevant[0].document.path, "vectors.md");
+    assert!(relevant[0].matched_terms.contains(&"std::vec".to_string()));
+    assert!(relevant.iter().all(|other| other.document.path == "vectors.md" || other.score < relevant[0].score));
+    
+    // Store a document describing a command-line flag
+    code_storage.store_document(Document {
+        path: "cli.md".to_string(),
+        content: "# Command line\n\nPass --verbose to print every request.".to_string(),
+        title: "Command line".to_string(),
+        summary: None,
+    }).unwrap();
+    
+    // Verify searching for the flag finds the document instead of excluding it
+    let relevant = code_storage.find_relevant_documents("--verbose");
+    assert_eq!(relevant[0].path, "cli.md");
+    assert!(code_storage.find_relevant_documents("+--verbose").iter().any(|doc| doc.path == "cli.md"));
+}
+
+#[test]
+fn test_image_alt_text_is_searchable() {
+    // Create a storage keeping few keywords per document, and a document describing its diagrams
+    let mut storage = DocumentStorage::new_in_memory();
+    storage.set_max_keywords(Some(3));
+    let doc = Document {
+        path: "docs/architecture.md".to_string(),
+        content: "# Architecture\n\nThe service runs behind the gateway. The gateway routes the service calls.\n\n\
+            ![Cluster topology overview](images/topology.png)\n\n\
+            <img src=\"https://example.com/badge.svg\" alt=\"Build status\">\n".to_string(),
+        title: "Architecture".to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    
+    // Verify both images are extracted, telling local from remote ones
+    let images = &storage.get_document("docs/architecture.md").unwrap().images;
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0].alt, "Cluster topology overview");
+    assert_eq!(images[0].path.as_deref(), Some("docs/images/topology.png"));
+    assert!(!images[0].remote);
+    assert_eq!(images[1].alt, "Build status");
+    assert!(images[1].remote);
+    assert_eq!(images[1].path, None);
+    
+    // Verify the alt text is searchable even though the keywords are capped
+    let results = storage.find_relevant_documents("topology");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].path, "docs/architecture.md");
+}
+
+/// Create an in-memory storage with a document last changed at the given time
+fn storage_with(path: &str, content: &str, last_modified: u64) -> DocumentStorage {
+    let mut storage = DocumentStorage::new_in_memory();
+    let doc = Document {
+        path: path.to_string(),
+        content: content.to_string(),
+        title: content.lines().next().unwrap_or_default().trim_start_matches("# ").to_string(),
+        summary: None,
+    };
+    storage.store_document(doc).unwrap();
+    storage.set_last_modified(&HashMap::from([(path.to_string(), last_modified)])).unwrap();
+    storage
+}
+
+#[test]
+fn test_merge_storages() {
+    // Create two storages sharing a path, the second one with a newer version and another document
+    let existing = || storage_with("guide.md", "# Guide\n\nInstall the server with cargo.", 1000);
+    let mut other = storage_with("guide.md", "# Guide\n\nInstall the server with docker.", 2000);
+    other
+        .store_document(Document {
+            path: "api.md".to_string(),
+            content: "# API\n\nThe endpoints return JSON.".to_string(),
+            title: "API".to_string(),
+            summary: None,
+        })
+        .unwrap();
+    
+    // Merge keeping the newest documents
+    let mut storage = existing();
+    assert_eq!(storage.merge(&other, MergePolicy::KeepNewest).unwrap(), 2);
+    
+    // Verify the newer guide replaced the existing one, and both storages are searchable
+    assert!(storage.get_document("guide.md").unwrap().content.contains("docker"));
+    assert_eq!(storage.get_document("guide.md").unwrap().last_modified, Some(2000));
+    assert!(storage.find_relevant_documents("cargo").is_empty());
+    assert_eq!(storage.find_relevant_documents("docker").len(), 1);
+    assert_eq!(storage.find_relevant_documents("endpoints").len(), 1);
+    
+    // Merge keeping the existing documents
+    let mut storage = existing();
+    assert_eq!(storage.merge(&other, MergePolicy::KeepExisting).unwrap(), 1);
+    
+    // Verify the existing guide was kept
+    assert!(storage.get_document("guide.md").unwrap().content.contains("cargo"));
+    assert!(storage.find_relevant_documents("docker").is_empty());
+    assert_eq!(storage.find_relevant_documents("endpoints").len(), 1);
+    
+    // Merge under a namespace
+    let mut storage = existing();
+    assert_eq!(storage.merge(&other, MergePolicy::Namespace("other".to_string())).unwrap(), 2);
+    
+    // Verify both guides are kept, the merged one under the namespace
+    assert!(storage.get_document("guide.md").unwrap().content.contains("cargo"));
+    assert!(storage.get_document("other/guide.md").unwrap().content.contains("docker"));
+    assert!(storage.get_document("other/api.md").is_some());
+    assert_eq!(storage.get_documents_by_title("Guide").len(), 2);
+}
+
+#[test]
+fn test_recency_boost_ranks_newer_documents_first() {
+    let mut storage = DocumentStorage::new_in_memory();
+    
+    // Store two equally relevant documents, with different contents so they aren't aliases,
+    // the first one modified a year before the second one
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    for (path, last_modified) in [("old.md", now - 400 * 86_400), ("new.md", now - 30 * 86_400)] {
+        let doc = Document {
+            path: path.to_string(),
+            content: format!("# Caching\n\nConfigure the cache before deploying {}.", path),
+            title: "Caching".to_string(),
+            summary: None,
+        };
+        storage.store_document(doc).unwrap();
+        storage.set_last_modified(&HashMap::from([(path.to_string(), last_modified)])).unwrap();
+    }
+    
+    // Verify both documents score the same without the boost
+    let results = storage.score_relevant_documents("cache");
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].score, results[1].score);
+    
+    // Enable the recency boost
+    storage.set_recency_boost(Some(RecencyBoost::default()));
+    let results = storage.score_relevant_documents("cache");
+    
+    // Verify the newer document ranks first, while the keyword relevance is unchanged
+    let paths: Vec<&str> = results.iter().map(|result| result.document.path.as_str()).collect();
+    assert_eq!(paths, vec!["new.md", "old.md"]);
+    assert!(results[0].score > results[1].score);
+    assert_eq!(results[0].relevance, results[1].relevance);
+}
+
+#[test]
+fn test_restructuredtext_markup_left_out_of_keywords() {
+    // Process a reStructuredText guide with sections, directives and roles
+    let content = "\
+=============
+Cluster Guide
+=============
+
+.. contents::
+   :depth: 2
+
+Provisioning
+------------
+
+Read :ref:`the scaling chapter <scaling>` before provisioning nodes.
+
+.. note:: Replicas need dedicated volumes.
+
+.. code-block:: bash
+
+   kubectl apply
+
+.. _scaling:
+";
+    let document = DocumentScanner::new().process_content("docs/cluster.rst", content.to_string()).unwrap();
+    
+    // Verify the overlined title is extracted, and the summary is plain prose
+    assert_eq!(document.title, "Cluster Guide");
+    assert_eq!(document.summary.as_deref(), Some("Read the scaling chapter before provisioning nodes. Replicas need dedicated volumes."));
+    let outline = DocumentScanner::new().extract_outline(&document.path, &document.content);
+    let headings: Vec<_> = outline.iter().map(|h| (h.level, h.text.as_str())).collect();
+    assert_eq!(headings, vec![(1, "Cluster Guide"), (2, "Provisioning")]);
+    
+    // Store it
+    let mut storage = DocumentStorage::new_in_memory();
+    storage.store_document(document).unwrap();
+    let stored = storage.get_document("docs/cluster.rst").unwrap();
+    
+    // Verify directive content and role text are keywords, but not their markers
+    for keyword in ["replicas", "volumes", "scaling", "kubectl"] {
+        assert!(stored.keywords.contains(&keyword.to_string()), "missing {}", keyword);
+    }
+    for marker in ["note", "ref", "contents", "depth", "code-block", "block"] {
+        assert!(!stored.keywords.contains(&marker.to_string()), "unexpected {}", marker);
+    }
+}
+
+#[test]
+fn test_replace_source_keeps_repository_files() {
+    // Store a repository file under the releases directory, and the notes of two releases
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    let document = |path: &str, content: &str| Document {
+        path: path.to_string(),
+        content: content.to_string(),
+        title: path.to_string(),
+        summary: None,
+    };
+    storage.store_document(document("releases/notes.md", "# Release process\n\nTag the main branch.")).unwrap();
+    storage.replace_source("releases", vec![
+        document("releases/v1.0.md", "# v1.0\n\nFirst release."),
+        document("releases/v1.1.md", "# v1.1\n\nFixed the cache."),
+    ]).unwrap();
+    assert_eq!(storage.get_document("releases/v1.0.md").unwrap().source.as_deref(), Some("releases"));
+    assert_eq!(storage.get_document("releases/notes.md").unwrap().source, None);
+    
+    // Verify a later releases scan only removes the releases it no longer lists, even after a reload
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    storage.replace_source("releases", vec![document("releases/v1.1.md", "# v1.1\n\nFixed the cache.")]).unwrap();
+    assert!(storage.get_document("releases/v1.0.md").is_none());
+    assert!(storage.get_document("releases/v1.1.md").is_some());
+    assert!(storage.get_document("releases/notes.md").unwrap().content.contains("Tag the main branch"));
+    
+    // Verify other sources are left alone too
+    storage.replace_source("wiki", Vec::new()).unwrap();
+    assert_eq!(storage.get_all_documents().len(), 2);
+}
+
+#[test]
+fn test_replace_source_stores_duplicates_apart() {
+    // Store a repository file, and a wiki page with the same content
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    let document = |path: &str| Document {
+        path: path.to_string(),
+        content: "# Setup\n\nInstall the toolchain before building.".to_string(),
+        title: "Setup".to_string(),
+        summary: None,
+    };
+    storage.store_document(document("docs/setup.md")).unwrap();
+    storage.replace_source("wiki", vec![document("wiki/Setup.md")]).unwrap();
+    
+    // Verify both are stored as documents rather than aliases
+    assert_eq!(storage.get_all_documents().len(), 2);
+    assert!(storage.get_document("docs/setup.md").unwrap().aliases.is_empty());
+    assert_eq!(storage.get_document("wiki/Setup.md").unwrap().path, "wiki/Setup.md");
+    
+    // Verify refreshing either of them after a reload keeps them apart, whichever is canonical
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    storage.store_document(document("docs/setup.md")).unwrap();
+    storage.replace_source("wiki", vec![document("wiki/Setup.md")]).unwrap();
+    assert_eq!(storage.get_all_documents().len(), 2);
+    assert!(storage.get_all_documents().iter().all(|stored| stored.aliases.is_empty()));
+    
+    // Verify removing the wiki page leaves the repository file to alias new copies to
+    storage.replace_source("wiki", Vec::new()).unwrap();
+    storage.store_document(document("mirror/setup.md")).unwrap();
+    assert_eq!(storage.get_document("mirror/setup.md").unwrap().path, "docs/setup.md");
+}
+
+#[test]
+fn test_keyword_weights_favor_early_and_frequent_terms() {
+    let mut storage = DocumentStorage::new_in_memory();
+    
+    // Store a document mentioning one term in its first line, another twice, and a third only at its end
+    storage.store_document(Document {
+        path: "guide.md".to_string(),
+        content: "Deployment requires a configured cluster.\n\nThe cluster runs every service.\n\nSee the appendix.".to_string(),
+        title: "Guide".to_string(),
+        summary: None,
+    }).unwrap();
+    let doc = storage.get_document("guide.md").unwrap();
+    
+    // Verify the first line term weighs more than the term only at the end, and the frequent term the most
+    assert!(doc.keyword_weight("deployment") > doc.keyword_weight("appendix"));
+    assert_eq!(doc.keyword_weight("cluster"), 1.0);
+    assert_eq!(doc.weighted_keywords[0].keyword, "cluster");
+    assert_eq!(doc.keyword_weight("missing"), 0.0);
+    
+    // Verify the flat keyword list is still available, with the same keywords as the body
+    let mut weighted: Vec<&str> = doc
+        .weighted_keywords
+        .iter()
+        .filter(|weighted| weighted.weight > 0.0)
+        .map(|weighted| weighted.keyword.as_str())
+        .collect();
+    let mut flat: Vec<&str> = doc.keywords.iter().map(String::as_str).collect();
+    weighted.sort();
+    flat.sort();
+    assert_eq!(weighted, flat);
+    
+    // Store a document only mentioning deployment at its end
+    storage.store_document(Document {
+        path: "notes.md".to_string(),
+        content: "Notes about services and clusters.\n\nAlso deployment.".to_string(),
+        title: "Notes".to_string(),
+        summary: None,
+    }).unwrap();
+    
+    // Verify the document where the term comes first ranks first
+    let results = storage.score_relevant_documents("deployment");
+    assert_eq!(results[0].document.path, "guide.md");
+    assert!(results[0].score > results[1].score);
+}
+
+#[test]
+fn test_keywords_stored_once_across_fields() {
+    let mut storage = DocumentStorage::new_in_memory();
+    
+    // Store a document with a term in its title, a heading and its body, and terms only in one field
+    storage.store_document(Document {
+        path: "cluster.md".to_string(),
+        content: "# Cluster Setup\n\n## Cluster nodes\n\nEach cluster needs three replicas.\n\n## Networking\n\nOpen the ports.".to_string(),
+        title: "Cluster Setup".to_string(),
+        summary: None,
+    }).unwrap();
+    let doc = storage.get_document("cluster.md").unwrap();
+    let entries = |keyword: &str| doc.weighted_keywords.iter().filter(|weighted| weighted.keyword == keyword).count();
+    
+    // Verify the term is stored once, with its most significant field and its weight in the body combined
+    assert_eq!(entries("cluster"), 1);
+    assert_eq!(doc.keyword_field("cluster"), Some(KeywordField::Title));
+    assert!(doc.keyword_weight("cluster") > 0.0);
+    
+    // Verify terms found in a single field keep that field
+    assert_eq!(entries("networking"), 1);
+    assert_eq!(doc.keyword_field("networking"), Some(KeywordField::Heading));
+    assert_eq!(doc.keyword_field("replicas"), Some(KeywordField::Body));
+    assert_eq!(doc.keyword_field("missing"), None);
+    
+    // Verify no keyword is stored twice
+    let mut keywords: Vec<&str> = doc.weighted_keywords.iter().map(|weighted| weighted.keyword.as_str()).collect();
+    let count = keywords.len();
+    keywords.sort();
+    keywords.dedup();
+    assert_eq!(keywords.len(), count);
+    
+    // Verify title matches still rank above body matches
+    storage.store_document(Document {
+        path: "notes.md".to_string(),
+        content: "Notes about a cluster.".to_string(),
+        title: "Notes".to_string(),
+        summary: None,
+    }).unwrap();
+    let results = storage.score_relevant_documents("cluster");
+    assert_eq!(results[0].document.path, "cluster.md");
+}
+
+#[test]
+fn test_keywords_saved_once() {
+    // Create a persistent storage with a document
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    storage.store_document(Document {
+        path: "cluster.md".to_string(),
+        content: "# Cluster Setup\n\nEach cluster needs three replicas.".to_string(),
+        title: "Cluster Setup".to_string(),
+        summary: None,
+    }).unwrap();
+    let keywords = storage.get_document("cluster.md").unwrap().keywords.clone();
+    
+    // Verify only the weighted keywords are written to disk
+    let index_path = temp_dir.path().join("documents.json");
+    let index: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&index_path).unwrap()).unwrap();
+    assert!(index["cluster.md"].get("keywords").is_none());
+    assert!(!index["cluster.md"]["weighted_keywords"].as_array().unwrap().is_empty());
+    
+    // Verify the flat keywords are rebuilt when the index is loaded
+    let reloaded = DocumentStorage::new(temp_dir.path()).unwrap();
+    let document = reloaded.get_document("cluster.md").unwrap();
+    assert_eq!(document.keywords, keywords);
+    assert!(document.keywords.contains(&"replicas".to_string()));
+    assert_eq!(document.keyword_field("cluster"), Some(KeywordField::Title));
+    
+    // Verify indexes saved with flat keywords only are still read
+    let mut legacy = index.clone();
+    legacy["cluster.md"].as_object_mut().unwrap().remove("weighted_keywords");
+    legacy["cluster.md"]["keywords"] = serde_json::json!(["cluster", "replicas"]);
+    std::fs::write(&index_path, legacy.to_string()).unwrap();
+    let migrated = DocumentStorage::new(temp_dir.path()).unwrap();
+    let document = migrated.get_document("cluster.md").unwrap();
+    assert!(document.keyword_weight("replicas") > 0.0);
+    assert_eq!(document.keyword_field("cluster"), Some(KeywordField::Title));
+}
+
+#[test]
+fn test_reload_from_disk() {
+    // Create a persistent storage with two documents
+    let temp_dir = tempfile::tempdir().unwrap();
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    storage.store_documents(vec![
+        Document {
+            path: "docs/intro.md".to_string(),
+            content: "# Intro\n\nWelcome to the project.".to_string(),
+            title: "Intro".to_string(),
+            summary: None,
+        },
+        Document {
+            path: "docs/old.md".to_string(),
+            content: "# Old\n\nDeprecated pipeline notes.".to_string(),
+            title: "Old".to_string(),
+            summary: None,
+        },
+    ]).unwrap();
+    
+    // Edit the saved index out of band: retitle a document and remove the other
+    let index_path = temp_dir.path().join("documents.json");
+    let mut index: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&index_path).unwrap()).unwrap();
+    index["docs/intro.md"]["title"] = serde_json::json!("Getting started");
+    index.as_object_mut().unwrap().remove("docs/old.md");
+    std::fs::write(&index_path, index.to_string()).unwrap();
+    
+    // Verify the stale copy is served until the storage is reloaded
+    assert_eq!(storage.get_document("docs/intro.md").unwrap().title, "Intro");
+    assert_eq!(storage.reload_from_disk().unwrap(), 1);
+    
+    // Verify the reloaded documents and their indexes reflect the change
+    assert_eq!(storage.get_document("docs/intro.md").unwrap().title, "Getting started");
+    assert_eq!(storage.get_documents_by_title("Getting started").len(), 1);
+    assert!(storage.get_document("docs/old.md").is_none());
+    assert!(storage.find_relevant_documents("pipeline").is_empty());
+    
+    // Verify an unreadable index keeps the documents in memory
+    std::fs::write(&index_path, "{ not json").unwrap();
+    assert!(storage.reload_from_disk().is_err());
+    assert_eq!(storage.get_all_documents().len(), 1);
+    
+    // Verify a missing index keeps the documents in memory too, rather than emptying the storage
+    std::fs::remove_file(&index_path).unwrap();
+    assert!(matches!(storage.reload_from_disk(), Err(KodeError::NotFound(_))));
+    assert_eq!(storage.get_all_documents().len(), 1);
+    
+    // Verify storages kept in memory have nothing to reload
+    assert!(DocumentStorage::new_in_memory().reload_from_disk().is_err());
+}
+
+#[test]
+fn test_corrupt_index_set_aside() {
+    // Create a storage directory with a truncated index
+    let temp_dir = tempfile::tempdir().unwrap();
+    let index_path = temp_dir.path().join("documents.json");
+    std::fs::write(&index_path, r##"{"docs/intro.md": {"path": "docs/intro.md", "content": "# Int"##).unwrap();
+    
+    // Verify the storage opens empty, and reports the corrupt index
+    let mut storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    assert!(storage.get_all_documents().is_empty());
+    let recovery = storage.recovery().unwrap();
+    assert_eq!(recovery.backup_path, temp_dir.path().join("documents.json.bak"));
+    assert!(recovery.reason.contains("EOF"));
+    
+    // Verify the corrupt index was kept as a backup, and isn't loaded again
+    assert!(!index_path.exists());
+    assert!(std::fs::read_to_string(&recovery.backup_path).unwrap().contains("docs/intro.md"));
+    storage.store_document(Document {
+        path: "docs/intro.md".to_string(),
+        content: "# Intro\n\nWelcome to the project.".to_string(),
+        title: "Intro".to_string(),
+        summary: None,
+    }).unwrap();
+    let storage = DocumentStorage::new(temp_dir.path()).unwrap();
+    assert!(storage.recovery().is_none());
+    assert_eq!(storage.get_all_documents().len(), 1);
+}
+
+#[test]
+fn test_related_documents_weighted() {
+    // Create a guide, a document only sharing its title with it, and two documents
+    // sharing as many of its body keywords
+    let mut storage = DocumentStorage::new_in_memory();
+    let document = |path: &str, title: &str, content: &str| Document {
+        path: path.to_string(),
+        content: content.to_string(),
+        title: title.to_string(),
+        summary: None,
+    };
+    storage.store_documents(vec![
+        document("guide.md", "Kubernetes", "# Kubernetes\n\nRolling updates for services."),
+        document("cluster.md", "Kubernetes", "# Kubernetes\n\nNode pools and autoscaling."),
+        document("b.md", "Notes", "# Notes\n\nRolling updates."),
+        document("a.md", "Notes", "# Notes\n\nRolling updates!"),
+    ]).unwrap();
+    
+    // Verify title terms count toward similarity, and outweigh body terms
+    let related = storage.related_documents("guide.md", 5).unwrap();
+    let paths: Vec<&str> = related.iter().map(|(doc, _)| doc.path.as_str()).collect();
+    assert_eq!(paths, vec!["cluster.md", "a.md", "b.md"]);
+    
+    // Verify documents as similar are ordered by path
+    assert_eq!(related[1].1, related[2].1);
+}
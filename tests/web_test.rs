@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use kode_ai_rs::document::DocumentScanner;
+use kode_ai_rs::storage::DocumentStorage;
+use kode_ai_rs::web::{WebFetcher, WebOptions};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const GUIDE_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>Deployment Guide</title>
+    <style>body { color: red; }</style>
+</head>
+<body>
+    <nav><a href="/">Home</a></nav>
+    <h1>Deploying</h1>
+    <p>Run the <code>deploy</code> command to ship the service.</p>
+    <script>trackVisitor();</script>
+</body>
+</html>"#;
+
+#[tokio::test]
+async fn test_fetch_pages_indexes_html_as_text() {
+    // Serve an HTML page and nothing else
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/guide.html"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(GUIDE_PAGE, "text/html; charset=utf-8"))
+        .mount(&server)
+        .await;
+
+    // Fetch the page and a missing one
+    let options = WebOptions {
+        request_timeout: Duration::from_secs(5),
+        ..WebOptions::default()
+    };
+    let fetcher = WebFetcher::new(options, DocumentScanner::new()).unwrap();
+    let guide_url = format!("{}/guide.html", server.uri());
+    let missing_url = format!("{}/missing.html", server.uri());
+    let result = fetcher.fetch_pages(&[guide_url.clone(), missing_url.clone()]).await;
+
+    // The page is converted to text, titled after its <title>, and keyed by its URL
+    assert_eq!(result.documents.len(), 1);
+    let document = &result.documents[0];
+    assert_eq!(document.path, guide_url);
+    assert_eq!(document.title, "Deployment Guide");
+    assert!(document.content.contains("# Deploying"));
+    assert!(document.content.contains("Run the `deploy` command to ship the service."));
+    assert!(!document.content.contains("trackVisitor"));
+    assert!(!document.content.contains("color: red"));
+    assert!(!document.content.contains("<p>"));
+
+    // The missing page is reported as a failure
+    assert_eq!(result.failures.len(), 1);
+    assert_eq!(result.failures[0].path, missing_url);
+
+    // The page is searchable once stored
+    let mut storage = DocumentStorage::new_in_memory();
+    storage.store_documents(result.documents).unwrap();
+    let relevant = storage.find_relevant_documents("deploy service");
+    assert_eq!(relevant.len(), 1);
+    assert_eq!(relevant[0].path, guide_url);
+}
+
+#[tokio::test]
+async fn test_fetch_page_rejects_other_schemes() {
+    // Only HTTP(S) pages can be fetched
+    let fetcher = WebFetcher::new(WebOptions::default(), DocumentScanner::new()).unwrap();
+    let result = fetcher.fetch_pages(&["file:///etc/passwd".to_string()]).await;
+
+    assert!(result.documents.is_empty());
+    assert_eq!(result.failures.len(), 1);
+}